@@ -4,6 +4,110 @@ use anyhow::{Context, Result};
 use tracing::info;
 
 use synapse_core::entities::MemoryNode;
+use synapse_infra::adapters::candle_adapter::CandleAdapter;
+use synapse_core::ports::{EmbeddingPort, ImmunePort, LlmPort};
+use synapse_infra::adapters::cached_embedder::{CachedEmbedder, DEFAULT_CACHE_CAPACITY};
+use synapse_infra::adapters::candle_embedding_adapter::CandleEmbeddingAdapter;
+use synapse_infra::adapters::model_descriptor::EmbeddingBackend;
+use synapse_infra::adapters::openai_adapter::{OpenAiEmbeddingAdapter, OpenAiLlmAdapter};
+use synapse_infra::adapters::ort_adapter::OrtAdapter;
+use synapse_infra::adapters::ort_classifier_adapter::OrtInjectionClassifier;
+use synapse_infra::adapters::private_file::load_or_create_key;
+use synapse_infra::adapters::immune_adapter::BasicImmuneAdapter;
+use synapse_immune::engine::RELOAD_INTERVAL;
+use synapse_immune::{EmbeddingScanner, GuardedMemory, InjectionDetector, RuleEngine, RuleScanner, QUARANTINE_NAMESPACE, REVIEW_KEY_FILE};
+use synapse_core::logic::embedding_check::EmbeddingCheck;
+use synapse_core::logic::threat_db::ThreatDatabase;
+use synapse_core::MemoryPort;
+use std::sync::Arc;
+
+use crate::config::Config;
+
+/// Load the LLM described in the config (local model or HTTP endpoint).
+async fn load_llm() -> Result<Arc<dyn LlmPort>> {
+    let config = Config::load_or_default().await?;
+    let llm: Arc<dyn LlmPort> = match config.llm_endpoint {
+        Some(endpoint) => Arc::new(OpenAiLlmAdapter::new(endpoint)?),
+        None => Arc::new(CandleAdapter::from_descriptor(&config.llm).context("Failed to load LLM")?),
+    };
+    Ok(llm)
+}
+
+/// Load the embedding model described in the config.
+async fn load_embedder() -> Result<Arc<dyn EmbeddingPort>> {
+    let config = Config::load_or_default().await?;
+    if let Some(endpoint) = config.embedding_endpoint {
+        return cached(OpenAiEmbeddingAdapter::new(endpoint)?.detect_dimension().await?);
+    }
+    let descriptor = &config.embedding;
+    match descriptor.embedding_backend()? {
+        EmbeddingBackend::Candle => cached(CandleEmbeddingAdapter::from_descriptor(descriptor)?),
+        _ => cached(OrtAdapter::from_descriptor(descriptor)?),
+    }
+}
+
+/// Wrap an embedder with the persistent embedding cache.
+fn cached<E: EmbeddingPort + 'static>(embedder: E) -> Result<Arc<dyn EmbeddingPort>> {
+    let cached = CachedEmbedder::new(embedder, DEFAULT_CACHE_CAPACITY)
+        .with_persistent_tier("synapse_data/embedding_cache")?;
+    Ok(Arc::new(cached))
+}
+
+/// Immune adapter verifying the installation against the configured
+/// integrity manifest, and scanning processes with the threat rules if any
+/// are configured.
+async fn immune_adapter() -> Result<Arc<dyn ImmunePort>> {
+    let config = Config::load_or_default().await?;
+    let mut immune = BasicImmuneAdapter::new();
+    if let Some(checker) = config.integrity.load_checker(std::path::Path::new("synapse_data"))? {
+        immune = immune.with_integrity(checker);
+    }
+    let Some(rules) = &config.threat_rules else {
+        return Ok(Arc::new(immune));
+    };
+    let engine = Arc::new(RuleEngine::load(rules).context("Failed to load threat rules")?);
+    engine.watch(RELOAD_INTERVAL);
+    Ok(Arc::new(RuleScanner::new(Arc::new(immune), context_adapter(), engine)))
+}
+
+/// Open the memory store behind embedding validation for `embedder` and
+/// prompt-injection screening.
+async fn guarded_memory(embedder: &Arc<dyn EmbeddingPort>) -> Result<Arc<GuardedMemory>> {
+    let config = Config::load_or_default().await?;
+    let mut detector = InjectionDetector::new().with_threshold(config.injection.threshold);
+    if let Some(descriptor) = &config.injection.classifier {
+        let classifier = OrtInjectionClassifier::from_descriptor(descriptor)
+            .context("Failed to load injection classifier")?;
+        detector = detector.with_classifier(Arc::new(classifier));
+    }
+    let memory = synapse_infra::adapters::surrealdb_adapter::SurrealDbAdapter::new("synapse_data/memory").await?
+        .with_embedding_check(EmbeddingCheck::for_embedder(embedder.as_ref()));
+    let review_key = load_or_create_key(&std::path::Path::new("synapse_data").join(REVIEW_KEY_FILE))
+        .context("Failed to load review key")?;
+    let guarded = GuardedMemory::new(Arc::new(memory), immune_adapter().await?)
+        .with_detector(detector)
+        .with_review_key(review_key);
+    Ok(Arc::new(guarded))
+}
+
+/// Replicate `memory` to the devices configured under `p2p`, if any.
+///
+/// Only long-running commands replicate; what one-shot commands store is
+/// offered to peers the next time a node starts.
+async fn replicated(memory: Arc<dyn MemoryPort>) -> Result<Arc<dyn MemoryPort>> {
+    let config = Config::load_or_default().await?;
+    let Some(p2p) = &config.p2p else {
+        return Ok(memory);
+    };
+    // The CLI scans no processes, so shared threats only matter to the server
+    let threats = Arc::new(ThreatDatabase::new());
+    let immune = Arc::new(BasicImmuneAdapter::with_threat_db(threats.clone()));
+    let replication = p2p
+        .start("synapse_data/p2p", memory, immune, threats)
+        .context("Failed to start P2P node")?;
+    info!("P2P node {} started", replication.handle.local_peer_id());
+    Ok(replication.memory)
+}
 
 /// Initialize a new Synapse database.
 pub async fn init(path: &str) -> Result<()> {
@@ -106,31 +210,42 @@ pub async fn store(content: &str, namespace: &str) -> Result<()> {
 
     // 1. Initialize Embedding Adapter
     println!("🧠 Loading embedding model...");
-    let embedder = synapse_infra::adapters::ort_adapter::OrtAdapter::new()?;
+    let embedder = load_embedder().await
+        .context("Failed to load embedding model")?;
 
-    // 2. Generate Embedding
-    println!("🧮 Generating embedding...");
-    use synapse_core::ports::EmbeddingPort;
-    let embedding = embedder.embed(content).await?;
-
-    // 3. Initialize Memory Adapter (SurrealDB)
-    let memory = synapse_infra::adapters::surrealdb_adapter::SurrealDbAdapter::new("synapse_data/memory").await?;
+    // 2. Initialize Memory Adapter (SurrealDB, screened for prompt injection)
+    let memory = guarded_memory(&embedder).await?;
 
-    // 4. Create MemoryNode
+    // 3. Create MemoryNode
     let node = MemoryNode::new(content.to_string())
-        .with_embedding(embedding.clone())
         .with_namespace(namespace.to_string());
 
-    // 5. Store in SurrealDB
-    use synapse_core::ports::MemoryPort;
-    let node_id = memory.store(node).await
+    // 4. Embed (chunking long text) and store in SurrealDB
+    println!("🧮 Generating embedding...");
+    let tokens = embedder.count_tokens(content);
+    let chunked = synapse_core::logic::chunked_memory::ChunkedMemory::new(memory.clone(), embedder.clone());
+    let node_id = chunked.store(node).await
         .context("Failed to store memory")?;
 
+    let stored = memory.inner().get_by_id(&node_id).await?
+        .context("Stored memory not found")?;
+    if stored.namespace == QUARANTINE_NAMESPACE {
+        println!("⚠️  Memory quarantined as a possible prompt injection");
+        println!("   ID: {}", node_id);
+        return Ok(());
+    }
+    let embedding = &stored.embedding;
     println!("✅ Embedding generated (dim: {})", embedding.len());
     println!("   Vector: [{:.4}, {:.4}, {:.4}, ...]", embedding[0], embedding[1], embedding[2]);
+    if tokens > embedder.max_input_tokens() {
+        println!("   {} tokens, split into {} chunks", tokens,
+                 stored.metadata.get(synapse_core::logic::chunked_memory::CHUNK_IDS_KEY)
+                     .and_then(|ids| ids.as_array())
+                     .map_or(0, |ids| ids.len()));
+    }
     println!("✅ Memory stored in namespace '{}'", namespace);
     println!("   ID: {}", node_id);
-    println!("   Content: {}...", &content[..content.len().min(50)]);
+    println!("   Content: {}...", content.chars().take(50).collect::<String>());
 
     Ok(())
 }
@@ -141,16 +256,16 @@ pub async fn search(query: &str, top_k: usize) -> Result<()> {
     info!("Searching for: {}", query);
 
     // 1. Initialize adapters
-    let embedder = synapse_infra::adapters::ort_adapter::OrtAdapter::new()?;
-    let memory = synapse_infra::adapters::surrealdb_adapter::SurrealDbAdapter::new("synapse_data/memory").await?;
+    let embedder = load_embedder().await
+        .context("Failed to load embedding model")?;
+    let memory = guarded_memory(&embedder).await?;
 
     // 2. Generate query embedding
-    use synapse_core::ports::EmbeddingPort;
     let query_embedding = embedder.embed(query).await?;
 
-    // 3. Search
-    use synapse_core::ports::MemoryPort;
-    let results = memory.search(&query_embedding, top_k)
+    // 3. Search (chunk hits are mapped back to their parent memory)
+    let chunked = synapse_core::logic::chunked_memory::ChunkedMemory::new(memory, embedder);
+    let results = chunked.search(&query_embedding, top_k)
         .await
         .context("Search failed")?;
 
@@ -161,7 +276,7 @@ pub async fn search(query: &str, top_k: usize) -> Result<()> {
         println!("   {}. [Distance: {:.3}] {}",
                  i + 1,
                  result.distance,
-                 &result.node.content[..result.node.content.len().min(60)]);
+                 result.node.content.chars().take(60).collect::<String>());
         println!("      ID: {} | Layer: {} | Namespace: {}",
                  result.node.id,
                  result.node.layer,
@@ -172,6 +287,36 @@ pub async fn search(query: &str, top_k: usize) -> Result<()> {
     Ok(())
 }
 
+/// Scan stored embeddings for corruption and outliers.
+pub async fn scan(reembed: bool) -> Result<()> {
+    let embedder = load_embedder().await
+        .context("Failed to load embedding model")?;
+    let memory = synapse_infra::adapters::surrealdb_adapter::SurrealDbAdapter::new("synapse_data/memory").await?
+        .with_embedding_check(EmbeddingCheck::for_embedder(embedder.as_ref()));
+    let scanner = EmbeddingScanner::new(Arc::new(memory), immune_adapter().await?)
+        .with_embedder(embedder);
+
+    println!("🔬 Scanning embeddings...");
+    let outliers = scanner.scan().await.context("Embedding scan failed")?;
+    if outliers.is_empty() {
+        println!("✅ No anomalous embeddings found");
+        return Ok(());
+    }
+    for outlier in &outliers {
+        match &outlier.issue {
+            Some(issue) => println!("   ❌ {} [{}] {}", outlier.id, outlier.namespace, issue),
+            None => println!("   ⚠️  {} [{}] outlier (score {:.1})", outlier.id, outlier.namespace, outlier.score),
+        }
+    }
+    if reembed {
+        let updated = scanner.reembed(&outliers).await?;
+        println!("🔁 Re-embedded {} of {} flagged memories", updated, outliers.len());
+    } else {
+        println!("   Run with --reembed to recompute them from their content");
+    }
+    Ok(())
+}
+
 /// Show statistics.
 pub async fn stats() -> Result<()> {
     info!("Gathering statistics...");
@@ -196,23 +341,46 @@ pub async fn stats() -> Result<()> {
     Ok(())
 }
 
-/// Interactive chat mode.
-pub async fn chat() -> Result<()> {
+/// Interactive chat mode, grounded in long-term memory.
+pub async fn chat(namespace: Option<&str>, show_memories: bool) -> Result<()> {
     println!("💬 Synapse Chat (interactive mode)");
-    println!("   Type 'exit' to quit\n");
+    println!("   Type 'exit' to quit, Ctrl+C stops a response or quits at the prompt\n");
 
     println!("🧠 Loading LLM...");
-    let llm = synapse_infra::adapters::candle_adapter::CandleAdapter::new()?;
-    use synapse_core::ports::LlmPort;
+    let llm = load_llm().await?;
+    let embedder = load_embedder().await
+        .context("Failed to load embedding model")?;
+    let memory = replicated(guarded_memory(&embedder).await?).await?;
+    let buffer = std::sync::Arc::new(
+        synapse_infra::adapters::sled_adapter::SledAdapter::new("synapse_data/buffer")?
+    );
+
+    let mut session = synapse_core::logic::memory_chat::MemoryChat::new(llm, embedder, memory, buffer);
+    if let Some(namespace) = namespace {
+        session = session.with_namespace(namespace);
+    }
+    tracing::debug!("Chat session {}", session.session_id());
+    use synapse_core::ports::{GenerationParams, TokenEvent};
+    use futures::StreamExt;
 
-    // REPL loop
+    // REPL loop. Once a turn has listened for Ctrl+C, tokio keeps the
+    // SIGINT handler installed, so the idle prompt has to listen too.
     use std::io::{self, Write};
-    let mut input = String::new();
+    use tokio::io::AsyncBufReadExt;
+    let mut lines = tokio::io::BufReader::new(tokio::io::stdin()).lines();
     loop {
         print!("> ");
         io::stdout().flush()?;
-        input.clear();
-        io::stdin().read_line(&mut input)?;
+        let input = tokio::select! {
+            _ = tokio::signal::ctrl_c() => {
+                println!();
+                break;
+            }
+            line = lines.next_line() => match line? {
+                Some(line) => line,
+                None => break,
+            },
+        };
         let prompt = input.trim();
         if prompt == "exit" {
             break;
@@ -221,29 +389,103 @@ pub async fn chat() -> Result<()> {
             continue;
         }
 
-        print!("🤖 Generating...");
+        let turn = session.prepare(prompt).await?;
+        if show_memories {
+            for (i, memory) in turn.memories.iter().enumerate() {
+                println!("   📎 [{}] ({:.3}) {}", i + 1, memory.distance,
+                         memory.node.content.chars().take(60).collect::<String>());
+            }
+        }
+
+        print!("🤖 Synapse: ");
         io::stdout().flush()?;
-        let response = llm.generate(prompt, 200).await?;
-        println!("\r\x1B[2K🤖 Synapse: {}", response);
+        let params = GenerationParams::default().with_max_tokens(200);
+        let mut stream = session.stream(&turn, &params).await?;
+        let cancel = tokio::signal::ctrl_c();
+        tokio::pin!(cancel);
+        let mut reply = String::new();
+        let mut finished = false;
+
+        // Dropping the stream at the end of this block stops generation.
+        loop {
+            tokio::select! {
+                _ = &mut cancel => {
+                    println!("\n   ⏹️  Cancelled");
+                    break;
+                }
+                event = stream.next() => match event {
+                    Some(Ok(TokenEvent::Delta(text))) => {
+                        print!("{}", text);
+                        io::stdout().flush()?;
+                        reply.push_str(&text);
+                    }
+                    Some(Ok(TokenEvent::Done(usage))) => {
+                        println!();
+                        tracing::debug!(
+                            "Generated {} tokens (prompt: {}, finish: {:?})",
+                            usage.completion_tokens, usage.prompt_tokens, usage.finish_reason
+                        );
+                        finished = true;
+                    }
+                    Some(Err(e)) => {
+                        println!("\n   ❌ Generation failed: {}", e);
+                        break;
+                    }
+                    None => break,
+                }
+            }
+        }
+        drop(stream);
+
+        // Only complete replies are remembered.
+        if finished {
+            session.record(prompt, reply.trim()).await?;
+        }
     }
 
     Ok(())
 }
 
 
+/// The context adapter for the current OS.
+fn context_adapter() -> Arc<dyn synapse_core::ports::ContextPort> {
+    #[cfg(target_os = "windows")]
+    let adapter = synapse_infra::adapters::context_adapter::WindowsContextAdapter::new();
+
+    #[cfg(target_os = "linux")]
+    let adapter = synapse_infra::adapters::linux_context_adapter::LinuxContextAdapter::new();
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    let adapter = synapse_infra::adapters::context_adapter::WindowsContextAdapter::new();
+
+    Arc::new(adapter)
+}
+
 /// Test Context Observer.
 pub async fn context() -> Result<()> {
-    use synapse_core::ports::ContextPort;
-
     println!("👁️  Synapse Context Observer");
-    println!("   Monitoring active window... (Press Ctrl+C to stop)");
 
-    #[cfg(target_os = "windows")]
-    let adapter = synapse_infra::adapters::context_adapter::WindowsContextAdapter::new();
+    let adapter = context_adapter();
+
+    let mut processes = adapter.list_processes().await?;
+    println!("   ⚙️  {} processes running", processes.len());
+    processes.sort_by_key(|p| std::cmp::Reverse(p.memory_bytes));
+    for process in processes.iter().take(5).filter(|p| p.memory_bytes > 0) {
+        println!("      {:>7} {:<16} {:>6} MB  {}",
+            process.pid,
+            process.user.as_deref().unwrap_or("?"),
+            process.memory_bytes / (1024 * 1024),
+            process.cmdline.join(" ").chars().take(60).collect::<String>()
+        );
+    }
 
-    #[cfg(not(target_os = "windows"))]
-    let adapter = synapse_infra::adapters::context_adapter::MockContextAdapter::new();
+    #[cfg(target_os = "linux")]
+    if !synapse_infra::adapters::linux_context_adapter::LinuxContextAdapter::has_display() {
+        println!("   ⚠️  No X display (headless); active window tracking unavailable");
+        return Ok(());
+    }
 
+    println!("   Monitoring active window... (Press Ctrl+C to stop)");
     loop {
         match adapter.get_active_window().await {
             Ok(info) => {
@@ -265,6 +507,24 @@ pub async fn context() -> Result<()> {
     }
 }
 
+/// Record the context observer into a replayable timeline.
+pub async fn context_record(path: &std::path::Path, seconds: u64) -> Result<()> {
+    use synapse_infra::adapters::scripted_context_adapter::ContextRecorder;
+
+    let adapter = context_adapter();
+
+    println!("⏺️  Recording context for {}s...", seconds);
+    let recorder = ContextRecorder::new(adapter);
+    recorder
+        .record(std::time::Duration::from_secs(1), std::time::Duration::from_secs(seconds))
+        .await?;
+
+    let timeline = recorder.timeline();
+    timeline.save(path)?;
+    println!("✅ Saved {} events to {}", timeline.events.len(), path.display());
+    Ok(())
+}
+
 /// Run metabolism process.
 pub async fn process() -> Result<()> {
     info!("Starting metabolism process...");
@@ -273,21 +533,21 @@ pub async fn process() -> Result<()> {
     // Buffer (Sled)
     let buffer = synapse_infra::adapters::sled_adapter::SledAdapter::new("synapse_data/buffer")?;
 
-    // Memory (SurrealDB with persistence)
-    let memory = synapse_infra::adapters::surrealdb_adapter::SurrealDbAdapter::new("synapse_data/memory").await?;
-
     // LLM (Candle)
-    let llm = synapse_infra::adapters::candle_adapter::CandleAdapter::new()?;
+    let llm = load_llm().await?;
+
+    // Embedder (ORT or Candle)
+    let embedder = load_embedder().await?;
 
-    // Embedder (ORT)
-    let embedder = synapse_infra::adapters::ort_adapter::OrtAdapter::new()?;
+    // Memory (SurrealDB with persistence), screened like every other write
+    let memory = guarded_memory(&embedder).await?;
 
     // Metabolism Logic
     let metabolism = synapse_core::logic::metabolism::Metabolism::new(
         std::sync::Arc::new(buffer),
-        std::sync::Arc::new(memory),
-        std::sync::Arc::new(llm),
-        std::sync::Arc::new(embedder),
+        memory,
+        llm,
+        embedder,
     );
 
     println!("🔄 Digesting interactions...");
@@ -305,15 +565,9 @@ pub async fn digest(force: bool, consolidate: bool) -> Result<()> {
 
     // Initialize adapters
     let buffer = synapse_infra::adapters::sled_adapter::SledAdapter::new("synapse_data/buffer")?;
-    let memory = std::sync::Arc::new(
-        synapse_infra::adapters::surrealdb_adapter::SurrealDbAdapter::new("synapse_data/memory").await?
-    );
-    let llm = std::sync::Arc::new(
-        synapse_infra::adapters::candle_adapter::CandleAdapter::new()?
-    );
-    let embedder = std::sync::Arc::new(
-        synapse_infra::adapters::ort_adapter::OrtAdapter::new()?
-    );
+    let llm = load_llm().await?;
+    let embedder = load_embedder().await?;
+    let memory = guarded_memory(&embedder).await?;
 
     println!("🧠 Synapse Digest");
     println!("─────────────────");
@@ -440,3 +694,73 @@ pub async fn wallet_status() -> Result<()> {
 
     Ok(())
 }
+
+/// Check the executable and model files against the signed integrity manifest.
+pub async fn verify() -> Result<()> {
+    use synapse_infra::adapters::integrity::{parse_public_key, IntegrityChecker};
+
+    let config = Config::load_or_default().await?.integrity;
+    let key = config.trusted_key().context(
+        "No trusted integrity key; set integrity.public_key in synapse.json or run `synapse verify --sign <key-file>`",
+    )?;
+    let manifest = config.manifest_path(std::path::Path::new("synapse_data"));
+    let checker = IntegrityChecker::new(&manifest, &config.models_dir, parse_public_key(key)?)?;
+
+    println!("🛡️  Verifying installation against {}", manifest.display());
+    let issues = tokio::task::spawn_blocking(move || checker.verify()).await??;
+    if issues.is_empty() {
+        println!("✅ Executable and models match the signed manifest");
+        return Ok(());
+    }
+
+    for issue in &issues {
+        println!("   ❌ {}", issue);
+    }
+    anyhow::bail!("Integrity check failed ({} issue(s))", issues.len())
+}
+
+/// Hash the installed executables and models and sign a fresh manifest
+/// (install time).
+///
+/// A new signing key is written to `key_file` if it does not exist yet.
+pub async fn verify_sign(key_file: &std::path::Path) -> Result<()> {
+    use synapse_infra::adapters::integrity::{
+        generate_signing_key, installed_executables, parse_signing_key, IntegrityManifest, INSTALLED_BINARIES,
+    };
+    use synapse_infra::adapters::private_file::write_private;
+
+    let config = Config::load_or_default().await?.integrity;
+    let key = if key_file.exists() {
+        parse_signing_key(&std::fs::read_to_string(key_file).context("Failed to read signing key")?)?
+    } else {
+        let key = generate_signing_key();
+        write_private(key_file, hex::encode(key.to_bytes()).as_bytes())
+            .context("Failed to create signing key file")?;
+        println!("🔑 Generated signing key at {} (keep it off this machine)", key_file.display());
+        key
+    };
+
+    let public_key = key.verifying_key();
+    let executable = std::env::current_exe().context("Cannot locate the running executable")?;
+    let executables = installed_executables(&executable);
+    for executable in &executables {
+        println!("   Signing {}", executable.display());
+    }
+    if executables.len() < INSTALLED_BINARIES.len() {
+        println!("   ⚠️  Not all of {} are next to this executable; the others will fail verification",
+                 INSTALLED_BINARIES.join(", "));
+    }
+    let models_dir = config.models_dir.clone();
+    let manifest = tokio::task::spawn_blocking(move || IntegrityManifest::generate(&executables, &models_dir, &key))
+        .await??;
+    let manifest_path = config.manifest_path(std::path::Path::new("synapse_data"));
+    if let Some(parent) = manifest_path.parent() {
+        std::fs::create_dir_all(parent).context("Failed to create manifest directory")?;
+    }
+    manifest.save(&manifest_path)?;
+
+    println!("✅ Signed {} files into {}", manifest.files.len(), manifest_path.display());
+    println!("   Public key: {}", hex::encode(public_key.as_bytes()));
+    println!("   Set it as integrity.public_key in synapse.json, or build with SYNAPSE_INTEGRITY_PUBLIC_KEY");
+    Ok(())
+}
@@ -3,6 +3,10 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
+use synapse_infra::adapters::integrity::IntegrityConfig;
+use synapse_infra::adapters::model_descriptor::ModelDescriptor;
+use synapse_infra::adapters::openai_adapter::OpenAiConfig;
+use synapse_p2p::P2pSettings;
 use tokio::fs;
 
 /// Synapse configuration.
@@ -22,6 +26,59 @@ pub struct Config {
 
     /// Default namespace
     pub default_namespace: String,
+
+    /// Chat/summarization model
+    #[serde(default = "ModelDescriptor::tinyllama")]
+    pub llm: ModelDescriptor,
+
+    /// Embedding model
+    #[serde(default = "ModelDescriptor::minilm")]
+    pub embedding: ModelDescriptor,
+
+    /// OpenAI-compatible server to use instead of the local LLM
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub llm_endpoint: Option<OpenAiConfig>,
+
+    /// OpenAI-compatible server to use instead of the local embedding model
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_endpoint: Option<OpenAiConfig>,
+
+    /// Self-integrity verification
+    #[serde(default)]
+    pub integrity: IntegrityConfig,
+
+    /// Prompt-injection screening of stored and retrieved memories
+    #[serde(default)]
+    pub injection: InjectionConfig,
+
+    /// Threat rule file or directory, reloaded when it changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threat_rules: Option<PathBuf>,
+
+    /// Replication to the user's other devices
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub p2p: Option<P2pSettings>,
+}
+
+/// How memories are screened for prompt injection.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InjectionConfig {
+    /// Score at or above which a memory is quarantined
+    pub threshold: f32,
+
+    /// ONNX sequence-classification model to use alongside the heuristics
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classifier: Option<ModelDescriptor>,
+}
+
+impl Default for InjectionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: synapse_immune::injection::DEFAULT_THRESHOLD,
+            classifier: None,
+        }
+    }
 }
 
 impl Default for Config {
@@ -32,6 +89,14 @@ impl Default for Config {
             embedding_dim: 384,
             ethical_threshold: 0.95,
             default_namespace: "default".to_string(),
+            llm: ModelDescriptor::tinyllama(),
+            embedding: ModelDescriptor::minilm(),
+            llm_endpoint: None,
+            embedding_endpoint: None,
+            integrity: IntegrityConfig::default(),
+            injection: InjectionConfig::default(),
+            threat_rules: None,
+            p2p: None,
         }
     }
 }
@@ -49,6 +114,16 @@ impl Config {
         Ok(config)
     }
 
+    /// Load configuration from the default path, or defaults if it does not exist.
+    pub async fn load_or_default() -> Result<Self> {
+        let path = Self::default_path();
+        if fs::try_exists(&path).await.unwrap_or(false) {
+            Self::load(&path).await
+        } else {
+            Ok(Self::default())
+        }
+    }
+
     /// Save configuration to file.
     pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
         let json = serde_json::to_string_pretty(self)
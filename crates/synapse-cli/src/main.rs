@@ -50,11 +50,34 @@ enum Commands {
     /// Show statistics
     Stats,
 
+    /// Scan stored embeddings for corruption and outliers
+    Scan {
+        /// Recompute flagged embeddings from their content
+        #[arg(long)]
+        reembed: bool,
+    },
+
     /// Chat with the AI (interactive mode)
-    Chat,
+    Chat {
+        /// Only recall memories from this namespace
+        #[arg(short, long)]
+        namespace: Option<String>,
+
+        /// Show which memories were used for each reply
+        #[arg(short, long)]
+        show_memories: bool,
+    },
 
     /// Test Context Observer (Active Window)
-    Context,
+    Context {
+        /// Record the session to a timeline file (.json or .yaml) for later replay
+        #[arg(long, value_name = "FILE")]
+        record: Option<std::path::PathBuf>,
+
+        /// How long to record, in seconds
+        #[arg(long, default_value = "60")]
+        duration: u64,
+    },
 
     /// Run Metabolism Process (Digest Buffer)
     Process,
@@ -73,6 +96,13 @@ enum Commands {
     /// Test Sensory Capabilities (Camera/Mic)
     Senses,
 
+    /// Verify the executable and models against the signed integrity manifest
+    Verify {
+        /// Generate and sign a new manifest with this key file instead (install time)
+        #[arg(long, value_name = "KEY_FILE")]
+        sign: Option<std::path::PathBuf>,
+    },
+
     /// Manage Wallet & Tokenomics
     Wallet {
         #[command(subcommand)]
@@ -118,12 +148,16 @@ async fn main() -> anyhow::Result<()> {
         Commands::Stats => {
             commands::stats().await?;
         }
-        Commands::Chat => {
-            commands::chat().await?;
+        Commands::Scan { reembed } => {
+            commands::scan(reembed).await?;
         }
-        Commands::Context => {
-            commands::context().await?;
+        Commands::Chat { namespace, show_memories } => {
+            commands::chat(namespace.as_deref(), show_memories).await?;
         }
+        Commands::Context { record, duration } => match record {
+            Some(path) => commands::context_record(&path, duration).await?,
+            None => commands::context().await?,
+        },
         Commands::Process => {
             commands::process().await?;
         }
@@ -133,6 +167,10 @@ async fn main() -> anyhow::Result<()> {
         Commands::Senses => {
             commands::senses().await?;
         }
+        Commands::Verify { sign } => match sign {
+            Some(key_file) => commands::verify_sign(&key_file).await?,
+            None => commands::verify().await?,
+        },
         Commands::Wallet { action } => {
             match action {
                 WalletCommands::Balance => commands::wallet_balance().await?,
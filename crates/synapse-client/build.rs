@@ -0,0 +1,7 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Client stubs only, so consumers need neither the server nor synapse-core.
+    tonic_build::configure()
+        .build_server(false)
+        .compile_protos(&["../../proto/synapse/v1/synapse.proto"], &["../../proto"])?;
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! # Synapse Client
+//!
+//! Typed gRPC client for a Synapse node, generated from
+//! `proto/synapse/v1/synapse.proto`.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let client = synapse_client::SynapseClient::connect("http://127.0.0.1:7879")
+//!     .await?
+//!     .with_api_key("change-me")?;
+//! let id = client.store("Rex is a dog", Some("pets")).await?;
+//! for hit in client.search("dogs", 5).await? {
+//!     println!("{:.3} {}", hit.distance, hit.memory.map(|m| m.content).unwrap_or_default());
+//! }
+//! # let _ = id;
+//! # Ok(())
+//! # }
+//! ```
+
+/// Generated protobuf messages and service clients.
+pub mod pb {
+    tonic::include_proto!("synapse.v1");
+}
+
+use pb::buffer_service_client::BufferServiceClient;
+use pb::lifecycle_service_client::LifecycleServiceClient;
+use pb::memory_service_client::MemoryServiceClient;
+use tonic::metadata::errors::InvalidMetadataValue;
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::service::interceptor::InterceptedService;
+use tonic::service::Interceptor;
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Request, Status};
+
+/// Metadata key carrying the API key.
+pub const API_KEY_METADATA: &str = "x-api-key";
+
+/// Adds the API key (if any) to every call.
+#[derive(Debug, Clone, Default)]
+pub struct ApiKey(Option<MetadataValue<Ascii>>);
+
+impl Interceptor for ApiKey {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        if let Some(key) = &self.0 {
+            request.metadata_mut().insert(API_KEY_METADATA, key.clone());
+        }
+        Ok(request)
+    }
+}
+
+/// Authenticated channel type used by the generated clients.
+pub type AuthChannel = InterceptedService<Channel, ApiKey>;
+
+/// Client for all Synapse services over one connection.
+#[derive(Debug, Clone)]
+pub struct SynapseClient {
+    channel: Channel,
+    api_key: ApiKey,
+}
+
+impl SynapseClient {
+    /// Connect to a node, e.g. `http://127.0.0.1:7879`.
+    pub async fn connect(endpoint: impl Into<String>) -> Result<Self, tonic::transport::Error> {
+        let channel = Endpoint::new(endpoint.into())?.connect().await?;
+        Ok(Self::from_channel(channel))
+    }
+
+    /// Use an existing channel.
+    pub fn from_channel(channel: Channel) -> Self {
+        Self {
+            channel,
+            api_key: ApiKey::default(),
+        }
+    }
+
+    /// Send `api_key` with every call.
+    pub fn with_api_key(mut self, api_key: &str) -> Result<Self, InvalidMetadataValue> {
+        self.api_key = ApiKey(Some(api_key.parse()?));
+        Ok(self)
+    }
+
+    /// Generated memory service client.
+    pub fn memory(&self) -> MemoryServiceClient<AuthChannel> {
+        MemoryServiceClient::with_interceptor(self.channel.clone(), self.api_key.clone())
+    }
+
+    /// Generated buffer service client.
+    pub fn buffer(&self) -> BufferServiceClient<AuthChannel> {
+        BufferServiceClient::with_interceptor(self.channel.clone(), self.api_key.clone())
+    }
+
+    /// Generated lifecycle service client.
+    pub fn lifecycle(&self) -> LifecycleServiceClient<AuthChannel> {
+        LifecycleServiceClient::with_interceptor(self.channel.clone(), self.api_key.clone())
+    }
+
+    /// Store a memory and return its ID.
+    pub async fn store(&self, content: &str, namespace: Option<&str>) -> Result<String, Status> {
+        let request = pb::StoreRequest {
+            content: content.to_string(),
+            namespace: namespace.map(str::to_string),
+            ..Default::default()
+        };
+        Ok(self.memory().store(request).await?.into_inner().id)
+    }
+
+    /// Search memories, closest first.
+    pub async fn search(&self, query: &str, top_k: u32) -> Result<Vec<pb::SearchHit>, Status> {
+        let request = pb::SearchRequest {
+            query: query.to_string(),
+            top_k,
+            namespace: None,
+        };
+        Ok(self.memory().search(request).await?.into_inner().hits)
+    }
+
+    /// Fetch a memory by ID.
+    pub async fn get(&self, id: &str) -> Result<pb::Memory, Status> {
+        let request = pb::GetRequest { id: id.to_string() };
+        Ok(self.memory().get(request).await?.into_inner())
+    }
+
+    /// Delete a memory and its chunks.
+    pub async fn delete(&self, id: &str) -> Result<(), Status> {
+        let request = pb::DeleteRequest { id: id.to_string() };
+        self.memory().delete(request).await?;
+        Ok(())
+    }
+
+    /// Queue an exchange in the short-term buffer.
+    pub async fn push(&self, user_input: &str, ai_response: &str) -> Result<pb::PushResponse, Status> {
+        let request = pb::PushRequest {
+            user_input: user_input.to_string(),
+            ai_response: ai_response.to_string(),
+            session_id: None,
+        };
+        Ok(self.buffer().push(request).await?.into_inner())
+    }
+
+    /// Digest the buffer; returns the number of exchanges digested.
+    pub async fn digest(&self, force: bool) -> Result<u64, Status> {
+        let request = pb::DigestRequest { force };
+        Ok(self.lifecycle().digest(request).await?.into_inner().digested)
+    }
+
+    /// Memory and buffer counts.
+    pub async fn stats(&self) -> Result<pb::StatsResponse, Status> {
+        Ok(self.lifecycle().stats(pb::StatsRequest {}).await?.into_inner())
+    }
+}
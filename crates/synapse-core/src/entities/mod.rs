@@ -6,8 +6,10 @@ pub mod interaction;
 pub mod node_type;
 pub mod proof_of_sentience;
 pub mod wallet;
+pub mod chat_message;
 
 pub use memory_node::*;
 pub use genesis_block::*;
 pub use interaction::*;
 pub use node_type::*;
+pub use chat_message::*;
@@ -0,0 +1,241 @@
+//! Chat Templates - Render chat messages into model-specific prompts.
+//!
+//! Every instruction-tuned model expects its own turn markers. This module
+//! keeps a registry of the common formats so adapters can pick one per model,
+//! either by name (from configuration) or by sniffing the Jinja template
+//! shipped in GGUF `tokenizer.chat_template` metadata.
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+use crate::error::{Error, Result};
+use crate::{ChatMessage, ChatRole};
+
+/// Known chat prompt formats.
+///
+/// Rendered prompts never include the leading BOS token; tokenizers add it
+/// when encoding with special tokens enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatTemplate {
+    /// `<|im_start|>role ... <|im_end|>` (Qwen, OpenHermes, many fine-tunes)
+    ChatMl,
+    /// `[INST] <<SYS>> ... <</SYS>> ... [/INST]` (Llama-2, Mistral)
+    Llama2,
+    /// `<|start_header_id|>role<|end_header_id|> ... <|eot_id|>` (Llama-3)
+    Llama3,
+    /// `<|user|> ... <|end|>` (Phi-3)
+    Phi3,
+    /// `<|user|> ... </s>` (Zephyr, TinyLlama-Chat)
+    #[default]
+    Zephyr,
+    /// `User: ...` transcript for models and services without turn markers
+    Plain,
+}
+
+impl ChatTemplate {
+    /// All registered templates.
+    pub const ALL: [ChatTemplate; 6] = [
+        ChatTemplate::ChatMl,
+        ChatTemplate::Llama2,
+        ChatTemplate::Llama3,
+        ChatTemplate::Phi3,
+        ChatTemplate::Zephyr,
+        ChatTemplate::Plain,
+    ];
+
+    /// Registry name, as used in configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChatTemplate::ChatMl => "chatml",
+            ChatTemplate::Llama2 => "llama2",
+            ChatTemplate::Llama3 => "llama3",
+            ChatTemplate::Phi3 => "phi3",
+            ChatTemplate::Zephyr => "zephyr",
+            ChatTemplate::Plain => "plain",
+        }
+    }
+
+    /// Guess the template from a Jinja chat template (e.g. GGUF
+    /// `tokenizer.chat_template` metadata) by looking for its turn markers.
+    pub fn detect(jinja: &str) -> Option<Self> {
+        if jinja.contains("<|im_start|>") {
+            Some(ChatTemplate::ChatMl)
+        } else if jinja.contains("<|start_header_id|>") {
+            Some(ChatTemplate::Llama3)
+        } else if jinja.contains("[INST]") {
+            Some(ChatTemplate::Llama2)
+        } else if jinja.contains("<|assistant|>") && jinja.contains("<|end|>") {
+            Some(ChatTemplate::Phi3)
+        } else if jinja.contains("<|assistant|>") {
+            Some(ChatTemplate::Zephyr)
+        } else {
+            None
+        }
+    }
+
+    /// Special tokens that end an assistant turn.
+    pub fn stop_tokens(&self) -> &'static [&'static str] {
+        match self {
+            ChatTemplate::ChatMl => &["<|im_end|>"],
+            ChatTemplate::Llama2 => &["</s>"],
+            ChatTemplate::Llama3 => &["<|eot_id|>", "<|end_of_text|>"],
+            ChatTemplate::Phi3 => &["<|end|>", "<|endoftext|>"],
+            ChatTemplate::Zephyr => &["</s>"],
+            ChatTemplate::Plain => &["\nUser:"],
+        }
+    }
+
+    /// Render messages into a prompt that ends with an open assistant turn.
+    pub fn render(&self, messages: &[ChatMessage]) -> String {
+        let mut out = String::new();
+        match self {
+            ChatTemplate::ChatMl => {
+                for m in messages {
+                    out.push_str(&format!("<|im_start|>{}\n{}<|im_end|>\n", m.role, m.content));
+                }
+                out.push_str("<|im_start|>assistant\n");
+            }
+            ChatTemplate::Llama2 => {
+                // Llama-2 has no system turn: the system prompt is folded into
+                // the first user instruction.
+                let mut system: Option<&str> = None;
+                let mut first = true;
+                for m in messages {
+                    match m.role {
+                        ChatRole::System => system = Some(&m.content),
+                        ChatRole::User => {
+                            if !first {
+                                out.push_str("<s>");
+                            }
+                            out.push_str("[INST] ");
+                            if let Some(sys) = system.take() {
+                                out.push_str(&format!("<<SYS>>\n{}\n<</SYS>>\n\n", sys));
+                            }
+                            out.push_str(&format!("{} [/INST]", m.content));
+                            first = false;
+                        }
+                        ChatRole::Assistant => {
+                            out.push_str(&format!(" {} </s>", m.content));
+                        }
+                    }
+                }
+            }
+            ChatTemplate::Llama3 => {
+                for m in messages {
+                    out.push_str(&format!(
+                        "<|start_header_id|>{}<|end_header_id|>\n\n{}<|eot_id|>",
+                        m.role, m.content
+                    ));
+                }
+                out.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+            }
+            ChatTemplate::Phi3 => {
+                for m in messages {
+                    out.push_str(&format!("<|{}|>\n{}<|end|>\n", m.role, m.content));
+                }
+                out.push_str("<|assistant|>\n");
+            }
+            ChatTemplate::Zephyr => {
+                for m in messages {
+                    out.push_str(&format!("<|{}|>\n{}</s>\n", m.role, m.content));
+                }
+                out.push_str("<|assistant|>\n");
+            }
+            ChatTemplate::Plain => {
+                for m in messages {
+                    let label = match m.role {
+                        ChatRole::System => "System",
+                        ChatRole::User => "User",
+                        ChatRole::Assistant => "Assistant",
+                    };
+                    out.push_str(&format!("{}: {}\n", label, m.content));
+                }
+                out.push_str("Assistant:");
+            }
+        }
+        out
+    }
+}
+
+impl FromStr for ChatTemplate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let wanted = s.trim().to_ascii_lowercase().replace(['-', '_'], "");
+        ChatTemplate::ALL
+            .into_iter()
+            .find(|t| t.name() == wanted)
+            .ok_or_else(|| Error::Validation {
+                message: format!(
+                    "Unknown chat template '{}' (known: {})",
+                    s,
+                    ChatTemplate::ALL.map(|t| t.name()).join(", ")
+                ),
+            })
+    }
+}
+
+impl std::fmt::Display for ChatTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn conversation() -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::system("Be brief."),
+            ChatMessage::user("Hi"),
+            ChatMessage::assistant("Hello!"),
+            ChatMessage::user("Who are you?"),
+        ]
+    }
+
+    #[test]
+    fn test_render_chatml() {
+        let prompt = ChatTemplate::ChatMl.render(&conversation());
+        assert!(prompt.starts_with("<|im_start|>system\nBe brief.<|im_end|>\n"));
+        assert!(prompt.contains("<|im_start|>assistant\nHello!<|im_end|>\n"));
+        assert!(prompt.ends_with("<|im_start|>user\nWho are you?<|im_end|>\n<|im_start|>assistant\n"));
+    }
+
+    #[test]
+    fn test_render_llama2_folds_system_prompt() {
+        let prompt = ChatTemplate::Llama2.render(&conversation());
+        assert_eq!(
+            prompt,
+            "[INST] <<SYS>>\nBe brief.\n<</SYS>>\n\nHi [/INST] Hello! </s><s>[INST] Who are you? [/INST]"
+        );
+    }
+
+    #[test]
+    fn test_render_zephyr_matches_tinyllama_format() {
+        let prompt = ChatTemplate::Zephyr.render(&[ChatMessage::user("Hello")]);
+        assert_eq!(prompt, "<|user|>\nHello</s>\n<|assistant|>\n");
+    }
+
+    #[test]
+    fn test_detect_from_jinja() {
+        let qwen = "{% for message in messages %}{{'<|im_start|>' + message['role'] }}{% endfor %}";
+        let llama3 = "{{ '<|start_header_id|>' + message['role'] + '<|end_header_id|>' }}";
+        let phi3 = "{{'<|user|>' + '\n' + message['content'] + '<|end|>'}}{{'<|assistant|>'}}";
+        let zephyr = "{{ '<|user|>\n' + message['content'] + eos_token }}{{ '<|assistant|>' }}";
+
+        assert_eq!(ChatTemplate::detect(qwen), Some(ChatTemplate::ChatMl));
+        assert_eq!(ChatTemplate::detect(llama3), Some(ChatTemplate::Llama3));
+        assert_eq!(ChatTemplate::detect(phi3), Some(ChatTemplate::Phi3));
+        assert_eq!(ChatTemplate::detect(zephyr), Some(ChatTemplate::Zephyr));
+        assert_eq!(ChatTemplate::detect("{{ messages }}"), None);
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("ChatML".parse::<ChatTemplate>().unwrap(), ChatTemplate::ChatMl);
+        assert_eq!("llama-3".parse::<ChatTemplate>().unwrap(), ChatTemplate::Llama3);
+        assert!("vicuna".parse::<ChatTemplate>().is_err());
+    }
+}
@@ -0,0 +1,370 @@
+//! Chunked Memory - Store long text as a parent node with chunk children.
+//!
+//! Text that fits the embedder's input limit is stored as a single node.
+//! Longer text is split with [`TextChunker`]: the parent node keeps the full
+//! content (embedded as the mean of its chunks) and every chunk is stored as
+//! a child node linked by a `chunk_of` relationship. Searches map chunk hits
+//! back to their parent so callers always see whole memories; chunks whose
+//! parent is gone (or withheld by the store, e.g. quarantined) are dropped.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::Result;
+use crate::logic::chunking::TextChunker;
+use crate::ports::{EmbeddingPort, MemoryPort, SearchResult};
+use crate::MemoryNode;
+
+/// Metadata key on chunk nodes holding the parent node ID.
+pub const PARENT_ID_KEY: &str = "parent_id";
+/// Metadata key on chunk nodes holding the chunk position.
+pub const CHUNK_INDEX_KEY: &str = "chunk_index";
+/// Metadata key on parent nodes listing their chunk node IDs.
+pub const CHUNK_IDS_KEY: &str = "chunk_ids";
+/// Relationship from a chunk node to its parent.
+pub const CHUNK_OF_RELATION: &str = "chunk_of";
+
+/// Memory service that chunks long text before embedding.
+pub struct ChunkedMemory {
+    memory: Arc<dyn MemoryPort>,
+    embedder: Arc<dyn EmbeddingPort>,
+    overlap_tokens: usize,
+}
+
+impl ChunkedMemory {
+    /// Create a new ChunkedMemory service.
+    pub fn new(memory: Arc<dyn MemoryPort>, embedder: Arc<dyn EmbeddingPort>) -> Self {
+        Self {
+            memory,
+            embedder,
+            overlap_tokens: 32,
+        }
+    }
+
+    /// Set the token overlap between consecutive chunks.
+    pub fn with_overlap(mut self, overlap_tokens: usize) -> Self {
+        self.overlap_tokens = overlap_tokens;
+        self
+    }
+
+    /// Embed and store `node`, chunking its content if it exceeds the
+    /// embedder's input limit. Returns the ID of the (parent) node.
+    pub async fn store(&self, mut node: MemoryNode) -> Result<String> {
+        let max_tokens = self.embedder.max_input_tokens();
+        if self.embedder.count_tokens(&node.content) <= max_tokens {
+            node.embedding = self.embedder.embed(&node.content).await?;
+            return self.memory.store(node).await;
+        }
+
+        let chunker = TextChunker::new(max_tokens, self.overlap_tokens);
+        let chunks = chunker.chunk(&node.content, |s| self.embedder.count_tokens(s));
+        let texts: Vec<String> = chunks.iter().map(|c| c.text.clone()).collect();
+        let embeddings = self.embedder.embed_batch(&texts).await?;
+
+        let children: Vec<MemoryNode> = chunks
+            .iter()
+            .zip(embeddings)
+            .map(|(chunk, embedding)| {
+                let mut child = MemoryNode::with_layer(chunk.text.clone(), node.layer)
+                    .with_embedding(embedding)
+                    .with_namespace(node.namespace.clone())
+                    .with_metadata(PARENT_ID_KEY, serde_json::json!(node.id))
+                    .with_metadata(CHUNK_INDEX_KEY, serde_json::json!(chunk.index));
+                child.node_type = node.node_type;
+                child.source = node.source.clone();
+                child
+            })
+            .collect();
+
+        node.embedding = mean_embedding(children.iter().map(|c| c.embedding.as_slice()));
+        node.metadata.insert(
+            CHUNK_IDS_KEY.to_string(),
+            serde_json::json!(children.iter().map(|c| c.id.clone()).collect::<Vec<_>>()),
+        );
+        let parent_id = self.memory.store(node).await?;
+
+        for child in children {
+            let child_id = self.memory.store(child).await?;
+            self.memory.add_relationship(&child_id, CHUNK_OF_RELATION, &parent_id).await?;
+        }
+        Ok(parent_id)
+    }
+
+    /// Search all memories, returning whole (parent) nodes.
+    pub async fn search(&self, embedding: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        let hits = self.memory.search(embedding, oversample(top_k)).await?;
+        self.resolve_parents(hits, top_k).await
+    }
+
+    /// Search one namespace, returning whole (parent) nodes.
+    pub async fn search_namespace(&self, embedding: &[f32], namespace: &str, top_k: usize) -> Result<Vec<SearchResult>> {
+        let hits = self.memory.search_namespace(embedding, namespace, oversample(top_k)).await?;
+        self.resolve_parents(hits, top_k).await
+    }
+
+    /// Delete a node and its chunks.
+    pub async fn delete(&self, id: &str) -> Result<()> {
+        if let Some(node) = self.memory.get_by_id(id).await? {
+            for chunk_id in chunk_ids(&node) {
+                self.memory.delete(&chunk_id).await?;
+            }
+        }
+        self.memory.delete(id).await
+    }
+
+    /// Replace chunk hits with their parents, keeping each parent's best
+    /// distance and dropping duplicates and chunks without a parent.
+    async fn resolve_parents(&self, hits: Vec<SearchResult>, top_k: usize) -> Result<Vec<SearchResult>> {
+        let mut results: Vec<SearchResult> = Vec::new();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+
+        for hit in hits {
+            let node = match hit.node.metadata.get(PARENT_ID_KEY).and_then(|v| v.as_str()) {
+                Some(parent_id) => match self.memory.get_by_id(parent_id).await? {
+                    Some(parent) => parent,
+                    // A chunk must not stand in for a parent the store withholds
+                    None => continue,
+                },
+                None => hit.node,
+            };
+
+            match seen.get(&node.id) {
+                Some(&i) => {
+                    if hit.distance < results[i].distance {
+                        results[i].distance = hit.distance;
+                    }
+                }
+                None => {
+                    seen.insert(node.id.clone(), results.len());
+                    results.push(SearchResult { node, distance: hit.distance });
+                }
+            }
+        }
+
+        results.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal));
+        results.truncate(top_k);
+        Ok(results)
+    }
+}
+
+/// Whether `node` is a chunk of a larger memory.
+pub fn is_chunk(node: &MemoryNode) -> bool {
+    node.metadata.contains_key(PARENT_ID_KEY)
+}
+
+/// IDs of the chunk nodes of a chunked memory (empty for other nodes).
+pub fn chunk_ids(node: &MemoryNode) -> Vec<String> {
+    node.metadata
+        .get(CHUNK_IDS_KEY)
+        .and_then(|v| v.as_array())
+        .map(|ids| ids.iter().filter_map(|id| id.as_str().map(str::to_string)).collect())
+        .unwrap_or_default()
+}
+
+/// Chunks of one memory can crowd the raw results, so fetch extra.
+fn oversample(top_k: usize) -> usize {
+    top_k.saturating_mul(3).max(top_k)
+}
+
+/// L2-normalized mean of a set of embeddings.
+pub fn mean_embedding<'a>(embeddings: impl Iterator<Item = &'a [f32]>) -> Vec<f32> {
+    let mut sum: Vec<f32> = Vec::new();
+    for embedding in embeddings {
+        if sum.is_empty() {
+            sum = vec![0.0; embedding.len()];
+        }
+        for (acc, x) in sum.iter_mut().zip(embedding) {
+            *acc += x;
+        }
+    }
+    let norm: f32 = sum.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        sum.iter_mut().for_each(|x| *x /= norm);
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use tokio::sync::Mutex;
+
+    // === Mock Memory: distance = position of the query word in the content ===
+    struct MockMemory {
+        nodes: Mutex<Vec<MemoryNode>>,
+        relationships: Mutex<Vec<(String, String, String)>>,
+    }
+
+    impl MockMemory {
+        fn new() -> Self {
+            Self {
+                nodes: Mutex::new(Vec::new()),
+                relationships: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl MemoryPort for MockMemory {
+        async fn store(&self, node: MemoryNode) -> Result<String> {
+            let id = node.id.clone();
+            self.nodes.lock().await.push(node);
+            Ok(id)
+        }
+
+        async fn search(&self, embedding: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+            // Nodes whose embedding shares the query's hot dimension match.
+            let hot = embedding.iter().position(|&x| x > 0.5).unwrap_or(0);
+            let mut results: Vec<SearchResult> = self.nodes.lock().await
+                .iter()
+                .filter(|n| n.embedding.get(hot).is_some_and(|&x| x > 0.0))
+                .map(|n| SearchResult { node: n.clone(), distance: 1.0 - n.embedding[hot] })
+                .collect();
+            results.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+            results.truncate(top_k);
+            Ok(results)
+        }
+
+        async fn search_layer(&self, embedding: &[f32], _layer: u8, top_k: usize) -> Result<Vec<SearchResult>> {
+            self.search(embedding, top_k).await
+        }
+
+        async fn search_namespace(&self, embedding: &[f32], _namespace: &str, top_k: usize) -> Result<Vec<SearchResult>> {
+            self.search(embedding, top_k).await
+        }
+
+        async fn get_by_id(&self, id: &str) -> Result<Option<MemoryNode>> {
+            Ok(self.nodes.lock().await.iter().find(|n| n.id == id).cloned())
+        }
+
+        async fn get_by_layer(&self, layer: u8) -> Result<Vec<MemoryNode>> {
+            Ok(self.nodes.lock().await.iter().filter(|n| n.layer == layer).cloned().collect())
+        }
+
+        async fn update(&self, _node: MemoryNode) -> Result<()> {
+            Ok(())
+        }
+
+        async fn delete(&self, id: &str) -> Result<()> {
+            self.nodes.lock().await.retain(|n| n.id != id);
+            Ok(())
+        }
+
+        async fn count(&self) -> Result<usize> {
+            Ok(self.nodes.lock().await.len())
+        }
+
+        async fn add_relationship(&self, from_id: &str, relation: &str, to_id: &str) -> Result<()> {
+            self.relationships.lock().await.push((from_id.to_string(), relation.to_string(), to_id.to_string()));
+            Ok(())
+        }
+
+        async fn count_by_layer(&self, layer: u8) -> Result<usize> {
+            Ok(self.nodes.lock().await.iter().filter(|n| n.layer == layer).count())
+        }
+    }
+
+    // === Mock Embedder: one dimension per keyword, 6 tokens max ===
+    const KEYWORDS: [&str; 3] = ["apple", "banana", "cherry"];
+
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl EmbeddingPort for KeywordEmbedder {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            Ok(KEYWORDS.iter().map(|k| if text.contains(k) { 1.0 } else { 0.0 }).collect())
+        }
+
+        fn count_tokens(&self, text: &str) -> usize {
+            text.split_whitespace().count()
+        }
+
+        fn max_input_tokens(&self) -> usize {
+            6
+        }
+
+        fn dimension(&self) -> usize {
+            KEYWORDS.len()
+        }
+
+        fn provider_name(&self) -> &str {
+            "keyword"
+        }
+    }
+
+    fn service(memory: Arc<MockMemory>) -> ChunkedMemory {
+        ChunkedMemory::new(memory, Arc::new(KeywordEmbedder)).with_overlap(0)
+    }
+
+    #[tokio::test]
+    async fn test_short_text_is_stored_whole() {
+        let memory = Arc::new(MockMemory::new());
+        service(memory.clone()).store(MemoryNode::new("I like apple pie.".into())).await.unwrap();
+
+        let nodes = memory.nodes.lock().await;
+        assert_eq!(nodes.len(), 1);
+        assert!(!is_chunk(&nodes[0]));
+        assert_eq!(nodes[0].embedding, vec![1.0, 0.0, 0.0]);
+    }
+
+    #[tokio::test]
+    async fn test_long_text_stores_parent_and_linked_chunks() {
+        let memory = Arc::new(MockMemory::new());
+        let text = "An apple a day is good. Bananas are yellow, a banana is sweet. The cherry is red and small.";
+        let parent_id = service(memory.clone()).store(MemoryNode::new(text.into())).await.unwrap();
+
+        let nodes = memory.nodes.lock().await;
+        let parent = nodes.iter().find(|n| n.id == parent_id).unwrap();
+        let chunks: Vec<&MemoryNode> = nodes.iter().filter(|n| is_chunk(n)).collect();
+
+        assert_eq!(parent.content, text);
+        assert_eq!(chunk_ids(parent).len(), chunks.len());
+        assert!(chunks.len() >= 3);
+        for chunk in &chunks {
+            assert_eq!(chunk.metadata[PARENT_ID_KEY], serde_json::json!(parent_id));
+            assert!(chunk.content.split_whitespace().count() <= 6);
+        }
+        let relationships = memory.relationships.lock().await;
+        assert_eq!(relationships.len(), chunks.len());
+        assert!(relationships.iter().all(|(_, rel, to)| rel == CHUNK_OF_RELATION && to == &parent_id));
+    }
+
+    #[tokio::test]
+    async fn test_chunk_hits_map_back_to_parent() {
+        let memory = Arc::new(MockMemory::new());
+        let service = service(memory.clone());
+        let text = "An apple a day is good. Nothing to see in this part. The cherry is red and small.";
+        let parent_id = service.store(MemoryNode::new(text.into())).await.unwrap();
+        service.store(MemoryNode::new("A cherry tart.".into())).await.unwrap();
+
+        let results = service.search(&[0.0, 0.0, 1.0], 5).await.unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| !is_chunk(&r.node)));
+        assert!(results.iter().any(|r| r.node.id == parent_id && r.node.content == text));
+    }
+
+    #[tokio::test]
+    async fn test_chunks_of_missing_parent_are_dropped() {
+        let memory = Arc::new(MockMemory::new());
+        let service = service(memory.clone());
+        let text = "An apple a day is good. Nothing to see in this part. The cherry is red and small.";
+        let parent_id = service.store(MemoryNode::new(text.into())).await.unwrap();
+        // As a guarded store does with a quarantined parent
+        memory.delete(&parent_id).await.unwrap();
+
+        assert!(service.search(&[0.0, 0.0, 1.0], 5).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_removes_chunks() {
+        let memory = Arc::new(MockMemory::new());
+        let service = service(memory.clone());
+        let text = "An apple a day is good. Bananas are yellow, a banana is sweet. The cherry is red.";
+        let parent_id = service.store(MemoryNode::new(text.into())).await.unwrap();
+
+        service.delete(&parent_id).await.unwrap();
+        assert!(memory.nodes.lock().await.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+//! Text Chunking - Split long text into embedding-sized pieces.
+//!
+//! Embedding models have a hard input limit (512 tokens for MiniLM). Long
+//! notes are split into overlapping chunks that each fit the limit, cutting
+//! at sentence boundaries where possible and at word boundaries otherwise.
+//! Token counts come from a caller-supplied function so the chunker stays
+//! independent of any particular tokenizer.
+
+/// A piece of a longer text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextChunk {
+    /// Position of the chunk in the source text (0-based)
+    pub index: usize,
+    /// Chunk text
+    pub text: String,
+    /// Byte offset of the chunk start in the source text
+    pub start: usize,
+    /// Byte offset of the chunk end in the source text
+    pub end: usize,
+}
+
+/// Token-budgeted text chunker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextChunker {
+    /// Maximum tokens per chunk
+    pub max_tokens: usize,
+    /// Tokens repeated from the end of one chunk at the start of the next
+    pub overlap_tokens: usize,
+}
+
+impl Default for TextChunker {
+    fn default() -> Self {
+        Self::new(256, 32)
+    }
+}
+
+impl TextChunker {
+    /// Create a chunker. `overlap_tokens` is capped below `max_tokens`.
+    pub fn new(max_tokens: usize, overlap_tokens: usize) -> Self {
+        let max_tokens = max_tokens.max(1);
+        Self {
+            max_tokens,
+            overlap_tokens: overlap_tokens.min(max_tokens - 1),
+        }
+    }
+
+    /// Split `text` into chunks of at most `max_tokens` tokens as measured by
+    /// `count_tokens`. A single word longer than the budget becomes its own chunk.
+    pub fn chunk(&self, text: &str, count_tokens: impl Fn(&str) -> usize) -> Vec<TextChunk> {
+        // Sentences, with over-long sentences broken into words.
+        let mut units: Vec<(usize, usize, usize)> = Vec::new();
+        for (start, end) in sentence_spans(text) {
+            let tokens = count_tokens(&text[start..end]);
+            if tokens <= self.max_tokens {
+                units.push((start, end, tokens));
+            } else {
+                units.extend(
+                    word_spans(text, start, end)
+                        .into_iter()
+                        .map(|(s, e)| (s, e, count_tokens(&text[s..e]))),
+                );
+            }
+        }
+
+        let mut chunks = Vec::new();
+        let mut window: Vec<(usize, usize, usize)> = Vec::new();
+        let mut window_tokens = 0;
+        for unit in units {
+            if !window.is_empty() && window_tokens + unit.2 > self.max_tokens {
+                push_chunk(&mut chunks, text, &window);
+
+                // Carry trailing units into the next chunk as overlap.
+                let mut carried = 0;
+                let mut keep = 0;
+                for &(_, _, tokens) in window.iter().rev() {
+                    if carried + tokens > self.overlap_tokens || keep + 1 >= window.len() {
+                        break;
+                    }
+                    carried += tokens;
+                    keep += 1;
+                }
+                if carried + unit.2 > self.max_tokens {
+                    keep = 0;
+                    carried = 0;
+                }
+                window.drain(..window.len() - keep);
+                window_tokens = carried;
+            }
+            window_tokens += unit.2;
+            window.push(unit);
+        }
+        if !window.is_empty() {
+            push_chunk(&mut chunks, text, &window);
+        }
+        chunks
+    }
+}
+
+fn push_chunk(chunks: &mut Vec<TextChunk>, text: &str, window: &[(usize, usize, usize)]) {
+    let start = window[0].0;
+    let end = window[window.len() - 1].1;
+    chunks.push(TextChunk {
+        index: chunks.len(),
+        text: text[start..end].to_string(),
+        start,
+        end,
+    });
+}
+
+/// Byte spans of sentences: text up to `.`, `!` or `?` followed by whitespace,
+/// or up to a line break. Spans are trimmed and never empty.
+fn sentence_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let next_is_space = chars.peek().is_none_or(|&(_, n)| n.is_whitespace());
+        if c == '\n' || (matches!(c, '.' | '!' | '?') && next_is_space) {
+            push_trimmed(&mut spans, text, start, i + c.len_utf8());
+            start = i + c.len_utf8();
+        }
+    }
+    push_trimmed(&mut spans, text, start, text.len());
+    spans
+}
+
+/// Byte spans of whitespace-separated words within `start..end`.
+fn word_spans(text: &str, start: usize, end: usize) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut word_start = None;
+    for (i, c) in text[start..end].char_indices() {
+        match (c.is_whitespace(), word_start) {
+            (false, None) => word_start = Some(start + i),
+            (true, Some(s)) => {
+                spans.push((s, start + i));
+                word_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = word_start {
+        spans.push((s, end));
+    }
+    spans
+}
+
+fn push_trimmed(spans: &mut Vec<(usize, usize)>, text: &str, start: usize, end: usize) {
+    let slice = &text[start..end];
+    let trimmed_start = start + (slice.len() - slice.trim_start().len());
+    let trimmed_end = end - (slice.len() - slice.trim_end().len());
+    if trimmed_start < trimmed_end {
+        spans.push((trimmed_start, trimmed_end));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(s: &str) -> usize {
+        s.split_whitespace().count()
+    }
+
+    #[test]
+    fn test_short_text_is_one_chunk() {
+        let chunks = TextChunker::new(50, 5).chunk("One sentence. Two sentences.", words);
+
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].text, "One sentence. Two sentences.");
+        assert_eq!((chunks[0].start, chunks[0].end), (0, 28));
+    }
+
+    #[test]
+    fn test_splits_at_sentence_boundaries_with_overlap() {
+        let text = "Alpha beta gamma. Delta epsilon zeta. Eta theta iota. Kappa lambda mu.";
+        let chunks = TextChunker::new(6, 3).chunk(text, words);
+
+        let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "Alpha beta gamma. Delta epsilon zeta.",
+                "Delta epsilon zeta. Eta theta iota.",
+                "Eta theta iota. Kappa lambda mu.",
+            ]
+        );
+        for chunk in &chunks {
+            assert_eq!(&text[chunk.start..chunk.end], chunk.text);
+            assert!(words(&chunk.text) <= 6);
+        }
+    }
+
+    #[test]
+    fn test_long_sentence_falls_back_to_words() {
+        let text = "one two three four five six seven eight nine ten";
+        let chunks = TextChunker::new(4, 0).chunk(text, words);
+
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[0].text, "one two three four");
+        assert_eq!(chunks[2].text, "nine ten");
+        assert_eq!(chunks.iter().map(|c| c.index).collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_empty_text_has_no_chunks() {
+        assert!(TextChunker::default().chunk("  \n ", words).is_empty());
+    }
+}
@@ -4,16 +4,21 @@
 //! It's part of the HiRAG (Hierarchical RAG) system for creating hierarchical memory structures.
 
 use crate::error::Result;
+use crate::logic::chunked_memory::is_chunk;
+use crate::logic::summarizer::MapReduceSummarizer;
 use crate::ports::{EmbeddingPort, LlmPort, MemoryPort};
 use crate::{MemoryNode, NodeType};
 use chrono::Utc;
 use std::sync::Arc;
 use uuid::Uuid;
 
+/// Completion length reserved for a layer summary.
+const CONSOLIDATION_SUMMARY_TOKENS: usize = 500;
+
 /// Layer Consolidator: Summarizes nodes from one layer into higher-level summaries.
 pub struct LayerConsolidator {
     memory: Arc<dyn MemoryPort>,
-    llm: Arc<dyn LlmPort>,
+    summarizer: MapReduceSummarizer,
     embedder: Arc<dyn EmbeddingPort>,
     threshold: usize,
 }
@@ -32,12 +37,20 @@ impl LayerConsolidator {
     ) -> Self {
         Self {
             memory,
-            llm,
+            summarizer: MapReduceSummarizer::new(llm)
+                .with_instruction("Summarize the following items into a concise overview:")
+                .with_summary_tokens(CONSOLIDATION_SUMMARY_TOKENS),
             embedder,
             threshold: 5, // Default: consolidate when 5+ nodes exist at a layer
         }
     }
 
+    /// Replace the summarizer (e.g. to change fan-in or the call limit).
+    pub fn with_summarizer(mut self, summarizer: MapReduceSummarizer) -> Self {
+        self.summarizer = summarizer;
+        self
+    }
+
     /// Set custom threshold for testing or configuration.
     pub fn with_threshold(mut self, threshold: usize) -> Self {
         self.threshold = threshold;
@@ -54,27 +67,20 @@ impl LayerConsolidator {
             return Ok(None);
         }
 
-        // 2. Get all nodes at this layer
-        let nodes = self.memory.get_by_layer(layer).await?;
+        // 2. Get all nodes at this layer (chunks are covered by their parent)
+        let mut nodes = self.memory.get_by_layer(layer).await?;
+        nodes.retain(|n| !is_chunk(n));
         if nodes.is_empty() {
             return Ok(None);
         }
 
-        // 3. Combine content for summarization
-        let combined_content: String = nodes
+        // 3. Summarize via LLM (map-reduce when the nodes exceed the context window)
+        let items: Vec<String> = nodes
             .iter()
             .enumerate()
             .map(|(i, n)| format!("{}. {}", i + 1, n.content))
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        // 4. Generate summary via LLM
-        let prompt = format!(
-            "Summarize the following {} items into a concise overview:\n\n{}",
-            nodes.len(),
-            combined_content
-        );
-        let summary = self.llm.generate(&prompt, 500).await?;
+            .collect();
+        let summary = self.summarizer.summarize(&items).await?.text;
 
         // 5. Generate embedding for the summary
         let embedding = self.embedder.embed(&summary).await?;
@@ -225,6 +231,28 @@ mod tests {
         }
     }
 
+    // === Mock LLM that fits only a couple of items per prompt ===
+    #[derive(Default)]
+    struct SmallWindowLlm {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl crate::ports::LlmPort for SmallWindowLlm {
+        async fn generate(&self, _prompt: &str, _max_tokens: usize) -> Result<String> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok("Partial".to_string())
+        }
+
+        async fn generate_with_params(&self, _prompt: &str, _max_tokens: usize, _temp: f32, _top_p: f32) -> Result<String> {
+            Ok("Mock summary".to_string())
+        }
+
+        fn context_window(&self) -> usize {
+            CONSOLIDATION_SUMMARY_TOKENS + 40
+        }
+    }
+
     // === Mock Embedder ===
     struct MockEmbedder;
 
@@ -307,4 +335,23 @@ mod tests {
 
         assert!(result.is_some(), "Should consolidate with custom threshold");
     }
+
+    #[tokio::test]
+    async fn test_consolidate_oversized_layer_covers_every_node() {
+        let memory = Arc::new(MockMemory::new());
+        let llm = Arc::new(SmallWindowLlm::default());
+
+        for i in 0..8 {
+            let node = MemoryNode::new(format!("Fact number {} about the user's long-running project", i));
+            memory.store(node).await.unwrap();
+        }
+
+        let consolidator = LayerConsolidator::new(memory.clone(), llm.clone(), Arc::new(MockEmbedder));
+        let result = consolidator.consolidate_layer(0).await.unwrap();
+
+        assert!(result.is_some());
+        assert!(llm.calls.load(std::sync::atomic::Ordering::SeqCst) > 1, "Should need several prompts");
+        let relationships = memory.relationships.lock().await;
+        assert_eq!(relationships.len(), 8, "Every node should be summarized");
+    }
 }
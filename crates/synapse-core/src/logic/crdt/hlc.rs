@@ -0,0 +1,91 @@
+//! Hybrid logical clock.
+//!
+//! Timestamps combine wall-clock milliseconds with a logical counter and the
+//! replica id, so every write gets a unique, totally ordered stamp that still
+//! tracks real time closely and never goes backwards after observing a peer.
+
+use serde::{Deserialize, Serialize};
+
+/// Identifier of a replica (one per device).
+pub type ReplicaId = u64;
+
+/// A hybrid logical clock reading. Ordered by wall time, then counter, then replica.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Timestamp {
+    /// Wall-clock milliseconds since the Unix epoch
+    pub wall_ms: u64,
+    /// Logical counter for events within the same millisecond
+    pub counter: u32,
+    /// Replica that issued the stamp
+    pub replica: ReplicaId,
+}
+
+/// Hybrid logical clock for one replica.
+#[derive(Debug, Clone)]
+pub struct HybridClock {
+    last: Timestamp,
+}
+
+impl HybridClock {
+    pub fn new(replica: ReplicaId) -> Self {
+        Self {
+            last: Timestamp {
+                wall_ms: 0,
+                counter: 0,
+                replica,
+            },
+        }
+    }
+
+    pub fn replica(&self) -> ReplicaId {
+        self.last.replica
+    }
+
+    /// Stamp a local event using the system clock.
+    pub fn tick(&mut self) -> Timestamp {
+        let wall_ms = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        self.tick_at(wall_ms)
+    }
+
+    /// Stamp a local event at the given wall time.
+    pub fn tick_at(&mut self, wall_ms: u64) -> Timestamp {
+        if wall_ms > self.last.wall_ms {
+            self.last.wall_ms = wall_ms;
+            self.last.counter = 0;
+        } else {
+            self.last.counter += 1;
+        }
+        self.last
+    }
+
+    /// Advance past a stamp received from another replica, so later local
+    /// events are ordered after it.
+    pub fn observe(&mut self, remote: Timestamp) {
+        if (remote.wall_ms, remote.counter) > (self.last.wall_ms, self.last.counter) {
+            self.last.wall_ms = remote.wall_ms;
+            self.last.counter = remote.counter;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_monotonic_and_causal() {
+        let mut a = HybridClock::new(1);
+        let t1 = a.tick_at(100);
+        let t2 = a.tick_at(100);
+        let t3 = a.tick_at(50); // wall clock went backwards
+        assert!(t1 < t2 && t2 < t3);
+        assert_eq!(t3.wall_ms, 100);
+
+        // B's clock is behind, but its next event follows what it observed
+        let mut b = HybridClock::new(2);
+        b.observe(t3);
+        let t4 = b.tick_at(10);
+        assert!(t4 > t3);
+        assert_eq!(t4.replica, 2);
+    }
+}
@@ -0,0 +1,33 @@
+//! Last-writer-wins register.
+
+use serde::{Deserialize, Serialize};
+
+use super::hlc::Timestamp;
+
+/// A value tagged with the stamp of the write that produced it.
+///
+/// Merging keeps the value with the greater stamp. Stamps are unique per
+/// write, so replicas agree on the winner regardless of merge order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LwwRegister<T> {
+    pub value: T,
+    pub stamp: Timestamp,
+}
+
+impl<T: Clone> LwwRegister<T> {
+    pub fn new(value: T, stamp: Timestamp) -> Self {
+        Self { value, stamp }
+    }
+
+    /// Write `value` if `stamp` is newer than the current one.
+    pub fn set(&mut self, value: T, stamp: Timestamp) {
+        if stamp > self.stamp {
+            self.value = value;
+            self.stamp = stamp;
+        }
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        self.set(other.value.clone(), other.stamp);
+    }
+}
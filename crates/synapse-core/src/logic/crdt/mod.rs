@@ -0,0 +1,17 @@
+//! CRDTs for replicating memory between devices.
+//!
+//! Node fields are last-writer-wins registers stamped by a hybrid logical
+//! clock, graph edges form an observed-remove set, and deletes leave
+//! tombstones so a stale copy of a node cannot bring it back. States merge
+//! in any order to the same result; replicas sync by exchanging the
+//! [`Delta`] since each other's [`VersionVector`].
+
+pub mod hlc;
+pub mod lww;
+pub mod or_set;
+pub mod replica;
+
+pub use hlc::{HybridClock, ReplicaId, Timestamp};
+pub use lww::LwwRegister;
+pub use or_set::OrSet;
+pub use replica::{Change, Delta, NodeState, Replica, ReplicaState, VersionVector};
@@ -0,0 +1,203 @@
+//! Observed-remove set.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::hlc::Timestamp;
+
+/// Set where each add is tagged with a unique stamp and a remove only
+/// cancels the adds it has observed. A concurrent re-add therefore survives
+/// a remove, and a remove is never undone by replaying an old add.
+///
+/// Removed stamps are kept as tombstones (with the stamp of the remove, so
+/// they can be shipped in deltas).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T: Serialize + Clone",
+    deserialize = "T: Deserialize<'de> + Ord + Clone"
+))]
+#[serde(into = "OrSetWire<T>", from = "OrSetWire<T>")]
+pub struct OrSet<T: Ord> {
+    /// Live adds: element -> add stamps not yet removed
+    entries: BTreeMap<T, BTreeSet<Timestamp>>,
+    /// Tombstones: add stamp -> stamp of the remove
+    removed: BTreeMap<Timestamp, Timestamp>,
+}
+
+impl<T: Ord> Default for OrSet<T> {
+    fn default() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            removed: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> OrSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, element: &T) -> bool {
+        self.entries.contains_key(element)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.entries.keys()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.removed.is_empty()
+    }
+
+    /// Add `element` with a fresh stamp.
+    pub fn add(&mut self, element: T, stamp: Timestamp) {
+        if !self.removed.contains_key(&stamp) {
+            self.entries.entry(element).or_default().insert(stamp);
+        }
+    }
+
+    /// Remove every observed add of elements matching `predicate`.
+    pub fn remove_where(&mut self, predicate: impl Fn(&T) -> bool, stamp: Timestamp) {
+        let matching: Vec<T> = self
+            .entries
+            .keys()
+            .filter(|e| predicate(e))
+            .cloned()
+            .collect();
+        for element in matching {
+            if let Some(adds) = self.entries.remove(&element) {
+                for add in adds {
+                    self.removed.insert(add, stamp);
+                }
+            }
+        }
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        for (add, removed_at) in &other.removed {
+            let entry = self.removed.entry(*add).or_insert(*removed_at);
+            *entry = (*entry).min(*removed_at);
+        }
+        for (element, adds) in &other.entries {
+            for add in adds {
+                if !self.removed.contains_key(add) {
+                    self.entries
+                        .entry(element.clone())
+                        .or_default()
+                        .insert(*add);
+                }
+            }
+        }
+        let removed = &self.removed;
+        self.entries.retain(|_, adds| {
+            adds.retain(|add| !removed.contains_key(add));
+            !adds.is_empty()
+        });
+    }
+
+    /// All stamps held by the set (adds and removes).
+    pub fn stamps(&self) -> impl Iterator<Item = Timestamp> + '_ {
+        self.entries
+            .values()
+            .flatten()
+            .copied()
+            .chain(self.removed.iter().flat_map(|(add, rm)| [*add, *rm]))
+    }
+
+    /// The part of the set containing adds or removes accepted by `unseen`.
+    pub fn delta(&self, unseen: impl Fn(&Timestamp) -> bool) -> Self {
+        let mut delta = Self::new();
+        for (element, adds) in &self.entries {
+            let adds: BTreeSet<Timestamp> = adds.iter().filter(|a| unseen(a)).copied().collect();
+            if !adds.is_empty() {
+                delta.entries.insert(element.clone(), adds);
+            }
+        }
+        delta.removed = self
+            .removed
+            .iter()
+            .filter(|(add, rm)| unseen(add) || unseen(rm))
+            .map(|(add, rm)| (*add, *rm))
+            .collect();
+        delta
+    }
+}
+
+/// Serialized form: JSON maps need string keys, so entries travel as lists.
+#[derive(Serialize, Deserialize)]
+struct OrSetWire<T> {
+    adds: Vec<(T, Timestamp)>,
+    removed: Vec<(Timestamp, Timestamp)>,
+}
+
+impl<T: Ord + Clone> From<OrSet<T>> for OrSetWire<T> {
+    fn from(set: OrSet<T>) -> Self {
+        Self {
+            adds: set
+                .entries
+                .into_iter()
+                .flat_map(|(e, adds)| adds.into_iter().map(move |a| (e.clone(), a)))
+                .collect(),
+            removed: set.removed.into_iter().collect(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> From<OrSetWire<T>> for OrSet<T> {
+    fn from(wire: OrSetWire<T>) -> Self {
+        let mut set = OrSet::new();
+        set.removed = wire.removed.into_iter().collect();
+        for (element, add) in wire.adds {
+            set.add(element, add);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts(wall_ms: u64, replica: u64) -> Timestamp {
+        Timestamp {
+            wall_ms,
+            counter: 0,
+            replica,
+        }
+    }
+
+    #[test]
+    fn test_concurrent_add_survives_remove() {
+        let mut a = OrSet::new();
+        a.add("edge", ts(1, 1));
+        let mut b = a.clone();
+
+        // A removes the edge while B re-adds it concurrently
+        a.remove_where(|e| *e == "edge", ts(2, 1));
+        b.add("edge", ts(2, 2));
+
+        let mut merged = a.clone();
+        merged.merge(&b);
+        assert!(merged.contains(&"edge"));
+
+        // Replaying the original add does not resurrect a removed element
+        let mut c = OrSet::new();
+        c.add("edge", ts(1, 1));
+        a.merge(&c);
+        assert!(!a.contains(&"edge"));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut set = OrSet::new();
+        set.add(("a".to_string(), 1u8), ts(1, 1));
+        set.add(("b".to_string(), 2u8), ts(2, 1));
+        set.remove_where(|(name, _)| name == "a", ts(3, 1));
+
+        let json = serde_json::to_string(&set).unwrap();
+        let back: OrSet<(String, u8)> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, set);
+    }
+}
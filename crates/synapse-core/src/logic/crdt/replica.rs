@@ -0,0 +1,757 @@
+//! Replicated memory state: nodes, edges, version vectors and deltas.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use super::hlc::{HybridClock, ReplicaId, Timestamp};
+use super::lww::LwwRegister;
+use super::or_set::OrSet;
+use crate::{MemoryNode, NodeType, Relationship};
+
+/// CRDT form of a [`MemoryNode`]: one LWW register per field, so concurrent
+/// edits to different fields both survive, plus a delete tombstone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeState {
+    pub id: String,
+    /// Immutable; a node never moves between namespaces
+    pub namespace: String,
+    /// Earliest creation time seen
+    pub created_at: i64,
+    pub content: LwwRegister<String>,
+    pub layer: LwwRegister<u8>,
+    pub node_type: LwwRegister<NodeType>,
+    pub embedding: LwwRegister<Vec<f32>>,
+    pub source: LwwRegister<String>,
+    pub updated_at: LwwRegister<i64>,
+    /// Per-key registers; `None` means the key was removed
+    pub metadata: BTreeMap<String, LwwRegister<Option<serde_json::Value>>>,
+    /// Set once the node is deleted. Deletes are final: later or concurrent
+    /// edits never bring the node back.
+    pub deleted: Option<Timestamp>,
+}
+
+impl NodeState {
+    fn new(node: &MemoryNode, stamp: Timestamp) -> Self {
+        Self {
+            id: node.id.clone(),
+            namespace: node.namespace.clone(),
+            created_at: node.created_at,
+            content: LwwRegister::new(node.content.clone(), stamp),
+            layer: LwwRegister::new(node.layer, stamp),
+            node_type: LwwRegister::new(node.node_type, stamp),
+            embedding: LwwRegister::new(node.embedding.clone(), stamp),
+            source: LwwRegister::new(node.source.clone(), stamp),
+            updated_at: LwwRegister::new(node.updated_at, stamp),
+            metadata: node
+                .metadata
+                .iter()
+                .map(|(k, v)| (k.clone(), LwwRegister::new(Some(v.clone()), stamp)))
+                .collect(),
+            deleted: None,
+        }
+    }
+
+    /// Write the fields of `node` that differ from the current values.
+    fn update(&mut self, node: &MemoryNode, stamp: Timestamp) {
+        fn write<T: Clone + PartialEq>(reg: &mut LwwRegister<T>, value: &T, stamp: Timestamp) {
+            if &reg.value != value {
+                reg.set(value.clone(), stamp);
+            }
+        }
+        write(&mut self.content, &node.content, stamp);
+        write(&mut self.layer, &node.layer, stamp);
+        write(&mut self.node_type, &node.node_type, stamp);
+        write(&mut self.embedding, &node.embedding, stamp);
+        write(&mut self.source, &node.source, stamp);
+        write(&mut self.updated_at, &node.updated_at, stamp);
+
+        for (key, reg) in self.metadata.iter_mut() {
+            write(reg, &node.metadata.get(key).cloned(), stamp);
+        }
+        for (key, value) in &node.metadata {
+            self.metadata
+                .entry(key.clone())
+                .or_insert_with(|| LwwRegister::new(Some(value.clone()), stamp));
+        }
+    }
+
+    /// Whether the current field values are those of `node`.
+    fn holds(&self, node: &MemoryNode) -> bool {
+        let live_metadata = self.metadata.values().filter(|reg| reg.value.is_some()).count();
+        self.content.value == node.content
+            && self.layer.value == node.layer
+            && self.node_type.value == node.node_type
+            && self.embedding.value == node.embedding
+            && self.source.value == node.source
+            && self.updated_at.value == node.updated_at
+            && live_metadata == node.metadata.len()
+            && node
+                .metadata
+                .iter()
+                .all(|(k, v)| self.metadata.get(k).is_some_and(|reg| reg.value.as_ref() == Some(v)))
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.created_at = self.created_at.min(other.created_at);
+        self.content.merge(&other.content);
+        self.layer.merge(&other.layer);
+        self.node_type.merge(&other.node_type);
+        self.embedding.merge(&other.embedding);
+        self.source.merge(&other.source);
+        self.updated_at.merge(&other.updated_at);
+        for (key, reg) in &other.metadata {
+            match self.metadata.get_mut(key) {
+                Some(existing) => existing.merge(reg),
+                None => {
+                    self.metadata.insert(key.clone(), reg.clone());
+                }
+            }
+        }
+        self.deleted = match (self.deleted, other.deleted) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    /// Current value, or `None` if deleted.
+    pub fn to_node(&self) -> Option<MemoryNode> {
+        if self.deleted.is_some() {
+            return None;
+        }
+        Some(MemoryNode {
+            id: self.id.clone(),
+            content: self.content.value.clone(),
+            layer: self.layer.value,
+            node_type: self.node_type.value,
+            created_at: self.created_at,
+            updated_at: self.updated_at.value,
+            embedding: self.embedding.value.clone(),
+            metadata: self
+                .metadata
+                .iter()
+                .filter_map(|(k, reg)| reg.value.clone().map(|v| (k.clone(), v)))
+                .collect(),
+            namespace: self.namespace.clone(),
+            source: self.source.value.clone(),
+        })
+    }
+
+    fn stamps(&self) -> impl Iterator<Item = Timestamp> + '_ {
+        [
+            self.content.stamp,
+            self.layer.stamp,
+            self.node_type.stamp,
+            self.embedding.stamp,
+            self.source.stamp,
+            self.updated_at.stamp,
+        ]
+        .into_iter()
+        .chain(self.metadata.values().map(|reg| reg.stamp))
+        .chain(self.deleted)
+    }
+}
+
+/// Highest stamp seen from each replica.
+///
+/// A replica's version only advances when it holds every change up to that
+/// stamp, so "everything after my version" is exactly what it is missing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionVector(BTreeMap<ReplicaId, Timestamp>);
+
+impl VersionVector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether `stamp` is covered by this vector.
+    pub fn has_seen(&self, stamp: &Timestamp) -> bool {
+        self.0.get(&stamp.replica).is_some_and(|seen| stamp <= seen)
+    }
+
+    /// Whether this vector has seen everything `other` has.
+    pub fn covers(&self, other: &VersionVector) -> bool {
+        other.0.values().all(|stamp| self.has_seen(stamp))
+    }
+
+    pub fn observe(&mut self, stamp: Timestamp) {
+        let seen = self.0.entry(stamp.replica).or_insert(stamp);
+        if stamp > *seen {
+            *seen = stamp;
+        }
+    }
+
+    pub fn merge(&mut self, other: &VersionVector) {
+        for stamp in other.0.values() {
+            self.observe(*stamp);
+        }
+    }
+
+    pub fn get(&self, replica: ReplicaId) -> Option<Timestamp> {
+        self.0.get(&replica).copied()
+    }
+}
+
+/// Replicated state of one namespace. Also used as the delta format.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplicaState {
+    pub nodes: BTreeMap<String, NodeState>,
+    pub edges: OrSet<Relationship>,
+}
+
+impl ReplicaState {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.edges.is_empty()
+    }
+
+    /// Join with another state. Commutative, associative and idempotent.
+    pub fn merge(&mut self, other: &ReplicaState) {
+        for (id, node) in &other.nodes {
+            match self.nodes.get_mut(id) {
+                Some(existing) => existing.merge(node),
+                None => {
+                    self.nodes.insert(id.clone(), node.clone());
+                }
+            }
+        }
+        self.edges.merge(&other.edges);
+    }
+
+    /// The part of this state not covered by `since`. Merging it into a
+    /// replica whose version is `since` gives the same result as merging
+    /// the full state.
+    pub fn delta_since(&self, since: &VersionVector) -> ReplicaState {
+        let unseen = |stamp: &Timestamp| !since.has_seen(stamp);
+        ReplicaState {
+            nodes: self
+                .nodes
+                .iter()
+                .filter(|(_, node)| node.stamps().any(|s| unseen(&s)))
+                .map(|(id, node)| (id.clone(), node.clone()))
+                .collect(),
+            edges: self.edges.delta(unseen),
+        }
+    }
+
+    /// Live nodes.
+    pub fn nodes(&self) -> impl Iterator<Item = MemoryNode> + '_ {
+        self.nodes.values().filter_map(NodeState::to_node)
+    }
+
+    /// Live edges whose endpoints are not deleted.
+    pub fn edges(&self) -> impl Iterator<Item = &Relationship> {
+        self.edges
+            .iter()
+            .filter(|edge| !self.is_deleted(&edge.from_id) && !self.is_deleted(&edge.to_id))
+    }
+
+    fn is_deleted(&self, id: &str) -> bool {
+        self.nodes
+            .get(id)
+            .is_some_and(|node| node.deleted.is_some())
+    }
+}
+
+/// Changes to send to another replica.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delta {
+    /// Version the receiver must already have for `until` to be valid
+    pub since: VersionVector,
+    /// Sender's version once the delta is applied
+    pub until: VersionVector,
+    pub state: ReplicaState,
+}
+
+impl Delta {
+    pub fn is_empty(&self) -> bool {
+        self.state.is_empty()
+    }
+
+    /// Split into parts of at most `max_nodes` nodes, for transports with a
+    /// message size limit. Only the last part advances the receiver's version.
+    pub fn split(self, max_nodes: usize) -> Vec<Delta> {
+        let max_nodes = max_nodes.max(1);
+        if self.state.nodes.len() <= max_nodes {
+            return vec![self];
+        }
+
+        let Delta {
+            since,
+            until,
+            state,
+        } = self;
+        let mut nodes: Vec<(String, NodeState)> = state.nodes.into_iter().collect();
+        let mut parts = Vec::new();
+        while nodes.len() > max_nodes {
+            let rest = nodes.split_off(max_nodes);
+            parts.push(Delta {
+                since: since.clone(),
+                until: since.clone(),
+                state: ReplicaState {
+                    nodes: nodes.into_iter().collect(),
+                    edges: OrSet::new(),
+                },
+            });
+            nodes = rest;
+        }
+        parts.push(Delta {
+            since,
+            until,
+            state: ReplicaState {
+                nodes: nodes.into_iter().collect(),
+                edges: state.edges,
+            },
+        });
+        parts
+    }
+}
+
+/// Effect of merging remote changes, to apply to a local `MemoryPort`.
+#[derive(Debug, Clone)]
+pub enum Change {
+    Upsert(MemoryNode),
+    Delete(String),
+    Relate(Relationship),
+}
+
+/// One device's replica of a namespace: state, clock and version.
+///
+/// Local writes return a [`Delta`] to broadcast. Remote deltas are merged
+/// with [`apply`](Self::apply), which reports what changed. A delta that
+/// skips changes this replica never saw is still merged, but the version
+/// does not advance; the caller should then ask the sender for
+/// [`delta_since`](Self::delta_since) its version.
+#[derive(Debug, Clone)]
+pub struct Replica {
+    clock: HybridClock,
+    state: ReplicaState,
+    version: VersionVector,
+}
+
+impl Replica {
+    pub fn new(replica: ReplicaId) -> Self {
+        Self {
+            clock: HybridClock::new(replica),
+            state: ReplicaState::default(),
+            version: VersionVector::new(),
+        }
+    }
+
+    /// Resume from a persisted state and version. The clock continues
+    /// after every stamp in `version`, so new writes are never older.
+    pub fn restore(replica: ReplicaId, state: ReplicaState, version: VersionVector) -> Self {
+        let mut clock = HybridClock::new(replica);
+        for stamp in version.0.values() {
+            clock.observe(*stamp);
+        }
+        Self {
+            clock,
+            state,
+            version,
+        }
+    }
+
+    pub fn state(&self) -> &ReplicaState {
+        &self.state
+    }
+
+    pub fn version(&self) -> &VersionVector {
+        &self.version
+    }
+
+    /// Everything the holder of `since` is missing.
+    pub fn delta_since(&self, since: &VersionVector) -> Delta {
+        Delta {
+            since: since.clone(),
+            until: self.version.clone(),
+            state: self.state.delta_since(since),
+        }
+    }
+
+    /// Record a local create or update.
+    pub fn upsert(&mut self, node: &MemoryNode) -> Delta {
+        let stamp = self.clock.tick();
+        let state = match self.state.nodes.get_mut(&node.id) {
+            Some(existing) if existing.deleted.is_some() => {
+                return self.local_delta(stamp, ReplicaState::default());
+            }
+            Some(existing) => {
+                existing.update(node, stamp);
+                existing.clone()
+            }
+            None => {
+                let state = NodeState::new(node, stamp);
+                self.state.nodes.insert(node.id.clone(), state.clone());
+                state
+            }
+        };
+        let delta = ReplicaState {
+            nodes: BTreeMap::from([(node.id.clone(), state)]),
+            ..Default::default()
+        };
+        self.local_delta(stamp, delta)
+    }
+
+    /// Record a local delete. Edges touching the node are removed too.
+    pub fn delete(&mut self, id: &str, namespace: &str) -> Delta {
+        let stamp = self.clock.tick();
+        let node = self.state.nodes.entry(id.to_string()).or_insert_with(|| {
+            // Deleting a node that predates replication: a bare tombstone
+            let node = MemoryNode {
+                id: id.to_string(),
+                namespace: namespace.to_string(),
+                ..Default::default()
+            };
+            let origin = Timestamp {
+                wall_ms: 0,
+                counter: 0,
+                replica: stamp.replica,
+            };
+            NodeState::new(&node, origin)
+        });
+        node.deleted.get_or_insert(stamp);
+        let node = node.clone();
+
+        self.state
+            .edges
+            .remove_where(|e| e.from_id == id || e.to_id == id, stamp);
+        let delta = ReplicaState {
+            nodes: BTreeMap::from([(id.to_string(), node)]),
+            edges: self.state.edges.delta(|s| *s == stamp),
+        };
+        self.local_delta(stamp, delta)
+    }
+
+    /// Record a local edge.
+    pub fn relate(&mut self, edge: Relationship) -> Delta {
+        let stamp = self.clock.tick();
+        self.state.edges.add(edge.clone(), stamp);
+        let mut edges = OrSet::new();
+        edges.add(edge, stamp);
+        let delta = ReplicaState {
+            nodes: BTreeMap::new(),
+            edges,
+        };
+        self.local_delta(stamp, delta)
+    }
+
+    /// Record a node found in the local store, e.g. one written before
+    /// replication was enabled. Nodes held unchanged or deleted are left
+    /// alone. Returns whether the state changed.
+    pub fn seed(&mut self, node: &MemoryNode) -> bool {
+        match self.state.nodes.get(&node.id) {
+            Some(existing) if existing.deleted.is_some() || existing.holds(node) => false,
+            _ => {
+                self.upsert(node);
+                true
+            }
+        }
+    }
+
+    /// Record an edge found in the local store unless it is already held
+    /// or an endpoint is deleted. Returns whether the state changed.
+    pub fn seed_edge(&mut self, edge: &Relationship) -> bool {
+        if self.state.edges.contains(edge)
+            || self.state.is_deleted(&edge.from_id)
+            || self.state.is_deleted(&edge.to_id)
+        {
+            return false;
+        }
+        self.relate(edge.clone());
+        true
+    }
+
+    fn local_delta(&mut self, stamp: Timestamp, state: ReplicaState) -> Delta {
+        let since = self.version.clone();
+        self.version.observe(stamp);
+        Delta {
+            since,
+            until: self.version.clone(),
+            state,
+        }
+    }
+
+    /// Merge a remote delta and report the resulting local changes.
+    pub fn apply(&mut self, delta: &Delta) -> Vec<Change> {
+        for stamp in delta.until.0.values() {
+            self.clock.observe(*stamp);
+        }
+
+        let before: BTreeMap<&String, Option<NodeState>> = delta
+            .state
+            .nodes
+            .keys()
+            .map(|id| (id, self.state.nodes.get(id).cloned()))
+            .collect();
+        let edges_before: BTreeSet<Relationship> = self.state.edges().cloned().collect();
+
+        self.state.merge(&delta.state);
+        if self.version.covers(&delta.since) {
+            self.version.merge(&delta.until);
+        }
+
+        let mut changes = Vec::new();
+        for (id, old) in before {
+            let new = &self.state.nodes[id];
+            let was_deleted = old.as_ref().is_some_and(|n| n.deleted.is_some());
+            if new.deleted.is_some() {
+                if !was_deleted {
+                    changes.push(Change::Delete(id.clone()));
+                }
+            } else if old.as_ref() != Some(new) {
+                changes.extend(new.to_node().map(Change::Upsert));
+            }
+        }
+        changes.extend(
+            self.state
+                .edges()
+                .filter(|edge| !edges_before.contains(*edge))
+                .cloned()
+                .map(Change::Relate),
+        );
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn node(id: &str, content: &str) -> MemoryNode {
+        let mut node = MemoryNode::new(content.to_string()).with_namespace("shared".to_string());
+        node.id = id.to_string();
+        node
+    }
+
+    fn edge(from: &str, to: &str) -> Relationship {
+        Relationship {
+            from_id: from.to_string(),
+            relation: "related".to_string(),
+            to_id: to.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_concurrent_field_edits_both_survive() {
+        let mut a = Replica::new(1);
+        let mut b = Replica::new(2);
+        let original = node("n1", "original");
+        b.apply(&a.upsert(&original));
+
+        let mut edited = original.clone();
+        edited.content = "edited on A".to_string();
+        let delta_a = a.upsert(&edited);
+        let delta_b = b.upsert(
+            &original
+                .clone()
+                .with_metadata("tag", serde_json::json!("from B")),
+        );
+
+        a.apply(&delta_b);
+        b.apply(&delta_a);
+        assert_eq!(a.state(), b.state());
+        let merged = a.state().nodes().next().unwrap();
+        assert_eq!(merged.content, "edited on A");
+        assert_eq!(merged.metadata["tag"], serde_json::json!("from B"));
+    }
+
+    #[test]
+    fn test_delete_does_not_resurrect() {
+        let mut a = Replica::new(1);
+        let mut b = Replica::new(2);
+        b.apply(&a.upsert(&node("n1", "v1")));
+        b.apply(&a.upsert(&node("n2", "v1")));
+        b.apply(&a.relate(edge("n1", "n2")));
+
+        // B edits while A deletes; the stale edit arrives after the delete
+        let stale = b.upsert(&node("n1", "v2"));
+        let changes = b.apply(&a.delete("n1", "shared"));
+        assert!(matches!(changes.as_slice(), [Change::Delete(id)] if id == "n1"));
+        assert!(a.apply(&stale).is_empty());
+
+        assert_eq!(a.state(), b.state());
+        assert_eq!(a.state().nodes().count(), 1);
+        assert_eq!(a.state().edges().count(), 0);
+    }
+
+    #[test]
+    fn test_gap_is_filled_by_delta_sync() {
+        let mut a = Replica::new(1);
+        let mut b = Replica::new(2);
+        let _missed = a.upsert(&node("n1", "hello"));
+        a.upsert(&node("n2", "world"));
+        let relate = a.relate(edge("n1", "n2"));
+
+        // B only sees the last broadcast: merged, but its version holds back
+        b.apply(&relate);
+        assert!(!b.version().covers(a.version()));
+
+        let changes = b.apply(&a.delta_since(b.version()));
+        assert_eq!(changes.len(), 2);
+        assert!(b.version().covers(a.version()));
+        assert_eq!(b.state(), a.state());
+        assert_eq!(b.state().edges().count(), 1);
+
+        // Nothing left to send, and re-applying is a no-op
+        assert!(a.delta_since(b.version()).is_empty());
+        assert!(b.apply(&a.delta_since(&VersionVector::new())).is_empty());
+    }
+
+    #[test]
+    fn test_seed_existing_store() {
+        let mut a = Replica::new(1);
+        assert!(a.seed(&node("n1", "old")));
+        assert!(a.seed(&node("n2", "old")));
+        assert!(a.seed_edge(&edge("n1", "n2")));
+        let version = a.version().clone();
+
+        // Seeding the same store again is a no-op
+        assert!(!a.seed(&node("n1", "old")));
+        assert!(!a.seed_edge(&edge("n1", "n2")));
+        assert_eq!(a.version(), &version);
+
+        // Edited outside replication: recorded; deleted: stays deleted
+        assert!(a.seed(&node("n1", "edited")));
+        a.delete("n2", "shared");
+        assert!(!a.seed(&node("n2", "old")));
+        assert!(!a.seed_edge(&edge("n1", "n2")));
+
+        let mut b = Replica::new(2);
+        b.apply(&a.delta_since(b.version()));
+        let nodes: Vec<MemoryNode> = b.state().nodes().collect();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].content, "edited");
+    }
+
+    #[test]
+    fn test_split_delta() {
+        let mut a = Replica::new(1);
+        for i in 0..5 {
+            a.upsert(&node(&format!("n{i}"), "x"));
+        }
+        a.relate(edge("n0", "n1"));
+
+        let parts = a.delta_since(&VersionVector::new()).split(2);
+        assert_eq!(parts.len(), 3);
+
+        let mut b = Replica::new(2);
+        for part in &parts[..2] {
+            b.apply(part);
+            assert!(b.version().get(1).is_none());
+        }
+        b.apply(&parts[2]);
+        assert_eq!(b.state(), a.state());
+        assert_eq!(b.version(), a.version());
+    }
+
+    #[derive(Debug, Clone)]
+    enum Op {
+        Upsert {
+            id: u8,
+            content: u8,
+            tag: Option<u8>,
+        },
+        Delete {
+            id: u8,
+        },
+        Relate {
+            from: u8,
+            to: u8,
+        },
+    }
+
+    fn op() -> impl Strategy<Value = Op> {
+        prop_oneof![
+            (0u8..4, 0u8..3, proptest::option::of(0u8..3))
+                .prop_map(|(id, content, tag)| Op::Upsert { id, content, tag }),
+            (0u8..4).prop_map(|id| Op::Delete { id }),
+            (0u8..4, 0u8..4).prop_map(|(from, to)| Op::Relate { from, to }),
+        ]
+    }
+
+    /// Run ops on a fresh replica, syncing from `shared` halfway through so
+    /// replicas have causal history in common.
+    fn replica(id: ReplicaId, ops: &[Op], shared: &Replica) -> Replica {
+        let mut replica = Replica::new(id);
+        for (i, op) in ops.iter().enumerate() {
+            if i == ops.len() / 2 {
+                replica.apply(&shared.delta_since(replica.version()));
+            }
+            match op {
+                Op::Upsert { id, content, tag } => {
+                    let mut n = node(&format!("n{id}"), &format!("c{content}"));
+                    if let Some(tag) = tag {
+                        n = n.with_metadata("tag", serde_json::json!(tag));
+                    }
+                    replica.upsert(&n);
+                }
+                Op::Delete { id } => {
+                    replica.delete(&format!("n{id}"), "shared");
+                }
+                Op::Relate { from, to } => {
+                    replica.relate(edge(&format!("n{from}"), &format!("n{to}")));
+                }
+            }
+        }
+        replica
+    }
+
+    fn merged(a: &ReplicaState, b: &ReplicaState) -> ReplicaState {
+        let mut out = a.clone();
+        out.merge(b);
+        out
+    }
+
+    fn ops() -> impl Strategy<Value = Vec<Op>> {
+        proptest::collection::vec(op(), 0..12)
+    }
+
+    proptest! {
+        #[test]
+        fn prop_merge_is_commutative_associative_idempotent(
+            ops_a in ops(),
+            ops_b in ops(),
+            ops_c in ops(),
+        ) {
+            let a = replica(1, &ops_a, &Replica::new(0));
+            let b = replica(2, &ops_b, &a);
+            let c = replica(3, &ops_c, &b);
+            let (a, b, c) = (a.state(), b.state(), c.state());
+
+            prop_assert_eq!(merged(a, b), merged(b, a));
+            prop_assert_eq!(merged(&merged(a, b), c), merged(a, &merged(b, c)));
+            prop_assert_eq!(&merged(a, a), a);
+            prop_assert_eq!(merged(&merged(a, b), b), merged(a, b));
+        }
+
+        #[test]
+        fn prop_delta_sync_converges(
+            ops_a in ops(),
+            ops_b in ops(),
+            ops_c in ops(),
+        ) {
+            let a = replica(1, &ops_a, &Replica::new(0));
+            let c = replica(3, &ops_c, &a);
+            let mut b = replica(2, &ops_b, &c);
+            let mut a_synced = a.clone();
+
+            // Exchange deltas both ways, then relay C's changes through B
+            b.apply(&a.delta_since(b.version()));
+            a_synced.apply(&b.delta_since(a_synced.version()));
+            b.apply(&c.delta_since(b.version()));
+            a_synced.apply(&b.delta_since(a_synced.version()));
+
+            let expected = merged(&merged(a.state(), b.state()), c.state());
+            prop_assert_eq!(a_synced.state(), &expected);
+            prop_assert_eq!(b.state(), &expected);
+            prop_assert_eq!(a_synced.version(), b.version());
+
+            // Deltas survive the wire format
+            let delta = b.delta_since(&VersionVector::new());
+            let json = serde_json::to_string(&delta).unwrap();
+            let back: Delta = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(back, delta);
+        }
+    }
+}
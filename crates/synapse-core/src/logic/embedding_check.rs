@@ -0,0 +1,379 @@
+//! Embedding Check - Reject corrupted embeddings and find outliers.
+//!
+//! [`EmbeddingCheck`] validates a single vector before it is stored: every
+//! value finite, not all zero, the provider's dimension and, for providers
+//! that L2-normalize, unit length. [`OutlierDetector`] looks at all the
+//! embeddings of one namespace and flags those far from any cluster: each
+//! node's distance to its k-th nearest neighbour is compared with the
+//! namespace's typical value using a robust (median/MAD) z-score, so
+//! namespaces with several topics do not flag every topic but the largest.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::error::Error;
+use crate::ports::EmbeddingPort;
+use crate::MemoryNode;
+
+/// Allowed deviation from unit length for normalized providers.
+pub const DEFAULT_NORM_TOLERANCE: f32 = 0.01;
+
+/// Why an embedding was rejected.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmbeddingIssue {
+    /// NaN or infinite value at this index
+    NonFinite { index: usize },
+    /// All values are zero, so no direction to compare
+    Zero,
+    Dimension { expected: usize, actual: usize },
+    /// Length of a vector that should be unit length
+    Norm { norm: f32 },
+}
+
+impl EmbeddingIssue {
+    /// Short machine-readable name.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::NonFinite { .. } => "non_finite",
+            Self::Zero => "zero_vector",
+            Self::Dimension { .. } => "dimension",
+            Self::Norm { .. } => "norm",
+        }
+    }
+}
+
+impl fmt::Display for EmbeddingIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NonFinite { index } => write!(f, "non-finite value at index {}", index),
+            Self::Zero => write!(f, "zero vector"),
+            Self::Dimension { expected, actual } => {
+                write!(f, "dimension {} instead of {}", actual, expected)
+            }
+            Self::Norm { norm } => write!(f, "norm {:.4} instead of 1", norm),
+        }
+    }
+}
+
+impl From<EmbeddingIssue> for Error {
+    fn from(issue: EmbeddingIssue) -> Self {
+        Error::Validation {
+            message: format!("invalid embedding: {}", issue),
+        }
+    }
+}
+
+/// What a valid embedding looks like.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingCheck {
+    /// Required length, if known
+    pub dimension: Option<usize>,
+    /// Whether vectors must be unit length
+    pub normalized: bool,
+    pub norm_tolerance: f32,
+}
+
+impl EmbeddingCheck {
+    /// Only require finite, non-zero values.
+    pub fn new() -> Self {
+        Self {
+            dimension: None,
+            normalized: false,
+            norm_tolerance: DEFAULT_NORM_TOLERANCE,
+        }
+    }
+
+    /// Also require the embedder's dimension and, if it normalizes, unit length.
+    pub fn for_embedder(embedder: &dyn EmbeddingPort) -> Self {
+        Self {
+            dimension: Some(embedder.dimension()),
+            normalized: embedder.is_normalized(),
+            norm_tolerance: DEFAULT_NORM_TOLERANCE,
+        }
+    }
+
+    pub fn validate(&self, embedding: &[f32]) -> Result<(), EmbeddingIssue> {
+        if let Some(index) = embedding.iter().position(|x| !x.is_finite()) {
+            return Err(EmbeddingIssue::NonFinite { index });
+        }
+        if let Some(expected) = self.dimension {
+            if embedding.len() != expected {
+                return Err(EmbeddingIssue::Dimension {
+                    expected,
+                    actual: embedding.len(),
+                });
+            }
+        }
+        let norm = norm(embedding);
+        if norm == 0.0 {
+            return Err(EmbeddingIssue::Zero);
+        }
+        if self.normalized && (norm - 1.0).abs() > self.norm_tolerance {
+            return Err(EmbeddingIssue::Norm { norm });
+        }
+        Ok(())
+    }
+}
+
+impl Default for EmbeddingCheck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A node whose embedding is invalid or far from the rest of its namespace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outlier {
+    pub id: String,
+    pub namespace: String,
+    /// Robust z-score of the nearest-neighbour distance (infinite when the
+    /// embedding is invalid)
+    pub score: f32,
+    /// Set when the embedding failed validation
+    pub issue: Option<EmbeddingIssue>,
+}
+
+impl Outlier {
+    /// Short machine-readable reason.
+    pub fn kind(&self) -> &'static str {
+        self.issue.as_ref().map_or("outlier", EmbeddingIssue::kind)
+    }
+}
+
+/// Finds statistical outliers among the embeddings of one namespace.
+#[derive(Debug, Clone)]
+pub struct OutlierDetector {
+    neighbors: usize,
+    threshold: f32,
+    min_nodes: usize,
+    max_references: usize,
+}
+
+impl OutlierDetector {
+    /// Defaults: 5th nearest neighbour, modified z-score above 3.5, at least
+    /// 10 nodes, distances measured against at most 2000 nodes.
+    pub fn new() -> Self {
+        Self {
+            neighbors: 5,
+            threshold: 3.5,
+            min_nodes: 10,
+            max_references: 2000,
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_neighbors(mut self, neighbors: usize) -> Self {
+        self.neighbors = neighbors.max(1);
+        self
+    }
+
+    /// Below this many valid embeddings only validation is done.
+    pub fn with_min_nodes(mut self, min_nodes: usize) -> Self {
+        self.min_nodes = min_nodes;
+        self
+    }
+
+    /// Bound the quadratic cost on large namespaces by comparing every
+    /// node against an evenly spaced sample of this size.
+    pub fn with_max_references(mut self, max_references: usize) -> Self {
+        self.max_references = max_references.max(2);
+        self
+    }
+
+    /// Invalid and outlying nodes among `nodes`, worst first. Nodes without
+    /// an embedding are skipped. Without a dimension in `check`, the most
+    /// common one in `nodes` is required.
+    pub fn find(&self, nodes: &[MemoryNode], check: &EmbeddingCheck) -> Vec<Outlier> {
+        let mut check = check.clone();
+        if check.dimension.is_none() {
+            check.dimension = common_dimension(nodes);
+        }
+
+        let mut outliers = Vec::new();
+        let mut valid: Vec<(&MemoryNode, Vec<f32>)> = Vec::new();
+        for node in nodes.iter().filter(|n| !n.embedding.is_empty()) {
+            match check.validate(&node.embedding) {
+                Ok(()) => valid.push((node, unit(&node.embedding))),
+                Err(issue) => outliers.push(Outlier {
+                    id: node.id.clone(),
+                    namespace: node.namespace.clone(),
+                    score: f32::INFINITY,
+                    issue: Some(issue),
+                }),
+            }
+        }
+
+        if valid.len() >= self.min_nodes.max(2) {
+            let stride = valid.len().div_ceil(self.max_references);
+            let references: Vec<usize> = (0..valid.len()).step_by(stride).collect();
+            let k = self.neighbors.min(references.len() - 1);
+            let distances: Vec<f32> = (0..valid.len())
+                .map(|i| {
+                    let mut to_references: Vec<f32> = references
+                        .iter()
+                        .filter(|&&j| j != i)
+                        .map(|&j| cosine_distance(&valid[i].1, &valid[j].1))
+                        .collect();
+                    to_references.sort_by(f32::total_cmp);
+                    to_references[k.min(to_references.len()) - 1]
+                })
+                .collect();
+
+            let median = median(distances.clone());
+            let mad = median_absolute_deviation(&distances, median);
+            // 1.4826 scales the MAD to a standard deviation for normal data.
+            // The floor keeps near-duplicate namespaces from flagging noise.
+            let scale = (1.4826 * mad).max(0.01);
+            for ((node, _), distance) in valid.iter().zip(&distances) {
+                let score = (distance - median) / scale;
+                if score > self.threshold {
+                    outliers.push(Outlier {
+                        id: node.id.clone(),
+                        namespace: node.namespace.clone(),
+                        score,
+                        issue: None,
+                    });
+                }
+            }
+        }
+
+        outliers.sort_by(|a, b| b.score.total_cmp(&a.score));
+        outliers
+    }
+}
+
+impl Default for OutlierDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+fn unit(v: &[f32]) -> Vec<f32> {
+    let norm = norm(v);
+    v.iter().map(|x| x / norm).collect()
+}
+
+/// `1 - cos` of two unit vectors.
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    1.0 - a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>()
+}
+
+fn common_dimension(nodes: &[MemoryNode]) -> Option<usize> {
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    for node in nodes.iter().filter(|n| !n.embedding.is_empty()) {
+        *counts.entry(node.embedding.len()).or_default() += 1;
+    }
+    // Ties go to the larger dimension so the result is deterministic
+    counts
+        .into_iter()
+        .max_by_key(|&(dimension, count)| (count, dimension))
+        .map(|(dimension, _)| dimension)
+}
+
+fn median(mut values: Vec<f32>) -> f32 {
+    values.sort_by(f32::total_cmp);
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+fn median_absolute_deviation(values: &[f32], median_value: f32) -> f32 {
+    median(values.iter().map(|v| (v - median_value).abs()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(id: &str, embedding: Vec<f32>) -> MemoryNode {
+        let mut node = MemoryNode::new(id.to_string()).with_embedding(embedding);
+        node.id = id.to_string();
+        node
+    }
+
+    /// Unit vector near axis `axis` of 4 dimensions, jittered by `seed`.
+    fn near(axis: usize, seed: usize) -> Vec<f32> {
+        let mut v: Vec<f32> = (0..4)
+            .map(|d| 0.05 * ((seed * 7 + d * 13) as f32).sin())
+            .collect();
+        v[axis] += 1.0;
+        unit(&v)
+    }
+
+    #[test]
+    fn test_validate() {
+        let check = EmbeddingCheck {
+            dimension: Some(3),
+            normalized: true,
+            norm_tolerance: DEFAULT_NORM_TOLERANCE,
+        };
+        assert_eq!(check.validate(&[0.6, 0.8, 0.0]), Ok(()));
+        assert_eq!(
+            check.validate(&[0.6, f32::NAN, 0.0]),
+            Err(EmbeddingIssue::NonFinite { index: 1 })
+        );
+        assert_eq!(check.validate(&[0.0; 3]), Err(EmbeddingIssue::Zero));
+        assert_eq!(
+            check.validate(&[1.0, 0.0]),
+            Err(EmbeddingIssue::Dimension {
+                expected: 3,
+                actual: 2
+            })
+        );
+        assert!(matches!(
+            check.validate(&[3.0, 4.0, 0.0]),
+            Err(EmbeddingIssue::Norm { .. })
+        ));
+
+        // Unnormalized providers only need finite, non-zero values
+        assert_eq!(EmbeddingCheck::new().validate(&[3.0, 4.0]), Ok(()));
+        assert!(EmbeddingCheck::new().validate(&[f32::INFINITY]).is_err());
+    }
+
+    #[test]
+    fn test_find_outliers() {
+        // Two topics of ten nodes each, one stray node and two corrupt ones
+        let mut nodes: Vec<MemoryNode> = (0..20)
+            .map(|i| node(&format!("n{}", i), near(i % 2, i)))
+            .collect();
+        nodes.push(node("stray", near(3, 99)));
+        nodes.push(node("nan", vec![f32::NAN, 0.0, 0.0, 1.0]));
+        nodes.push(node("zero", vec![0.0; 4]));
+        nodes.push(node("short", vec![1.0, 0.0]));
+        nodes.push(MemoryNode::new("not embedded yet".to_string()));
+
+        let outliers = OutlierDetector::new().find(&nodes, &EmbeddingCheck::new());
+        let mut kinds: Vec<(&str, &str)> = outliers
+            .iter()
+            .map(|o| (o.id.as_str(), o.kind()))
+            .collect();
+        kinds.sort();
+        assert_eq!(
+            kinds,
+            vec![
+                ("nan", "non_finite"),
+                ("short", "dimension"),
+                ("stray", "outlier"),
+                ("zero", "zero_vector"),
+            ]
+        );
+        assert!(outliers.last().unwrap().score.is_finite());
+
+        // Too few nodes for statistics
+        let few = &nodes[..5];
+        assert!(OutlierDetector::new()
+            .find(few, &EmbeddingCheck::new())
+            .is_empty());
+    }
+}
@@ -0,0 +1,853 @@
+//! JSON Schema - Validation and incremental checking for structured output.
+//!
+//! Supports the subset of JSON Schema that structured LLM output needs:
+//! `type` (a name or a list of names), `properties`, `required`,
+//! `additionalProperties`, `items`, `minItems`, `maxItems`, `enum` and
+//! `const`. Annotations such as `title` and `description` are ignored;
+//! composition keywords (`anyOf`, `oneOf`, `allOf`, `$ref`) are rejected.
+//!
+//! [`JsonPrefixValidator`] checks text one character at a time and rejects it
+//! as soon as it can no longer be completed into a matching document, which
+//! lets a sampler constrain decoding token by token.
+
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::ChatMessage;
+
+/// A compiled schema node.
+#[derive(Debug)]
+enum SchemaNode {
+    Any,
+    Null,
+    Boolean,
+    Integer,
+    Number,
+    String,
+    /// Allowed values, serialized compactly
+    Enum(Vec<String>),
+    Array {
+        items: Arc<SchemaNode>,
+        min_items: usize,
+        max_items: Option<usize>,
+    },
+    Object {
+        properties: Vec<(String, Arc<SchemaNode>)>,
+        required: Vec<String>,
+        /// `None` when `additionalProperties` is `false`
+        additional: Option<Arc<SchemaNode>>,
+    },
+    /// `type` given as a list; the first character of a value picks the variant
+    Union(Vec<Arc<SchemaNode>>),
+}
+
+/// A compiled JSON Schema.
+#[derive(Debug, Clone)]
+pub struct JsonSchema {
+    root: Arc<SchemaNode>,
+    source: Value,
+}
+
+impl JsonSchema {
+    /// Compile `schema`, rejecting keywords outside the supported subset.
+    pub fn new(schema: &Value) -> Result<Self> {
+        Ok(Self {
+            root: compile(schema, "$")?,
+            source: schema.clone(),
+        })
+    }
+
+    /// The schema as given.
+    pub fn as_value(&self) -> &Value {
+        &self.source
+    }
+
+    /// Check `value` against the schema.
+    pub fn validate(&self, value: &Value) -> Result<()> {
+        check(&self.root, value, "$").map_err(|message| Error::Validation { message })
+    }
+
+    /// Extract the JSON document from an LLM reply and validate it.
+    pub fn parse(&self, text: &str) -> Result<Value> {
+        let value = extract_json(text).ok_or_else(|| Error::Validation {
+            message: "Reply does not contain a JSON document".to_string(),
+        })?;
+        self.validate(&value)?;
+        Ok(value)
+    }
+
+    /// Start an incremental check of a document against this schema.
+    pub fn prefix_validator(&self) -> JsonPrefixValidator {
+        JsonPrefixValidator {
+            stack: vec![Frame::Value(self.root.clone())],
+            complete: false,
+        }
+    }
+
+    /// Messages asking a model to answer `prompt` with a matching document.
+    pub fn instructions(&self, prompt: &str) -> Vec<ChatMessage> {
+        vec![
+            ChatMessage::system(format!(
+                "Reply with a single JSON value that matches this JSON Schema, and nothing else:\n{}",
+                self.source
+            )),
+            ChatMessage::user(prompt),
+        ]
+    }
+}
+
+fn unsupported(path: &str, what: &str) -> Error {
+    Error::Validation {
+        message: format!("Unsupported JSON Schema at {}: {}", path, what),
+    }
+}
+
+fn compile(schema: &Value, path: &str) -> Result<Arc<SchemaNode>> {
+    let object = match schema {
+        Value::Bool(true) => return Ok(Arc::new(SchemaNode::Any)),
+        Value::Object(object) => object,
+        _ => return Err(unsupported(path, "schema must be an object")),
+    };
+    for keyword in ["anyOf", "oneOf", "allOf", "not", "$ref"] {
+        if object.contains_key(keyword) {
+            return Err(unsupported(path, keyword));
+        }
+    }
+
+    if let Some(value) = object.get("const") {
+        return Ok(Arc::new(SchemaNode::Enum(vec![value.to_string()])));
+    }
+    if let Some(values) = object.get("enum") {
+        let values = values.as_array().ok_or_else(|| unsupported(path, "enum must be an array"))?;
+        return Ok(Arc::new(SchemaNode::Enum(values.iter().map(Value::to_string).collect())));
+    }
+
+    match object.get("type") {
+        Some(Value::String(name)) => compile_type(name, object, path),
+        Some(Value::Array(names)) => {
+            let variants = names
+                .iter()
+                .map(|name| {
+                    let name = name.as_str().ok_or_else(|| unsupported(path, "type must be a string"))?;
+                    compile_type(name, object, path)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Arc::new(SchemaNode::Union(variants)))
+        }
+        Some(_) => Err(unsupported(path, "type must be a string or an array")),
+        None if object.contains_key("properties") => compile_type("object", object, path),
+        None if object.contains_key("items") => compile_type("array", object, path),
+        None => Ok(Arc::new(SchemaNode::Any)),
+    }
+}
+
+fn compile_type(name: &str, object: &serde_json::Map<String, Value>, path: &str) -> Result<Arc<SchemaNode>> {
+    let node = match name {
+        "null" => SchemaNode::Null,
+        "boolean" => SchemaNode::Boolean,
+        "integer" => SchemaNode::Integer,
+        "number" => SchemaNode::Number,
+        "string" => SchemaNode::String,
+        "array" => SchemaNode::Array {
+            items: match object.get("items") {
+                Some(items) => compile(items, &format!("{}[]", path))?,
+                None => Arc::new(SchemaNode::Any),
+            },
+            min_items: object.get("minItems").and_then(Value::as_u64).unwrap_or(0) as usize,
+            max_items: object.get("maxItems").and_then(Value::as_u64).map(|n| n as usize),
+        },
+        "object" => {
+            let properties = match object.get("properties") {
+                Some(Value::Object(properties)) => properties
+                    .iter()
+                    .map(|(key, schema)| Ok((key.clone(), compile(schema, &format!("{}.{}", path, key))?)))
+                    .collect::<Result<Vec<_>>>()?,
+                Some(_) => return Err(unsupported(path, "properties must be an object")),
+                None => Vec::new(),
+            };
+            let required = object
+                .get("required")
+                .and_then(Value::as_array)
+                .map(|keys| keys.iter().filter_map(|k| k.as_str().map(str::to_string)).collect())
+                .unwrap_or_default();
+            let additional = match object.get("additionalProperties") {
+                Some(Value::Bool(false)) => None,
+                Some(Value::Bool(true)) | None => Some(Arc::new(SchemaNode::Any)),
+                Some(schema) => Some(compile(schema, &format!("{}.*", path))?),
+            };
+            SchemaNode::Object { properties, required, additional }
+        }
+        other => return Err(unsupported(path, &format!("unknown type '{}'", other))),
+    };
+    Ok(Arc::new(node))
+}
+
+fn check(node: &SchemaNode, value: &Value, path: &str) -> std::result::Result<(), String> {
+    let mismatch = |expected: &str| Err(format!("{}: expected {}, got {}", path, expected, value));
+    match node {
+        SchemaNode::Any => Ok(()),
+        SchemaNode::Null if value.is_null() => Ok(()),
+        SchemaNode::Null => mismatch("null"),
+        SchemaNode::Boolean if value.is_boolean() => Ok(()),
+        SchemaNode::Boolean => mismatch("a boolean"),
+        SchemaNode::Integer if value.is_i64() || value.is_u64() => Ok(()),
+        SchemaNode::Integer => mismatch("an integer"),
+        SchemaNode::Number if value.is_number() => Ok(()),
+        SchemaNode::Number => mismatch("a number"),
+        SchemaNode::String if value.is_string() => Ok(()),
+        SchemaNode::String => mismatch("a string"),
+        SchemaNode::Enum(allowed) => {
+            if allowed.contains(&value.to_string()) {
+                Ok(())
+            } else {
+                mismatch(&format!("one of [{}]", allowed.join(", ")))
+            }
+        }
+        SchemaNode::Array { items, min_items, max_items } => {
+            let Some(array) = value.as_array() else {
+                return mismatch("an array");
+            };
+            if array.len() < *min_items {
+                return Err(format!("{}: expected at least {} items, got {}", path, min_items, array.len()));
+            }
+            if let Some(max) = max_items.filter(|&max| array.len() > max) {
+                return Err(format!("{}: expected at most {} items, got {}", path, max, array.len()));
+            }
+            array
+                .iter()
+                .enumerate()
+                .try_for_each(|(i, item)| check(items, item, &format!("{}[{}]", path, i)))
+        }
+        SchemaNode::Object { properties, required, additional } => {
+            let Some(object) = value.as_object() else {
+                return mismatch("an object");
+            };
+            if let Some(missing) = required.iter().find(|key| !object.contains_key(*key)) {
+                return Err(format!("{}: missing required property '{}'", path, missing));
+            }
+            for (key, item) in object {
+                let item_path = format!("{}.{}", path, key);
+                match properties.iter().find(|(name, _)| name == key) {
+                    Some((_, schema)) => check(schema, item, &item_path)?,
+                    None => match additional {
+                        Some(schema) => check(schema, item, &item_path)?,
+                        None => return Err(format!("{}: unexpected property '{}'", path, key)),
+                    },
+                }
+            }
+            Ok(())
+        }
+        SchemaNode::Union(variants) => {
+            if variants.iter().any(|v| check(v, value, path).is_ok()) {
+                Ok(())
+            } else {
+                mismatch("a value matching one of the listed types")
+            }
+        }
+    }
+}
+
+/// Find the JSON document in an LLM reply: the whole reply, a fenced code
+/// block, or the outermost `{...}` / `[...]` span.
+pub fn extract_json(text: &str) -> Option<Value> {
+    let trimmed = text.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Some(value);
+    }
+
+    if let Some(start) = trimmed.find("```") {
+        let body = &trimmed[start + 3..];
+        let body = body.strip_prefix("json").unwrap_or(body);
+        if let Some(end) = body.find("```") {
+            if let Ok(value) = serde_json::from_str(body[..end].trim()) {
+                return Some(value);
+            }
+        }
+    }
+
+    for (open, close) in [('{', '}'), ('[', ']')] {
+        if let (Some(start), Some(end)) = (trimmed.find(open), trimmed.rfind(close)) {
+            if start < end {
+                if let Ok(value) = serde_json::from_str(&trimmed[start..=end]) {
+                    return Some(value);
+                }
+            }
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ObjectState {
+    KeyOrEnd,
+    Key,
+    Colon,
+    CommaOrEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArrayState {
+    ValueOrEnd,
+    CommaOrEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NumberState {
+    Minus,
+    Zero,
+    Int,
+    Dot,
+    Frac,
+    Exp,
+    ExpSign,
+    ExpDigits,
+}
+
+impl NumberState {
+    fn start(c: char) -> Option<Self> {
+        match c {
+            '-' => Some(Self::Minus),
+            '0' => Some(Self::Zero),
+            '1'..='9' => Some(Self::Int),
+            _ => None,
+        }
+    }
+
+    fn next(self, c: char, integer: bool) -> Option<Self> {
+        use NumberState::*;
+        match (self, c) {
+            (Minus, '0') => Some(Zero),
+            (Minus, '1'..='9') | (Int, '0'..='9') => Some(Int),
+            (Zero | Int, '.') if !integer => Some(Dot),
+            (Dot | Frac, '0'..='9') => Some(Frac),
+            (Zero | Int | Frac, 'e' | 'E') if !integer => Some(Exp),
+            (Exp, '+' | '-') => Some(ExpSign),
+            (Exp | ExpSign | ExpDigits, '0'..='9') => Some(ExpDigits),
+            _ => None,
+        }
+    }
+
+    fn is_terminal(self) -> bool {
+        matches!(self, Self::Zero | Self::Int | Self::Frac | Self::ExpDigits)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Escape {
+    None,
+    Backslash,
+    Unicode(u8, u32),
+}
+
+#[derive(Debug, Clone)]
+enum Frame {
+    /// Expecting a value (leading whitespace allowed)
+    Value(Arc<SchemaNode>),
+    Object {
+        node: Arc<SchemaNode>,
+        state: ObjectState,
+        seen: Vec<String>,
+    },
+    Array {
+        node: Arc<SchemaNode>,
+        state: ArrayState,
+        count: usize,
+    },
+    String {
+        /// Property names the string may still become (`None` for values
+        /// and keys of objects that allow additional properties)
+        keys: Option<Vec<String>>,
+        is_key: bool,
+        decoded: String,
+        escape: Escape,
+    },
+    Number {
+        integer: bool,
+        state: NumberState,
+    },
+    /// Fixed spellings: `true`, `false`, `null` and enum values
+    Literal {
+        candidates: Vec<String>,
+        text: String,
+    },
+}
+
+/// Incremental checker: accepts text only while it is a prefix of some
+/// document matching the schema.
+#[derive(Debug, Clone)]
+pub struct JsonPrefixValidator {
+    stack: Vec<Frame>,
+    complete: bool,
+}
+
+impl JsonPrefixValidator {
+    /// Feed `text`; returns `false` (leaving the state undefined) if the text
+    /// can no longer be completed into a matching document.
+    pub fn feed(&mut self, text: &str) -> bool {
+        text.chars().all(|c| self.feed_char(c))
+    }
+
+    /// Whether the text so far is a complete matching document.
+    pub fn is_complete(&self) -> bool {
+        // A trailing number only ends at a delimiter.
+        self.complete || {
+            let mut probe = self.clone();
+            probe.feed_char(' ') && probe.complete
+        }
+    }
+
+    /// Whether the document is closed, so only whitespace may follow.
+    ///
+    /// Unlike [`is_complete`](Self::is_complete) this is `false` for a
+    /// top-level number, which could still gain digits.
+    pub fn is_finished(&self) -> bool {
+        self.complete
+    }
+
+    fn feed_char(&mut self, c: char) -> bool {
+        loop {
+            let Some(frame) = self.stack.last_mut() else {
+                // Only trailing whitespace after the document.
+                return c.is_whitespace();
+            };
+
+            match frame {
+                Frame::Value(node) => {
+                    if c.is_whitespace() {
+                        return true;
+                    }
+                    let node = node.clone();
+                    self.stack.pop();
+                    return self.start_value(&node, c);
+                }
+                Frame::Object { node, state, seen } => {
+                    if c.is_whitespace() {
+                        return true;
+                    }
+                    let SchemaNode::Object { properties, required, additional } = node.as_ref() else {
+                        return false;
+                    };
+                    match (*state, c) {
+                        (ObjectState::KeyOrEnd | ObjectState::Key, '"') => {
+                            let keys = match additional {
+                                Some(_) => None,
+                                None => {
+                                    let unseen: Vec<String> = properties
+                                        .iter()
+                                        .map(|(name, _)| name.clone())
+                                        .filter(|name| !seen.contains(name))
+                                        .collect();
+                                    if unseen.is_empty() {
+                                        return false;
+                                    }
+                                    Some(unseen)
+                                }
+                            };
+                            self.stack.push(Frame::String {
+                                keys,
+                                is_key: true,
+                                decoded: String::new(),
+                                escape: Escape::None,
+                            });
+                            return true;
+                        }
+                        (ObjectState::Colon, ':') => {
+                            let key = seen.last().cloned().unwrap_or_default();
+                            let value_node = properties
+                                .iter()
+                                .find(|(name, _)| *name == key)
+                                .map(|(_, schema)| schema.clone())
+                                .or_else(|| additional.clone())
+                                .unwrap_or_else(|| Arc::new(SchemaNode::Any));
+                            *state = ObjectState::CommaOrEnd;
+                            self.stack.push(Frame::Value(value_node));
+                            return true;
+                        }
+                        (ObjectState::CommaOrEnd, ',') => {
+                            *state = ObjectState::Key;
+                            return true;
+                        }
+                        (ObjectState::KeyOrEnd | ObjectState::CommaOrEnd, '}') => {
+                            if !required.iter().all(|key| seen.contains(key)) {
+                                return false;
+                            }
+                            self.stack.pop();
+                            self.finish_value();
+                            return true;
+                        }
+                        _ => return false,
+                    }
+                }
+                Frame::Array { node, state, count } => {
+                    if c.is_whitespace() {
+                        return true;
+                    }
+                    let SchemaNode::Array { items, min_items, max_items } = node.as_ref() else {
+                        return false;
+                    };
+                    let full = max_items.is_some_and(|max| *count >= max);
+                    match (*state, c) {
+                        (_, ']') if *state == ArrayState::CommaOrEnd || *count == 0 => {
+                            if *count < *min_items {
+                                return false;
+                            }
+                            self.stack.pop();
+                            self.finish_value();
+                            return true;
+                        }
+                        (ArrayState::CommaOrEnd, ',') => {
+                            if full {
+                                return false;
+                            }
+                            *count += 1;
+                            let items = items.clone();
+                            self.stack.push(Frame::Value(items));
+                            return true;
+                        }
+                        (ArrayState::ValueOrEnd, _) => {
+                            if full {
+                                return false;
+                            }
+                            *count += 1;
+                            *state = ArrayState::CommaOrEnd;
+                            let items = items.clone();
+                            self.stack.push(Frame::Value(items));
+                            // Reprocess `c` as the start of the first item.
+                            continue;
+                        }
+                        _ => return false,
+                    }
+                }
+                Frame::String { keys, is_key, decoded, escape } => {
+                    let pushed = match escape {
+                        Escape::None => match c {
+                            '"' => {
+                                if let Some(keys) = keys {
+                                    if !keys.contains(decoded) {
+                                        return false;
+                                    }
+                                }
+                                let (is_key, key) = (*is_key, decoded.clone());
+                                self.stack.pop();
+                                if is_key {
+                                    // Record the key; the object now expects ':'.
+                                    if let Some(Frame::Object { state, seen, .. }) = self.stack.last_mut() {
+                                        if seen.contains(&key) {
+                                            return false;
+                                        }
+                                        seen.push(key);
+                                        *state = ObjectState::Colon;
+                                    }
+                                } else {
+                                    self.finish_value();
+                                }
+                                return true;
+                            }
+                            '\\' => {
+                                *escape = Escape::Backslash;
+                                return true;
+                            }
+                            c if (c as u32) < 0x20 => return false,
+                            c => c,
+                        },
+                        Escape::Backslash => {
+                            let unescaped = match c {
+                                '"' => '"',
+                                '\\' => '\\',
+                                '/' => '/',
+                                'b' => '\u{8}',
+                                'f' => '\u{c}',
+                                'n' => '\n',
+                                'r' => '\r',
+                                't' => '\t',
+                                'u' => {
+                                    *escape = Escape::Unicode(0, 0);
+                                    return true;
+                                }
+                                _ => return false,
+                            };
+                            *escape = Escape::None;
+                            unescaped
+                        }
+                        Escape::Unicode(n, acc) => {
+                            let Some(digit) = c.to_digit(16) else {
+                                return false;
+                            };
+                            let acc = *acc * 16 + digit;
+                            if *n < 3 {
+                                *escape = Escape::Unicode(*n + 1, acc);
+                                return true;
+                            }
+                            *escape = Escape::None;
+                            char::from_u32(acc).unwrap_or('\u{FFFD}')
+                        }
+                    };
+                    decoded.push(pushed);
+                    if let Some(keys) = keys {
+                        return keys.iter().any(|key| key.starts_with(decoded.as_str()));
+                    }
+                    return true;
+                }
+                Frame::Number { integer, state } => {
+                    if let Some(next) = state.next(c, *integer) {
+                        *state = next;
+                        return true;
+                    }
+                    if !state.is_terminal() {
+                        return false;
+                    }
+                    self.stack.pop();
+                    self.finish_value();
+                    // Reprocess the delimiter in the enclosing frame.
+                    continue;
+                }
+                Frame::Literal { candidates, text } => {
+                    let mut extended = text.clone();
+                    extended.push(c);
+                    if candidates.iter().any(|cand| cand.starts_with(&extended)) {
+                        *text = extended;
+                        self.settle_literal();
+                        return true;
+                    }
+                    if !candidates.contains(text) {
+                        return false;
+                    }
+                    self.stack.pop();
+                    self.finish_value();
+                    continue;
+                }
+            }
+        }
+    }
+
+    /// Begin a value of type `node` whose first character is `c`.
+    fn start_value(&mut self, node: &Arc<SchemaNode>, c: char) -> bool {
+        match node.as_ref() {
+            SchemaNode::Any => {
+                let concrete = match c {
+                    '{' => SchemaNode::Object {
+                        properties: Vec::new(),
+                        required: Vec::new(),
+                        additional: Some(node.clone()),
+                    },
+                    '[' => SchemaNode::Array {
+                        items: node.clone(),
+                        min_items: 0,
+                        max_items: None,
+                    },
+                    '"' => SchemaNode::String,
+                    't' | 'f' => SchemaNode::Boolean,
+                    'n' => SchemaNode::Null,
+                    _ => SchemaNode::Number,
+                };
+                self.start_value(&Arc::new(concrete), c)
+            }
+            SchemaNode::Union(variants) => {
+                // Integers and numbers share a first character; prefer the wider type.
+                let mut matching: Vec<&Arc<SchemaNode>> = variants.iter().filter(|v| starts_with(v, c)).collect();
+                matching.sort_by_key(|v| matches!(v.as_ref(), SchemaNode::Integer));
+                match matching.first() {
+                    Some(variant) => {
+                        let variant = (*variant).clone();
+                        self.start_value(&variant, c)
+                    }
+                    None => false,
+                }
+            }
+            SchemaNode::Null => self.start_literal(vec!["null".into()], c),
+            SchemaNode::Boolean => self.start_literal(vec!["true".into(), "false".into()], c),
+            SchemaNode::Enum(values) => self.start_literal(values.clone(), c),
+            SchemaNode::Integer | SchemaNode::Number => match NumberState::start(c) {
+                Some(state) => {
+                    let integer = matches!(node.as_ref(), SchemaNode::Integer);
+                    self.stack.push(Frame::Number { integer, state });
+                    true
+                }
+                None => false,
+            },
+            SchemaNode::String => {
+                if c != '"' {
+                    return false;
+                }
+                self.stack.push(Frame::String {
+                    keys: None,
+                    is_key: false,
+                    decoded: String::new(),
+                    escape: Escape::None,
+                });
+                true
+            }
+            SchemaNode::Object { .. } => {
+                if c != '{' {
+                    return false;
+                }
+                self.stack.push(Frame::Object {
+                    node: node.clone(),
+                    state: ObjectState::KeyOrEnd,
+                    seen: Vec::new(),
+                });
+                true
+            }
+            SchemaNode::Array { .. } => {
+                if c != '[' {
+                    return false;
+                }
+                self.stack.push(Frame::Array {
+                    node: node.clone(),
+                    state: ArrayState::ValueOrEnd,
+                    count: 0,
+                });
+                true
+            }
+        }
+    }
+
+    fn start_literal(&mut self, candidates: Vec<String>, c: char) -> bool {
+        let text = c.to_string();
+        let candidates: Vec<String> = candidates.into_iter().filter(|cand| cand.starts_with(&text)).collect();
+        if candidates.is_empty() {
+            return false;
+        }
+        self.stack.push(Frame::Literal { candidates, text });
+        self.settle_literal();
+        true
+    }
+
+    /// Close a literal as soon as it is complete and cannot be extended.
+    fn settle_literal(&mut self) {
+        if let Some(Frame::Literal { candidates, text }) = self.stack.last() {
+            let exact = candidates.contains(text);
+            let extendable = candidates.iter().any(|cand| cand.len() > text.len() && cand.starts_with(text.as_str()));
+            if exact && !extendable {
+                self.stack.pop();
+                self.finish_value();
+            }
+        }
+    }
+
+    /// A value's frame was just popped; the document is complete if it was the root.
+    fn finish_value(&mut self) {
+        if self.stack.is_empty() {
+            self.complete = true;
+        }
+    }
+}
+
+/// Whether a value of type `node` can start with `c`.
+fn starts_with(node: &SchemaNode, c: char) -> bool {
+    match node {
+        SchemaNode::Any => true,
+        SchemaNode::Null => c == 'n',
+        SchemaNode::Boolean => c == 't' || c == 'f',
+        SchemaNode::Integer | SchemaNode::Number => NumberState::start(c).is_some(),
+        SchemaNode::String => c == '"',
+        SchemaNode::Enum(values) => values.iter().any(|v| v.starts_with(c)),
+        SchemaNode::Array { .. } => c == '[',
+        SchemaNode::Object { .. } => c == '{',
+        SchemaNode::Union(variants) => variants.iter().any(|v| starts_with(v, c)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn person() -> JsonSchema {
+        JsonSchema::new(&json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "age": { "type": "integer" },
+                "tags": { "type": "array", "items": { "enum": ["friend", "work"] }, "maxItems": 2 },
+                "email": { "type": ["string", "null"] }
+            },
+            "required": ["name", "age"],
+            "additionalProperties": false
+        }))
+        .unwrap()
+    }
+
+    fn accepts(schema: &JsonSchema, text: &str) -> bool {
+        schema.prefix_validator().feed(text)
+    }
+
+    fn completes(schema: &JsonSchema, text: &str) -> bool {
+        let mut validator = schema.prefix_validator();
+        validator.feed(text) && validator.is_complete()
+    }
+
+    #[test]
+    fn test_validate_reports_path() {
+        let schema = person();
+        assert!(schema.validate(&json!({ "name": "Ada", "age": 36, "tags": ["work"] })).is_ok());
+
+        let error = schema.validate(&json!({ "name": "Ada", "age": "36" })).unwrap_err().to_string();
+        assert!(error.contains("$.age"), "{}", error);
+        let error = schema.validate(&json!({ "name": "Ada" })).unwrap_err().to_string();
+        assert!(error.contains("age"), "{}", error);
+        assert!(schema.validate(&json!({ "name": "Ada", "age": 1, "extra": true })).is_err());
+        assert!(schema.validate(&json!({ "name": "Ada", "age": 1, "tags": ["a", "b", "c"] })).is_err());
+    }
+
+    #[test]
+    fn test_prefix_accepts_partial_documents() {
+        let schema = person();
+        for prefix in ["", "  {", "{\"na", "{\"name\": \"A", "{\"name\":\"A\\u00e9\",\"age\":-1", "{\"age\": 3, \"tags\": [\"fr"] {
+            assert!(accepts(&schema, prefix), "should accept {:?}", prefix);
+            assert!(!completes(&schema, prefix), "should not be complete: {:?}", prefix);
+        }
+    }
+
+    #[test]
+    fn test_prefix_rejects_impossible_text() {
+        let schema = person();
+        for text in [
+            "Sure! {",
+            "{\"nickname\"",
+            "{\"name\": 5",
+            "{\"age\": 1.5",
+            "{\"age\": 01",
+            "{\"name\": \"a\", \"name\"",
+            "{\"age\": 1, \"tags\": [\"home\"",
+            "{\"age\": 1, \"tags\": [\"work\", \"work\", ",
+            "{\"name\": \"a\"}",
+        ] {
+            assert!(!accepts(&schema, text), "should reject {:?}", text);
+        }
+    }
+
+    #[test]
+    fn test_complete_documents() {
+        let schema = person();
+        assert!(completes(&schema, "{\"name\": \"Ada\", \"age\": 36}"));
+        assert!(completes(&schema, "{\"age\":36,\"name\":\"Ada\",\"email\":null,\"tags\":[]} \n"));
+        assert!(!accepts(&schema, "{\"age\":36,\"name\":\"Ada\"} x"));
+
+        let number = JsonSchema::new(&json!({ "type": "number" })).unwrap();
+        assert!(completes(&number, "-12.5e3"));
+        assert!(!completes(&number, "-12."));
+    }
+
+    #[test]
+    fn test_any_schema_accepts_any_json() {
+        let schema = JsonSchema::new(&json!({})).unwrap();
+        assert!(completes(&schema, r#"{"a": [1, true, null, {"b": "c"}]}"#));
+        assert!(!accepts(&schema, r#"{"a" 1"#));
+    }
+
+    #[test]
+    fn test_rejects_unsupported_keywords() {
+        assert!(JsonSchema::new(&json!({ "anyOf": [{ "type": "string" }] })).is_err());
+        assert!(JsonSchema::new(&json!({ "type": "date" })).is_err());
+    }
+
+    #[test]
+    fn test_extract_json_from_reply() {
+        assert_eq!(extract_json(" {\"a\": 1} "), Some(json!({ "a": 1 })));
+        assert_eq!(extract_json("Here you go:\n```json\n[1, 2]\n```"), Some(json!([1, 2])));
+        assert_eq!(extract_json("The answer is {\"a\": {\"b\": 2}} as requested."), Some(json!({ "a": { "b": 2 } })));
+        assert_eq!(extract_json("no json here"), None);
+    }
+}
@@ -0,0 +1,157 @@
+//! Request handling shared by the HTTP, MCP and gRPC front ends.
+//!
+//! Each front end decodes its own wire format and maps [`Error`]s to its own
+//! status codes; the limits, validation and calls into the core live here so
+//! the APIs cannot drift apart.
+
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::logic::chunked_memory::ChunkedMemory;
+use crate::logic::metabolism::Metabolism;
+use crate::ports::{BufferPort, EmbeddingPort, LlmPort, MemoryPort, SearchResult};
+use crate::{Interaction, MemoryNode};
+
+/// Largest `top_k` a search may ask for.
+pub const MAX_TOP_K: usize = 100;
+/// `top_k` used when a request does not set one.
+pub const DEFAULT_TOP_K: usize = 5;
+/// Highest HiRAG layer reported by [`stats`].
+pub const MAX_STATS_LAYER: u8 = 10;
+
+/// Memory and buffer sizes reported by the stats endpoints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub total_memories: usize,
+    pub buffer_size: usize,
+    /// Non-empty layers as `(layer, count)`, lowest first
+    pub layers: Vec<(u8, usize)>,
+}
+
+/// Embed (chunking long text) and store `node`.
+pub async fn store(memory: Arc<dyn MemoryPort>, embedder: Arc<dyn EmbeddingPort>, node: MemoryNode) -> Result<String> {
+    if node.content.trim().is_empty() {
+        return Err(invalid("content must not be empty"));
+    }
+    ChunkedMemory::new(memory, embedder).store(node).await
+}
+
+/// Semantic search over whole memories, closest first.
+pub async fn search(
+    memory: Arc<dyn MemoryPort>,
+    embedder: Arc<dyn EmbeddingPort>,
+    query: &str,
+    namespace: Option<&str>,
+    top_k: usize,
+) -> Result<Vec<SearchResult>> {
+    validate_top_k(top_k)?;
+    let embedding = embedder.embed(query).await?;
+    let memory = ChunkedMemory::new(memory, embedder);
+    match namespace {
+        Some(namespace) => memory.search_namespace(&embedding, namespace, top_k).await,
+        None => memory.search(&embedding, top_k).await,
+    }
+}
+
+/// Reject a `top_k` outside `1..=MAX_TOP_K`.
+pub fn validate_top_k(top_k: usize) -> Result<()> {
+    if top_k == 0 || top_k > MAX_TOP_K {
+        return Err(invalid(format!("top_k must be between 1 and {}", MAX_TOP_K)));
+    }
+    Ok(())
+}
+
+/// Reject relation names the store cannot use.
+pub fn validate_relation(relation: &str) -> Result<()> {
+    // The relation names a graph table, so keep it to identifier characters.
+    let valid = !relation.is_empty() && relation.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(invalid("relation must be a non-empty identifier (letters, digits, '_')"));
+    }
+    Ok(())
+}
+
+/// Queue an exchange for digestion; returns the new buffer size.
+pub async fn push(buffer: &dyn BufferPort, interaction: Interaction) -> Result<usize> {
+    if interaction.user_input.trim().is_empty() {
+        return Err(invalid("user_input must not be empty"));
+    }
+    buffer.push(interaction).await?;
+    buffer.len().await
+}
+
+/// Summarize the buffer into Layer 0; `force` digests below the threshold.
+pub async fn digest(
+    buffer: Arc<dyn BufferPort>,
+    memory: Arc<dyn MemoryPort>,
+    llm: Arc<dyn LlmPort>,
+    embedder: Arc<dyn EmbeddingPort>,
+    force: bool,
+) -> Result<usize> {
+    let mut metabolism = Metabolism::new(buffer, memory, llm, embedder);
+    if force {
+        metabolism = metabolism.with_threshold(1);
+    }
+    metabolism.digest().await
+}
+
+/// Count memories per layer (up to [`MAX_STATS_LAYER`]) and buffered exchanges.
+pub async fn stats(memory: &dyn MemoryPort, buffer: &dyn BufferPort) -> Result<MemoryStats> {
+    let total_memories = memory.count().await?;
+    let buffer_size = buffer.len().await?;
+
+    let mut layers = Vec::new();
+    for layer in 0..=MAX_STATS_LAYER {
+        let count = memory.count_by_layer(layer).await?;
+        if count > 0 {
+            layers.push((layer, count));
+        }
+    }
+
+    Ok(MemoryStats {
+        total_memories,
+        buffer_size,
+        layers,
+    })
+}
+
+/// Compare secrets (e.g. API keys) without short-circuiting on the first
+/// differing byte.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::Validation {
+        message: message.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_k_bounds() {
+        assert!(validate_top_k(0).is_err());
+        assert!(validate_top_k(1).is_ok());
+        assert!(validate_top_k(MAX_TOP_K).is_ok());
+        assert!(validate_top_k(MAX_TOP_K + 1).is_err());
+    }
+
+    #[test]
+    fn test_relation_must_be_identifier() {
+        assert!(validate_relation("summarizes").is_ok());
+        assert!(validate_relation("chunk_of2").is_ok());
+        for bad in ["", "a-b", "x->memory_node", "rel; DELETE memory_node", "räksmörgås"] {
+            assert!(matches!(validate_relation(bad), Err(Error::Validation { .. })), "{:?} accepted", bad);
+        }
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+    }
+}
@@ -0,0 +1,409 @@
+//! Memory Chat - Retrieval-augmented conversation over long-term memory.
+//!
+//! Each user turn is embedded and used to retrieve relevant memories, which
+//! are placed in the system prompt within a token budget. Completed exchanges
+//! are pushed into the short-term buffer so metabolism can digest them.
+
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::logic::chunked_memory::ChunkedMemory;
+use crate::logic::prompt_builder::{PromptBuilder, PromptSection};
+use crate::ports::{BufferPort, EmbeddingPort, GenerationParams, LlmPort, MemoryPort, SearchResult, TokenStream};
+use crate::{ChatMessage, ChatRole, Interaction};
+
+/// A prepared turn: the prompt to send and the memories it includes.
+#[derive(Debug, Clone)]
+pub struct ChatTurn {
+    /// Messages to send to the LLM
+    pub messages: Vec<ChatMessage>,
+    /// Memories placed in the prompt, best match first
+    pub memories: Vec<SearchResult>,
+}
+
+/// Chat session that grounds replies in long-term memory.
+pub struct MemoryChat {
+    llm: Arc<dyn LlmPort>,
+    embedder: Arc<dyn EmbeddingPort>,
+    memory: ChunkedMemory,
+    buffer: Arc<dyn BufferPort>,
+    namespace: Option<String>,
+    session_id: String,
+    top_k: usize,
+    context_budget: usize,
+    reserved_output: usize,
+    history_turns: usize,
+    history: Vec<(String, String)>,
+}
+
+impl MemoryChat {
+    /// Create a chat session with a fresh session ID.
+    pub fn new(
+        llm: Arc<dyn LlmPort>,
+        embedder: Arc<dyn EmbeddingPort>,
+        memory: Arc<dyn MemoryPort>,
+        buffer: Arc<dyn BufferPort>,
+    ) -> Self {
+        Self {
+            llm,
+            memory: ChunkedMemory::new(memory, embedder.clone()),
+            embedder,
+            buffer,
+            namespace: None,
+            session_id: Uuid::new_v4().to_string(),
+            top_k: 5,
+            context_budget: 512,
+            reserved_output: 256,
+            history_turns: 4,
+            history: Vec::new(),
+        }
+    }
+
+    /// Only retrieve memories from `namespace`.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Resume or name a session.
+    pub fn with_session(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = session_id.into();
+        self
+    }
+
+    /// Number of memories to retrieve per turn.
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k;
+        self
+    }
+
+    /// Token budget for memories placed in the prompt (further limited by
+    /// the LLM's context window).
+    pub fn with_context_budget(mut self, tokens: usize) -> Self {
+        self.context_budget = tokens;
+        self
+    }
+
+    /// Tokens of the context window kept free for the reply.
+    pub fn with_reserved_output(mut self, tokens: usize) -> Self {
+        self.reserved_output = tokens;
+        self
+    }
+
+    /// Number of previous exchanges replayed in the prompt.
+    pub fn with_history_turns(mut self, turns: usize) -> Self {
+        self.history_turns = turns;
+        self
+    }
+
+    /// The session ID recorded on every interaction.
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Retrieve memories for `user_input` and build the prompt.
+    pub async fn prepare(&self, user_input: &str) -> Result<ChatTurn> {
+        let embedding = self.embedder.embed(user_input).await?;
+        let hits = match &self.namespace {
+            Some(namespace) => self.memory.search_namespace(&embedding, namespace, self.top_k).await?,
+            None => self.memory.search(&embedding, self.top_k).await?,
+        };
+
+        // Best matches first; skip any memory that would overflow the budget.
+        let mut used = 0;
+        let mut memories = Vec::new();
+        for hit in hits {
+            let cost = self.llm.count_tokens(&hit.node.content);
+            if used + cost <= self.context_budget {
+                used += cost;
+                memories.push(hit);
+            }
+        }
+
+        let mut intro = String::from("You are Synapse, an assistant with long-term memory.");
+        if !memories.is_empty() {
+            intro.push_str(" Use these memories if they are relevant:");
+        }
+        let skip = self.history.len().saturating_sub(self.history_turns);
+        let history = self.history[skip..]
+            .iter()
+            .flat_map(|(user, assistant)| [ChatMessage::user(user.clone()), ChatMessage::assistant(assistant.clone())])
+            .collect();
+
+        let prompt = PromptBuilder::for_llm(self.llm.as_ref())
+            .reserve_output(self.reserved_output)
+            .section(PromptSection::text("system", ChatRole::System, intro).with_priority(3))
+            .section(
+                PromptSection::items(
+                    "memories",
+                    ChatRole::System,
+                    memories.iter().enumerate().map(|(i, m)| format!("[{}] {}", i + 1, m.node.content)),
+                )
+                .with_priority(1),
+            )
+            .section(PromptSection::messages("history", history).with_priority(2))
+            .section(PromptSection::text("query", ChatRole::User, user_input).with_priority(3))
+            .build();
+
+        // Report only the memories that made it into the prompt.
+        memories.truncate(prompt.section("memories").map_or(0, |r| r.items_kept));
+        let messages = prompt.messages;
+
+        Ok(ChatTurn { messages, memories })
+    }
+
+    /// Stream the reply to a prepared turn.
+    ///
+    /// Call [`MemoryChat::record`] with the collected reply afterwards.
+    pub async fn stream(&self, turn: &ChatTurn, params: &GenerationParams) -> Result<TokenStream> {
+        self.llm.chat_stream(&turn.messages, params).await
+    }
+
+    /// Add a completed exchange to the history and the short-term buffer.
+    pub async fn record(&mut self, user_input: &str, reply: &str) -> Result<()> {
+        let interaction = Interaction::new(user_input.to_string(), reply.to_string())
+            .with_session(self.session_id.clone());
+        self.buffer.push(interaction).await?;
+        self.history.push((user_input.to_string(), reply.to_string()));
+        Ok(())
+    }
+
+    /// Answer `user_input` and record the exchange.
+    pub async fn send(&mut self, user_input: &str, params: &GenerationParams) -> Result<(String, ChatTurn)> {
+        let turn = self.prepare(user_input).await?;
+        let reply = self.llm.chat(&turn.messages, params).await?;
+        self.record(user_input, &reply).await?;
+        Ok((reply, turn))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryNode;
+    use async_trait::async_trait;
+    use tokio::sync::Mutex;
+
+    // === Mock Memory: nodes match when they share the query's hot dimension ===
+    struct MockMemory {
+        nodes: Vec<MemoryNode>,
+        namespaces: Mutex<Vec<String>>,
+    }
+
+    impl MockMemory {
+        fn matching(&self, embedding: &[f32], namespace: Option<&str>) -> Vec<SearchResult> {
+            let hot = embedding.iter().position(|&x| x > 0.5).unwrap_or(0);
+            self.nodes
+                .iter()
+                .filter(|n| n.embedding[hot] > 0.0 && namespace.is_none_or(|ns| n.namespace == ns))
+                .map(|n| SearchResult { node: n.clone(), distance: 0.1 })
+                .collect()
+        }
+    }
+
+    #[async_trait]
+    impl MemoryPort for MockMemory {
+        async fn store(&self, node: MemoryNode) -> Result<String> {
+            Ok(node.id)
+        }
+
+        async fn search(&self, embedding: &[f32], _top_k: usize) -> Result<Vec<SearchResult>> {
+            Ok(self.matching(embedding, None))
+        }
+
+        async fn search_layer(&self, embedding: &[f32], _layer: u8, top_k: usize) -> Result<Vec<SearchResult>> {
+            self.search(embedding, top_k).await
+        }
+
+        async fn search_namespace(&self, embedding: &[f32], namespace: &str, _top_k: usize) -> Result<Vec<SearchResult>> {
+            self.namespaces.lock().await.push(namespace.to_string());
+            Ok(self.matching(embedding, Some(namespace)))
+        }
+
+        async fn get_by_id(&self, id: &str) -> Result<Option<MemoryNode>> {
+            Ok(self.nodes.iter().find(|n| n.id == id).cloned())
+        }
+
+        async fn get_by_layer(&self, _layer: u8) -> Result<Vec<MemoryNode>> {
+            Ok(Vec::new())
+        }
+
+        async fn update(&self, _node: MemoryNode) -> Result<()> {
+            Ok(())
+        }
+
+        async fn delete(&self, _id: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn count(&self) -> Result<usize> {
+            Ok(self.nodes.len())
+        }
+
+        async fn add_relationship(&self, _from_id: &str, _relation: &str, _to_id: &str) -> Result<()> {
+            Ok(())
+        }
+
+        async fn count_by_layer(&self, _layer: u8) -> Result<usize> {
+            Ok(0)
+        }
+    }
+
+    // === Mock Embedder: one dimension per keyword ===
+    const KEYWORDS: [&str; 2] = ["coffee", "cat"];
+
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl EmbeddingPort for KeywordEmbedder {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+            Ok(KEYWORDS.iter().map(|k| if text.contains(k) { 1.0 } else { 0.0 }).collect())
+        }
+
+        fn dimension(&self) -> usize {
+            KEYWORDS.len()
+        }
+
+        fn provider_name(&self) -> &str {
+            "keyword"
+        }
+    }
+
+    // === Mock LLM: records the prompt and replies with a fixed answer ===
+    struct RecordingLlm {
+        prompts: Mutex<Vec<Vec<ChatMessage>>>,
+    }
+
+    #[async_trait]
+    impl LlmPort for RecordingLlm {
+        async fn generate(&self, _prompt: &str, _max_tokens: usize) -> Result<String> {
+            Ok("Noted.".to_string())
+        }
+
+        async fn generate_with_params(&self, prompt: &str, max_tokens: usize, _temp: f32, _top_p: f32) -> Result<String> {
+            self.generate(prompt, max_tokens).await
+        }
+
+        async fn chat(&self, messages: &[ChatMessage], _params: &GenerationParams) -> Result<String> {
+            self.prompts.lock().await.push(messages.to_vec());
+            Ok("Noted.".to_string())
+        }
+    }
+
+    // === Mock Buffer ===
+    struct MockBuffer {
+        items: Mutex<Vec<Interaction>>,
+    }
+
+    #[async_trait]
+    impl BufferPort for MockBuffer {
+        async fn push(&self, interaction: Interaction) -> Result<()> {
+            self.items.lock().await.push(interaction);
+            Ok(())
+        }
+
+        async fn pop_batch(&self, size: usize) -> Result<Vec<Interaction>> {
+            let mut items = self.items.lock().await;
+            let n = size.min(items.len());
+            Ok(items.drain(..n).collect())
+        }
+
+        async fn peek(&self, size: usize) -> Result<Vec<Interaction>> {
+            Ok(self.items.lock().await.iter().take(size).cloned().collect())
+        }
+
+        async fn len(&self) -> Result<usize> {
+            Ok(self.items.lock().await.len())
+        }
+
+        async fn clear(&self) -> Result<()> {
+            self.items.lock().await.clear();
+            Ok(())
+        }
+    }
+
+    struct Fixture {
+        llm: Arc<RecordingLlm>,
+        memory: Arc<MockMemory>,
+        buffer: Arc<MockBuffer>,
+    }
+
+    fn fixture(nodes: Vec<MemoryNode>) -> (Fixture, MemoryChat) {
+        let fixture = Fixture {
+            llm: Arc::new(RecordingLlm { prompts: Mutex::new(Vec::new()) }),
+            memory: Arc::new(MockMemory { nodes, namespaces: Mutex::new(Vec::new()) }),
+            buffer: Arc::new(MockBuffer { items: Mutex::new(Vec::new()) }),
+        };
+        let chat = MemoryChat::new(
+            fixture.llm.clone(),
+            Arc::new(KeywordEmbedder),
+            fixture.memory.clone(),
+            fixture.buffer.clone(),
+        );
+        (fixture, chat)
+    }
+
+    fn node(content: &str, namespace: &str) -> MemoryNode {
+        let embedding = KEYWORDS.iter().map(|k| if content.contains(k) { 1.0 } else { 0.0 }).collect();
+        MemoryNode::new(content.to_string()).with_embedding(embedding).with_namespace(namespace.to_string())
+    }
+
+    #[tokio::test]
+    async fn test_relevant_memories_reach_the_prompt() {
+        let (fixture, mut chat) = fixture(vec![
+            node("The user drinks coffee black.", "default"),
+            node("The user has a cat named Miso.", "default"),
+        ]);
+
+        let (reply, turn) = chat.send("How do I take my coffee?", &GenerationParams::default()).await.unwrap();
+
+        assert_eq!(reply, "Noted.");
+        assert_eq!(turn.memories.len(), 1);
+        let prompts = fixture.llm.prompts.lock().await;
+        assert!(prompts[0][0].content.contains("coffee black"));
+        assert!(!prompts[0][0].content.contains("Miso"));
+    }
+
+    #[tokio::test]
+    async fn test_namespace_limits_retrieval() {
+        let (fixture, chat) = fixture(vec![node("Work coffee order: latte.", "work"), node("Home coffee: black.", "home")]);
+        let chat = chat.with_namespace("work");
+
+        let turn = chat.prepare("coffee?").await.unwrap();
+
+        assert_eq!(*fixture.memory.namespaces.lock().await, vec!["work".to_string()]);
+        assert_eq!(turn.memories.len(), 1);
+        assert!(turn.memories[0].node.content.contains("latte"));
+    }
+
+    #[tokio::test]
+    async fn test_budget_skips_memories_that_do_not_fit() {
+        let long = format!("coffee {}", "x".repeat(400));
+        let (_fixture, chat) = fixture(vec![node(&long, "default"), node("Short coffee note.", "default")]);
+        let chat = chat.with_context_budget(20);
+
+        let turn = chat.prepare("coffee").await.unwrap();
+
+        assert_eq!(turn.memories.len(), 1);
+        assert_eq!(turn.memories[0].node.content, "Short coffee note.");
+    }
+
+    #[tokio::test]
+    async fn test_exchanges_are_buffered_and_replayed() {
+        let (fixture, mut chat) = fixture(Vec::new());
+        let chat_session = chat.session_id().to_string();
+
+        chat.send("Hello", &GenerationParams::default()).await.unwrap();
+        let turn = chat.prepare("Again").await.unwrap();
+
+        let buffered = fixture.buffer.items.lock().await;
+        assert_eq!(buffered.len(), 1);
+        assert_eq!(buffered[0].user_input, "Hello");
+        assert_eq!(buffered[0].session_id, chat_session);
+
+        let contents: Vec<&str> = turn.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(&contents[1..], &["Hello", "Noted.", "Again"]);
+    }
+}
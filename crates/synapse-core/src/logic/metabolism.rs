@@ -1,5 +1,6 @@
 use crate::ports::{BufferPort, MemoryPort, LlmPort, EmbeddingPort};
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::logic::summarizer::MapReduceSummarizer;
 use crate::{MemoryNode, NodeType};
 use std::sync::Arc;
 use chrono::Utc;
@@ -9,7 +10,7 @@ use uuid::Uuid;
 pub struct Metabolism {
     buffer: Arc<dyn BufferPort>,
     memory: Arc<dyn MemoryPort>,
-    llm: Arc<dyn LlmPort>,
+    summarizer: MapReduceSummarizer,
     embedder: Arc<dyn EmbeddingPort>,
     threshold: usize,
 }
@@ -24,7 +25,8 @@ impl Metabolism {
         Self {
             buffer,
             memory,
-            llm,
+            summarizer: MapReduceSummarizer::new(llm)
+                .with_instruction("Summarize the following conversation concisely:"),
             embedder,
             threshold: 10, // Default threshold
         }
@@ -37,26 +39,34 @@ impl Metabolism {
             return Ok(0);
         }
 
-        // 1. Pop batch from buffer
-        let interactions = self.buffer.pop_batch(self.threshold).await?;
+        // 1. Peek at the next batch
+        let interactions = self.buffer.peek(self.threshold).await?;
         if interactions.is_empty() {
             return Ok(0);
         }
 
-        // 2. Combine content
-        let mut combined_text = String::new();
-        for interaction in &interactions {
-            combined_text.push_str(&format!("User: {}\nAI: {}\n", interaction.user_input, interaction.ai_response));
-        }
-
-
-        // 3. Summarize via LLM
-        let summary = self.llm.summarize(&combined_text).await?;
+        // 2. Summarize via LLM (map-reduce when the batch exceeds the context
+        // window). A batch over the summarizer's call limit is halved until it
+        // fits; a single interaction that still does not fit is kept verbatim
+        // so it cannot stall the buffer.
+        let transcript: Vec<String> = interactions
+            .iter()
+            .map(|i| format!("User: {}\nAI: {}", i.user_input, i.ai_response))
+            .collect();
+        let mut batch = transcript.len();
+        let summary = loop {
+            match self.summarizer.summarize(&transcript[..batch]).await {
+                Ok(outcome) => break outcome.text,
+                Err(Error::Validation { .. }) if batch > 1 => batch /= 2,
+                Err(Error::Validation { .. }) => break transcript[0].clone(),
+                Err(e) => return Err(e),
+            }
+        };
 
-        // 4. Generate embedding
+        // 3. Generate embedding
         let embedding = self.embedder.embed(&summary).await?;
 
-        // 5. Create MemoryNode (Layer 0)
+        // 4. Create MemoryNode (Layer 0)
         let node = MemoryNode {
             id: Uuid::new_v4().to_string(),
             content: summary,
@@ -71,10 +81,17 @@ impl Metabolism {
         };
 
 
-        // 6. Store in long-term memory
+        // 5. Store in long-term memory, and only then remove the batch
         self.memory.store(node).await?;
+        self.buffer.pop_batch(batch).await?;
 
-        Ok(interactions.len())
+        Ok(batch)
+    }
+
+    /// Replace the summarizer (e.g. to change fan-in or the call limit).
+    pub fn with_summarizer(mut self, summarizer: MapReduceSummarizer) -> Self {
+        self.summarizer = summarizer;
+        self
     }
 
     /// Set custom threshold for testing.
@@ -212,6 +229,28 @@ mod tests {
         }
     }
 
+    // === Mock LLM with a context window barely larger than the summary ===
+    #[derive(Default)]
+    struct SmallWindowLlm {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LlmPort for SmallWindowLlm {
+        async fn generate(&self, _prompt: &str, _max_tokens: usize) -> Result<String> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok("Mock summary".to_string())
+        }
+
+        async fn generate_with_params(&self, _prompt: &str, _max_tokens: usize, _temp: f32, _top_p: f32) -> Result<String> {
+            Ok("Mock summary".to_string())
+        }
+
+        fn context_window(&self) -> usize {
+            crate::ports::SUMMARY_TOKENS + 30
+        }
+    }
+
     // === Mock Embedder ===
     struct MockEmbedder;
 
@@ -332,5 +371,126 @@ mod tests {
         assert_eq!(buffer.len().await.unwrap(), 0);
         assert_eq!(memory.count().await.unwrap(), 3);
     }
-}
 
+    #[tokio::test]
+    async fn test_digest_map_reduces_oversized_batch() {
+        let buffer = Arc::new(MockBuffer::new());
+        let memory = Arc::new(MockMemory::new());
+        let llm = Arc::new(SmallWindowLlm::default());
+
+        let metabolism = Metabolism::new(
+            buffer.clone(),
+            memory.clone(),
+            llm.clone(),
+            Arc::new(MockEmbedder),
+        ).with_threshold(5);
+
+        for i in 0..5 {
+            buffer.push(Interaction::new(
+                format!("Question {}", i),
+                format!("Answer {}", i),
+            )).await.unwrap();
+        }
+
+        // Two interactions fit per prompt: 3 partial summaries, then 1 combined.
+        let digested = metabolism.digest().await.unwrap();
+        assert_eq!(digested, 5);
+        assert_eq!(buffer.len().await.unwrap(), 0);
+        assert_eq!(memory.count().await.unwrap(), 1);
+        assert_eq!(llm.calls.load(std::sync::atomic::Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_digest_splits_batch_over_call_limit() {
+        let buffer = Arc::new(MockBuffer::new());
+        let memory = Arc::new(MockMemory::new());
+        let llm = Arc::new(SmallWindowLlm::default());
+
+        let metabolism = Metabolism::new(
+            buffer.clone(),
+            memory.clone(),
+            llm.clone(),
+            Arc::new(MockEmbedder),
+        )
+        .with_summarizer(MapReduceSummarizer::new(llm.clone()).with_max_calls(1))
+        .with_threshold(5);
+
+        for i in 0..5 {
+            buffer.push(Interaction::new(
+                format!("Question {}", i),
+                format!("Answer {}", i),
+            )).await.unwrap();
+        }
+
+        // Five need 4 calls and two still fit one prompt: 5 -> 2.
+        assert_eq!(metabolism.digest().await.unwrap(), 2);
+        assert_eq!(buffer.len().await.unwrap(), 3);
+        assert_eq!(memory.count().await.unwrap(), 1);
+
+        // One interaction that cannot be summarized is kept verbatim.
+        let verbatim = Metabolism::new(buffer.clone(), memory.clone(), llm.clone(), Arc::new(MockEmbedder))
+            .with_summarizer(MapReduceSummarizer::new(llm.clone()).with_max_calls(0))
+            .with_threshold(3);
+        assert_eq!(verbatim.digest().await.unwrap(), 1);
+        assert_eq!(buffer.len().await.unwrap(), 2);
+        let nodes = memory.get_by_layer(0).await.unwrap();
+        assert_eq!(nodes[1].content, "User: Question 2\nAI: Answer 2");
+    }
+
+    #[tokio::test]
+    async fn test_digest_keeps_batch_when_store_fails() {
+        struct FailingMemory(MockMemory);
+
+        #[async_trait]
+        impl MemoryPort for FailingMemory {
+            async fn store(&self, _node: MemoryNode) -> Result<String> {
+                Err(Error::System("disk full".to_string()))
+            }
+            async fn search(&self, embedding: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+                self.0.search(embedding, top_k).await
+            }
+            async fn search_layer(&self, embedding: &[f32], layer: u8, top_k: usize) -> Result<Vec<SearchResult>> {
+                self.0.search_layer(embedding, layer, top_k).await
+            }
+            async fn search_namespace(&self, embedding: &[f32], namespace: &str, top_k: usize) -> Result<Vec<SearchResult>> {
+                self.0.search_namespace(embedding, namespace, top_k).await
+            }
+            async fn get_by_id(&self, id: &str) -> Result<Option<MemoryNode>> {
+                self.0.get_by_id(id).await
+            }
+            async fn get_by_layer(&self, layer: u8) -> Result<Vec<MemoryNode>> {
+                self.0.get_by_layer(layer).await
+            }
+            async fn update(&self, node: MemoryNode) -> Result<()> {
+                self.0.update(node).await
+            }
+            async fn delete(&self, id: &str) -> Result<()> {
+                self.0.delete(id).await
+            }
+            async fn count(&self) -> Result<usize> {
+                self.0.count().await
+            }
+            async fn add_relationship(&self, from_id: &str, relation: &str, to_id: &str) -> Result<()> {
+                self.0.add_relationship(from_id, relation, to_id).await
+            }
+            async fn count_by_layer(&self, layer: u8) -> Result<usize> {
+                self.0.count_by_layer(layer).await
+            }
+        }
+
+        let buffer = Arc::new(MockBuffer::new());
+        let metabolism = Metabolism::new(
+            buffer.clone(),
+            Arc::new(FailingMemory(MockMemory::new())),
+            Arc::new(MockLlm),
+            Arc::new(MockEmbedder),
+        ).with_threshold(2);
+
+        for i in 0..2 {
+            buffer.push(Interaction::new(format!("Q{}", i), format!("A{}", i))).await.unwrap();
+        }
+
+        assert!(metabolism.digest().await.is_err());
+        assert_eq!(buffer.len().await.unwrap(), 2, "Nothing is lost when the store fails");
+    }
+}
@@ -2,6 +2,17 @@
 
 pub mod metabolism;
 pub mod consolidation;
+pub mod crdt;
+pub mod chat_template;
+pub mod chunking;
+pub mod chunked_memory;
+pub mod embedding_check;
+pub mod json_schema;
+pub mod memory_api;
+pub mod memory_chat;
+pub mod prompt_builder;
+pub mod summarizer;
+pub mod threat_db;
 // pub mod dreaming;
 // pub mod hirag;
 // pub mod sanitizer;
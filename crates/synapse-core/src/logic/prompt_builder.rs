@@ -0,0 +1,456 @@
+//! Prompt Builder - Assemble prompts that fit the model's context window.
+//!
+//! A prompt is a list of named sections (system instructions, memories,
+//! history, query, ...). Sections are fitted in priority order; whatever does
+//! not fit is truncated, trimmed item by item, summarized or dropped according
+//! to the section's [`Overflow`] policy, and the outcome is reported per
+//! section. Sections are emitted in the order they were added.
+//!
+//! Token counts come from a hook (usually [`LlmPort::count_tokens`]). Chat
+//! template markup is not counted, so reserve some slack in
+//! [`PromptBuilder::reserve_output`].
+
+use crate::error::Result;
+use crate::ports::LlmPort;
+use crate::{ChatMessage, ChatRole};
+
+/// What to do with a section that does not fit the remaining budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Cut the text, keeping the beginning.
+    Truncate,
+    /// Keep the leading items that fit (e.g. ranked memories).
+    KeepFirst,
+    /// Keep the trailing items that fit (e.g. conversation history).
+    KeepLast,
+    /// Replace the text with an LLM summary (see [`PromptBuilder::build_summarizing`]).
+    Summarize,
+    /// Leave the section out.
+    Drop,
+}
+
+/// How a section fared in the final prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionOutcome {
+    /// Included in full
+    Kept,
+    /// Cut short or reduced to fewer items
+    Truncated,
+    /// Replaced by a summary
+    Summarized,
+    /// Left out entirely
+    Dropped,
+}
+
+/// Per-section result of [`PromptBuilder::build`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionReport {
+    pub name: String,
+    pub outcome: SectionOutcome,
+    /// Items included (a partially kept item counts)
+    pub items_kept: usize,
+    pub items_total: usize,
+    /// Tokens the section uses in the prompt
+    pub tokens: usize,
+}
+
+/// A named part of a prompt.
+#[derive(Debug, Clone)]
+pub struct PromptSection {
+    name: String,
+    priority: u8,
+    overflow: Overflow,
+    items: Vec<ChatMessage>,
+    /// Join items into one message (`Some(separator)`) or keep them as
+    /// separate messages (`None`).
+    separator: Option<String>,
+}
+
+impl PromptSection {
+    /// A single block of text; truncated on overflow.
+    pub fn text(name: impl Into<String>, role: ChatRole, text: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            priority: 0,
+            overflow: Overflow::Truncate,
+            items: vec![ChatMessage::new(role, text)],
+            separator: Some(String::new()),
+        }
+    }
+
+    /// A list of items joined by newlines into one message; the leading
+    /// items are kept on overflow.
+    pub fn items<S: Into<String>>(name: impl Into<String>, role: ChatRole, items: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            name: name.into(),
+            priority: 0,
+            overflow: Overflow::KeepFirst,
+            items: items.into_iter().map(|item| ChatMessage::new(role, item)).collect(),
+            separator: Some("\n".to_string()),
+        }
+    }
+
+    /// Separate chat messages; the most recent are kept on overflow.
+    pub fn messages(name: impl Into<String>, messages: Vec<ChatMessage>) -> Self {
+        Self {
+            name: name.into(),
+            priority: 0,
+            overflow: Overflow::KeepLast,
+            items: messages,
+            separator: None,
+        }
+    }
+
+    /// Higher priorities are fitted first (default 0).
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Separator between items of a joined section.
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = Some(separator.into());
+        self
+    }
+
+    /// The section's items rendered as messages.
+    fn render(&self, items: &[ChatMessage]) -> Vec<ChatMessage> {
+        match (&self.separator, items.first()) {
+            (Some(separator), Some(first)) => {
+                let text = items.iter().map(|m| m.content.as_str()).collect::<Vec<_>>().join(separator);
+                vec![ChatMessage::new(first.role, text)]
+            }
+            _ => items.to_vec(),
+        }
+    }
+}
+
+/// An assembled prompt with its per-section report.
+#[derive(Debug, Clone)]
+pub struct BuiltPrompt {
+    /// Included sections, adjacent messages with the same role merged
+    pub messages: Vec<ChatMessage>,
+    /// One entry per section, in the order they were added
+    pub report: Vec<SectionReport>,
+    /// Tokens used by all sections
+    pub tokens: usize,
+}
+
+impl BuiltPrompt {
+    /// The prompt as plain text, sections separated by blank lines.
+    pub fn to_text(&self) -> String {
+        self.messages.iter().map(|m| m.content.as_str()).collect::<Vec<_>>().join("\n\n")
+    }
+
+    /// Report for the section called `name`.
+    pub fn section(&self, name: &str) -> Option<&SectionReport> {
+        self.report.iter().find(|r| r.name == name)
+    }
+
+    /// Sections that were not included in full.
+    pub fn dropped(&self) -> impl Iterator<Item = &SectionReport> {
+        self.report.iter().filter(|r| r.outcome != SectionOutcome::Kept)
+    }
+}
+
+/// Assembles prioritized sections within a token budget.
+pub struct PromptBuilder<'a> {
+    context_window: usize,
+    reserved_output: usize,
+    count_tokens: Box<dyn Fn(&str) -> usize + Send + Sync + 'a>,
+    sections: Vec<PromptSection>,
+}
+
+impl<'a> PromptBuilder<'a> {
+    /// Create a builder for a model with `context_window` tokens, counting
+    /// tokens with `count_tokens`.
+    pub fn new(context_window: usize, count_tokens: impl Fn(&str) -> usize + Send + Sync + 'a) -> Self {
+        Self {
+            context_window,
+            reserved_output: 0,
+            count_tokens: Box::new(count_tokens),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Create a builder using `llm`'s tokenizer and context window.
+    pub fn for_llm<L: LlmPort + ?Sized>(llm: &'a L) -> Self {
+        Self::new(llm.context_window(), move |text| llm.count_tokens(text))
+    }
+
+    /// Keep `tokens` free for the model's reply.
+    pub fn reserve_output(mut self, tokens: usize) -> Self {
+        self.reserved_output = tokens;
+        self
+    }
+
+    /// Add a section.
+    pub fn section(mut self, section: PromptSection) -> Self {
+        self.sections.push(section);
+        self
+    }
+
+    /// Tokens available to the prompt.
+    pub fn budget(&self) -> usize {
+        self.context_window.saturating_sub(self.reserved_output)
+    }
+
+    /// Count tokens with the builder's hook.
+    pub fn count(&self, text: &str) -> usize {
+        (self.count_tokens)(text)
+    }
+
+    /// Fit the sections into the budget. [`Overflow::Summarize`] sections
+    /// are truncated; use [`PromptBuilder::build_summarizing`] to summarize them.
+    pub fn build(&self) -> BuiltPrompt {
+        self.fit(&self.sections)
+    }
+
+    /// Like [`PromptBuilder::build`], but overflowing [`Overflow::Summarize`]
+    /// sections are replaced by `llm`'s summary of them (then truncated if the
+    /// summary still does not fit).
+    pub async fn build_summarizing(&self, llm: &dyn LlmPort) -> Result<BuiltPrompt> {
+        let first = self.fit(&self.sections);
+        let mut sections = self.sections.clone();
+        let mut summarized = Vec::new();
+        for (i, section) in sections.iter_mut().enumerate() {
+            if section.overflow == Overflow::Summarize && first.report[i].outcome != SectionOutcome::Kept {
+                let text = section.render(&section.items).into_iter().map(|m| m.content).collect::<Vec<_>>().join("\n");
+                let role = section.items.first().map_or(ChatRole::User, |m| m.role);
+                section.items = vec![ChatMessage::new(role, llm.summarize(&text).await?)];
+                section.overflow = Overflow::Truncate;
+                summarized.push(i);
+            }
+        }
+
+        let mut built = self.fit(&sections);
+        for i in summarized {
+            if built.report[i].outcome == SectionOutcome::Kept {
+                built.report[i].outcome = SectionOutcome::Summarized;
+                built.report[i].items_total = self.sections[i].items.len();
+            }
+        }
+        Ok(built)
+    }
+
+    fn fit(&self, sections: &[PromptSection]) -> BuiltPrompt {
+        let mut order: Vec<usize> = (0..sections.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(sections[i].priority));
+
+        let mut remaining = self.budget();
+        let mut kept: Vec<Vec<ChatMessage>> = vec![Vec::new(); sections.len()];
+        let mut report: Vec<SectionReport> = sections
+            .iter()
+            .map(|s| SectionReport {
+                name: s.name.clone(),
+                outcome: SectionOutcome::Dropped,
+                items_kept: 0,
+                items_total: s.items.len(),
+                tokens: 0,
+            })
+            .collect();
+
+        for i in order {
+            let section = &sections[i];
+            let (items, outcome) = self.fit_section(section, remaining);
+            let tokens = self.cost(&section.render(&items));
+            remaining = remaining.saturating_sub(tokens);
+            report[i].items_kept = items.len();
+            report[i].tokens = tokens;
+            report[i].outcome = if items.is_empty() && !section.items.is_empty() { SectionOutcome::Dropped } else { outcome };
+            kept[i] = items;
+        }
+
+        let mut messages: Vec<ChatMessage> = Vec::new();
+        for (section, items) in sections.iter().zip(&kept) {
+            for message in section.render(items) {
+                match messages.last_mut() {
+                    Some(last) if last.role == message.role => {
+                        last.content.push_str("\n\n");
+                        last.content.push_str(&message.content);
+                    }
+                    _ => messages.push(message),
+                }
+            }
+        }
+
+        let tokens = report.iter().map(|r| r.tokens).sum();
+        BuiltPrompt { messages, report, tokens }
+    }
+
+    /// The items of `section` that fit in `budget` tokens.
+    fn fit_section(&self, section: &PromptSection, budget: usize) -> (Vec<ChatMessage>, SectionOutcome) {
+        if self.cost(&section.render(&section.items)) <= budget {
+            return (section.items.clone(), SectionOutcome::Kept);
+        }
+
+        let items = match section.overflow {
+            Overflow::Drop => Vec::new(),
+            Overflow::Truncate | Overflow::Summarize => {
+                let rendered = section.render(&section.items);
+                let role = rendered.first().map_or(ChatRole::User, |m| m.role);
+                let text = rendered.into_iter().map(|m| m.content).collect::<Vec<_>>().join("\n\n");
+                let cut = self.truncate(&text, budget);
+                if cut.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![ChatMessage::new(role, cut)]
+                }
+            }
+            Overflow::KeepFirst => {
+                let n = self.longest_fit(section, budget, |n| &section.items[..n]);
+                let mut items = section.items[..n].to_vec();
+                if items.is_empty() {
+                    // Nothing fits whole: keep the start of the first item.
+                    let first = &section.items[0];
+                    let cut = self.truncate(&first.content, budget);
+                    if !cut.is_empty() {
+                        items.push(ChatMessage::new(first.role, cut));
+                    }
+                }
+                items
+            }
+            Overflow::KeepLast => {
+                let len = section.items.len();
+                let n = self.longest_fit(section, budget, |n| &section.items[len - n..]);
+                section.items[len - n..].to_vec()
+            }
+        };
+        (items, SectionOutcome::Truncated)
+    }
+
+    /// Largest `n` such that `slice(n)` renders within `budget`.
+    fn longest_fit<'s>(&self, section: &PromptSection, budget: usize, slice: impl Fn(usize) -> &'s [ChatMessage]) -> usize {
+        (0..=section.items.len())
+            .take_while(|&n| self.cost(&section.render(slice(n))) <= budget)
+            .last()
+            .unwrap_or(0)
+    }
+
+    fn cost(&self, messages: &[ChatMessage]) -> usize {
+        messages.iter().map(|m| self.count(&m.content)).sum()
+    }
+
+    /// Longest prefix of `text` within `budget` tokens.
+    fn truncate(&self, text: &str, budget: usize) -> String {
+        let boundaries: Vec<usize> = text.char_indices().map(|(i, _)| i).chain([text.len()]).collect();
+        let (mut lo, mut hi) = (0, boundaries.len() - 1);
+        while lo < hi {
+            let mid = (lo + hi).div_ceil(2);
+            if self.count(&text[..boundaries[mid]]) <= budget {
+                lo = mid;
+            } else {
+                hi = mid - 1;
+            }
+        }
+        text[..boundaries[lo]].trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+
+    #[test]
+    fn test_everything_fits() {
+        let built = PromptBuilder::new(100, words)
+            .section(PromptSection::text("system", ChatRole::System, "Be brief."))
+            .section(PromptSection::text("query", ChatRole::User, "Hi there"))
+            .build();
+
+        assert_eq!(built.messages, vec![ChatMessage::system("Be brief."), ChatMessage::user("Hi there")]);
+        assert_eq!(built.tokens, 4);
+        assert_eq!(built.dropped().count(), 0);
+    }
+
+    #[test]
+    fn test_priorities_decide_what_is_cut() {
+        let built = PromptBuilder::new(12, words)
+            .reserve_output(2)
+            .section(PromptSection::text("system", ChatRole::System, "You are helpful.").with_priority(3))
+            .section(PromptSection::items("memories", ChatRole::System, ["one two three", "four five six", "seven eight"]).with_priority(1))
+            .section(PromptSection::text("query", ChatRole::User, "what is four?").with_priority(3))
+            .build();
+
+        // 10 tokens: system 3 + query 3 leave 4 for memories, so one whole item fits.
+        let memories = built.section("memories").unwrap();
+        assert_eq!(memories.outcome, SectionOutcome::Truncated);
+        assert_eq!((memories.items_kept, memories.items_total), (1, 3));
+        assert_eq!(built.messages[0].content, "You are helpful.\n\none two three");
+        assert_eq!(built.messages[1].content, "what is four?");
+        assert!(built.tokens <= 10);
+    }
+
+    #[test]
+    fn test_history_keeps_latest_turns() {
+        let history = vec![
+            ChatMessage::user("first question here"),
+            ChatMessage::assistant("first answer"),
+            ChatMessage::user("second question"),
+            ChatMessage::assistant("second answer"),
+        ];
+        let built = PromptBuilder::new(6, words)
+            .section(PromptSection::messages("history", history))
+            .section(PromptSection::text("query", ChatRole::User, "and now?").with_priority(1))
+            .build();
+
+        assert_eq!(built.section("history").unwrap().items_kept, 2);
+        assert_eq!(built.messages[0], ChatMessage::user("second question"));
+        assert_eq!(built.messages[1], ChatMessage::assistant("second answer"));
+    }
+
+    #[test]
+    fn test_truncate_and_drop() {
+        let built = PromptBuilder::new(5, words)
+            .section(PromptSection::text("doc", ChatRole::User, "a b c d e f g h").with_priority(1))
+            .section(PromptSection::text("extra", ChatRole::User, "x y").with_overflow(Overflow::Drop))
+            .build();
+
+        assert_eq!(built.to_text(), "a b c d e");
+        assert_eq!(built.section("doc").unwrap().outcome, SectionOutcome::Truncated);
+        assert_eq!(built.section("extra").unwrap().outcome, SectionOutcome::Dropped);
+    }
+
+    #[test]
+    fn test_oversized_first_item_is_cut() {
+        let built = PromptBuilder::new(3, words)
+            .section(PromptSection::items("batch", ChatRole::User, ["a b c d e", "f"]))
+            .build();
+
+        assert_eq!(built.to_text(), "a b c");
+        assert_eq!(built.section("batch").unwrap().items_kept, 1);
+    }
+
+    struct ShortSummaries;
+
+    #[async_trait::async_trait]
+    impl LlmPort for ShortSummaries {
+        async fn generate(&self, _prompt: &str, _max_tokens: usize) -> Result<String> {
+            Ok("in short".to_string())
+        }
+
+        async fn generate_with_params(&self, prompt: &str, max_tokens: usize, _temp: f32, _top_p: f32) -> Result<String> {
+            self.generate(prompt, max_tokens).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_build_summarizing_replaces_overflow() {
+        let builder = PromptBuilder::new(4, words)
+            .section(PromptSection::text("doc", ChatRole::User, "a long text that does not fit").with_overflow(Overflow::Summarize));
+
+        let built = builder.build_summarizing(&ShortSummaries).await.unwrap();
+
+        assert_eq!(built.to_text(), "in short");
+        assert_eq!(built.section("doc").unwrap().outcome, SectionOutcome::Summarized);
+    }
+}
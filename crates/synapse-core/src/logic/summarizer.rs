@@ -0,0 +1,291 @@
+//! Map-Reduce Summarizer - Summarize text of any length.
+//!
+//! Items are packed into chunks that fit the LLM's context window (items that
+//! are too long on their own are split with [`TextChunker`]). Each chunk is
+//! summarized (map), then the partial summaries are summarized in groups of
+//! at most `fan_in` (reduce) until a single summary remains.
+
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::logic::chunking::TextChunker;
+use crate::logic::prompt_builder::{PromptBuilder, PromptSection};
+use crate::ports::{LlmPort, SUMMARY_TOKENS};
+use crate::ChatRole;
+
+const MAP_INSTRUCTION: &str = "Summarize the following text concisely:";
+const REDUCE_INSTRUCTION: &str = "Combine the following partial summaries into one concise summary:";
+const CUE: &str = "Summary:";
+
+/// Result of [`MapReduceSummarizer::summarize`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SummaryOutcome {
+    /// The final summary
+    pub text: String,
+    /// LLM calls made
+    pub llm_calls: usize,
+    /// Rounds of summarization (1 when everything fit in one prompt)
+    pub levels: usize,
+}
+
+/// Hierarchical summarizer that never exceeds the context window.
+pub struct MapReduceSummarizer {
+    llm: Arc<dyn LlmPort>,
+    fan_in: usize,
+    max_calls: usize,
+    summary_tokens: usize,
+    instruction: String,
+}
+
+impl MapReduceSummarizer {
+    /// Create a summarizer (fan-in 4, at most 32 LLM calls).
+    pub fn new(llm: Arc<dyn LlmPort>) -> Self {
+        Self {
+            llm,
+            fan_in: 4,
+            max_calls: 32,
+            summary_tokens: SUMMARY_TOKENS,
+            instruction: MAP_INSTRUCTION.to_string(),
+        }
+    }
+
+    /// Maximum partial summaries combined per reduce call (at least 2).
+    pub fn with_fan_in(mut self, fan_in: usize) -> Self {
+        self.fan_in = fan_in.max(2);
+        self
+    }
+
+    /// Maximum LLM calls per summary; larger inputs are rejected up front.
+    pub fn with_max_calls(mut self, max_calls: usize) -> Self {
+        self.max_calls = max_calls;
+        self
+    }
+
+    /// Completion length reserved for each summary.
+    pub fn with_summary_tokens(mut self, tokens: usize) -> Self {
+        self.summary_tokens = tokens;
+        self
+    }
+
+    /// Instruction for summarizing the raw items.
+    pub fn with_instruction(mut self, instruction: impl Into<String>) -> Self {
+        self.instruction = instruction.into();
+        self
+    }
+
+    /// Summarize `items` (joined by newlines where they share a prompt).
+    pub async fn summarize(&self, items: &[String]) -> Result<SummaryOutcome> {
+        if items.is_empty() {
+            return Err(Error::Validation {
+                message: "Nothing to summarize".to_string(),
+            });
+        }
+
+        let chunks = self.pack(items, &self.instruction, usize::MAX)?;
+        let planned = chunks.len() + reduce_calls(chunks.len(), self.fan_in);
+        if planned > self.max_calls {
+            return Err(Error::Validation {
+                message: format!(
+                    "Summarizing {} chunks needs about {} LLM calls (limit {})",
+                    chunks.len(),
+                    planned,
+                    self.max_calls
+                ),
+            });
+        }
+
+        let mut calls = 0;
+        let mut summaries = Vec::with_capacity(chunks.len());
+        for chunk in &chunks {
+            summaries.push(self.call(&self.instruction, chunk).await?);
+            calls += 1;
+        }
+
+        let mut levels = 1;
+        while summaries.len() > 1 {
+            let groups = self.pack(&summaries, REDUCE_INSTRUCTION, self.fan_in)?;
+            if groups.len() >= summaries.len() {
+                return Err(Error::Validation {
+                    message: "Partial summaries are too long to combine; reduce summary_tokens".to_string(),
+                });
+            }
+            if calls + groups.len() > self.max_calls {
+                return Err(Error::Validation {
+                    message: format!("Summary needs more than {} LLM calls", self.max_calls),
+                });
+            }
+
+            summaries.clear();
+            for group in &groups {
+                summaries.push(self.call(REDUCE_INSTRUCTION, group).await?);
+                calls += 1;
+            }
+            levels += 1;
+        }
+
+        Ok(SummaryOutcome {
+            text: summaries.remove(0),
+            llm_calls: calls,
+            levels,
+        })
+    }
+
+    /// Summarize one chunk.
+    async fn call(&self, instruction: &str, text: &str) -> Result<String> {
+        let prompt = self.prompt(instruction, text).build();
+        self.llm.generate(&prompt.to_text(), self.summary_tokens).await
+    }
+
+    fn prompt<'a>(&'a self, instruction: &str, text: &str) -> PromptBuilder<'a> {
+        PromptBuilder::for_llm(self.llm.as_ref())
+            .reserve_output(self.summary_tokens)
+            .section(PromptSection::text("instruction", ChatRole::User, instruction).with_priority(1))
+            .section(PromptSection::text("text", ChatRole::User, text))
+            .section(PromptSection::text("cue", ChatRole::User, CUE).with_priority(1))
+    }
+
+    /// Group `items` into newline-joined chunks that fit the prompt, at most
+    /// `max_items` per chunk. Items too long on their own are split.
+    fn pack(&self, items: &[String], instruction: &str, max_items: usize) -> Result<Vec<String>> {
+        let builder = self.prompt(instruction, "");
+        let overhead = builder.count(instruction) + builder.count(CUE);
+        let budget = builder.budget().saturating_sub(overhead);
+        if budget == 0 {
+            return Err(Error::Validation {
+                message: "Context window too small to summarize anything".to_string(),
+            });
+        }
+
+        let chunker = TextChunker::new(budget, 0);
+        let mut chunks = Vec::new();
+        let mut current: Vec<&str> = Vec::new();
+        let mut pieces: Vec<String> = Vec::new();
+        for item in items {
+            if builder.count(item) > budget {
+                pieces.extend(chunker.chunk(item, |s| builder.count(s)).into_iter().map(|c| c.text));
+            } else {
+                pieces.push(item.clone());
+            }
+        }
+
+        for piece in &pieces {
+            current.push(piece);
+            if current.len() > max_items || builder.count(&current.join("\n")) > budget {
+                current.pop();
+                if !current.is_empty() {
+                    chunks.push(current.join("\n"));
+                }
+                current = vec![piece];
+            }
+        }
+        if !current.is_empty() {
+            chunks.push(current.join("\n"));
+        }
+        Ok(chunks)
+    }
+}
+
+/// Reduce calls needed to combine `n` summaries `fan_in` at a time.
+fn reduce_calls(mut n: usize, fan_in: usize) -> usize {
+    let mut calls = 0;
+    while n > 1 {
+        n = n.div_ceil(fan_in);
+        calls += n;
+    }
+    calls
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use tokio::sync::Mutex;
+
+    /// Counts words as tokens, has a 40-token window and numbers its summaries.
+    struct WordLlm {
+        prompts: Mutex<Vec<String>>,
+    }
+
+    impl WordLlm {
+        fn new() -> Arc<Self> {
+            Arc::new(Self { prompts: Mutex::new(Vec::new()) })
+        }
+    }
+
+    #[async_trait]
+    impl LlmPort for WordLlm {
+        async fn generate(&self, prompt: &str, _max_tokens: usize) -> Result<String> {
+            let mut prompts = self.prompts.lock().await;
+            prompts.push(prompt.to_string());
+            Ok(format!("summary{}", prompts.len()))
+        }
+
+        async fn generate_with_params(&self, prompt: &str, max_tokens: usize, _temp: f32, _top_p: f32) -> Result<String> {
+            self.generate(prompt, max_tokens).await
+        }
+
+        fn count_tokens(&self, text: &str) -> usize {
+            text.split_whitespace().count()
+        }
+
+        fn context_window(&self) -> usize {
+            40
+        }
+    }
+
+    fn summarizer(llm: Arc<WordLlm>) -> MapReduceSummarizer {
+        // 40 - 10 reserved - 6 instruction/cue words = 24 words per chunk.
+        MapReduceSummarizer::new(llm).with_summary_tokens(10)
+    }
+
+    fn items(count: usize, words: usize) -> Vec<String> {
+        (0..count).map(|i| vec![format!("w{}", i); words].join(" ")).collect()
+    }
+
+    #[tokio::test]
+    async fn test_small_input_is_one_call() {
+        let llm = WordLlm::new();
+        let outcome = summarizer(llm.clone()).summarize(&items(3, 5)).await.unwrap();
+
+        assert_eq!(outcome, SummaryOutcome { text: "summary1".into(), llm_calls: 1, levels: 1 });
+        assert!(llm.prompts.lock().await[0].starts_with(MAP_INSTRUCTION));
+    }
+
+    #[tokio::test]
+    async fn test_large_input_is_mapped_then_reduced() {
+        let llm = WordLlm::new();
+        // 10 items of 10 words: two per chunk -> 5 map calls, then 2 + 1 reduce calls.
+        let outcome = summarizer(llm.clone()).with_fan_in(3).summarize(&items(10, 10)).await.unwrap();
+
+        assert_eq!(outcome.llm_calls, 8);
+        assert_eq!(outcome.levels, 3);
+        assert_eq!(outcome.text, "summary8");
+
+        let prompts = llm.prompts.lock().await;
+        for i in 0..10 {
+            let marker = format!("w{} ", i);
+            assert!(prompts[..5].iter().any(|p| p.contains(&marker)), "item {} never summarized", i);
+        }
+        assert!(prompts[5].starts_with(REDUCE_INSTRUCTION));
+        assert!(prompts[5].contains("summary1\nsummary2\nsummary3"));
+        assert!(prompts.iter().all(|p| p.split_whitespace().count() <= 30));
+    }
+
+    #[tokio::test]
+    async fn test_oversized_item_is_split() {
+        let llm = WordLlm::new();
+        let outcome = summarizer(llm.clone()).summarize(&items(1, 60)).await.unwrap();
+
+        assert_eq!(outcome.llm_calls, 4); // 3 map + 1 reduce
+        assert_eq!(outcome.levels, 2);
+    }
+
+    #[tokio::test]
+    async fn test_call_limit_is_checked_up_front() {
+        let llm = WordLlm::new();
+        let result = summarizer(llm.clone()).with_max_calls(4).summarize(&items(10, 10)).await;
+
+        assert!(matches!(result, Err(Error::Validation { .. })));
+        assert!(llm.prompts.lock().await.is_empty());
+    }
+}
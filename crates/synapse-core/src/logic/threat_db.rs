@@ -0,0 +1,249 @@
+//! Threat Database - Known-bad indicators from local and peer reports.
+//!
+//! `ImmunePort` adapters record the threats they detect here, and reports
+//! received from other nodes are merged in, so scanning a process benefits
+//! from what the rest of the network has seen. Peers cannot get an
+//! indicator flagged at all on their own: that takes a local detection or
+//! [`CORROBORATING_PEERS`] trusted reporters. Anyone can mint identities,
+//! so only peers the caller trusts (e.g. paired devices) count.
+//! The database holds at most [`MAX_ENTRIES`] indicators.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::RwLock;
+
+use crate::ports::{ThreatLevel, ThreatReport};
+
+/// Distinct trusted peers that must report an indicator before their level
+/// is trusted.
+pub const CORROBORATING_PEERS: usize = 2;
+
+/// Indicators kept. When full, entries only untrusted peers reported are
+/// dropped first, oldest sighting first.
+pub const MAX_ENTRIES: usize = 10_000;
+
+/// Reporters remembered per indicator.
+pub const MAX_REPORTERS: usize = 64;
+
+/// What is known about one indicator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThreatEntry {
+    /// Highest level reported
+    pub level: ThreatLevel,
+    pub threat_type: String,
+    pub description: String,
+    pub first_seen: i64,
+    pub last_seen: i64,
+    /// Detected on this node
+    pub local: bool,
+    /// Peers that reported it (at most [`MAX_REPORTERS`])
+    pub reporters: BTreeSet<String>,
+    /// Those of `reporters` trusted to corroborate
+    pub trusted: BTreeSet<String>,
+}
+
+impl ThreatEntry {
+    /// Level to act on, after applying the corroboration rule.
+    pub fn effective_level(&self) -> ThreatLevel {
+        if self.local || self.trusted.len() >= CORROBORATING_PEERS {
+            self.level
+        } else {
+            ThreatLevel::Safe
+        }
+    }
+}
+
+/// Thread-safe indicator store shared by the immune adapter and the network.
+#[derive(Debug)]
+pub struct ThreatDatabase {
+    entries: RwLock<HashMap<String, ThreatEntry>>,
+    capacity: usize,
+}
+
+impl ThreatDatabase {
+    pub fn new() -> Self {
+        Self::with_capacity(MAX_ENTRIES)
+    }
+
+    /// Keep at most `capacity` indicators (local detections are never dropped).
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            capacity,
+        }
+    }
+
+    /// Record a threat detected on this node.
+    pub fn record_local(&self, report: &ThreatReport) {
+        self.record(report, None);
+    }
+
+    /// Record a threat reported by `peer`; only `trusted` peers count
+    /// towards corroboration. Returns `false` if the report was not
+    /// recorded: the peer had already reported this indicator, or the
+    /// database is full.
+    pub fn record_peer(&self, report: &ThreatReport, peer: &str, trusted: bool) -> bool {
+        self.record(report, Some((peer, trusted)))
+    }
+
+    fn record(&self, report: &ThreatReport, peer: Option<(&str, bool)>) -> bool {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        let key = normalize(&report.source_id);
+        if !entries.contains_key(&key) && entries.len() >= self.capacity {
+            // Untrusted reports may only displace other untrusted reports
+            let trusted = peer.is_none_or(|(_, trusted)| trusted);
+            let victim = entries
+                .iter()
+                .filter(|(_, entry)| !entry.local && (trusted || entry.trusted.is_empty()))
+                .min_by_key(|(_, entry)| (entry.trusted.len(), entry.last_seen))
+                .map(|(key, _)| key.clone());
+            match victim {
+                Some(victim) => {
+                    entries.remove(&victim);
+                }
+                None if peer.is_some() => return false,
+                None => {}
+            }
+        }
+
+        let entry = entries
+            .entry(key)
+            .or_insert_with(|| ThreatEntry {
+                level: report.level,
+                threat_type: report.threat_type.clone(),
+                description: report.description.clone(),
+                first_seen: report.timestamp,
+                last_seen: report.timestamp,
+                local: false,
+                reporters: BTreeSet::new(),
+                trusted: BTreeSet::new(),
+            });
+
+        if report.level > entry.level {
+            entry.level = report.level;
+            entry.threat_type = report.threat_type.clone();
+            entry.description = report.description.clone();
+        }
+        entry.first_seen = entry.first_seen.min(report.timestamp);
+        entry.last_seen = entry.last_seen.max(report.timestamp);
+        match peer {
+            Some((peer, trusted)) => {
+                if entry.reporters.contains(peer) {
+                    return false;
+                }
+                if trusted {
+                    entry.trusted.insert(peer.to_string());
+                } else if entry.reporters.len() >= MAX_REPORTERS {
+                    return false;
+                }
+                entry.reporters.insert(peer.to_string())
+            }
+            None => {
+                entry.local = true;
+                true
+            }
+        }
+    }
+
+    /// Level to act on for `indicator`; `Safe` if unknown.
+    pub fn level(&self, indicator: &str) -> ThreatLevel {
+        self.get(indicator)
+            .map(|entry| entry.effective_level())
+            .unwrap_or(ThreatLevel::Safe)
+    }
+
+    pub fn get(&self, indicator: &str) -> Option<ThreatEntry> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries.get(&normalize(indicator)).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for ThreatDatabase {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn normalize(indicator: &str) -> String {
+    indicator.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(indicator: &str, level: ThreatLevel) -> ThreatReport {
+        ThreatReport {
+            source_id: indicator.to_string(),
+            threat_type: "process".to_string(),
+            level,
+            description: "test".to_string(),
+            timestamp: 1_700_000_000,
+            rule: None,
+        }
+    }
+
+    #[test]
+    fn test_peer_reports_need_corroboration() {
+        let db = ThreatDatabase::new();
+        assert_eq!(db.level("evil.exe"), ThreatLevel::Safe);
+
+        assert!(db.record_peer(&report("evil.exe", ThreatLevel::Critical), "peer-a", true));
+        assert_eq!(db.level("EVIL.exe"), ThreatLevel::Safe);
+
+        // The same peer again does not count twice
+        assert!(!db.record_peer(&report("evil.exe", ThreatLevel::Critical), "peer-a", true));
+        assert_eq!(db.level("evil.exe"), ThreatLevel::Safe);
+
+        // Throwaway identities do not corroborate, even for a lower level
+        for peer in ["sybil-1", "sybil-2", "sybil-3"] {
+            assert!(db.record_peer(&report("evil.exe", ThreatLevel::Critical), peer, false));
+        }
+        assert_eq!(db.level("evil.exe"), ThreatLevel::Safe);
+        for peer in ["sybil-4", "sybil-5"] {
+            db.record_peer(&report("systemd", ThreatLevel::Suspicious), peer, false);
+        }
+        assert_eq!(db.level("systemd"), ThreatLevel::Safe);
+
+        db.record_peer(&report("evil.exe", ThreatLevel::Malicious), "peer-b", true);
+        assert_eq!(db.level("evil.exe"), ThreatLevel::Critical);
+    }
+
+    #[test]
+    fn test_capacity() {
+        let db = ThreatDatabase::with_capacity(2);
+        db.record_local(&report("dropper", ThreatLevel::Malicious));
+        assert!(db.record_peer(&report("corroborated", ThreatLevel::Malicious), "peer-a", true));
+
+        // Full of entries an untrusted report may not displace
+        assert!(!db.record_peer(&report("spam-1", ThreatLevel::Critical), "sybil", false));
+        assert_eq!(db.len(), 2);
+
+        // A trusted report displaces the peer-only entry, never the local one
+        assert!(db.record_peer(&report("worm", ThreatLevel::Malicious), "peer-b", true));
+        assert_eq!(db.len(), 2);
+        assert_eq!(db.level("dropper"), ThreatLevel::Malicious);
+        assert!(db.get("corroborated").is_none());
+
+        // Reporters per indicator are bounded too
+        for i in 0..MAX_REPORTERS {
+            db.record_peer(&report("worm", ThreatLevel::Malicious), &format!("sybil-{i}"), false);
+        }
+        assert_eq!(db.get("worm").unwrap().reporters.len(), MAX_REPORTERS);
+    }
+
+    #[test]
+    fn test_local_detection_is_trusted() {
+        let db = ThreatDatabase::new();
+        db.record_local(&report("dropper", ThreatLevel::Malicious));
+        assert_eq!(db.level("dropper"), ThreatLevel::Malicious);
+        assert_eq!(db.len(), 1);
+    }
+}
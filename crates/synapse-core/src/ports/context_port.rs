@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use crate::error::Result;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct WindowInfo {
     pub title: String,
     pub process_name: String,
@@ -10,7 +10,25 @@ pub struct WindowInfo {
     pub bounds: (i32, i32, u32, u32),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A running process with its command line and resource use.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    /// Short process name (e.g. `firefox`)
+    pub name: String,
+    pub cmdline: Vec<String>,
+    /// Path of the executable, when readable
+    pub exe: Option<String>,
+    pub user: Option<String>,
+    /// User + system CPU time consumed so far
+    pub cpu_time_ms: u64,
+    /// Resident memory
+    pub memory_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum InputEvent {
     KeyPress(String),
     MouseClick(i32, i32),
@@ -25,12 +43,27 @@ pub trait ContextPort: Send + Sync {
     /// Returns raw bytes (png/jpeg)
     async fn capture_screen(&self) -> Result<Vec<u8>>;
 
-    /// Gets information about the currently active window
+    /// Gets information about the currently active window.
+    /// `Error::NotFound` means nothing has focus.
     async fn get_active_window(&self) -> Result<WindowInfo>;
 
     /// Gets a list of all running processes (for anomaly detection)
     async fn get_running_processes(&self) -> Result<Vec<String>>;
 
+    /// Lists running processes with details. Adapters that only know
+    /// process names report just those.
+    async fn list_processes(&self) -> Result<Vec<ProcessInfo>> {
+        Ok(self
+            .get_running_processes()
+            .await?
+            .into_iter()
+            .map(|name| ProcessInfo {
+                name,
+                ..Default::default()
+            })
+            .collect())
+    }
+
     /// Analyzes input patterns to detect "Fake Humans" (bots).
     /// Returns a confidence score (0.0 - 1.0) where 1.0 is definitely human.
     async fn analyze_input_pattern(&self, duration_ms: u64) -> Result<f32>;
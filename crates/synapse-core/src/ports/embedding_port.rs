@@ -28,9 +28,39 @@ pub trait EmbeddingPort: Send + Sync {
         Ok(results)
     }
 
+    /// Count the tokens in `text`, excluding special tokens the model adds.
+    ///
+    /// The default is a rough estimate of four characters per token.
+    /// Adapters with a tokenizer should override this.
+    fn count_tokens(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+
+    /// Maximum tokens (as counted by [`EmbeddingPort::count_tokens`]) the
+    /// model embeds without truncation. Longer text should be chunked.
+    fn max_input_tokens(&self) -> usize {
+        512
+    }
+
+    /// Whether embeddings are L2-normalized (unit length).
+    ///
+    /// Defaults to false, which only disables the unit-length check in
+    /// [`EmbeddingCheck`](crate::logic::embedding_check::EmbeddingCheck).
+    fn is_normalized(&self) -> bool {
+        false
+    }
+
     /// Get the embedding dimension.
     fn dimension(&self) -> usize;
 
     /// Get the model/provider name.
     fn provider_name(&self) -> &str;
+
+    /// Identifies the exact model behind the provider, e.g. the resolved
+    /// model file, so caches never mix vectors from two models.
+    ///
+    /// Defaults to [`EmbeddingPort::provider_name`].
+    fn model_id(&self) -> &str {
+        self.provider_name()
+    }
 }
@@ -1,7 +1,9 @@
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use crate::error::Result;
 
-#[derive(Debug, Clone, PartialEq)]
+/// Severity of a threat, ordered from harmless to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum ThreatLevel {
     Safe,
     Suspicious,
@@ -9,13 +11,18 @@ pub enum ThreatLevel {
     Critical,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ThreatReport {
+    /// Indicator the report is about (process name, file, hash, ...)
     pub source_id: String,
     pub threat_type: String,
     pub level: ThreatLevel,
     pub description: String,
+    /// Unix timestamp (seconds)
     pub timestamp: i64,
+    /// Id of the detection rule that produced the report, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
 }
 
 /// Digital Immune System Port
@@ -0,0 +1,15 @@
+//! InjectionClassifierPort - Trait for prompt-injection classifiers.
+
+use async_trait::async_trait;
+use crate::error::Result;
+
+
+/// Port for models that score text for prompt injection.
+///
+/// Implementations:
+/// - `OrtInjectionClassifier` (sequence-classification ONNX model)
+#[async_trait]
+pub trait InjectionClassifierPort: Send + Sync {
+    /// Probability in `[0, 1]` that `text` tries to steer the model.
+    async fn injection_score(&self, text: &str) -> Result<f32>;
+}
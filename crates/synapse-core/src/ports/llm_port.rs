@@ -1,9 +1,158 @@
 //! LlmPort - Trait for LLM inference.
 
 use async_trait::async_trait;
-use crate::error::Result;
+use futures::stream::{self, Stream, StreamExt};
+use std::pin::Pin;
+use crate::error::{Error, Result};
+use crate::logic::chat_template::ChatTemplate;
+use crate::logic::json_schema::JsonSchema;
+use crate::logic::prompt_builder::{PromptBuilder, PromptSection};
+use crate::{ChatMessage, ChatRole};
 
 
+/// Decoding parameters for a generation request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationParams {
+    /// Maximum number of tokens to generate.
+    pub max_tokens: usize,
+    /// Sampling temperature (0.0 = greedy).
+    pub temperature: f32,
+    /// Nucleus sampling cutoff (1.0 = disabled).
+    pub top_p: f32,
+    /// Keep only the `k` most likely tokens (0 = disabled).
+    pub top_k: usize,
+    /// Drop tokens less likely than `min_p` times the top token (0.0 = disabled).
+    pub min_p: f32,
+    /// Divide the logits of recently seen tokens by this factor (1.0 = disabled).
+    pub repetition_penalty: f32,
+    /// Subtract this much per previous occurrence of a token (0.0 = disabled).
+    pub frequency_penalty: f32,
+    /// Stop generating when any of these strings appears; it is not included in the output.
+    pub stop: Vec<String>,
+    /// RNG seed for reproducible sampling (`None` = random).
+    pub seed: Option<u64>,
+}
+
+impl Default for GenerationParams {
+    fn default() -> Self {
+        Self {
+            max_tokens: 256,
+            temperature: 0.8,
+            top_p: 0.9,
+            top_k: 40,
+            min_p: 0.05,
+            repetition_penalty: 1.1,
+            frequency_penalty: 0.0,
+            stop: Vec::new(),
+            seed: None,
+        }
+    }
+}
+
+impl GenerationParams {
+    /// Deterministic argmax decoding with no penalties.
+    pub fn greedy() -> Self {
+        Self {
+            temperature: 0.0,
+            top_p: 1.0,
+            top_k: 0,
+            min_p: 0.0,
+            repetition_penalty: 1.0,
+            ..Self::default()
+        }
+    }
+
+    /// Set the token limit.
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Set the sampling temperature.
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Add a stop sequence.
+    pub fn with_stop(mut self, stop: impl Into<String>) -> Self {
+        self.stop.push(stop.into());
+        self
+    }
+
+    /// Set the RNG seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Cut `text` at the first stop sequence, if any.
+    pub fn truncate_at_stop<'a>(&self, text: &'a str) -> &'a str {
+        let end = self
+            .stop
+            .iter()
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| text.find(s.as_str()))
+            .min()
+            .unwrap_or(text.len());
+        &text[..end]
+    }
+}
+
+/// Why a generation stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FinishReason {
+    /// The model emitted an end-of-sequence token.
+    #[default]
+    Stop,
+    /// The `max_tokens` limit was reached.
+    Length,
+    /// The consumer dropped the stream before generation finished.
+    Cancelled,
+}
+
+/// Token accounting for a finished generation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GenerationUsage {
+    /// Tokens in the (formatted) prompt. Zero if the adapter cannot count them.
+    pub prompt_tokens: usize,
+    /// Tokens produced by the model. Zero if the adapter cannot count them.
+    pub completion_tokens: usize,
+    /// Why generation stopped.
+    pub finish_reason: FinishReason,
+}
+
+/// An event emitted by a streaming generation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenEvent {
+    /// A decoded text delta, in generation order.
+    Delta(String),
+    /// Final usage record, emitted once after the last delta.
+    Done(GenerationUsage),
+}
+
+/// Stream of token events returned by [`LlmPort::generate_stream`].
+pub type TokenStream = Pin<Box<dyn Stream<Item = Result<TokenEvent>> + Send>>;
+
+/// Completion length [`LlmPort::summarize`] reserves for the summary.
+pub const SUMMARY_TOKENS: usize = 256;
+
+/// How many replies the default [`LlmPort::generate_structured`] tries.
+pub const STRUCTURED_ATTEMPTS: usize = 3;
+
+/// Drain a [`TokenStream`] into the full text and its usage record.
+pub async fn collect_stream(mut stream: TokenStream) -> Result<(String, GenerationUsage)> {
+    let mut text = String::new();
+    let mut usage = GenerationUsage::default();
+    while let Some(event) = stream.next().await {
+        match event? {
+            TokenEvent::Delta(delta) => text.push_str(&delta),
+            TokenEvent::Done(done) => usage = done,
+        }
+    }
+    Ok((text, usage))
+}
+
 /// Port for LLM text generation.
 ///
 /// Implementations:
@@ -23,13 +172,203 @@ pub trait LlmPort: Send + Sync {
         top_p: f32,
     ) -> Result<String>;
 
+    /// Generate text as a stream of token deltas followed by a usage record.
+    ///
+    /// The default implementation runs [`LlmPort::generate_with_params`] and
+    /// emits the whole completion (cut at the first stop sequence) as a single
+    /// delta. Adapters with a token loop should override this, honour every
+    /// field of `params` and stop generating once the stream is dropped.
+    async fn generate_stream(&self, prompt: &str, params: &GenerationParams) -> Result<TokenStream> {
+        let text = self
+            .generate_with_params(prompt, params.max_tokens, params.temperature, params.top_p)
+            .await?;
+        let events = vec![
+            Ok(TokenEvent::Delta(params.truncate_at_stop(&text).to_string())),
+            Ok(TokenEvent::Done(GenerationUsage::default())),
+        ];
+        Ok(Box::pin(stream::iter(events)))
+    }
+
+    /// Generate the assistant's reply to a conversation.
+    ///
+    /// The default implementation renders the messages with
+    /// [`ChatTemplate::Plain`] and collects [`LlmPort::generate_stream`].
+    /// Adapters that know their model's prompt format should override this.
+    async fn chat(&self, messages: &[ChatMessage], params: &GenerationParams) -> Result<String> {
+        let stream = self.chat_stream(messages, params).await?;
+        Ok(collect_stream(stream).await?.0)
+    }
+
+    /// Streaming variant of [`LlmPort::chat`].
+    async fn chat_stream(&self, messages: &[ChatMessage], params: &GenerationParams) -> Result<TokenStream> {
+        let prompt = ChatTemplate::Plain.render(messages);
+        self.generate_stream(&prompt, params).await
+    }
+
+    /// Generate a JSON value matching `schema` (see [`JsonSchema`] for the
+    /// supported subset).
+    ///
+    /// The default implementation asks for JSON via [`LlmPort::chat`],
+    /// validates the reply and retries with the validation error as feedback,
+    /// up to [`STRUCTURED_ATTEMPTS`] times. Adapters that can constrain
+    /// decoding should override this.
+    async fn generate_structured(
+        &self,
+        prompt: &str,
+        schema: &serde_json::Value,
+        params: &GenerationParams,
+    ) -> Result<serde_json::Value> {
+        let schema = JsonSchema::new(schema)?;
+        let mut messages = schema.instructions(prompt);
+        let mut last_error = None;
+        for _ in 0..STRUCTURED_ATTEMPTS {
+            let reply = self.chat(&messages, params).await?;
+            match schema.parse(&reply) {
+                Ok(value) => return Ok(value),
+                Err(e) => {
+                    messages.push(ChatMessage::assistant(reply));
+                    messages.push(ChatMessage::user(format!(
+                        "That reply was invalid ({}). Reply with corrected JSON only.",
+                        e
+                    )));
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(Error::Validation {
+            message: format!(
+                "No valid JSON after {} attempts: {}",
+                STRUCTURED_ATTEMPTS,
+                last_error.map(|e| e.to_string()).unwrap_or_default()
+            ),
+        })
+    }
+
+    /// Number of tokens `text` encodes to.
+    ///
+    /// The default is a rough estimate of four characters per token.
+    /// Adapters with a tokenizer should override this.
+    fn count_tokens(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(4)
+    }
+
+    /// Tokens the model can attend to (prompt plus completion).
+    fn context_window(&self) -> usize {
+        2048
+    }
+
     /// Summarize text (for HiRAG layer creation).
+    ///
+    /// Text that does not fit the context window is truncated; use
+    /// [`MapReduceSummarizer`](crate::logic::summarizer::MapReduceSummarizer)
+    /// for text of any length.
     async fn summarize(&self, text: &str) -> Result<String> {
+        let prompt = PromptBuilder::for_llm(self)
+            .reserve_output(SUMMARY_TOKENS)
+            .section(PromptSection::text("instruction", ChatRole::User, "Summarize the following text concisely:").with_priority(2))
+            .section(PromptSection::text("text", ChatRole::User, text))
+            .section(PromptSection::text("cue", ChatRole::User, "Summary:").with_priority(2))
+            .build();
+        self.generate(&prompt.to_text(), SUMMARY_TOKENS).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct EchoLlm;
+
+    #[async_trait]
+    impl LlmPort for EchoLlm {
+        async fn generate(&self, prompt: &str, _max_tokens: usize) -> Result<String> {
+            Ok(format!("echo: {}", prompt))
+        }
+
+        async fn generate_with_params(&self, prompt: &str, max_tokens: usize, _temp: f32, _top_p: f32) -> Result<String> {
+            self.generate(prompt, max_tokens).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_stream_emits_delta_then_done() {
+        let stream = EchoLlm.generate_stream("hi", &GenerationParams::default()).await.unwrap();
+        let events: Vec<_> = stream.collect().await;
+
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].as_ref().unwrap(), &TokenEvent::Delta("echo: hi".to_string()));
+        assert!(matches!(events[1].as_ref().unwrap(), TokenEvent::Done(_)));
+    }
+
+    #[tokio::test]
+    async fn test_default_chat_renders_plain_transcript() {
+        let messages = vec![ChatMessage::system("Be brief."), ChatMessage::user("Hi")];
+        let reply = EchoLlm.chat(&messages, &GenerationParams::default()).await.unwrap();
+
+        assert_eq!(reply, "echo: System: Be brief.\nUser: Hi\nAssistant:");
+    }
+
+    #[tokio::test]
+    async fn test_default_stream_applies_stop_sequences() {
+        let params = GenerationParams::default().with_stop("Bye");
+        let stream = EchoLlm.generate_stream("Hi. Bye now", &params).await.unwrap();
+        let (text, _) = collect_stream(stream).await.unwrap();
+
+        assert_eq!(text, "echo: Hi. ");
+    }
+
+    #[test]
+    fn test_truncate_at_earliest_stop() {
+        let params = GenerationParams::default().with_stop("C").with_stop("B");
+        assert_eq!(params.truncate_at_stop("AABBCC"), "AA");
+        assert_eq!(GenerationParams::default().truncate_at_stop("AABBCC"), "AABBCC");
+    }
+
+    /// Replies with the scripted answers in order.
+    struct ScriptedLlm(std::sync::Mutex<Vec<&'static str>>);
+
+    #[async_trait]
+    impl LlmPort for ScriptedLlm {
+        async fn generate(&self, _prompt: &str, _max_tokens: usize) -> Result<String> {
+            Ok(self.0.lock().unwrap().remove(0).to_string())
+        }
+
+        async fn generate_with_params(&self, prompt: &str, max_tokens: usize, _temp: f32, _top_p: f32) -> Result<String> {
+            self.generate(prompt, max_tokens).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_structured_retries_until_valid() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": { "n": { "type": "integer" } },
+            "required": ["n"]
+        });
+        let llm = ScriptedLlm(std::sync::Mutex::new(vec!["no idea", "{\"n\": \"one\"}", "Sure: {\"n\": 1}"]));
+        let value = llm.generate_structured("Count", &schema, &GenerationParams::default()).await.unwrap();
+        assert_eq!(value, serde_json::json!({ "n": 1 }));
+
+        let llm = ScriptedLlm(std::sync::Mutex::new(vec!["{}"; STRUCTURED_ATTEMPTS]));
+        let error = llm.generate_structured("Count", &schema, &GenerationParams::default()).await;
+        assert!(matches!(error, Err(Error::Validation { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_collect_stream() {
+        let events = vec![
+            Ok(TokenEvent::Delta("Hel".to_string())),
+            Ok(TokenEvent::Delta("lo".to_string())),
+            Ok(TokenEvent::Done(GenerationUsage {
+                prompt_tokens: 3,
+                completion_tokens: 2,
+                finish_reason: FinishReason::Length,
+            })),
+        ];
+        let (text, usage) = collect_stream(Box::pin(stream::iter(events))).await.unwrap();
 
-        let prompt = format!(
-            "Summarize the following text concisely:\n\n{}\n\nSummary:",
-            text
-        );
-        self.generate(&prompt, 256).await
+        assert_eq!(text, "Hello");
+        assert_eq!(usage.completion_tokens, 2);
+        assert_eq!(usage.finish_reason, FinishReason::Length);
     }
 }
@@ -1,9 +1,10 @@
 //! MemoryPort - Trait for long-term semantic memory storage.
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use crate::MemoryNode;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 
 
 /// Search result from memory query.
@@ -15,6 +16,17 @@ pub struct SearchResult {
     pub distance: f32,
 }
 
+/// A directed graph edge between two memory nodes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Relationship {
+    /// Source node ID
+    pub from_id: String,
+    /// Edge label (e.g. "summarizes")
+    pub relation: String,
+    /// Target node ID
+    pub to_id: String,
+}
+
 /// Port for long-term semantic memory storage.
 ///
 /// Implementations:
@@ -57,7 +69,7 @@ pub trait MemoryPort: Send + Sync {
     /// Delete a node by ID.
     async fn delete(&self, id: &str) -> Result<()>;
 
-    /// Count total nodes.
+    /// Count total nodes, not counting chunks of chunked memories.
     async fn count(&self) -> Result<usize>;
 
     /// Add a relationship between two nodes (HiRAG graph edge).
@@ -65,6 +77,30 @@ pub trait MemoryPort: Send + Sync {
     /// e.g. `add_relationship("summary_id", "summarizes", "fact_id")`
     async fn add_relationship(&self, from_id: &str, relation: &str, to_id: &str) -> Result<()>;
 
-    /// Count nodes at a specific layer (for consolidation thresholds).
+    /// List relationships where `id` is either end.
+    ///
+    /// Stores without a graph return an error by default.
+    async fn get_related(&self, id: &str) -> Result<Vec<Relationship>> {
+        let _ = id;
+        Err(Error::System("This memory store does not support relationship queries".to_string()))
+    }
+
+    /// Get all nodes in a namespace.
+    ///
+    /// Stores that cannot list a namespace return an error by default.
+    async fn get_by_namespace(&self, namespace: &str) -> Result<Vec<MemoryNode>> {
+        let _ = namespace;
+        Err(Error::System("This memory store cannot list a namespace".to_string()))
+    }
+
+    /// Count nodes at a specific layer (for consolidation thresholds), not
+    /// counting chunks of chunked memories.
     async fn count_by_layer(&self, layer: u8) -> Result<usize>;
+
+    /// List the namespaces that hold at least one node, sorted.
+    ///
+    /// Stores that cannot enumerate namespaces return an error by default.
+    async fn list_namespaces(&self) -> Result<Vec<String>> {
+        Err(Error::System("This memory store cannot list namespaces".to_string()))
+    }
 }
@@ -12,6 +12,7 @@ pub mod immune_port;
 pub mod vision_port;
 pub mod audio_port;
 pub mod commerce_port;
+pub mod injection_port;
 
 pub use memory_port::*;
 pub use buffer_port::*;
@@ -22,3 +23,4 @@ pub use immune_port::*;
 pub use vision_port::*;
 pub use audio_port::*;
 pub use commerce_port::*;
+pub use injection_port::*;
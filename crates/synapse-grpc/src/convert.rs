@@ -0,0 +1,75 @@
+//! Conversions between domain types and protobuf messages.
+
+use std::collections::HashMap;
+
+use synapse_core::error::Error;
+use synapse_core::{MemoryNode, NodeType, Relationship, SearchResult};
+use tonic::Status;
+
+use crate::pb;
+
+pub fn status(error: Error) -> Status {
+    match &error {
+        Error::NotFound { .. } => Status::not_found(error.to_string()),
+        Error::Validation { .. } | Error::DimensionMismatch { .. } => Status::invalid_argument(error.to_string()),
+        Error::EthicsViolation { .. } => Status::permission_denied(error.to_string()),
+        _ => {
+            tracing::error!("{}", error);
+            Status::internal(error.to_string())
+        }
+    }
+}
+
+fn node_type(node_type: NodeType) -> pb::NodeType {
+    match node_type {
+        NodeType::Fact => pb::NodeType::Fact,
+        NodeType::Summary => pb::NodeType::Summary,
+        NodeType::Thought => pb::NodeType::Thought,
+        NodeType::Profile => pb::NodeType::Profile,
+        NodeType::System => pb::NodeType::System,
+        NodeType::External => pb::NodeType::External,
+    }
+}
+
+impl From<MemoryNode> for pb::Memory {
+    fn from(node: MemoryNode) -> Self {
+        Self {
+            metadata_json: serde_json::to_string(&node.metadata).unwrap_or_default(),
+            node_type: node_type(node.node_type).into(),
+            id: node.id,
+            content: node.content,
+            layer: node.layer.into(),
+            created_at: node.created_at,
+            updated_at: node.updated_at,
+            namespace: node.namespace,
+            source: node.source,
+        }
+    }
+}
+
+impl From<SearchResult> for pb::SearchHit {
+    fn from(result: SearchResult) -> Self {
+        Self {
+            memory: Some(result.node.into()),
+            distance: result.distance,
+        }
+    }
+}
+
+impl From<Relationship> for pb::Relationship {
+    fn from(relationship: Relationship) -> Self {
+        Self {
+            from_id: relationship.from_id,
+            relation: relationship.relation,
+            to_id: relationship.to_id,
+        }
+    }
+}
+
+/// Parse a `metadata_json` field (empty means no metadata).
+pub fn metadata(json: &str) -> Result<HashMap<String, serde_json::Value>, Status> {
+    if json.trim().is_empty() {
+        return Ok(HashMap::new());
+    }
+    serde_json::from_str(json).map_err(|e| Status::invalid_argument(format!("metadata_json must be a JSON object: {}", e)))
+}
@@ -0,0 +1,17 @@
+//! # Synapse gRPC
+//!
+//! tonic server for `proto/synapse/v1/synapse.proto`, implemented over the
+//! core ports. Clients should use the `synapse-client` crate.
+
+// tonic::Status is large, but it is the error type the service traits require.
+#![allow(clippy::result_large_err)]
+
+/// Generated protobuf messages and service traits.
+pub mod pb {
+    tonic::include_proto!("synapse.v1");
+}
+
+mod convert;
+pub mod service;
+
+pub use service::SynapseGrpc;
@@ -0,0 +1,376 @@
+//! gRPC service implementations.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use synapse_core::logic::chunked_memory::ChunkedMemory;
+use synapse_core::logic::consolidation::LayerConsolidator;
+use synapse_core::logic::memory_api::{self, constant_time_eq, DEFAULT_TOP_K};
+use synapse_core::ports::{BufferPort, EmbeddingPort, LlmPort, MemoryPort};
+use synapse_core::{Interaction, MemoryNode};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::service::Interceptor;
+use tonic::transport::Server;
+use tonic::{Request, Response, Status};
+
+use crate::convert::{metadata, status};
+use crate::pb;
+use crate::pb::buffer_service_server::{BufferService, BufferServiceServer};
+use crate::pb::lifecycle_service_server::{LifecycleService, LifecycleServiceServer};
+use crate::pb::memory_service_server::{MemoryService, MemoryServiceServer};
+
+/// Implements every Synapse service over the core ports.
+#[derive(Clone)]
+pub struct SynapseGrpc {
+    memory: Arc<dyn MemoryPort>,
+    embedder: Arc<dyn EmbeddingPort>,
+    llm: Arc<dyn LlmPort>,
+    buffer: Arc<dyn BufferPort>,
+    api_key: Option<Arc<str>>,
+    maintenance: Arc<Mutex<()>>,
+}
+
+impl SynapseGrpc {
+    pub fn new(
+        memory: Arc<dyn MemoryPort>,
+        embedder: Arc<dyn EmbeddingPort>,
+        llm: Arc<dyn LlmPort>,
+        buffer: Arc<dyn BufferPort>,
+    ) -> Self {
+        Self {
+            memory,
+            embedder,
+            llm,
+            buffer,
+            api_key: None,
+            maintenance: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Require this key (`authorization: Bearer` or `x-api-key` metadata).
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(Arc::from(api_key.into()));
+        self
+    }
+
+    /// Share the lock serializing digest/consolidation with another API in
+    /// the same process.
+    pub fn with_maintenance_lock(mut self, lock: Arc<Mutex<()>>) -> Self {
+        self.maintenance = lock;
+        self
+    }
+
+    /// Serve all services on `listener` until `shutdown` completes.
+    pub async fn serve(self, listener: TcpListener, shutdown: impl Future<Output = ()>) -> Result<(), tonic::transport::Error> {
+        let auth = ApiKeyCheck {
+            expected: self.api_key.clone(),
+        };
+        Server::builder()
+            .add_service(MemoryServiceServer::with_interceptor(self.clone(), auth.clone()))
+            .add_service(BufferServiceServer::with_interceptor(self.clone(), auth.clone()))
+            .add_service(LifecycleServiceServer::with_interceptor(self, auth))
+            .serve_with_incoming_shutdown(TcpListenerStream::new(listener), shutdown)
+            .await
+    }
+
+    fn chunked(&self) -> ChunkedMemory {
+        ChunkedMemory::new(self.memory.clone(), self.embedder.clone())
+    }
+
+    async fn existing(&self, id: &str) -> Result<MemoryNode, Status> {
+        self.memory
+            .get_by_id(id)
+            .await
+            .map_err(status)?
+            .ok_or_else(|| Status::not_found(format!("Memory not found: {}", id)))
+    }
+}
+
+/// Rejects calls without the configured API key.
+#[derive(Clone)]
+struct ApiKeyCheck {
+    expected: Option<Arc<str>>,
+}
+
+impl Interceptor for ApiKeyCheck {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        let Some(expected) = &self.expected else {
+            return Ok(request);
+        };
+        let metadata = request.metadata();
+        let bearer = metadata
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        let presented = bearer.or_else(|| metadata.get("x-api-key").and_then(|v| v.to_str().ok()));
+        match presented {
+            Some(key) if constant_time_eq(key.as_bytes(), expected.as_bytes()) => Ok(request),
+            _ => Err(Status::unauthenticated("Missing or invalid API key")),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl MemoryService for SynapseGrpc {
+    async fn store(&self, request: Request<pb::StoreRequest>) -> Result<Response<pb::StoreResponse>, Status> {
+        let request = request.into_inner();
+        let mut node = MemoryNode::new(request.content);
+        if let Some(namespace) = request.namespace {
+            node = node.with_namespace(namespace);
+        }
+        node.source = request.source.unwrap_or_else(|| "grpc".to_string());
+        node.metadata = metadata(&request.metadata_json)?;
+
+        let id = memory_api::store(self.memory.clone(), self.embedder.clone(), node)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(pb::StoreResponse { id }))
+    }
+
+    async fn search(&self, request: Request<pb::SearchRequest>) -> Result<Response<pb::SearchResponse>, Status> {
+        let request = request.into_inner();
+        let top_k = match request.top_k {
+            0 => DEFAULT_TOP_K,
+            k => k as usize,
+        };
+
+        let results = memory_api::search(
+            self.memory.clone(),
+            self.embedder.clone(),
+            &request.query,
+            request.namespace.as_deref(),
+            top_k,
+        )
+        .await
+        .map_err(status)?;
+        Ok(Response::new(pb::SearchResponse {
+            hits: results.into_iter().map(Into::into).collect(),
+        }))
+    }
+
+    async fn get(&self, request: Request<pb::GetRequest>) -> Result<Response<pb::Memory>, Status> {
+        let node = self.existing(&request.into_inner().id).await?;
+        Ok(Response::new(node.into()))
+    }
+
+    async fn delete(&self, request: Request<pb::DeleteRequest>) -> Result<Response<pb::DeleteResponse>, Status> {
+        let id = request.into_inner().id;
+        self.existing(&id).await?;
+        self.chunked().delete(&id).await.map_err(status)?;
+        Ok(Response::new(pb::DeleteResponse {}))
+    }
+
+    async fn relate(&self, request: Request<pb::Relationship>) -> Result<Response<pb::Relationship>, Status> {
+        let request = request.into_inner();
+        memory_api::validate_relation(&request.relation).map_err(status)?;
+        self.existing(&request.from_id).await?;
+        self.existing(&request.to_id).await?;
+
+        self.memory
+            .add_relationship(&request.from_id, &request.relation, &request.to_id)
+            .await
+            .map_err(status)?;
+        Ok(Response::new(request))
+    }
+
+    async fn get_related(&self, request: Request<pb::GetRequest>) -> Result<Response<pb::GetRelatedResponse>, Status> {
+        let id = request.into_inner().id;
+        self.existing(&id).await?;
+        let relationships = self.memory.get_related(&id).await.map_err(status)?;
+        Ok(Response::new(pb::GetRelatedResponse {
+            relationships: relationships.into_iter().map(Into::into).collect(),
+        }))
+    }
+}
+
+#[tonic::async_trait]
+impl BufferService for SynapseGrpc {
+    async fn push(&self, request: Request<pb::PushRequest>) -> Result<Response<pb::PushResponse>, Status> {
+        let request = request.into_inner();
+        let mut interaction = Interaction::new(request.user_input, request.ai_response);
+        if let Some(session_id) = request.session_id {
+            interaction = interaction.with_session(session_id);
+        }
+        let id = interaction.id.clone();
+        let buffer_size = memory_api::push(self.buffer.as_ref(), interaction).await.map_err(status)? as u64;
+        Ok(Response::new(pb::PushResponse { id, buffer_size }))
+    }
+}
+
+#[tonic::async_trait]
+impl LifecycleService for SynapseGrpc {
+    async fn digest(&self, request: Request<pb::DigestRequest>) -> Result<Response<pb::DigestResponse>, Status> {
+        let force = request.into_inner().force;
+        let _running = self.maintenance.lock().await;
+
+        let digested = memory_api::digest(
+            self.buffer.clone(),
+            self.memory.clone(),
+            self.llm.clone(),
+            self.embedder.clone(),
+            force,
+        )
+        .await
+        .map_err(status)? as u64;
+        Ok(Response::new(pb::DigestResponse { digested }))
+    }
+
+    async fn consolidate(&self, _request: Request<pb::ConsolidateRequest>) -> Result<Response<pb::ConsolidateResponse>, Status> {
+        let _running = self.maintenance.lock().await;
+
+        let summaries = LayerConsolidator::new(self.memory.clone(), self.llm.clone(), self.embedder.clone())
+            .consolidate_all()
+            .await
+            .map_err(status)? as u64;
+        Ok(Response::new(pb::ConsolidateResponse { summaries }))
+    }
+
+    async fn stats(&self, _request: Request<pb::StatsRequest>) -> Result<Response<pb::StatsResponse>, Status> {
+        let stats = memory_api::stats(self.memory.as_ref(), self.buffer.as_ref()).await.map_err(status)?;
+        Ok(Response::new(pb::StatsResponse {
+            total_memories: stats.total_memories as u64,
+            buffer_size: stats.buffer_size as u64,
+            layers: stats
+                .layers
+                .into_iter()
+                .map(|(layer, count)| pb::LayerCount {
+                    layer: layer.into(),
+                    count: count as u64,
+                })
+                .collect(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use synapse_client::pb as client_pb;
+    use synapse_core::logic::memory_api::MAX_TOP_K;
+    use synapse_client::SynapseClient;
+    use synapse_infra::adapters::mock_embedding_adapter::MockEmbeddingAdapter;
+    use synapse_infra::adapters::mock_llm_adapter::MockLlmAdapter;
+    use synapse_infra::adapters::sled_adapter::SledAdapter;
+    use synapse_infra::adapters::surrealdb_adapter::SurrealDbAdapter;
+    use tempfile::TempDir;
+    use tonic::Code;
+
+    struct TestNode {
+        client: SynapseClient,
+        _shutdown: tokio::sync::oneshot::Sender<()>,
+        _dir: TempDir,
+    }
+
+    async fn start(api_key: Option<&str>) -> TestNode {
+        let dir = TempDir::new().unwrap();
+        let buffer = SledAdapter::new(&dir.path().join("buffer").to_string_lossy()).unwrap();
+        let mut service = SynapseGrpc::new(
+            Arc::new(SurrealDbAdapter::new_memory().await.unwrap()),
+            Arc::new(MockEmbeddingAdapter::new()),
+            Arc::new(MockLlmAdapter::new()),
+            Arc::new(buffer),
+        );
+        if let Some(key) = api_key {
+            service = service.with_api_key(key);
+        }
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (shutdown, stop) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(service.serve(listener, async {
+            let _ = stop.await;
+        }));
+
+        let client = SynapseClient::connect(format!("http://{}", address)).await.unwrap();
+        TestNode {
+            client,
+            _shutdown: shutdown,
+            _dir: dir,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_round_trip() {
+        let node = start(None).await;
+        let client = &node.client;
+
+        let id = client.store("Rex is a dog", Some("pets")).await.unwrap();
+        let memory = client.get(&id).await.unwrap();
+        assert_eq!(memory.content, "Rex is a dog");
+        assert_eq!(memory.namespace, "pets");
+        assert_eq!(memory.source, "grpc");
+        assert_eq!(memory.node_type(), client_pb::NodeType::Fact);
+
+        let other = client.store("Cats purr", None).await.unwrap();
+        let hits = client.search("Rex is a dog", 5).await.unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].memory.as_ref().unwrap().id, id);
+        assert!(hits[0].distance <= hits[1].distance);
+
+        let edge = client_pb::Relationship {
+            from_id: other.clone(),
+            relation: "mentions".to_string(),
+            to_id: id.clone(),
+        };
+        client.memory().relate(edge.clone()).await.unwrap();
+        let related = client
+            .memory()
+            .get_related(client_pb::GetRequest { id: id.clone() })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(related.relationships, vec![edge]);
+
+        client.delete(&id).await.unwrap();
+        assert_eq!(client.get(&id).await.unwrap_err().code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_arguments() {
+        let node = start(None).await;
+        let client = &node.client;
+
+        assert_eq!(client.store(" ", None).await.unwrap_err().code(), Code::InvalidArgument);
+        assert_eq!(client.search("x", MAX_TOP_K as u32 + 1).await.unwrap_err().code(), Code::InvalidArgument);
+
+        let request = client_pb::StoreRequest {
+            content: "x".to_string(),
+            metadata_json: "[1, 2]".to_string(),
+            ..Default::default()
+        };
+        let error = client.memory().store(request).await.unwrap_err();
+        assert_eq!(error.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_buffer_and_lifecycle() {
+        let node = start(None).await;
+        let client = &node.client;
+
+        let pushed = client.push("My dog is Rex", "Nice name!").await.unwrap();
+        assert_eq!(pushed.buffer_size, 1);
+        assert_eq!(client.digest(false).await.unwrap(), 0);
+        assert_eq!(client.digest(true).await.unwrap(), 1);
+
+        let stats = client.stats().await.unwrap();
+        assert_eq!(stats.total_memories, 1);
+        assert_eq!(stats.buffer_size, 0);
+        assert_eq!(stats.layers, vec![client_pb::LayerCount { layer: 0, count: 1 }]);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_is_required_when_configured() {
+        let node = start(Some("secret")).await;
+
+        let error = node.client.stats().await.unwrap_err();
+        assert_eq!(error.code(), Code::Unauthenticated);
+
+        let wrong = node.client.clone().with_api_key("wrong").unwrap();
+        assert_eq!(wrong.stats().await.unwrap_err().code(), Code::Unauthenticated);
+
+        let authorized = node.client.clone().with_api_key("secret").unwrap();
+        assert_eq!(authorized.stats().await.unwrap().total_memories, 0);
+    }
+}
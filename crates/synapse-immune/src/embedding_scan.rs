@@ -0,0 +1,331 @@
+//! Periodic anomaly scan over stored embeddings.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use synapse_core::error::{Error, Result};
+use synapse_core::logic::chunked_memory::{chunk_ids, mean_embedding};
+use synapse_core::logic::embedding_check::{EmbeddingCheck, Outlier, OutlierDetector};
+use synapse_core::{EmbeddingPort, ImmunePort, MemoryPort, ThreatLevel, ThreatReport};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// Cosine distance below which a re-embedded vector counts as unchanged.
+const UNCHANGED_DISTANCE: f32 = 1e-4;
+
+/// Looks for corrupted and outlying embeddings, namespace by namespace.
+///
+/// Each flagged node is reported through the immune port once while it
+/// stays flagged. With an embedder, flagged nodes can be re-embedded from
+/// their content; a node whose fresh embedding matches the stored one is
+/// genuinely off-topic rather than corrupted and is left alone.
+pub struct EmbeddingScanner {
+    memory: Arc<dyn MemoryPort>,
+    immune: Arc<dyn ImmunePort>,
+    embedder: Option<Arc<dyn EmbeddingPort>>,
+    detector: OutlierDetector,
+    /// Flagged node ids by namespace, as of the last scan
+    reported: Mutex<HashMap<String, HashSet<String>>>,
+}
+
+impl EmbeddingScanner {
+    pub fn new(memory: Arc<dyn MemoryPort>, immune: Arc<dyn ImmunePort>) -> Self {
+        Self {
+            memory,
+            immune,
+            embedder: None,
+            detector: OutlierDetector::new(),
+            reported: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Validate against this embedder's dimension and normalization, and
+    /// use it to re-embed.
+    pub fn with_embedder(mut self, embedder: Arc<dyn EmbeddingPort>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    pub fn with_detector(mut self, detector: OutlierDetector) -> Self {
+        self.detector = detector;
+        self
+    }
+
+    fn check(&self) -> EmbeddingCheck {
+        self.embedder
+            .as_deref()
+            .map_or_else(EmbeddingCheck::new, EmbeddingCheck::for_embedder)
+    }
+
+    /// Scan every namespace and report new findings.
+    pub async fn scan(&self) -> Result<Vec<Outlier>> {
+        let mut outliers = Vec::new();
+        for namespace in self.memory.list_namespaces().await? {
+            outliers.extend(self.scan_namespace(&namespace).await?);
+        }
+        Ok(outliers)
+    }
+
+    /// Scan one namespace and report new findings.
+    pub async fn scan_namespace(&self, namespace: &str) -> Result<Vec<Outlier>> {
+        let nodes = self.memory.get_by_namespace(namespace).await?;
+        let detector = self.detector.clone();
+        let check = self.check();
+        let outliers = tokio::task::spawn_blocking(move || detector.find(&nodes, &check))
+            .await
+            .map_err(|e| Error::System(format!("Embedding scan failed: {}", e)))?;
+
+        // Still-flagged nodes stay reported; nodes that recovered are forgotten
+        let mut sent: HashSet<String> = {
+            let reported = self.reported.lock().unwrap_or_else(|e| e.into_inner());
+            let previous = reported.get(namespace);
+            outliers
+                .iter()
+                .filter(|o| previous.is_some_and(|ids| ids.contains(&o.id)))
+                .map(|o| o.id.clone())
+                .collect()
+        };
+        let fresh: Vec<&Outlier> = outliers.iter().filter(|o| !sent.contains(&o.id)).collect();
+        for outlier in fresh {
+            warn!(
+                "🚨 Anomalous embedding for memory {} in '{}': {}",
+                outlier.id,
+                outlier.namespace,
+                describe(outlier)
+            );
+            // An unsent report is retried on the next scan
+            match self.immune.report_threat(to_report(outlier)).await {
+                Ok(()) => {
+                    sent.insert(outlier.id.clone());
+                }
+                Err(e) => warn!("Failed to report memory {}: {}", outlier.id, e),
+            }
+        }
+        self.reported
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(namespace.to_string(), sent);
+        Ok(outliers)
+    }
+
+    /// Recompute the embeddings of `outliers` from their content and store
+    /// the ones that changed. Returns how many nodes were updated.
+    pub async fn reembed(&self, outliers: &[Outlier]) -> Result<usize> {
+        let embedder = self
+            .embedder
+            .as_ref()
+            .ok_or_else(|| Error::System("Re-embedding needs an embedder".to_string()))?;
+        let check = self.check();
+        let mut updated = 0;
+        for outlier in outliers {
+            let Some(mut node) = self.memory.get_by_id(&outlier.id).await? else {
+                continue;
+            };
+            let chunks = chunk_ids(&node);
+            let embedding = if chunks.is_empty() {
+                embedder.embed(&node.content).await?
+            } else {
+                // Chunked memories are embedded as the mean of their chunks
+                let mut texts = Vec::new();
+                for id in &chunks {
+                    if let Some(chunk) = self.memory.get_by_id(id).await? {
+                        texts.push(chunk.content);
+                    }
+                }
+                let embeddings = embedder.embed_batch(&texts).await?;
+                mean_embedding(embeddings.iter().map(Vec::as_slice))
+            };
+
+            if let Err(issue) = check.validate(&embedding) {
+                warn!("Re-embedding memory {} failed: {}", node.id, issue);
+                continue;
+            }
+            if outlier.issue.is_none() && unchanged(&node.embedding, &embedding) {
+                continue;
+            }
+            node.embedding = embedding;
+            node.updated_at = now();
+            self.memory.update(node).await?;
+            if let Some(flagged) = self
+                .reported
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .get_mut(&outlier.namespace)
+            {
+                flagged.remove(&outlier.id);
+            }
+            updated += 1;
+        }
+        Ok(updated)
+    }
+
+    /// Scan every `interval`, re-embedding flagged nodes if `reembed` is set.
+    pub fn watch(self: &Arc<Self>, interval: Duration, reembed: bool) -> JoinHandle<()> {
+        let scanner = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let outliers = match scanner.scan().await {
+                    Ok(outliers) => outliers,
+                    Err(e) => {
+                        warn!("Embedding scan failed: {}", e);
+                        continue;
+                    }
+                };
+                if !reembed || outliers.is_empty() {
+                    continue;
+                }
+                match scanner.reembed(&outliers).await {
+                    Ok(updated) => info!(
+                        "Re-embedded {} of {} flagged memories",
+                        updated,
+                        outliers.len()
+                    ),
+                    Err(e) => warn!("Re-embedding failed: {}", e),
+                }
+            }
+        })
+    }
+}
+
+fn describe(outlier: &Outlier) -> String {
+    match &outlier.issue {
+        Some(issue) => issue.to_string(),
+        None => format!(
+            "far from the rest of the namespace (score {:.1})",
+            outlier.score
+        ),
+    }
+}
+
+fn to_report(outlier: &Outlier) -> ThreatReport {
+    ThreatReport {
+        source_id: outlier.id.clone(),
+        threat_type: "embedding_anomaly".to_string(),
+        level: ThreatLevel::Suspicious,
+        description: format!(
+            "Embedding of memory in '{}': {}",
+            outlier.namespace,
+            describe(outlier)
+        ),
+        timestamp: now(),
+        rule: Some(outlier.kind().to_string()),
+    }
+}
+
+/// Whether two embeddings point the same way.
+fn unchanged(old: &[f32], new: &[f32]) -> bool {
+    if old.len() != new.len() {
+        return false;
+    }
+    let dot: f32 = old.iter().zip(new).map(|(a, b)| a * b).sum();
+    let norms = old.iter().map(|x| x * x).sum::<f32>().sqrt()
+        * new.iter().map(|x| x * x).sum::<f32>().sqrt();
+    norms > 0.0 && 1.0 - dot / norms < UNCHANGED_DISTANCE
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockMemory;
+    use async_trait::async_trait;
+    use synapse_core::MemoryNode;
+    use synapse_infra::adapters::immune_adapter::BasicImmuneAdapter;
+    use synapse_infra::adapters::mock_embedding_adapter::MockEmbeddingAdapter;
+
+    #[tokio::test]
+    async fn test_scan_reports_and_reembeds() {
+        let memory = Arc::new(MockMemory::default());
+        let immune = Arc::new(BasicImmuneAdapter::new());
+        let embedder = Arc::new(MockEmbeddingAdapter::new());
+
+        for i in 0..12 {
+            let content = format!("note {}", i);
+            let embedding = embedder.embed(&content).await.unwrap();
+            memory
+                .store(MemoryNode::new(content).with_embedding(embedding))
+                .await
+                .unwrap();
+        }
+        let mut corrupt =
+            MemoryNode::new("note 12".to_string()).with_embedding(vec![f32::NAN; 384]);
+        corrupt.namespace = "work".to_string();
+        let corrupt_id = memory.store(corrupt).await.unwrap();
+
+        let scanner = EmbeddingScanner::new(memory.clone(), immune.clone())
+            .with_embedder(embedder.clone())
+            .with_detector(OutlierDetector::new().with_min_nodes(usize::MAX));
+
+        let outliers = scanner.scan().await.unwrap();
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0].id, corrupt_id);
+        assert_eq!(outliers[0].kind(), "non_finite");
+        let entry = immune.threat_db().get(&corrupt_id).unwrap();
+        assert_eq!(entry.threat_type, "embedding_anomaly");
+
+        // Still flagged, but reported only once
+        assert_eq!(scanner.scan().await.unwrap().len(), 1);
+        assert_eq!(immune.threat_db().len(), 1);
+
+        assert_eq!(scanner.reembed(&outliers).await.unwrap(), 1);
+        let fixed = memory.get_by_id(&corrupt_id).await.unwrap().unwrap();
+        assert_eq!(fixed.embedding, embedder.embed("note 12").await.unwrap());
+        assert!(scanner.scan().await.unwrap().is_empty());
+    }
+
+    /// Fails every report while `down` is set.
+    struct FlakyImmune {
+        inner: BasicImmuneAdapter,
+        down: std::sync::atomic::AtomicBool,
+    }
+
+    #[async_trait]
+    impl ImmunePort for FlakyImmune {
+        async fn check_integrity(&self) -> Result<bool> {
+            self.inner.check_integrity().await
+        }
+
+        async fn scan_process(&self, process_name: &str) -> Result<ThreatLevel> {
+            self.inner.scan_process(process_name).await
+        }
+
+        async fn report_threat(&self, report: ThreatReport) -> Result<()> {
+            if self.down.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(Error::System("immune port unavailable".into()));
+            }
+            self.inner.report_threat(report).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_reports_are_retried() {
+        let memory = Arc::new(MockMemory::default());
+        for content in ["a", "b"] {
+            memory
+                .store(MemoryNode::new(content.to_string()).with_embedding(vec![f32::NAN; 4]))
+                .await
+                .unwrap();
+        }
+        let immune = Arc::new(FlakyImmune {
+            inner: BasicImmuneAdapter::new(),
+            down: true.into(),
+        });
+        let scanner = EmbeddingScanner::new(memory, immune.clone())
+            .with_detector(OutlierDetector::new().with_min_nodes(usize::MAX));
+
+        assert_eq!(scanner.scan().await.unwrap().len(), 2);
+        assert_eq!(immune.inner.threat_db().len(), 0);
+
+        immune.down.store(false, std::sync::atomic::Ordering::SeqCst);
+        scanner.scan().await.unwrap();
+        assert_eq!(immune.inner.threat_db().len(), 2);
+    }
+}
@@ -0,0 +1,522 @@
+//! Rule evaluation with hot reload.
+
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+
+use sha2::{Digest, Sha256};
+use synapse_core::error::{Error, Result};
+use synapse_core::ports::ProcessInfo;
+use synapse_core::{ThreatLevel, ThreatReport};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::netstat::{self, Socket};
+use crate::rules::{self, Rule, Severity};
+
+/// How often long-running processes poll rule files for changes.
+pub const RELOAD_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A rule that matched a process.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub rule_id: String,
+    pub description: String,
+    pub severity: Severity,
+    pub pid: u32,
+    pub process_name: String,
+    /// Executable hash, when the rule matched on it
+    pub sha256: Option<String>,
+}
+
+impl Detection {
+    pub fn level(&self) -> ThreatLevel {
+        self.severity.into()
+    }
+
+    /// A report naming the rule. The indicator is the executable hash when
+    /// the rule matched on it, and otherwise this process instance, so a
+    /// behavioural rule never flags every process with the same name.
+    pub fn to_report(&self) -> ThreatReport {
+        ThreatReport {
+            source_id: self
+                .sha256
+                .clone()
+                .unwrap_or_else(|| format!("{}[{}]", self.process_name, self.pid)),
+            threat_type: "signature".to_string(),
+            level: self.level(),
+            description: if self.description.is_empty() {
+                format!("{} matched rule {}", self.process_name, self.rule_id)
+            } else {
+                format!("{}: {}", self.process_name, self.description)
+            },
+            timestamp: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0),
+            rule: Some(self.rule_id.clone()),
+        }
+    }
+}
+
+/// Matches processes against loaded rules.
+pub struct RuleEngine {
+    source: Option<PathBuf>,
+    proc_root: PathBuf,
+    rules: RwLock<Vec<Rule>>,
+    stamps: Mutex<Vec<FileStamp>>,
+    hashes: Mutex<HashMap<PathBuf, (u64, SystemTime, String)>>,
+}
+
+impl RuleEngine {
+    /// Load rules from a file or a directory of rule files.
+    pub fn load(source: impl Into<PathBuf>) -> Result<Self> {
+        let source = source.into();
+        let rules = rules::load_rules(&source)?;
+        info!(
+            "Loaded {} threat rules from {}",
+            rules.len(),
+            source.display()
+        );
+        Ok(Self {
+            stamps: Mutex::new(stamps(&source)?),
+            source: Some(source),
+            ..Self::from_rules(rules)
+        })
+    }
+
+    /// Use fixed rules (never reloaded).
+    pub fn from_rules(rules: Vec<Rule>) -> Self {
+        Self {
+            source: None,
+            proc_root: PathBuf::from("/proc"),
+            rules: RwLock::new(rules),
+            stamps: Mutex::new(Vec::new()),
+            hashes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Read executables and sockets from a different procfs mount.
+    pub fn with_proc_root(mut self, proc_root: impl Into<PathBuf>) -> Self {
+        self.proc_root = proc_root.into();
+        self
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Reload the rule files if any was added, removed or modified.
+    ///
+    /// Returns whether new rules were loaded. If the changed files are
+    /// invalid the current rules stay active and an error is returned; the
+    /// files are retried on their next change.
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        let Some(source) = &self.source else {
+            return Ok(false);
+        };
+        let current = stamps(source)?;
+        {
+            let mut known = self.stamps.lock().unwrap_or_else(|e| e.into_inner());
+            if *known == current {
+                return Ok(false);
+            }
+            *known = current;
+        }
+
+        let rules = rules::load_rules(source)?;
+        info!(
+            "Reloaded {} threat rules from {}",
+            rules.len(),
+            source.display()
+        );
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = rules;
+        Ok(true)
+    }
+
+    /// Poll the rule files for changes every `interval`.
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let engine = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Err(e) = engine.reload_if_changed() {
+                    warn!("Keeping previous threat rules: {}", e);
+                }
+            }
+        })
+    }
+
+    /// Every rule that matches `process`. `table` is the full process list,
+    /// used to resolve the parent.
+    ///
+    /// Blocking: may hash the executable and read `/proc`.
+    pub fn evaluate(&self, process: &ProcessInfo, table: &[ProcessInfo]) -> Vec<Detection> {
+        self.evaluate_all(std::slice::from_ref(process), table)
+    }
+
+    /// Every rule that matches any of `processes`, in one pass that reads
+    /// the system socket table at most once.
+    ///
+    /// Blocking: may hash executables and read `/proc`.
+    pub fn evaluate_all(&self, processes: &[ProcessInfo], table: &[ProcessInfo]) -> Vec<Detection> {
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner());
+        let sockets = OnceCell::new();
+        processes
+            .iter()
+            .flat_map(|process| self.evaluate_with(&rules, process, table, &sockets))
+            .collect()
+    }
+
+    fn evaluate_with(
+        &self,
+        rules: &[Rule],
+        process: &ProcessInfo,
+        table: &[ProcessInfo],
+        sockets: &OnceCell<HashMap<u64, Socket>>,
+    ) -> Vec<Detection> {
+        let mut facts = Facts::new(self, process, table, sockets);
+        let mut detections = Vec::new();
+        for rule in rules {
+            if !facts.matches(rule) {
+                continue;
+            }
+            let sha256 = if rule.sha256.is_empty() {
+                None
+            } else {
+                facts.sha256().clone()
+            };
+            detections.push(Detection {
+                rule_id: rule.id.clone(),
+                description: rule.description.clone(),
+                severity: rule.severity,
+                pid: process.pid,
+                process_name: process.name.clone(),
+                sha256,
+            });
+        }
+        detections
+    }
+
+    fn hash_executable(&self, process: &ProcessInfo) -> Option<String> {
+        // /proc/<pid>/exe still opens when the file was deleted or replaced
+        let link = self.proc_root.join(process.pid.to_string()).join("exe");
+        let path = if link.exists() {
+            link
+        } else {
+            PathBuf::from(process.exe.as_ref()?)
+        };
+        let metadata = std::fs::metadata(&path).ok()?;
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let key = std::fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+
+        let mut hashes = self.hashes.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((len, mtime, hash)) = hashes.get(&key) {
+            if *len == metadata.len() && *mtime == modified {
+                return Some(hash.clone());
+            }
+        }
+        let hash = sha256_file(&path).ok()?;
+        hashes.insert(key, (metadata.len(), modified, hash.clone()));
+        Some(hash)
+    }
+}
+
+/// Lazily gathered facts about one process.
+struct Facts<'a> {
+    engine: &'a RuleEngine,
+    process: &'a ProcessInfo,
+    parent: Option<&'a str>,
+    cmdline: String,
+    sha256: Option<Option<String>>,
+    /// The system socket table, shared by every process in the pass
+    all_sockets: &'a OnceCell<HashMap<u64, Socket>>,
+    sockets: Option<Vec<Socket>>,
+}
+
+impl<'a> Facts<'a> {
+    fn new(
+        engine: &'a RuleEngine,
+        process: &'a ProcessInfo,
+        table: &'a [ProcessInfo],
+        all_sockets: &'a OnceCell<HashMap<u64, Socket>>,
+    ) -> Self {
+        let parent = process
+            .parent_pid
+            .and_then(|ppid| table.iter().find(|p| p.pid == ppid))
+            .map(|p| p.name.as_str());
+        Self {
+            engine,
+            process,
+            parent,
+            cmdline: process.cmdline.join(" "),
+            sha256: None,
+            all_sockets,
+            sockets: None,
+        }
+    }
+
+    fn sha256(&mut self) -> &Option<String> {
+        if self.sha256.is_none() {
+            self.sha256 = Some(self.engine.hash_executable(self.process));
+        }
+        self.sha256.as_ref().expect("computed above")
+    }
+
+    fn sockets(&mut self) -> &[Socket] {
+        if self.sockets.is_none() {
+            let all = self
+                .all_sockets
+                .get_or_init(|| netstat::sockets(&self.engine.proc_root));
+            self.sockets = Some(netstat::process_sockets(
+                &self.engine.proc_root,
+                self.process.pid,
+                all,
+            ));
+        }
+        self.sockets.as_deref().unwrap_or_default()
+    }
+
+    /// Cheap conditions first, so hashing and socket lookups only happen
+    /// for processes that are otherwise a match.
+    fn matches(&mut self, rule: &Rule) -> bool {
+        if let Some(name) = &rule.name {
+            if !name.is_match(&self.process.name) {
+                return false;
+            }
+        }
+        if let Some(path) = &rule.path {
+            if !self
+                .process
+                .exe
+                .as_deref()
+                .is_some_and(|exe| path.is_match(exe))
+            {
+                return false;
+            }
+        }
+        if let Some(cmdline) = &rule.cmdline {
+            if !cmdline.is_match(&self.cmdline) {
+                return false;
+            }
+        }
+        if let Some(parent) = &rule.parent {
+            if !self.parent.is_some_and(|name| parent.is_match(name)) {
+                return false;
+            }
+        }
+        if !rule.sha256.is_empty() {
+            match self.sha256() {
+                Some(hash) if rule.sha256.contains(hash) => {}
+                _ => return false,
+            }
+        }
+        if rule.needs_sockets() {
+            let listen = &rule.listen_ports;
+            let remote = &rule.remote_ports;
+            let sockets = self.sockets();
+            let listening = listen.is_empty()
+                || sockets
+                    .iter()
+                    .any(|s| s.listening && listen.contains(&s.local_port));
+            let connected = remote.is_empty()
+                || sockets
+                    .iter()
+                    .any(|s| s.remote_port != 0 && remote.contains(&s.remote_port));
+            if !(listening && connected) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A rule file with its size and modification time.
+type FileStamp = (PathBuf, u64, Option<SystemTime>);
+
+/// Sizes and modification times of the rule files, to detect edits.
+fn stamps(source: &Path) -> Result<Vec<FileStamp>> {
+    Ok(rules::rule_files(source)?
+        .into_iter()
+        .map(|file| {
+            let metadata = std::fs::metadata(&file).ok();
+            let len = metadata.as_ref().map_or(0, |m| m.len());
+            let modified = metadata.and_then(|m| m.modified().ok());
+            (file, len, modified)
+        })
+        .collect())
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| Error::System(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 16];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| Error::System(format!("Failed to read {}: {}", path.display(), e)))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, parent: Option<u32>, name: &str, cmdline: &[&str]) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            parent_pid: parent,
+            name: name.to_string(),
+            cmdline: cmdline.iter().map(|s| s.to_string()).collect(),
+            exe: Some(format!("/usr/bin/{}", name)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_conditions_must_all_match() {
+        let rules = rules::parse_rules(
+            r#"
+rules:
+  - id: webshell
+    severity: critical
+    name: "sh"
+    parent: "php-fpm*"
+  - id: miner-args
+    severity: high
+    cmdline: "--donate-level[ =]\\d+"
+  - id: tmp-binary
+    severity: medium
+    path: "/tmp/*"
+"#,
+            false,
+        )
+        .unwrap();
+        let engine = RuleEngine::from_rules(rules).with_proc_root("/nonexistent");
+
+        let table = vec![
+            process(1, None, "php-fpm8.2", &["php-fpm: master"]),
+            process(2, Some(1), "sh", &["sh", "-c", "id"]),
+            process(3, None, "bash", &["bash"]),
+            process(4, Some(3), "sh", &["sh"]),
+            process(5, Some(3), "kworker", &["kworker", "--donate-level", "1"]),
+        ];
+        let ids = |pid: u32| -> Vec<String> {
+            engine
+                .evaluate(&table[pid as usize - 1], &table)
+                .into_iter()
+                .map(|d| d.rule_id)
+                .collect()
+        };
+
+        assert_eq!(ids(2), vec!["webshell"]);
+        assert!(ids(4).is_empty());
+        assert_eq!(ids(5), vec!["miner-args"]);
+
+        let report = engine.evaluate(&table[1], &table)[0].to_report();
+        assert_eq!(report.rule.as_deref(), Some("webshell"));
+        assert_eq!(report.level, ThreatLevel::Critical);
+        assert_eq!(report.source_id, "sh[2]");
+    }
+
+    #[test]
+    fn test_hash_rule_reports_the_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe = dir.path().join("dropper");
+        std::fs::write(&exe, b"test").unwrap();
+        // sha256("test")
+        let hash = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+        let rules = rules::parse_rules(
+            &format!(
+                r#"{{"rules": [{{"id": "known-dropper", "severity": "high", "sha256": ["{}"]}}]}}"#,
+                hash
+            ),
+            true,
+        )
+        .unwrap();
+        let engine = RuleEngine::from_rules(rules).with_proc_root(dir.path());
+
+        let dropper = ProcessInfo {
+            pid: 9,
+            name: "update".to_string(),
+            exe: Some(exe.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let detections = engine.evaluate(&dropper, &[]);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].to_report().source_id, hash);
+
+        std::fs::write(&exe, b"different").unwrap();
+        assert!(engine.evaluate(&dropper, &[]).is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_socket_rule_over_a_pass() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("net")).unwrap();
+        std::fs::write(
+            root.path().join("net/tcp"),
+            "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n   \
+             0: 0100007F:A1B2 5DB8D822:0D05 01 00000000:00000000 00:00000000 00000000  1000        0 1002 1 0\n",
+        )
+        .unwrap();
+        for pid in [42, 43] {
+            std::fs::create_dir_all(root.path().join(format!("{}/fd", pid))).unwrap();
+        }
+        std::os::unix::fs::symlink("socket:[1002]", root.path().join("42/fd/3")).unwrap();
+
+        let rules = rules::parse_rules(
+            "rules:\n  - {id: stratum, severity: high, remote_ports: [3333]}\n",
+            false,
+        )
+        .unwrap();
+        let engine = RuleEngine::from_rules(rules).with_proc_root(root.path());
+        let table = vec![
+            process(42, None, "kworker", &[]),
+            process(43, None, "kworker", &[]),
+        ];
+
+        let detections = engine.evaluate_all(&table, &table);
+        assert_eq!(detections.len(), 1);
+        assert_eq!(detections[0].pid, 42);
+    }
+
+    #[test]
+    fn test_hot_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("base.yaml");
+        std::fs::write(&file, "rules:\n  - {id: a, severity: low, name: a}\n").unwrap();
+
+        let engine = RuleEngine::load(dir.path()).unwrap();
+        assert_eq!(engine.rule_count(), 1);
+        assert!(!engine.reload_if_changed().unwrap());
+
+        std::fs::write(
+            dir.path().join("extra.json"),
+            r#"{"rules": [{"id": "b", "severity": "high", "name": "b"}]}"#,
+        )
+        .unwrap();
+        assert!(engine.reload_if_changed().unwrap());
+        assert_eq!(engine.rule_count(), 2);
+
+        // A broken edit keeps the last good rules
+        std::fs::write(dir.path().join("extra.json"), "{").unwrap();
+        assert!(engine.reload_if_changed().is_err());
+        assert_eq!(engine.rule_count(), 2);
+        assert!(!engine.reload_if_changed().unwrap());
+    }
+}
@@ -1,3 +1,20 @@
+pub mod embedding_scan;
+pub mod engine;
+pub mod guarded_memory;
+pub mod injection;
+pub mod netstat;
+pub mod rules;
+pub mod scanner;
+#[cfg(test)]
+mod test_support;
+
+pub use embedding_scan::EmbeddingScanner;
+pub use engine::{Detection, RuleEngine};
+pub use guarded_memory::{GuardedMemory, QUARANTINE_NAMESPACE, REVIEW_KEY_FILE};
+pub use injection::{InjectionDetector, InjectionVerdict};
+pub use rules::{Rule, Severity};
+pub use scanner::{RuleScanner, SWEEP_INTERVAL};
+
 use std::sync::Arc;
 use synapse_core::ports::{ContextPort, ImmunePort};
 use tokio::sync::Mutex;
@@ -62,20 +79,13 @@ impl ImmuneSystem {
         tokio::spawn(async move {
             loop {
                 // Example: Check active window for known threats
-                match context.get_active_window().await {
-                    Ok(window) => {
-                        // Simple heuristic: check if process is in a blacklist (to be implemented)
-                        // In real version, we would use the ImmunePort to scan the process
-                        match infra.scan_process(&window.process_name).await {
-                            Ok(level) => {
-                                if level != synapse_core::ports::ThreatLevel::Safe {
-                                    warn!("⚠️ Potential threat detected in active window: {} ({:?})", window.process_name, level);
-                                }
-                            }
-                            Err(_) => {}
+                if let Ok(window) = context.get_active_window().await {
+                    // Wrap the ImmunePort in a RuleScanner to check the process against threat rules
+                    if let Ok(level) = infra.scan_process(&window.process_name).await {
+                        if level != synapse_core::ports::ThreatLevel::Safe {
+                            warn!("⚠️ Potential threat detected in active window: {} ({:?})", window.process_name, level);
                         }
                     }
-                    Err(_) => {}
                 }
                 tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
             }
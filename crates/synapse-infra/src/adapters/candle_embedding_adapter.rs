@@ -0,0 +1,295 @@
+//! Candle adapter for embeddings (pure Rust, no ONNX Runtime).
+//!
+//! Loads BERT-family sentence-transformer checkpoints in safetensors format
+//! (all-MiniLM, BGE, E5) together with their `config.json` and
+//! `tokenizer.json`. Batching and pooling match [`OrtAdapter`], so the same
+//! checkpoint produces the same vectors on either backend.
+//!
+//! nomic-embed checkpoints (`nomic_bert`) are not supported: candle-transformers
+//! has no implementation of that architecture, so export them to ONNX and use
+//! the ORT backend instead.
+//!
+//! [`OrtAdapter`]: super::ort_adapter::OrtAdapter
+
+use async_trait::async_trait;
+use std::path::Path;
+use std::sync::Arc;
+use synapse_core::{EmbeddingPort, Error};
+
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config};
+use tokenizers::{Encoding, Tokenizer, TruncationParams};
+
+use super::embedding_batch::{plan_batches, pool, PaddedBatch, DEFAULT_MAX_BATCH_SIZE};
+use super::model_descriptor::{ModelDescriptor, Pooling};
+
+/// `[CLS]` and `[SEP]` added around every input.
+const SPECIAL_TOKENS: usize = 2;
+
+/// Candle adapter for BERT sentence embeddings.
+pub struct CandleEmbeddingAdapter {
+    model: Arc<BertModel>,
+    tokenizer: Arc<Tokenizer>,
+    /// Same tokenizer without truncation, for counting
+    counting_tokenizer: Tokenizer,
+    device: Device,
+    provider: String,
+    model_id: String,
+    dimension: usize,
+    pooling: Pooling,
+    normalize: bool,
+    max_batch_size: usize,
+    max_sequence_length: usize,
+}
+
+impl CandleEmbeddingAdapter {
+    /// Load the model described by `descriptor`.
+    ///
+    /// Expects `config.json` and `tokenizer.json` next to the safetensors file.
+    pub fn from_descriptor(descriptor: &ModelDescriptor) -> Result<Self, Error> {
+        let model_path = descriptor.resolve_model_file("safetensors")?;
+        let tokenizer_path = descriptor.resolve_tokenizer(&model_path)?;
+        let config = read_config(&model_path.with_file_name("config.json"))?;
+
+        let device = Device::Cpu;
+
+        // Load Tokenizer, truncating to the model's position embeddings.
+        let max_sequence_length = descriptor
+            .context_length
+            .unwrap_or(config.max_position_embeddings)
+            .min(config.max_position_embeddings);
+        let counting_tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| Error::System(format!("Failed to load tokenizer {:?}: {}", tokenizer_path, e)))?;
+        let mut tokenizer = counting_tokenizer.clone();
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: max_sequence_length,
+                ..TruncationParams::default()
+            }))
+            .map_err(|e| Error::System(format!("Failed to set truncation: {}", e)))?;
+
+        // Load weights
+        // SAFETY: the file is memory-mapped read-only and must not be
+        // modified while the model is loaded.
+        let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[&model_path], DType::F32, &device) }
+            .map_err(|e| Error::System(format!("Failed to read weights {:?}: {}", model_path, e)))?;
+        let model = BertModel::load(vb, &config)
+            .map_err(|e| Error::System(format!("Failed to create BERT model: {}", e)))?;
+
+        let name = model_path
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "bert".to_string());
+
+        Ok(Self {
+            model: Arc::new(model),
+            tokenizer: Arc::new(tokenizer),
+            counting_tokenizer,
+            device,
+            provider: format!("candle-{}", name),
+            model_id: ModelDescriptor::model_id(&model_path),
+            dimension: descriptor.dimension.unwrap_or(config.hidden_size),
+            pooling: descriptor.pooling,
+            normalize: descriptor.normalize,
+            max_batch_size: descriptor.max_batch_size.unwrap_or(DEFAULT_MAX_BATCH_SIZE).max(1),
+            max_sequence_length,
+        })
+    }
+
+    /// Override the maximum number of texts per forward pass.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
+}
+
+/// Read a BERT `config.json`, rejecting architectures `BertModel` cannot run.
+fn read_config(path: &Path) -> Result<Config, Error> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| Error::System(format!("Failed to read model config {:?}: {}", path, e)))?;
+    let value: serde_json::Value = serde_json::from_str(&raw)
+        .map_err(|e| Error::System(format!("Invalid model config {:?}: {}", path, e)))?;
+
+    let model_type = value.get("model_type").and_then(|v| v.as_str()).unwrap_or("bert");
+    match model_type {
+        "bert" => {}
+        "nomic_bert" => {
+            return Err(Error::System(format!(
+                "nomic_bert models ({:?}) are not supported by the Candle embedder; export the model to ONNX and use the ORT backend.",
+                path
+            )))
+        }
+        other => {
+            return Err(Error::System(format!(
+                "Unsupported embedding architecture '{}' in {:?}. The Candle embedder runs BERT models (all-MiniLM, BGE, E5); use the ORT backend for others.",
+                other, path
+            )))
+        }
+    }
+
+    serde_json::from_value(value)
+        .map_err(|e| Error::System(format!("Invalid BERT config {:?}: {}", path, e)))
+}
+
+/// Run one padded batch through the model and pool the outputs.
+fn run_batch(
+    model: &BertModel,
+    device: &Device,
+    encodings: &[&Encoding],
+    pooling: Pooling,
+    normalize: bool,
+) -> Result<Vec<Vec<f32>>, Error> {
+    let batch = PaddedBatch::new(encodings);
+    let shape = (batch.batch_size, batch.seq_len);
+    let to_tensor = |values: &[i64]| {
+        let values: Vec<u32> = values.iter().map(|&v| v as u32).collect();
+        Tensor::from_vec(values, shape, device)
+    };
+
+    let hidden = to_tensor(&batch.input_ids)
+        .and_then(|input_ids| {
+            let token_type_ids = to_tensor(&batch.token_type_ids)?;
+            let attention_mask = to_tensor(&batch.attention_mask)?;
+            model.forward(&input_ids, &token_type_ids, Some(&attention_mask))
+        })
+        .and_then(|hidden| hidden.to_dtype(DType::F32))
+        .map_err(|e| Error::System(format!("BERT forward failed: {}", e)))?;
+
+    // `hidden` is [batch, seq_len, dim]
+    let dim = hidden.dim(2).map_err(|e| Error::System(e.to_string()))?;
+    let data = hidden
+        .flatten_all()
+        .and_then(|t| t.to_vec1::<f32>())
+        .map_err(|e| Error::System(format!("Failed to extract tensor: {}", e)))?;
+
+    Ok(pool(&data, &batch.attention_mask, shape.1, dim, pooling, normalize))
+}
+
+#[async_trait]
+impl EmbeddingPort for CandleEmbeddingAdapter {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, Error> {
+        let mut embeddings = self.embed_batch(&[text.to_string()]).await?;
+        embeddings
+            .pop()
+            .ok_or_else(|| Error::System("Candle returned no embedding".into()))
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let model = self.model.clone();
+        let tokenizer = self.tokenizer.clone();
+        let device = self.device.clone();
+        let texts = texts.to_vec();
+        let (pooling, normalize, max_batch_size) = (self.pooling, self.normalize, self.max_batch_size);
+
+        // The forward passes are CPU-bound, so they run on the blocking pool.
+        tokio::task::spawn_blocking(move || {
+            let encodings = tokenizer.encode_batch(texts, true)
+                .map_err(|e| Error::System(format!("Tokenization failed: {}", e)))?;
+            let lengths: Vec<usize> = encodings.iter().map(|e| e.get_ids().len()).collect();
+
+            let mut results: Vec<Vec<f32>> = vec![Vec::new(); encodings.len()];
+            for indices in plan_batches(&lengths, max_batch_size) {
+                let batch: Vec<&Encoding> = indices.iter().map(|&i| &encodings[i]).collect();
+                let embeddings = run_batch(&model, &device, &batch, pooling, normalize)?;
+                for (i, embedding) in indices.into_iter().zip(embeddings) {
+                    results[i] = embedding;
+                }
+            }
+            Ok(results)
+        })
+        .await
+        .map_err(|e| Error::System(format!("Embedding task failed: {}", e)))?
+    }
+
+    fn count_tokens(&self, text: &str) -> usize {
+        // Counted without truncation so callers can tell when to chunk.
+        match self.counting_tokenizer.encode(text, false) {
+            Ok(encoding) => encoding.get_ids().len(),
+            Err(_) => text.chars().count().div_ceil(4),
+        }
+    }
+
+    fn max_input_tokens(&self) -> usize {
+        self.max_sequence_length.saturating_sub(SPECIAL_TOKENS)
+    }
+
+    fn is_normalized(&self) -> bool {
+        self.normalize
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn provider_name(&self) -> &str {
+        &self.provider
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::model_descriptor::EmbeddingBackend;
+    use crate::adapters::ort_adapter::OrtAdapter;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_rejects_non_bert_config() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        std::fs::write(&path, r#"{ "model_type": "nomic_bert", "n_embd": 768 }"#).unwrap();
+
+        let message = read_config(&path).unwrap_err().to_string();
+        assert!(message.contains("nomic_bert"));
+        assert!(message.contains("ORT backend"));
+
+        std::fs::write(&path, r#"{ "model_type": "roberta" }"#).unwrap();
+        let message = read_config(&path).unwrap_err().to_string();
+        assert!(message.contains("'roberta'"));
+    }
+
+    /// Needs `models/all-MiniLM-L6-v2/` with `model.onnx` and `model.safetensors`
+    /// (plus `config.json` and `tokenizer.json`).
+    #[tokio::test]
+    #[ignore = "requires downloaded models"]
+    async fn test_matches_ort_on_fixtures() {
+        let fixtures: Vec<String> = [
+            "The quick brown fox jumps over the lazy dog.",
+            "Synapse stores memories as embeddings.",
+            "¿Dónde está la biblioteca?",
+            "",
+            &"A much longer passage that needs padding next to the short ones. ".repeat(8),
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+
+        let ort = OrtAdapter::new().unwrap();
+        let candle = CandleEmbeddingAdapter::from_descriptor(&ModelDescriptor {
+            backend: EmbeddingBackend::Candle,
+            ..ModelDescriptor::new("models/all-MiniLM-L6-v2/model.safetensors")
+        })
+        .unwrap();
+
+        let expected = ort.embed_batch(&fixtures).await.unwrap();
+        let actual = candle.embed_batch(&fixtures).await.unwrap();
+
+        for (text, (a, b)) in fixtures.iter().zip(expected.iter().zip(&actual)) {
+            assert_eq!(a.len(), b.len());
+            let max_diff = a.iter().zip(b).map(|(x, y)| (x - y).abs()).fold(0.0, f32::max);
+            let cosine: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+            assert!(max_diff < 1e-3, "max diff {} for {:?}", max_diff, text);
+            assert!(cosine > 0.9999, "cosine {} for {:?}", cosine, text);
+        }
+    }
+}
@@ -61,7 +61,9 @@ impl ContextPort for WindowsContextAdapter {
         unsafe {
             let hwnd = GetForegroundWindow();
             if hwnd.0 == 0 {
-                return Err(synapse_core::error::Error::System("No active window".into()));
+                return Err(synapse_core::error::Error::NotFound {
+                    id: "active window".into(),
+                });
             }
 
             let mut process_id: u32 = 0;
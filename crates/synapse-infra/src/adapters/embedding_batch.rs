@@ -0,0 +1,156 @@
+//! Padded batching and pooling shared by the embedding adapters.
+//!
+//! Texts are embedded in padded batches: inputs are sorted by token length
+//! and grouped so each batch pads to a similar length, then pooled with the
+//! attention mask so padding never leaks into the sentence embedding.
+
+use tokenizers::Encoding;
+
+use super::model_descriptor::Pooling;
+
+/// Texts per inference call when the descriptor does not set a batch size.
+pub(crate) const DEFAULT_MAX_BATCH_SIZE: usize = 32;
+
+/// Group text indices into batches of similar token length.
+///
+/// Indices are sorted by length so each batch pads to roughly the length of
+/// its own longest member instead of the longest text overall.
+pub(crate) fn plan_batches(lengths: &[usize], max_batch_size: usize) -> Vec<Vec<usize>> {
+    let mut order: Vec<usize> = (0..lengths.len()).collect();
+    order.sort_by_key(|&i| lengths[i]);
+    order.chunks(max_batch_size.max(1)).map(<[usize]>::to_vec).collect()
+}
+
+/// Row-major, right-padded `i64` input tensors for one batch.
+pub(crate) struct PaddedBatch {
+    pub batch_size: usize,
+    pub seq_len: usize,
+    pub input_ids: Vec<i64>,
+    pub attention_mask: Vec<i64>,
+    pub token_type_ids: Vec<i64>,
+}
+
+impl PaddedBatch {
+    pub fn new(encodings: &[&Encoding]) -> Self {
+        let batch_size = encodings.len();
+        let seq_len = encodings.iter().map(|e| e.get_ids().len()).max().unwrap_or(0);
+        let mut batch = Self {
+            batch_size,
+            seq_len,
+            input_ids: vec![0; batch_size * seq_len],
+            attention_mask: vec![0; batch_size * seq_len],
+            token_type_ids: vec![0; batch_size * seq_len],
+        };
+        for (row, encoding) in encodings.iter().enumerate() {
+            let offset = row * seq_len;
+            for (i, &id) in encoding.get_ids().iter().enumerate() {
+                batch.input_ids[offset + i] = id as i64;
+            }
+            for (i, &mask) in encoding.get_attention_mask().iter().enumerate() {
+                batch.attention_mask[offset + i] = mask as i64;
+            }
+            for (i, &type_id) in encoding.get_type_ids().iter().enumerate() {
+                batch.token_type_ids[offset + i] = type_id as i64;
+            }
+        }
+        batch
+    }
+}
+
+/// Pool `[batch, seq_len, dim]` hidden states into sentence embeddings,
+/// ignoring positions where the attention mask is 0, and optionally
+/// L2-normalize them.
+pub(crate) fn pool(
+    hidden: &[f32],
+    attention_mask: &[i64],
+    seq_len: usize,
+    dim: usize,
+    pooling: Pooling,
+    normalize: bool,
+) -> Vec<Vec<f32>> {
+    let batch_size = attention_mask.len() / seq_len.max(1);
+    (0..batch_size)
+        .map(|b| {
+            let rows = &hidden[b * seq_len * dim..(b + 1) * seq_len * dim];
+            let mask = &attention_mask[b * seq_len..(b + 1) * seq_len];
+
+            let mut vec = vec![0.0; dim];
+            match pooling {
+                Pooling::Cls => vec.copy_from_slice(&rows[..dim]),
+                Pooling::Mean => {
+                    let mut count = 0.0;
+                    for (token, _) in mask.iter().enumerate().filter(|(_, &m)| m == 1) {
+                        count += 1.0;
+                        for (acc, x) in vec.iter_mut().zip(&rows[token * dim..(token + 1) * dim]) {
+                            *acc += x;
+                        }
+                    }
+                    if count > 0.0 {
+                        vec.iter_mut().for_each(|x| *x /= count);
+                    }
+                }
+            }
+
+            // L2 Normalization (for cosine similarity)
+            let norm: f32 = vec.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if normalize && norm > 0.0 {
+                vec.iter_mut().for_each(|x| *x /= norm);
+            }
+            vec
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_batches_groups_by_length() {
+        let lengths = [12, 3, 40, 5, 11, 39];
+        let batches = plan_batches(&lengths, 2);
+
+        assert_eq!(batches, vec![vec![1, 3], vec![4, 0], vec![5, 2]]);
+    }
+
+    #[test]
+    fn test_plan_batches_covers_every_index_once() {
+        let lengths: Vec<usize> = (0..37).map(|i| (i * 7) % 13).collect();
+        let mut seen: Vec<usize> = plan_batches(&lengths, 8).into_iter().flatten().collect();
+        seen.sort();
+
+        assert_eq!(seen, (0..37).collect::<Vec<_>>());
+        assert!(plan_batches(&lengths, 8).iter().all(|b| b.len() <= 8));
+    }
+
+    #[test]
+    fn test_mean_pooling_ignores_padding() {
+        // Two sequences of length 3, dim 2; the second has one padded position
+        // whose hidden state would skew the mean if it were counted.
+        let hidden = [
+            1.0, 0.0, 3.0, 0.0, 2.0, 0.0, // seq 0
+            0.0, 1.0, 0.0, 3.0, 100.0, 100.0, // seq 1 (last is padding)
+        ];
+        let mask = [1, 1, 1, 1, 1, 0];
+        let pooled = pool(&hidden, &mask, 3, 2, Pooling::Mean, true);
+
+        assert_eq!(pooled[0], vec![1.0, 0.0]);
+        assert_eq!(pooled[1], vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_cls_pooling_takes_first_token() {
+        let hidden = [3.0, 4.0, 9.0, 9.0];
+        let pooled = pool(&hidden, &[1, 1], 2, 2, Pooling::Cls, true);
+
+        assert_eq!(pooled, vec![vec![0.6, 0.8]]);
+    }
+
+    #[test]
+    fn test_pooling_without_normalization() {
+        let hidden = [3.0, 4.0, 1.0, 0.0];
+        let pooled = pool(&hidden, &[1, 1], 2, 2, Pooling::Mean, false);
+
+        assert_eq!(pooled, vec![vec![2.0, 2.0]]);
+    }
+}
@@ -0,0 +1,712 @@
+//! Token generation loop and sampling for local LLM adapters.
+//!
+//! The loop is written against the small [`LogitsModel`] trait so the same
+//! sampling, penalty and stop-sequence logic drives every Candle model family
+//! and can be tested deterministically with a toy model.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use synapse_core::logic::json_schema::JsonPrefixValidator;
+use synapse_core::{Error, FinishReason, GenerationParams, GenerationUsage};
+
+/// Number of most recent tokens the repetition penalty looks at.
+const REPEAT_LAST_N: usize = 64;
+
+/// Sampled tokens checked against a grammar before falling back to scanning
+/// the vocabulary in logit order.
+const CONSTRAINED_SAMPLE_ATTEMPTS: usize = 16;
+
+/// A causal language model that yields next-token logits.
+pub trait LogitsModel {
+    /// Feed `tokens` starting at sequence position `pos` and return the
+    /// logits for the token that follows the last one.
+    ///
+    /// Position 0 starts a new sequence (resets any KV cache).
+    fn forward(&mut self, tokens: &[u32], pos: usize) -> Result<Vec<f32>, Error>;
+}
+
+/// Picks the next token from raw logits according to [`GenerationParams`].
+pub struct Sampler {
+    temperature: f32,
+    top_k: usize,
+    top_p: f32,
+    min_p: f32,
+    repetition_penalty: f32,
+    frequency_penalty: f32,
+    rng: StdRng,
+}
+
+impl Sampler {
+    /// Create a sampler; a fixed `params.seed` makes sampling reproducible.
+    pub fn new(params: &GenerationParams) -> Self {
+        let rng = match params.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self {
+            temperature: params.temperature,
+            top_k: params.top_k,
+            top_p: params.top_p,
+            min_p: params.min_p,
+            repetition_penalty: params.repetition_penalty,
+            frequency_penalty: params.frequency_penalty,
+            rng,
+        }
+    }
+
+    /// Sample a token id.
+    ///
+    /// `context` is every token so far (prompt + completion) and is used for
+    /// the repetition penalty; `generated` is the completion only and drives
+    /// the frequency penalty.
+    pub fn sample(&mut self, logits: &mut [f32], context: &[u32], generated: &[u32]) -> u32 {
+        self.apply_penalties(logits, context, generated);
+
+        if self.temperature <= 0.0 {
+            return argmax(logits);
+        }
+
+        // Softmax with temperature, numerically stabilised by the max logit.
+        let max = logits.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+        let mut candidates: Vec<(u32, f32)> = logits
+            .iter()
+            .enumerate()
+            .map(|(i, &l)| (i as u32, ((l - max) / self.temperature).exp()))
+            .collect();
+        let total: f32 = candidates.iter().map(|(_, p)| p).sum();
+        for (_, p) in candidates.iter_mut() {
+            *p /= total;
+        }
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        if self.top_k > 0 {
+            candidates.truncate(self.top_k);
+        }
+
+        if self.top_p < 1.0 {
+            let mut cumulative = 0.0;
+            let mut keep = candidates.len();
+            for (i, (_, p)) in candidates.iter().enumerate() {
+                cumulative += p;
+                if cumulative >= self.top_p {
+                    keep = i + 1;
+                    break;
+                }
+            }
+            candidates.truncate(keep.max(1));
+        }
+
+        if self.min_p > 0.0 {
+            let threshold = candidates[0].1 * self.min_p;
+            candidates.retain(|(_, p)| *p >= threshold);
+        }
+
+        let total: f32 = candidates.iter().map(|(_, p)| p).sum();
+        let mut r = self.rng.gen::<f32>() * total;
+        for (id, p) in &candidates {
+            if r < *p {
+                return *id;
+            }
+            r -= p;
+        }
+        candidates.last().map(|(id, _)| *id).unwrap_or_else(|| argmax(logits))
+    }
+
+    fn apply_penalties(&self, logits: &mut [f32], context: &[u32], generated: &[u32]) {
+        if self.repetition_penalty != 1.0 {
+            let start = context.len().saturating_sub(REPEAT_LAST_N);
+            let mut seen: Vec<u32> = context[start..].to_vec();
+            seen.sort_unstable();
+            seen.dedup();
+            for id in seen {
+                if let Some(l) = logits.get_mut(id as usize) {
+                    *l = if *l >= 0.0 { *l / self.repetition_penalty } else { *l * self.repetition_penalty };
+                }
+            }
+        }
+
+        if self.frequency_penalty != 0.0 {
+            let mut counts: HashMap<u32, usize> = HashMap::new();
+            for id in generated {
+                *counts.entry(*id).or_default() += 1;
+            }
+            for (id, count) in counts {
+                if let Some(l) = logits.get_mut(id as usize) {
+                    *l -= self.frequency_penalty * count as f32;
+                }
+            }
+        }
+    }
+}
+
+fn argmax(logits: &[f32]) -> u32 {
+    logits
+        .iter()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(b.1))
+        .map(|(i, _)| i as u32)
+        .unwrap_or(0)
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of a stop
+/// sequence; that much text must be held back until it is disambiguated.
+fn pending_stop_len(text: &str, stop: &[String]) -> usize {
+    stop.iter()
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            (1..s.len())
+                .rev()
+                .filter(|&n| s.is_char_boundary(n) && text.ends_with(&s[..n]))
+                .max()
+                .unwrap_or(0)
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Run autoregressive decoding.
+///
+/// `decode` turns completion token ids into text; `emit` receives text deltas
+/// and returns `false` to cancel generation.
+pub fn generate_tokens<M: LogitsModel>(
+    model: &mut M,
+    prompt_ids: &[u32],
+    params: &GenerationParams,
+    stop_token_ids: &[u32],
+    decode: impl Fn(&[u32]) -> Result<String, Error>,
+    mut emit: impl FnMut(String) -> bool,
+) -> Result<GenerationUsage, Error> {
+    let mut usage = GenerationUsage {
+        prompt_tokens: prompt_ids.len(),
+        completion_tokens: 0,
+        finish_reason: FinishReason::Length,
+    };
+    if prompt_ids.is_empty() {
+        return Err(Error::Validation {
+            message: "Prompt encodes to zero tokens".to_string(),
+        });
+    }
+
+    let mut sampler = Sampler::new(params);
+    let mut context: Vec<u32> = prompt_ids.to_vec();
+    let mut generated: Vec<u32> = Vec::with_capacity(params.max_tokens);
+    let mut emitted_len = 0;
+    let mut text = String::new();
+
+    // Pre-fill with the whole prompt, then feed one token at a time.
+    let mut logits = model.forward(prompt_ids, 0)?;
+
+    for step in 0..params.max_tokens {
+        if step > 0 {
+            let last = context[context.len() - 1];
+            logits = model.forward(&[last], context.len() - 1)?;
+        }
+
+        let next_token = sampler.sample(&mut logits, &context, &generated);
+
+        if stop_token_ids.contains(&next_token) {
+            usage.finish_reason = FinishReason::Stop;
+            break;
+        }
+
+        generated.push(next_token);
+        context.push(next_token);
+        usage.completion_tokens += 1;
+
+        // Decode the whole completion so multi-token characters and leading
+        // spaces come out right, then emit only the new, settled suffix.
+        text = decode(&generated)?;
+        if text.ends_with('\u{FFFD}') {
+            continue;
+        }
+
+        let stop_at = params.truncate_at_stop(&text).len();
+        let hit_stop = stop_at < text.len();
+        let settled = if hit_stop { stop_at } else { text.len() - pending_stop_len(&text, &params.stop) };
+
+        if settled > emitted_len && text.is_char_boundary(settled) && text.is_char_boundary(emitted_len) {
+            if !emit(text[emitted_len..settled].to_string()) {
+                usage.finish_reason = FinishReason::Cancelled;
+                return Ok(usage);
+            }
+            emitted_len = settled;
+        }
+
+        if hit_stop {
+            usage.finish_reason = FinishReason::Stop;
+            return Ok(usage);
+        }
+    }
+
+    // Flush text held back as a possible stop-sequence prefix.
+    if text.len() > emitted_len && text.is_char_boundary(emitted_len) && !emit(text[emitted_len..].to_string()) {
+        usage.finish_reason = FinishReason::Cancelled;
+    }
+
+    Ok(usage)
+}
+
+/// Decode every token id below `vocab_size` on its own, for [`generate_constrained`].
+///
+/// Ids that fail to decode map to an empty string and are always checked
+/// against the full completion instead.
+pub fn token_texts(vocab_size: usize, decode: impl Fn(&[u32]) -> Result<String, Error>) -> Vec<String> {
+    (0..vocab_size as u32).map(|id| decode(&[id]).unwrap_or_default()).collect()
+}
+
+/// A token accepted by [`generate_constrained`].
+enum Accepted {
+    Stop,
+    Token {
+        id: u32,
+        text: String,
+        validator: JsonPrefixValidator,
+    },
+}
+
+/// Check whether `id` may follow `generated` without leaving the grammar.
+///
+/// The token's own text from `token_texts` is fed to a copy of the validator
+/// first, so most candidates are rejected without decoding the completion.
+/// Tokens with no standalone text (byte pieces, specials) and tokens that
+/// pass are confirmed against the decoded completion, which is exact.
+#[allow(clippy::too_many_arguments)]
+fn accept_token(
+    id: u32,
+    generated: &[u32],
+    text: &str,
+    validator: &JsonPrefixValidator,
+    stop_token_ids: &[u32],
+    token_texts: &[String],
+    decode: &impl Fn(&[u32]) -> Result<String, Error>,
+) -> Result<Option<Accepted>, Error> {
+    if stop_token_ids.contains(&id) {
+        return Ok(validator.is_complete().then_some(Accepted::Stop));
+    }
+
+    let piece = token_texts
+        .get(id as usize)
+        .filter(|piece| !piece.is_empty() && !piece.contains('\u{FFFD}'));
+    if let Some(piece) = piece {
+        if !validator.clone().feed(piece) {
+            return Ok(None);
+        }
+    }
+
+    let mut ids = generated.to_vec();
+    ids.push(id);
+    let decoded = decode(&ids)?;
+    // Bytes of an unfinished character are checked once the character is.
+    let settled = decoded.trim_end_matches('\u{FFFD}');
+    let Some(delta) = settled.strip_prefix(text) else {
+        return Ok(None);
+    };
+
+    let mut next = validator.clone();
+    Ok(next.feed(delta).then(|| Accepted::Token {
+        id,
+        text: settled.to_string(),
+        validator: next,
+    }))
+}
+
+/// Run decoding constrained to a JSON document accepted by `validator`.
+///
+/// Each sampled token is checked against the validator; rejected tokens are
+/// masked out and sampling repeats, so the completion is always a valid
+/// prefix. End-of-sequence is only allowed once the document is complete and
+/// generation stops as soon as nothing but whitespace could follow. Stop
+/// sequences are ignored since they may legitimately occur inside strings.
+///
+/// `token_texts` holds each vocabulary entry decoded on its own (see
+/// [`token_texts`]); it lets candidates be screened without re-decoding the
+/// completion for every one of them.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_constrained<M: LogitsModel>(
+    model: &mut M,
+    prompt_ids: &[u32],
+    params: &GenerationParams,
+    stop_token_ids: &[u32],
+    token_texts: &[String],
+    decode: impl Fn(&[u32]) -> Result<String, Error>,
+    mut validator: JsonPrefixValidator,
+) -> Result<(String, GenerationUsage), Error> {
+    let mut usage = GenerationUsage {
+        prompt_tokens: prompt_ids.len(),
+        completion_tokens: 0,
+        finish_reason: FinishReason::Length,
+    };
+    if prompt_ids.is_empty() {
+        return Err(Error::Validation {
+            message: "Prompt encodes to zero tokens".to_string(),
+        });
+    }
+
+    let mut sampler = Sampler::new(params);
+    let mut context: Vec<u32> = prompt_ids.to_vec();
+    let mut generated: Vec<u32> = Vec::with_capacity(params.max_tokens);
+    let mut text = String::new();
+
+    let mut logits = model.forward(prompt_ids, 0)?;
+
+    for step in 0..params.max_tokens {
+        if step > 0 {
+            let last = context[context.len() - 1];
+            logits = model.forward(&[last], context.len() - 1)?;
+        }
+
+        let mut accepted = None;
+        for _ in 0..CONSTRAINED_SAMPLE_ATTEMPTS {
+            let id = sampler.sample(&mut logits.clone(), &context, &generated);
+            if logits[id as usize] == f32::NEG_INFINITY {
+                break;
+            }
+            accepted = accept_token(id, &generated, &text, &validator, stop_token_ids, token_texts, &decode)?;
+            if accepted.is_some() {
+                break;
+            }
+            logits[id as usize] = f32::NEG_INFINITY;
+        }
+
+        if accepted.is_none() {
+            let mut order: Vec<u32> = (0..logits.len() as u32)
+                .filter(|&id| logits[id as usize] > f32::NEG_INFINITY)
+                .collect();
+            order.sort_by(|&a, &b| logits[b as usize].total_cmp(&logits[a as usize]));
+            for id in order {
+                accepted = accept_token(id, &generated, &text, &validator, stop_token_ids, token_texts, &decode)?;
+                if accepted.is_some() {
+                    break;
+                }
+            }
+        }
+
+        match accepted {
+            None => {
+                return Err(Error::System(format!(
+                    "No token can continue the JSON document after {:?}",
+                    text
+                )))
+            }
+            Some(Accepted::Stop) => {
+                usage.finish_reason = FinishReason::Stop;
+                break;
+            }
+            Some(Accepted::Token { id, text: next_text, validator: next }) => {
+                generated.push(id);
+                context.push(id);
+                usage.completion_tokens += 1;
+                text = next_text;
+                validator = next;
+            }
+        }
+
+        if validator.is_finished() {
+            usage.finish_reason = FinishReason::Stop;
+            break;
+        }
+    }
+
+    Ok((text, usage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tiny bigram "model": logits for the next token depend only on the last one.
+    ///
+    /// Vocabulary: 0 = "</s>", 1 = "a", 2 = "b", 3 = "c", 4 = " ".
+    struct TinyModel {
+        table: Vec<Vec<f32>>,
+        calls: Vec<(Vec<u32>, usize)>,
+    }
+
+    impl TinyModel {
+        fn new() -> Self {
+            Self {
+                table: vec![
+                    vec![0.0, 3.0, 1.0, 1.0, 0.0], // after </s>
+                    vec![0.0, 0.5, 3.0, 1.0, 0.0], // after a -> b
+                    vec![0.0, 3.0, 0.5, 2.0, 0.0], // after b -> a (loops a-b-a-b)
+                    vec![5.0, 0.0, 0.0, 0.0, 1.0], // after c -> </s>
+                    vec![0.0, 2.0, 1.0, 1.0, 0.0], // after space -> a
+                ],
+                calls: Vec::new(),
+            }
+        }
+    }
+
+    impl LogitsModel for TinyModel {
+        fn forward(&mut self, tokens: &[u32], pos: usize) -> Result<Vec<f32>, Error> {
+            self.calls.push((tokens.to_vec(), pos));
+            Ok(self.table[*tokens.last().unwrap() as usize].clone())
+        }
+    }
+
+    fn decode(ids: &[u32]) -> Result<String, Error> {
+        Ok(ids.iter().map(|&id| ["", "a", "b", "c", " "][id as usize]).collect())
+    }
+
+    fn run(params: &GenerationParams) -> (String, GenerationUsage) {
+        run_with_stop_ids(params, &[0])
+    }
+
+    fn run_with_stop_ids(params: &GenerationParams, stop_token_ids: &[u32]) -> (String, GenerationUsage) {
+        let mut model = TinyModel::new();
+        let mut out = String::new();
+        let usage = generate_tokens(&mut model, &[0], params, stop_token_ids, decode, |d| {
+            out.push_str(&d);
+            true
+        })
+        .unwrap();
+        (out, usage)
+    }
+
+    #[test]
+    fn test_greedy_is_argmax_and_hits_length_limit() {
+        let (text, usage) = run(&GenerationParams::greedy().with_max_tokens(6));
+        assert_eq!(text, "ababab");
+        assert_eq!(usage.completion_tokens, 6);
+        assert_eq!(usage.finish_reason, FinishReason::Length);
+    }
+
+    #[test]
+    fn test_model_is_fed_incrementally() {
+        let mut model = TinyModel::new();
+        generate_tokens(&mut model, &[0, 4], &GenerationParams::greedy().with_max_tokens(3), &[0], decode, |_| true)
+            .unwrap();
+        assert_eq!(model.calls[0], (vec![0, 4], 0));
+        assert_eq!(model.calls[1], (vec![1], 2));
+        assert_eq!(model.calls[2], (vec![2], 3));
+    }
+
+    #[test]
+    fn test_repetition_penalty_breaks_loop() {
+        let mut params = GenerationParams::greedy().with_max_tokens(8);
+        params.repetition_penalty = 4.0;
+        let (text, usage) = run(&params);
+
+        // "a" then "b"; both are now penalised so "c" wins, and "c" leads to EOS.
+        assert_eq!(text, "abc");
+        assert_eq!(usage.finish_reason, FinishReason::Stop);
+    }
+
+    #[test]
+    fn test_frequency_penalty_accumulates() {
+        let mut params = GenerationParams::greedy().with_max_tokens(8);
+        params.frequency_penalty = 1.5;
+        let (text, _) = run(&params);
+
+        // After "ab" the second "a" costs 1.5 (3.0 - 1.5 < 2.0), so "c" wins.
+        assert_eq!(text, "abc");
+    }
+
+    #[test]
+    fn test_seeded_sampling_is_reproducible() {
+        let params = GenerationParams {
+            temperature: 1.5,
+            top_k: 0,
+            top_p: 1.0,
+            min_p: 0.0,
+            repetition_penalty: 1.0,
+            ..GenerationParams::default()
+        }
+        .with_max_tokens(32)
+        .with_seed(42);
+
+        let (first, _) = run_with_stop_ids(&params, &[]);
+        let (second, _) = run_with_stop_ids(&params, &[]);
+        let (other, _) = run_with_stop_ids(&params.clone().with_seed(7), &[]);
+
+        assert_eq!(first, second);
+        assert_ne!(first, other, "different seeds should diverge on 32 hot samples");
+    }
+
+    #[test]
+    fn test_top_k_one_equals_greedy() {
+        let params = GenerationParams {
+            temperature: 2.0,
+            top_k: 1,
+            repetition_penalty: 1.0,
+            ..GenerationParams::default()
+        }
+        .with_max_tokens(6);
+        let (text, _) = run(&params);
+        assert_eq!(text, "ababab");
+    }
+
+    #[test]
+    fn test_min_p_filters_unlikely_tokens() {
+        let params = GenerationParams {
+            temperature: 1.0,
+            top_k: 0,
+            top_p: 1.0,
+            min_p: 0.5,
+            repetition_penalty: 1.0,
+            ..GenerationParams::default()
+        };
+        let mut sampler = Sampler::new(&params.with_seed(1));
+        for _ in 0..50 {
+            let mut logits = vec![0.0, 3.0, 1.0, 2.9, 0.0];
+            let id = sampler.sample(&mut logits, &[], &[]);
+            assert!(id == 1 || id == 3, "sampled {}", id);
+        }
+    }
+
+    #[test]
+    fn test_top_p_keeps_nucleus() {
+        let params = GenerationParams {
+            temperature: 1.0,
+            top_k: 0,
+            top_p: 0.5,
+            min_p: 0.0,
+            repetition_penalty: 1.0,
+            ..GenerationParams::default()
+        };
+        let mut sampler = Sampler::new(&params.with_seed(3));
+        for _ in 0..50 {
+            let mut logits = vec![5.0, 0.0, 0.0, 0.0, 0.0];
+            assert_eq!(sampler.sample(&mut logits, &[], &[]), 0);
+        }
+    }
+
+    #[test]
+    fn test_stop_sequence_is_excluded_and_ends_generation() {
+        let params = GenerationParams::greedy().with_max_tokens(20).with_stop("bab");
+        let (text, usage) = run(&params);
+
+        assert_eq!(text, "a");
+        assert_eq!(usage.finish_reason, FinishReason::Stop);
+    }
+
+    #[test]
+    fn test_partial_stop_prefix_is_flushed_at_length_limit() {
+        let params = GenerationParams::greedy().with_max_tokens(3).with_stop("abc");
+        let (text, usage) = run(&params);
+
+        assert_eq!(text, "aba");
+        assert_eq!(usage.finish_reason, FinishReason::Length);
+    }
+
+    /// Model with fixed logits over a vocabulary of JSON fragments.
+    struct FragmentModel(Vec<f32>);
+
+    impl LogitsModel for FragmentModel {
+        fn forward(&mut self, _tokens: &[u32], _pos: usize) -> Result<Vec<f32>, Error> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn run_constrained(vocab: &'static [&'static str], logits: Vec<f32>, schema: serde_json::Value) -> (String, GenerationUsage) {
+        let schema = synapse_core::logic::json_schema::JsonSchema::new(&schema).unwrap();
+        let decode = |ids: &[u32]| Ok(ids.iter().map(|&id| vocab[id as usize]).collect());
+        let texts = token_texts(vocab.len(), decode);
+        let params = GenerationParams::greedy().with_max_tokens(32);
+        generate_constrained(&mut FragmentModel(logits), &[0], &params, &[0], &texts, decode, schema.prefix_validator())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_constrained_steers_away_from_invalid_tokens() {
+        const VOCAB: &[&str] = &["</s>", "{", "}", "\"", "name", ":", "\"bob\"", "x", " "];
+        // "x" is always the model's favourite and is never valid.
+        let logits = vec![1.0, 0.9, 0.8, 0.7, 0.6, 0.5, 2.0, 10.0, 0.1];
+        let schema = serde_json::json!({
+            "type": "object",
+            "properties": { "name": { "enum": ["bob"] } },
+            "required": ["name"],
+            "additionalProperties": false
+        });
+        let (text, usage) = run_constrained(VOCAB, logits, schema);
+
+        assert_eq!(text, r#"{"name":"bob"}"#);
+        assert_eq!(usage.finish_reason, FinishReason::Stop);
+    }
+
+    #[test]
+    fn test_constrained_respects_item_limits() {
+        const VOCAB: &[&str] = &["</s>", "[", "]", "1", ",", "."];
+        // Greedy order: ".", ",", "]", "1", "[".
+        let logits = vec![0.0, 0.1, 0.3, 0.2, 0.4, 0.5];
+        let schema = serde_json::json!({
+            "type": "array",
+            "items": { "type": "integer" },
+            "minItems": 2,
+            "maxItems": 2
+        });
+        let (text, usage) = run_constrained(VOCAB, logits, schema);
+
+        assert_eq!(text, "[1,1]");
+        assert_eq!(usage.completion_tokens, 5);
+    }
+
+    #[test]
+    fn test_constrained_sampling_stays_valid() {
+        const VOCAB: &[&str] = &["</s>", "[", "]", "1", "2", ",", " ", "x"];
+        let schema = synapse_core::logic::json_schema::JsonSchema::new(&serde_json::json!({
+            "type": "array",
+            "items": { "type": "integer" }
+        }))
+        .unwrap();
+        let decode = |ids: &[u32]| Ok(ids.iter().map(|&id| VOCAB[id as usize]).collect());
+        let texts = token_texts(VOCAB.len(), decode);
+        let params = GenerationParams { temperature: 1.5, ..GenerationParams::default() }.with_max_tokens(200);
+
+        for seed in 0..10 {
+            let mut model = FragmentModel(vec![0.0; VOCAB.len()]);
+            let params = params.clone().with_seed(seed);
+            let (text, usage) =
+                generate_constrained(&mut model, &[0], &params, &[0], &texts, decode, schema.prefix_validator()).unwrap();
+            let mut validator = schema.prefix_validator();
+            assert!(validator.feed(&text), "invalid prefix {:?}", text);
+            if usage.finish_reason == FinishReason::Stop {
+                let value: serde_json::Value = serde_json::from_str(&text).unwrap();
+                assert!(schema.validate(&value).is_ok());
+            }
+        }
+    }
+
+    #[test]
+    fn test_constrained_screens_candidates_without_decoding() {
+        // Only "{", "}" and "</s>" are ever valid; every other token is junk
+        // the model prefers, so each step has to scan past all of it.
+        let mut vocab: Vec<String> = vec!["</s>".into(), "{".into(), "}".into()];
+        vocab.extend((0..200).map(|i| format!("x{}", i)));
+        let mut logits = vec![0.0, 0.1, 0.2];
+        logits.extend((0..200).map(|i| 10.0 + i as f32));
+
+        let decodes = std::cell::Cell::new(0);
+        let decode = |ids: &[u32]| {
+            decodes.set(decodes.get() + 1);
+            Ok(ids.iter().map(|&id| vocab[id as usize].as_str()).collect())
+        };
+        let texts = token_texts(vocab.len(), decode);
+        decodes.set(0);
+
+        let schema = synapse_core::logic::json_schema::JsonSchema::new(&serde_json::json!({ "type": "object" })).unwrap();
+        let params = GenerationParams::greedy().with_max_tokens(8);
+        let (text, usage) =
+            generate_constrained(&mut FragmentModel(logits), &[0], &params, &[0], &texts, decode, schema.prefix_validator())
+                .unwrap();
+
+        assert_eq!(text, "{}");
+        assert_eq!(usage.finish_reason, FinishReason::Stop);
+        assert_eq!(decodes.get(), usage.completion_tokens, "only accepted tokens are decoded");
+    }
+
+    #[test]
+    fn test_cancel_stops_generation() {
+        let mut model = TinyModel::new();
+        let mut deltas = 0;
+        let usage = generate_tokens(&mut model, &[0], &GenerationParams::greedy(), &[0], decode, |_| {
+            deltas += 1;
+            deltas < 2
+        })
+        .unwrap();
+
+        assert_eq!(usage.finish_reason, FinishReason::Cancelled);
+        assert_eq!(usage.completion_tokens, 2);
+    }
+}
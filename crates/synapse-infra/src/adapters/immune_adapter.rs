@@ -1,32 +1,158 @@
+use std::sync::Arc;
+
 use async_trait::async_trait;
+use synapse_core::logic::threat_db::ThreatDatabase;
 use synapse_core::ports::{ImmunePort, ThreatLevel, ThreatReport};
-use synapse_core::error::Result;
+use synapse_core::error::{Error, Result};
+use tracing::{error, warn};
+
+use super::integrity::{IntegrityChecker, IntegrityIssue};
 
-pub struct BasicImmuneAdapter;
+pub struct BasicImmuneAdapter {
+    threats: Arc<ThreatDatabase>,
+    integrity: Option<Arc<IntegrityChecker>>,
+}
 
 impl BasicImmuneAdapter {
     pub fn new() -> Self {
-        Self
+        Self::with_threat_db(Arc::new(ThreatDatabase::new()))
+    }
+
+    /// Use a threat database shared with other components (e.g. the p2p
+    /// node that merges reports from peers).
+    pub fn with_threat_db(threats: Arc<ThreatDatabase>) -> Self {
+        Self {
+            threats,
+            integrity: None,
+        }
+    }
+
+    /// Verify the executable and model files against a signed manifest.
+    pub fn with_integrity(mut self, checker: IntegrityChecker) -> Self {
+        self.integrity = Some(Arc::new(checker));
+        self
+    }
+
+    pub fn threat_db(&self) -> Arc<ThreatDatabase> {
+        self.threats.clone()
+    }
+}
+
+impl Default for BasicImmuneAdapter {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 #[async_trait]
 impl ImmunePort for BasicImmuneAdapter {
+    /// Each changed file is logged and recorded as a critical threat.
+    /// Without a checker, or when the manifest cannot be read, the
+    /// installation cannot be verified and this fails.
     async fn check_integrity(&self) -> Result<bool> {
-        // TODO: Implement hash verification of own binary
-        Ok(true)
+        let checker = self.integrity.clone().ok_or_else(|| {
+            Error::System("No integrity manifest or trusted key configured".to_string())
+        })?;
+        let issues = tokio::task::spawn_blocking(move || checker.verify())
+            .await
+            .map_err(|e| Error::System(format!("Integrity check panicked: {}", e)))??;
+
+        for issue in &issues {
+            error!("Integrity violation: {}", issue);
+            self.threats.record_local(&integrity_report(issue));
+        }
+        Ok(issues.is_empty())
     }
 
     async fn scan_process(&self, process_name: &str) -> Result<ThreatLevel> {
-        if process_name.contains("malware") {
-            Ok(ThreatLevel::Critical)
+        let heuristic = if process_name.contains("malware") {
+            ThreatLevel::Critical
         } else {
-            Ok(ThreatLevel::Safe)
-        }
+            ThreatLevel::Safe
+        };
+        Ok(heuristic.max(self.threats.level(process_name)))
     }
 
+    /// Record the threat locally. Sharing it with peers is done by wrapping
+    /// this adapter (see `synapse-p2p`'s `CollectiveImmune`).
     async fn report_threat(&self, report: ThreatReport) -> Result<()> {
-        println!("📡 Reporting threat to network: {:?}", report);
+        warn!(
+            "Threat reported: {} ({:?}, {}): {}",
+            report.source_id, report.level, report.threat_type, report.description
+        );
+        self.threats.record_local(&report);
         Ok(())
     }
 }
+
+fn integrity_report(issue: &IntegrityIssue) -> ThreatReport {
+    ThreatReport {
+        source_id: issue.path().unwrap_or("integrity manifest").to_string(),
+        threat_type: "integrity".to_string(),
+        level: ThreatLevel::Critical,
+        description: issue.to_string(),
+        timestamp: unix_timestamp(),
+        rule: None,
+    }
+}
+
+fn unix_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reported_threats_are_detected() {
+        let immune = BasicImmuneAdapter::new();
+        assert_eq!(immune.scan_process("dropper.exe").await.unwrap(), ThreatLevel::Safe);
+
+        immune
+            .report_threat(ThreatReport {
+                source_id: "dropper.exe".to_string(),
+                threat_type: "process".to_string(),
+                level: ThreatLevel::Malicious,
+                description: "Spawned from a macro".to_string(),
+                timestamp: 0,
+                rule: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(immune.scan_process("dropper.exe").await.unwrap(), ThreatLevel::Malicious);
+    }
+
+    #[tokio::test]
+    async fn test_check_integrity_reports_changed_file() {
+        use crate::adapters::integrity::{generate_signing_key, installed_executables, IntegrityManifest};
+
+        let dir = tempfile::tempdir().unwrap();
+        let models = dir.path().join("models");
+        std::fs::create_dir_all(&models).unwrap();
+        std::fs::write(models.join("model.gguf"), b"weights").unwrap();
+        let exe = dir.path().join("synapse");
+        std::fs::write(&exe, b"binary").unwrap();
+
+        let key = generate_signing_key();
+        let manifest = dir.path().join("integrity.json");
+        IntegrityManifest::generate(&installed_executables(&exe), &models, &key).unwrap().save(&manifest).unwrap();
+        let checker = IntegrityChecker::new(&manifest, &models, key.verifying_key())
+            .unwrap()
+            .with_executable(&exe);
+        let immune = BasicImmuneAdapter::new().with_integrity(checker);
+        assert!(immune.check_integrity().await.unwrap());
+
+        std::fs::write(models.join("model.gguf"), b"tampered!").unwrap();
+        assert!(!immune.check_integrity().await.unwrap());
+        assert_eq!(immune.threat_db().level("model.gguf"), ThreatLevel::Critical);
+
+        // Deleting the manifest does not turn verification off
+        std::fs::remove_file(&manifest).unwrap();
+        assert!(immune.check_integrity().await.is_err());
+        assert!(BasicImmuneAdapter::new().check_integrity().await.is_err());
+    }
+}
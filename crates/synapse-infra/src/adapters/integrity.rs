@@ -0,0 +1,437 @@
+//! Self-integrity verification.
+//!
+//! At install time a manifest of SHA-256 hashes is generated for the
+//! installed executables and every file under `models/`, and signed with an
+//! Ed25519 key. At runtime the same files are re-hashed and compared against
+//! the manifest, whose signature is checked with a public key that does not
+//! come from the manifest itself. Each binary checks its own entry and the
+//! models; the other binaries' entries are left to them.
+//!
+//! Model files are large, so a checker caches hashes and only recomputes
+//! them when a file's size or modification time changes. An attacker who
+//! can restore timestamps evades the periodic check; a fresh checker (as
+//! used by `synapse verify`) always re-hashes everything.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use synapse_core::error::{Error, Result};
+
+/// Prefix of executable entries, followed by the binary's name.
+pub const EXECUTABLE_PREFIX: &str = "@executable:";
+
+/// Binaries of an installation, signed together.
+pub const INSTALLED_BINARIES: &[&str] = &["synapse-cli", "synapse-server"];
+
+/// Manifest entry for `executable`, e.g. `@executable:synapse-server`.
+pub fn executable_entry(executable: &Path) -> String {
+    let name = executable
+        .file_stem()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    format!("{}{}", EXECUTABLE_PREFIX, name)
+}
+
+/// `executable` and the [`INSTALLED_BINARIES`] found next to it.
+pub fn installed_executables(executable: &Path) -> Vec<PathBuf> {
+    let mut executables = vec![executable.to_path_buf()];
+    for name in INSTALLED_BINARIES {
+        let path = executable.with_file_name(format!("{}{}", name, std::env::consts::EXE_SUFFIX));
+        if path.is_file() && !executables.contains(&path) {
+            executables.push(path);
+        }
+    }
+    executables
+}
+
+/// Where the signed integrity manifest lives and who may sign it (the
+/// `integrity` section of `synapse.json`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IntegrityConfig {
+    /// Signed manifest written by `synapse verify --sign`; `integrity.json`
+    /// in the data directory if unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<PathBuf>,
+
+    /// Directory of model files covered by the manifest
+    pub models_dir: PathBuf,
+
+    /// Hex Ed25519 key trusted to sign the manifest. Ignored when one was
+    /// compiled in through `SYNAPSE_INTEGRITY_PUBLIC_KEY`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+}
+
+impl Default for IntegrityConfig {
+    fn default() -> Self {
+        Self {
+            manifest: None,
+            models_dir: PathBuf::from("./models"),
+            public_key: None,
+        }
+    }
+}
+
+impl IntegrityConfig {
+    /// The manifest for an installation keeping its data in `data_dir`.
+    pub fn manifest_path(&self, data_dir: &Path) -> PathBuf {
+        self.manifest
+            .clone()
+            .unwrap_or_else(|| data_dir.join("integrity.json"))
+    }
+
+    /// The trusted signing key, preferring one baked into the binary.
+    pub fn trusted_key(&self) -> Option<&str> {
+        option_env!("SYNAPSE_INTEGRITY_PUBLIC_KEY").or(self.public_key.as_deref())
+    }
+
+    /// Checker for the manifest, or `None` (with a warning) if no key is
+    /// trusted yet. A missing manifest is not skipped: every check then
+    /// fails.
+    pub fn load_checker(&self, data_dir: &Path) -> Result<Option<IntegrityChecker>> {
+        let Some(key) = self.trusted_key() else {
+            tracing::warn!("No trusted integrity key; set integrity.public_key in synapse.json. The installation is not verified");
+            return Ok(None);
+        };
+        let checker = IntegrityChecker::new(self.manifest_path(data_dir), &self.models_dir, parse_public_key(key)?)?;
+        Ok(Some(checker))
+    }
+}
+
+/// Signed list of expected file hashes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IntegrityManifest {
+    /// Path (relative to the models directory, or an [`executable_entry`]) to hex SHA-256
+    pub files: BTreeMap<String, String>,
+    /// Hex Ed25519 signature over the JSON encoding of `files`
+    pub signature: String,
+}
+
+impl IntegrityManifest {
+    /// Hash `executables` and everything under `models_dir`, and sign the result.
+    pub fn generate(executables: &[PathBuf], models_dir: &Path, key: &SigningKey) -> Result<Self> {
+        let mut files = BTreeMap::new();
+        for executable in executables {
+            files.insert(executable_entry(executable), hash_file(executable)?);
+        }
+        for (name, path) in list_files(models_dir)? {
+            files.insert(name, hash_file(&path)?);
+        }
+        let signature = hex::encode(key.sign(&signed_bytes(&files)?).to_bytes());
+        Ok(Self { files, signature })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            Error::System(format!("Failed to read integrity manifest {}: {}", path.display(), e))
+        })?;
+        serde_json::from_str(&json)
+            .map_err(|e| Error::System(format!("Invalid integrity manifest: {}", e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).map_err(|e| {
+            Error::System(format!("Failed to write integrity manifest {}: {}", path.display(), e))
+        })
+    }
+
+    /// Whether the manifest was signed by the holder of `key`.
+    pub fn verify_signature(&self, key: &VerifyingKey) -> bool {
+        let Ok(bytes) = hex::decode(&self.signature) else {
+            return false;
+        };
+        let Ok(signature) = Signature::from_slice(&bytes) else {
+            return false;
+        };
+        signed_bytes(&self.files)
+            .map(|message| key.verify(&message, &signature).is_ok())
+            .unwrap_or(false)
+    }
+}
+
+/// A difference between the installation and its manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityIssue {
+    /// The manifest itself was altered or signed with another key
+    InvalidSignature,
+    /// File contents differ from the manifest
+    Modified(String),
+    /// File listed in the manifest no longer exists
+    Missing(String),
+    /// File present but not listed in the manifest
+    Unexpected(String),
+}
+
+impl IntegrityIssue {
+    /// The file concerned, if any.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Self::InvalidSignature => None,
+            Self::Modified(p) | Self::Missing(p) | Self::Unexpected(p) => Some(p),
+        }
+    }
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSignature => write!(f, "manifest signature is invalid"),
+            Self::Modified(path) => write!(f, "{} was modified", path),
+            Self::Missing(path) => write!(f, "{} is missing", path),
+            Self::Unexpected(path) => write!(f, "{} is not in the manifest", path),
+        }
+    }
+}
+
+/// Compares the installation against a signed manifest.
+pub struct IntegrityChecker {
+    manifest_path: PathBuf,
+    models_dir: PathBuf,
+    executable: PathBuf,
+    public_key: VerifyingKey,
+    cache: Mutex<HashMap<PathBuf, (u64, SystemTime, String)>>,
+}
+
+impl IntegrityChecker {
+    /// Check the running executable and `models_dir` against the manifest at
+    /// `manifest_path`, trusting signatures from `public_key`.
+    pub fn new(
+        manifest_path: impl Into<PathBuf>,
+        models_dir: impl Into<PathBuf>,
+        public_key: VerifyingKey,
+    ) -> Result<Self> {
+        let executable = std::env::current_exe()
+            .map_err(|e| Error::System(format!("Cannot locate the running executable: {}", e)))?;
+        Ok(Self {
+            manifest_path: manifest_path.into(),
+            models_dir: models_dir.into(),
+            executable,
+            public_key,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Check a different executable than the running one.
+    pub fn with_executable(mut self, executable: impl Into<PathBuf>) -> Self {
+        self.executable = executable.into();
+        self
+    }
+
+    /// Re-hash the installation and list every difference from the manifest.
+    ///
+    /// Blocking: hashes files on the calling thread. Fails if the manifest
+    /// cannot be read at all.
+    pub fn verify(&self) -> Result<Vec<IntegrityIssue>> {
+        let manifest = IntegrityManifest::load(&self.manifest_path)?;
+        if !manifest.verify_signature(&self.public_key) {
+            return Ok(vec![IntegrityIssue::InvalidSignature]);
+        }
+
+        let mut actual = list_files(&self.models_dir)?;
+        let own_entry = executable_entry(&self.executable);
+        actual.insert(own_entry.clone(), self.executable.clone());
+
+        let mut issues = Vec::new();
+        for (name, expected) in &manifest.files {
+            if name.starts_with(EXECUTABLE_PREFIX) && name != &own_entry {
+                continue;
+            }
+            match actual.remove(name) {
+                None => issues.push(IntegrityIssue::Missing(name.clone())),
+                Some(path) => {
+                    if &self.cached_hash(&path)? != expected {
+                        issues.push(IntegrityIssue::Modified(name.clone()));
+                    }
+                }
+            }
+        }
+        issues.extend(actual.into_keys().map(IntegrityIssue::Unexpected));
+        Ok(issues)
+    }
+
+    fn cached_hash(&self, path: &Path) -> Result<String> {
+        let metadata = std::fs::metadata(path)
+            .map_err(|e| Error::System(format!("Failed to stat {}: {}", path.display(), e)))?;
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((len, mtime, hash)) = cache.get(path) {
+            if *len == metadata.len() && *mtime == modified {
+                return Ok(hash.clone());
+            }
+        }
+        let hash = hash_file(path)?;
+        cache.insert(path.to_path_buf(), (metadata.len(), modified, hash.clone()));
+        Ok(hash)
+    }
+}
+
+/// Parse a hex Ed25519 public key.
+pub fn parse_public_key(hex_key: &str) -> Result<VerifyingKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key.trim())
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| Error::Validation {
+            message: "public key must be 64 hex characters".to_string(),
+        })?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| Error::Validation {
+        message: format!("invalid public key: {}", e),
+    })
+}
+
+/// Parse a hex Ed25519 secret key.
+pub fn parse_signing_key(hex_key: &str) -> Result<SigningKey> {
+    let bytes: [u8; 32] = hex::decode(hex_key.trim())
+        .ok()
+        .and_then(|b| b.try_into().ok())
+        .ok_or_else(|| Error::Validation {
+            message: "signing key must be 64 hex characters".to_string(),
+        })?;
+    Ok(SigningKey::from_bytes(&bytes))
+}
+
+/// Generate a new signing key.
+pub fn generate_signing_key() -> SigningKey {
+    SigningKey::from_bytes(&rand::random::<[u8; 32]>())
+}
+
+fn signed_bytes(files: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+    Ok(serde_json::to_vec(files)?)
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path)
+        .map_err(|e| Error::System(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1 << 20];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .map_err(|e| Error::System(format!("Failed to read {}: {}", path.display(), e)))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Every file under `root`, keyed by its `/`-separated relative path.
+fn list_files(root: &Path) -> Result<BTreeMap<String, PathBuf>> {
+    let mut files = BTreeMap::new();
+    if !root.exists() {
+        return Ok(files);
+    }
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .map_err(|e| Error::System(format!("Failed to list {}: {}", dir.display(), e)))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| Error::System(format!("Failed to list {}: {}", dir.display(), e)))?
+                .path();
+            if path.is_dir() {
+                pending.push(path);
+            } else if let Ok(relative) = path.strip_prefix(root) {
+                let name = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.insert(name, path);
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let models = dir.path().join("models");
+        std::fs::create_dir_all(models.join("minilm")).unwrap();
+        std::fs::write(models.join("minilm/model.onnx"), b"weights").unwrap();
+        std::fs::write(models.join("tokenizer.json"), b"{}").unwrap();
+        let exe = dir.path().join("synapse-cli");
+        std::fs::write(&exe, b"binary").unwrap();
+        let server = dir.path().join("synapse-server");
+        std::fs::write(&server, b"server binary").unwrap();
+
+        let key = generate_signing_key();
+        let manifest_path = dir.path().join("integrity.json");
+        let executables = installed_executables(&exe);
+        assert_eq!(executables, vec![exe.clone(), server.clone()]);
+        IntegrityManifest::generate(&executables, &models, &key)
+            .unwrap()
+            .save(&manifest_path)
+            .unwrap();
+
+        let checker = IntegrityChecker::new(&manifest_path, &models, key.verifying_key())
+            .unwrap()
+            .with_executable(&exe);
+        assert!(checker.verify().unwrap().is_empty());
+
+        // Each binary checks its own entry against the same manifest
+        let server_checker = IntegrityChecker::new(&manifest_path, &models, key.verifying_key())
+            .unwrap()
+            .with_executable(&server);
+        assert!(server_checker.verify().unwrap().is_empty());
+        std::fs::write(&server, b"patched").unwrap();
+        assert_eq!(
+            server_checker.verify().unwrap(),
+            vec![IntegrityIssue::Modified("@executable:synapse-server".to_string())]
+        );
+        assert!(checker.verify().unwrap().is_empty());
+
+        std::fs::write(models.join("minilm/model.onnx"), b"poisoned").unwrap();
+        std::fs::remove_file(models.join("tokenizer.json")).unwrap();
+        std::fs::write(models.join("extra.bin"), b"?").unwrap();
+        assert_eq!(
+            checker.verify().unwrap(),
+            vec![
+                IntegrityIssue::Modified("minilm/model.onnx".to_string()),
+                IntegrityIssue::Missing("tokenizer.json".to_string()),
+                IntegrityIssue::Unexpected("extra.bin".to_string()),
+            ]
+        );
+
+        // A manifest re-signed with another key is not trusted
+        IntegrityManifest::generate(&executables, &models, &generate_signing_key())
+            .unwrap()
+            .save(&manifest_path)
+            .unwrap();
+        assert_eq!(checker.verify().unwrap(), vec![IntegrityIssue::InvalidSignature]);
+    }
+
+    #[test]
+    fn test_manifest_follows_data_dir() {
+        let config: IntegrityConfig = serde_json::from_str("{}").unwrap();
+        assert_eq!(
+            config.manifest_path(Path::new("/srv/synapse")),
+            PathBuf::from("/srv/synapse/integrity.json")
+        );
+
+        let config = IntegrityConfig {
+            manifest: Some(PathBuf::from("/etc/synapse/integrity.json")),
+            ..config
+        };
+        assert_eq!(
+            config.manifest_path(Path::new("/srv/synapse")),
+            PathBuf::from("/etc/synapse/integrity.json")
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! Linux context adapter.
+//!
+//! Processes are read from `/proc`. The active window comes from the X11
+//! server named by `$DISPLAY`, using the EWMH `_NET_ACTIVE_WINDOW` hint that
+//! all common window managers maintain. On headless hosts (or Wayland without
+//! XWayland) process listing still works and `get_active_window` returns an
+//! error, which callers such as the immune context loop already tolerate.
+//! Screen capture and input pattern analysis are not implemented yet and
+//! always return an error rather than a made-up value.
+//! Both read files or talk to the X server synchronously, so they run on
+//! tokio's blocking pool.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use synapse_core::error::{Error, Result};
+use synapse_core::ports::{ContextPort, ProcessInfo, WindowInfo};
+use tracing::debug;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt, MapState, Window};
+use x11rb::rust_connection::RustConnection;
+
+/// `/proc` reports CPU time in USER_HZ ticks, which is fixed at 100 on Linux.
+const TICKS_PER_SECOND: u64 = 100;
+
+pub struct LinuxContextAdapter {
+    inner: Arc<Inner>,
+}
+
+/// State shared with the blocking tasks.
+struct Inner {
+    proc_root: PathBuf,
+    users: HashMap<u32, String>,
+    display: Mutex<Option<X11Session>>,
+}
+
+impl LinuxContextAdapter {
+    pub fn new() -> Self {
+        Self::with_proc_root("/proc")
+    }
+
+    /// Read processes from a different procfs mount (e.g. a container's).
+    pub fn with_proc_root(proc_root: impl Into<PathBuf>) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                proc_root: proc_root.into(),
+                users: std::fs::read_to_string("/etc/passwd")
+                    .map(|passwd| parse_passwd(&passwd))
+                    .unwrap_or_default(),
+                display: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Whether an X display is configured for this process.
+    pub fn has_display() -> bool {
+        std::env::var_os("DISPLAY").is_some_and(|d| !d.is_empty())
+    }
+
+    async fn blocking<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Inner) -> Result<T> + Send + 'static,
+    {
+        let inner = self.inner.clone();
+        tokio::task::spawn_blocking(move || f(&inner))
+            .await
+            .map_err(|e| Error::System(format!("Context task panicked: {}", e)))?
+    }
+}
+
+impl Inner {
+    fn list_processes(&self) -> Result<Vec<ProcessInfo>> {
+        let entries = std::fs::read_dir(&self.proc_root).map_err(|e| {
+            Error::System(format!("Failed to read {}: {}", self.proc_root.display(), e))
+        })?;
+
+        let mut processes: Vec<ProcessInfo> = entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+            // Processes may exit between listing and reading
+            .filter_map(|pid| self.read_process(pid))
+            .collect();
+        processes.sort_by_key(|p| p.pid);
+        Ok(processes)
+    }
+
+    fn read_process(&self, pid: u32) -> Option<ProcessInfo> {
+        let dir = self.proc_root.join(pid.to_string());
+        let stat = parse_stat(&std::fs::read_to_string(dir.join("stat")).ok()?)?;
+        let status = std::fs::read_to_string(dir.join("status")).unwrap_or_default();
+        let cmdline = std::fs::read(dir.join("cmdline"))
+            .map(|raw| {
+                raw.split(|&b| b == 0)
+                    .filter(|arg| !arg.is_empty())
+                    .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Some(ProcessInfo {
+            pid,
+            parent_pid: Some(stat.ppid).filter(|&ppid| ppid != 0),
+            name: stat.name,
+            cmdline,
+            exe: std::fs::read_link(dir.join("exe"))
+                .ok()
+                .map(|p| p.to_string_lossy().into_owned()),
+            user: status_field(&status, "Uid:")
+                .and_then(|uid| uid.parse().ok())
+                .map(|uid: u32| self.users.get(&uid).cloned().unwrap_or_else(|| uid.to_string())),
+            cpu_time_ms: stat.cpu_ticks * 1000 / TICKS_PER_SECOND,
+            memory_bytes: status_field(&status, "VmRSS:")
+                .and_then(|kb| kb.parse::<u64>().ok())
+                .map_or(0, |kb| kb * 1024),
+        })
+    }
+
+    fn process_name(&self, pid: u32) -> Option<String> {
+        std::fs::read_to_string(self.proc_root.join(pid.to_string()).join("comm"))
+            .ok()
+            .map(|comm| comm.trim_end().to_string())
+    }
+
+    fn active_window(&self) -> Result<WindowInfo> {
+        if !LinuxContextAdapter::has_display() {
+            return Err(Error::System("No X display available".into()));
+        }
+
+        let mut display = self.display.lock().unwrap_or_else(|e| e.into_inner());
+        if display.is_none() {
+            *display = Some(X11Session::connect()?);
+        }
+        let session = display.as_ref().expect("connected above");
+
+        match session.active_window() {
+            Ok((mut info, pid)) => {
+                info.process_name = pid
+                    .and_then(|pid| self.process_name(pid))
+                    .unwrap_or_else(|| "Unknown".to_string());
+                Ok(info)
+            }
+            Err(e) => {
+                // Reconnect next time in case the X server went away
+                *display = None;
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Default for LinuxContextAdapter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl ContextPort for LinuxContextAdapter {
+    async fn capture_screen(&self) -> Result<Vec<u8>> {
+        Err(Error::System("Screen capture is not supported on Linux yet".into()))
+    }
+
+    async fn get_active_window(&self) -> Result<WindowInfo> {
+        self.blocking(|inner| inner.active_window()).await
+    }
+
+    async fn get_running_processes(&self) -> Result<Vec<String>> {
+        Ok(self
+            .list_processes()
+            .await?
+            .into_iter()
+            .map(|p| p.name)
+            .collect())
+    }
+
+    async fn list_processes(&self) -> Result<Vec<ProcessInfo>> {
+        self.blocking(|inner| inner.list_processes()).await
+    }
+
+    async fn analyze_input_pattern(&self, _duration_ms: u64) -> Result<f32> {
+        Err(Error::System("Input pattern analysis is not supported on Linux yet".into()))
+    }
+}
+
+/// An open connection to the X server with the EWMH atoms resolved.
+struct X11Session {
+    conn: RustConnection,
+    root: Window,
+    net_active_window: Atom,
+    net_wm_name: Atom,
+    net_wm_pid: Atom,
+    utf8_string: Atom,
+}
+
+impl X11Session {
+    fn connect() -> Result<Self> {
+        let (conn, screen) = x11rb::connect(None).map_err(x11_error)?;
+        let root = conn.setup().roots[screen].root;
+        let atom = |name: &[u8]| -> Result<Atom> {
+            Ok(conn
+                .intern_atom(false, name)
+                .map_err(x11_error)?
+                .reply()
+                .map_err(x11_error)?
+                .atom)
+        };
+        let session = Self {
+            net_active_window: atom(b"_NET_ACTIVE_WINDOW")?,
+            net_wm_name: atom(b"_NET_WM_NAME")?,
+            net_wm_pid: atom(b"_NET_WM_PID")?,
+            utf8_string: atom(b"UTF8_STRING")?,
+            root,
+            conn,
+        };
+        debug!("Connected to X display");
+        Ok(session)
+    }
+
+    /// The focused window and the pid that owns it, if advertised.
+    fn active_window(&self) -> Result<(WindowInfo, Option<u32>)> {
+        let window = self
+            .property32(self.root, self.net_active_window, AtomEnum::WINDOW.into())?
+            .filter(|&w| w != x11rb::NONE)
+            .ok_or_else(|| Error::NotFound {
+                id: "active window".into(),
+            })?;
+
+        let title = match self.property_string(window, self.net_wm_name, self.utf8_string)? {
+            Some(title) => title,
+            None => self
+                .property_string(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into())?
+                .unwrap_or_default(),
+        };
+        let pid = self.property32(window, self.net_wm_pid, AtomEnum::CARDINAL.into())?;
+
+        let attributes = self
+            .conn
+            .get_window_attributes(window)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?;
+        let geometry = self
+            .conn
+            .get_geometry(window)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?;
+        let origin = self
+            .conn
+            .translate_coordinates(window, self.root, 0, 0)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?;
+
+        let info = WindowInfo {
+            title,
+            process_name: String::new(),
+            is_visible: attributes.map_state == MapState::VIEWABLE,
+            bounds: (
+                origin.dst_x.into(),
+                origin.dst_y.into(),
+                geometry.width.into(),
+                geometry.height.into(),
+            ),
+        };
+        Ok((info, pid))
+    }
+
+    fn property32(&self, window: Window, property: Atom, kind: Atom) -> Result<Option<u32>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, kind, 0, 1)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?;
+        Ok(reply.value32().and_then(|mut values| values.next()))
+    }
+
+    fn property_string(&self, window: Window, property: Atom, kind: Atom) -> Result<Option<String>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, kind, 0, 1024)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?;
+        if reply.type_ == x11rb::NONE {
+            return Ok(None);
+        }
+        Ok(Some(String::from_utf8_lossy(&reply.value).into_owned()))
+    }
+}
+
+fn x11_error(e: impl std::fmt::Display) -> Error {
+    Error::System(format!("X11 error: {}", e))
+}
+
+struct Stat {
+    name: String,
+    ppid: u32,
+    cpu_ticks: u64,
+}
+
+/// Parse `/proc/<pid>/stat`. The name is parenthesised and may itself
+/// contain spaces or parentheses, so fields are counted from the last `)`.
+fn parse_stat(stat: &str) -> Option<Stat> {
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    let name = stat.get(open + 1..close)?.to_string();
+    // Fields after the name, starting with `state` (field 3 in proc(5))
+    let fields: Vec<&str> = stat.get(close + 1..)?.split_whitespace().collect();
+    let field = |n: usize| fields.get(n - 3).and_then(|f| f.parse::<u64>().ok());
+
+    Some(Stat {
+        name,
+        ppid: field(4)? as u32,
+        cpu_ticks: field(14)? + field(15)?,
+    })
+}
+
+/// First value of a `Key:\tvalue ...` line in `/proc/<pid>/status`.
+fn status_field<'a>(status: &'a str, key: &str) -> Option<&'a str> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix(key))?
+        .split_whitespace()
+        .next()
+}
+
+fn parse_passwd(passwd: &str) -> HashMap<u32, String> {
+    passwd
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn fake_process(root: &Path, pid: u32, stat: &str, cmdline: &[u8], status: &str) {
+        let dir = root.join(pid.to_string());
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("stat"), stat).unwrap();
+        std::fs::write(dir.join("cmdline"), cmdline).unwrap();
+        std::fs::write(dir.join("status"), status).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lists_processes_from_proc() {
+        let proc_root = tempfile::tempdir().unwrap();
+        fake_process(
+            proc_root.path(),
+            1,
+            "1 (systemd) S 0 1 1 0 -1 4194560 0 0 0 0 150 50 0 0 20 0 1 0 1 0 0",
+            b"/sbin/init\0splash\0",
+            "Name:\tsystemd\nUid:\t0\t0\t0\t0\nVmRSS:\t  1024 kB\n",
+        );
+        fake_process(
+            proc_root.path(),
+            42,
+            "42 (tmux: server (1)) S 1 42 42 0 -1 0 0 0 0 0 7 3 0 0 20 0 1 0 1 0 0",
+            b"",
+            "Name:\ttmux\nUid:\t4242\t4242\t4242\t4242\n",
+        );
+        std::fs::create_dir_all(proc_root.path().join("self")).unwrap();
+        std::fs::create_dir_all(proc_root.path().join("99")).unwrap(); // exited
+
+        let adapter = LinuxContextAdapter::with_proc_root(proc_root.path());
+        let processes = adapter.list_processes().await.unwrap();
+        assert_eq!(processes.len(), 2);
+
+        let init = &processes[0];
+        assert_eq!(init.name, "systemd");
+        assert_eq!(init.parent_pid, None);
+        assert_eq!(init.cmdline, vec!["/sbin/init", "splash"]);
+        assert_eq!(init.user.as_deref(), Some("root"));
+        assert_eq!(init.cpu_time_ms, 2000);
+        assert_eq!(init.memory_bytes, 1024 * 1024);
+
+        let tmux = &processes[1];
+        assert_eq!(tmux.name, "tmux: server (1)");
+        assert_eq!(tmux.parent_pid, Some(1));
+        assert!(tmux.cmdline.is_empty());
+        assert_eq!(tmux.user.as_deref(), Some("4242"));
+        assert_eq!(tmux.cpu_time_ms, 100);
+
+        assert_eq!(
+            adapter.get_running_processes().await.unwrap(),
+            vec!["systemd", "tmux: server (1)"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lists_real_processes() {
+        let adapter = LinuxContextAdapter::new();
+        let processes = adapter.list_processes().await.unwrap();
+        let me = processes
+            .iter()
+            .find(|p| p.pid == std::process::id())
+            .expect("own process is listed");
+        assert!(!me.cmdline.is_empty());
+        assert!(me.memory_bytes > 0);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_capture_is_an_error() {
+        let adapter = LinuxContextAdapter::new();
+        assert!(adapter.capture_screen().await.is_err());
+        assert!(adapter.analyze_input_pattern(1000).await.is_err());
+    }
+
+    /// Run with `xvfb-run cargo test -- --ignored`. The test plays the window
+    /// manager and advertises its own window as active.
+    #[tokio::test]
+    #[ignore = "needs an X server"]
+    async fn test_active_window_under_x11() {
+        use x11rb::protocol::xproto::{CreateWindowAux, PropMode, WindowClass};
+        use x11rb::wrapper::ConnectionExt as _;
+
+        let session = X11Session::connect().unwrap();
+        let conn = &session.conn;
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            session.root,
+            10,
+            20,
+            300,
+            200,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            x11rb::COPY_FROM_PARENT,
+            &CreateWindowAux::new(),
+        )
+        .unwrap();
+        conn.change_property8(
+            PropMode::REPLACE,
+            window,
+            session.net_wm_name,
+            session.utf8_string,
+            "Synapse ✓".as_bytes(),
+        )
+        .unwrap();
+        conn.change_property32(
+            PropMode::REPLACE,
+            window,
+            session.net_wm_pid,
+            AtomEnum::CARDINAL,
+            &[std::process::id()],
+        )
+        .unwrap();
+        conn.map_window(window).unwrap();
+        conn.change_property32(
+            PropMode::REPLACE,
+            session.root,
+            session.net_active_window,
+            AtomEnum::WINDOW,
+            &[window],
+        )
+        .unwrap();
+        conn.sync().unwrap();
+
+        let adapter = LinuxContextAdapter::new();
+        let info = adapter.get_active_window().await.unwrap();
+        assert_eq!(info.title, "Synapse ✓");
+        assert_eq!(info.process_name, adapter.inner.process_name(std::process::id()).unwrap());
+        assert!(info.is_visible);
+        assert_eq!(info.bounds, (10, 20, 300, 200));
+    }
+}
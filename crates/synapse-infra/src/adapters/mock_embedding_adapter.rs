@@ -62,6 +62,10 @@ impl EmbeddingPort for MockEmbeddingAdapter {
         Ok(embedding)
     }
 
+    fn is_normalized(&self) -> bool {
+        true
+    }
+
     fn dimension(&self) -> usize {
         self.dimension
     }
@@ -3,11 +3,23 @@
 pub mod surrealdb_adapter;
 pub mod sled_adapter;
 pub mod ort_adapter;
+pub mod ort_classifier_adapter;
+pub mod candle_embedding_adapter;
+pub mod cached_embedder;
+pub mod openai_adapter;
+mod embedding_batch;
 pub mod context_adapter;
+#[cfg(target_os = "linux")]
+pub mod linux_context_adapter;
+pub mod scripted_context_adapter;
 pub mod immune_adapter;
+pub mod integrity;
+pub mod private_file;
 pub mod mock_llm_adapter;
 pub mod mock_embedding_adapter;
 pub mod candle_adapter;
+pub mod generation;
+pub mod model_descriptor;
 pub mod vision_adapter;
 pub mod audio_adapter;
 
@@ -16,3 +28,6 @@ pub use sled_adapter::*;
 pub use ort_adapter::*;
 pub use mock_llm_adapter::*;
 pub use mock_embedding_adapter::*;
+pub use model_descriptor::*;
+pub use cached_embedder::*;
+pub use openai_adapter::*;
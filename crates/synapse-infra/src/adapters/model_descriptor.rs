@@ -0,0 +1,336 @@
+//! Model descriptors - where a model lives and how to load it.
+//!
+//! Descriptors are read from the Synapse config so adapters no longer depend
+//! on the process working directory. Relative paths are resolved against a
+//! list of search roots and every location tried is reported on failure.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use synapse_core::logic::chat_template::ChatTemplate;
+use synapse_core::Error;
+
+/// Environment variable that overrides the first model search root.
+pub const SYNAPSE_HOME_ENV: &str = "SYNAPSE_HOME";
+
+/// Model architecture family (quantized GGUF LLMs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelArchitecture {
+    /// Read `general.architecture` from the GGUF metadata
+    #[default]
+    Auto,
+    /// Llama 1/2/3, TinyLlama, Mistral (GGUF reports these as `llama`)
+    Llama,
+    /// Phi-2
+    Phi2,
+    /// Phi-3 / Phi-3.5
+    Phi3,
+    /// Qwen2 / Qwen2.5
+    Qwen2,
+}
+
+impl ModelArchitecture {
+    /// Architectures that can be loaded, for error messages.
+    pub const SUPPORTED: &'static str = "llama (incl. mistral, tinyllama), phi2, phi3, qwen2";
+
+    /// Map a GGUF `general.architecture` value to a supported family.
+    pub fn from_gguf_name(name: &str) -> Option<Self> {
+        match name {
+            "llama" | "mistral" => Some(ModelArchitecture::Llama),
+            "phi2" => Some(ModelArchitecture::Phi2),
+            "phi3" => Some(ModelArchitecture::Phi3),
+            "qwen2" => Some(ModelArchitecture::Qwen2),
+            _ => None,
+        }
+    }
+}
+
+/// Inference backend for embedding models.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingBackend {
+    /// ONNX Runtime for `*.onnx`, Candle for `*.safetensors`
+    #[default]
+    Auto,
+    /// ONNX Runtime (`*.onnx`)
+    Ort,
+    /// Candle, pure Rust (`*.safetensors`)
+    Candle,
+}
+
+/// Pooling strategy for turning token embeddings into a sentence embedding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pooling {
+    /// Attention-mask-aware mean over tokens (sentence-transformers default)
+    #[default]
+    Mean,
+    /// First (`[CLS]`) token
+    Cls,
+}
+
+/// Describes a model on disk and how to load it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelDescriptor {
+    /// Model file, or a directory containing it
+    pub path: PathBuf,
+
+    /// Architecture family (LLMs)
+    #[serde(default)]
+    pub architecture: ModelArchitecture,
+
+    /// Tokenizer file (default: `tokenizer.json` next to the model)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokenizer: Option<PathBuf>,
+
+    /// Context window in tokens (default: read from the model)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_length: Option<usize>,
+
+    /// Chat template (LLMs, default: detected from the model)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<ChatTemplate>,
+
+    /// Inference backend (embedding models)
+    #[serde(default)]
+    pub backend: EmbeddingBackend,
+
+    /// Pooling strategy (embedding models)
+    #[serde(default)]
+    pub pooling: Pooling,
+
+    /// L2-normalize embeddings (embedding models)
+    #[serde(default = "default_normalize")]
+    pub normalize: bool,
+
+    /// Output dimension (embedding models, default: read from the model)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimension: Option<usize>,
+
+    /// Texts per inference call (embedding models)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_batch_size: Option<usize>,
+}
+
+impl ModelDescriptor {
+    /// Create a descriptor with defaults for everything but the path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            architecture: ModelArchitecture::Auto,
+            tokenizer: None,
+            context_length: None,
+            template: None,
+            backend: EmbeddingBackend::Auto,
+            pooling: Pooling::Mean,
+            normalize: true,
+            dimension: None,
+            max_batch_size: None,
+        }
+    }
+
+    /// TinyLlama-1.1B-Chat as downloaded by `synapse init`.
+    pub fn tinyllama() -> Self {
+        Self {
+            architecture: ModelArchitecture::Llama,
+            ..Self::new("models/tinyllama-1.1b/model.gguf")
+        }
+    }
+
+    /// all-MiniLM-L6-v2 (ONNX) as downloaded by `synapse init`.
+    pub fn minilm() -> Self {
+        Self {
+            dimension: Some(384),
+            ..Self::new("models/all-MiniLM-L6-v2/model.onnx")
+        }
+    }
+
+    /// Directories relative model paths are resolved against, in order:
+    /// `$SYNAPSE_HOME`, the current directory, the executable's directory
+    /// and `~/.synapse`.
+    pub fn search_roots() -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        if let Some(home) = std::env::var_os(SYNAPSE_HOME_ENV) {
+            roots.push(PathBuf::from(home));
+        }
+        if let Ok(cwd) = std::env::current_dir() {
+            roots.push(cwd);
+        }
+        if let Some(dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+            roots.push(dir);
+        }
+        if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+            roots.push(PathBuf::from(home).join(".synapse"));
+        }
+        roots.dedup();
+        roots
+    }
+
+    /// Locations tried for a (possibly relative) path.
+    fn candidates(path: &Path) -> Vec<PathBuf> {
+        if path.is_absolute() {
+            vec![path.to_path_buf()]
+        } else {
+            Self::search_roots().into_iter().map(|root| root.join(path)).collect()
+        }
+    }
+
+    /// Find the model file. If `path` is a directory, `model.<extension>` or
+    /// the first `*.<extension>` file inside it is used.
+    pub fn resolve_model_file(&self, extension: &str) -> Result<PathBuf, Error> {
+        let candidates = Self::candidates(&self.path);
+        for candidate in &candidates {
+            if candidate.is_file() {
+                return Ok(candidate.clone());
+            }
+            if candidate.is_dir() {
+                let preferred = candidate.join(format!("model.{}", extension));
+                if preferred.is_file() {
+                    return Ok(preferred);
+                }
+                let mut found: Vec<PathBuf> = std::fs::read_dir(candidate)
+                    .map_err(|e| Error::System(format!("Failed to read {:?}: {}", candidate, e)))?
+                    .filter_map(|entry| entry.ok().map(|e| e.path()))
+                    .filter(|p| p.extension().is_some_and(|ext| ext == extension))
+                    .collect();
+                found.sort();
+                if let Some(first) = found.into_iter().next() {
+                    return Ok(first);
+                }
+            }
+        }
+        Err(not_found(&format!("Model '{}' (*.{})", self.path.display(), extension), &candidates))
+    }
+
+    /// Pick the embedding backend, resolving `Auto` from the model files present.
+    pub fn embedding_backend(&self) -> Result<EmbeddingBackend, Error> {
+        match self.backend {
+            EmbeddingBackend::Auto => match self.resolve_model_file("onnx") {
+                Ok(_) => Ok(EmbeddingBackend::Ort),
+                Err(onnx_error) => self
+                    .resolve_model_file("safetensors")
+                    .map(|_| EmbeddingBackend::Candle)
+                    .map_err(|_| onnx_error),
+            },
+            explicit => Ok(explicit),
+        }
+    }
+
+    /// Identifier for a resolved model file that does not depend on the search
+    /// root it was found under: `<directory>/<file>`, e.g.
+    /// `all-MiniLM-L6-v2/model.onnx`.
+    pub fn model_id(model_file: &Path) -> String {
+        let file = model_file.file_name().map(|f| f.to_string_lossy()).unwrap_or_default();
+        match model_file.parent().and_then(|dir| dir.file_name()) {
+            Some(dir) => format!("{}/{}", dir.to_string_lossy(), file),
+            None => file.into_owned(),
+        }
+    }
+
+    /// Find the tokenizer, defaulting to `tokenizer.json` next to the model.
+    pub fn resolve_tokenizer(&self, model_file: &Path) -> Result<PathBuf, Error> {
+        let candidates = match &self.tokenizer {
+            Some(path) => Self::candidates(path),
+            None => vec![model_file.with_file_name("tokenizer.json")],
+        };
+        candidates
+            .iter()
+            .find(|c| c.is_file())
+            .cloned()
+            .ok_or_else(|| not_found("Tokenizer", &candidates))
+    }
+}
+
+fn default_normalize() -> bool {
+    true
+}
+
+fn not_found(what: &str, searched: &[PathBuf]) -> Error {
+    let list: String = searched
+        .iter()
+        .map(|p| format!("\n  - {}", p.display()))
+        .collect();
+    Error::System(format!(
+        "{} not found. Searched:{}\nSet the model path in synapse.json, set {}, or run 'synapse init' to download models.",
+        what, list, SYNAPSE_HOME_ENV
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_model_id_ignores_search_root() {
+        let a = ModelDescriptor::model_id(Path::new("/opt/synapse/models/all-MiniLM-L6-v2/model.onnx"));
+        let b = ModelDescriptor::model_id(Path::new("/home/me/.synapse/models/all-MiniLM-L6-v2/model.onnx"));
+        assert_eq!(a, "all-MiniLM-L6-v2/model.onnx");
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn test_resolve_directory_prefers_model_file() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("a.gguf"), b"").unwrap();
+        std::fs::write(dir.path().join("model.gguf"), b"").unwrap();
+
+        let descriptor = ModelDescriptor::new(dir.path());
+        assert_eq!(descriptor.resolve_model_file("gguf").unwrap(), dir.path().join("model.gguf"));
+    }
+
+    #[test]
+    fn test_resolve_directory_falls_back_to_any_matching_file() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("qwen2-0.5b.Q4_K_M.gguf"), b"").unwrap();
+        std::fs::write(dir.path().join("tokenizer.json"), b"{}").unwrap();
+
+        let descriptor = ModelDescriptor::new(dir.path());
+        let model = descriptor.resolve_model_file("gguf").unwrap();
+        assert_eq!(model, dir.path().join("qwen2-0.5b.Q4_K_M.gguf"));
+        assert_eq!(descriptor.resolve_tokenizer(&model).unwrap(), dir.path().join("tokenizer.json"));
+    }
+
+    #[test]
+    fn test_missing_model_lists_searched_paths() {
+        let descriptor = ModelDescriptor::new("models/does-not-exist/model.gguf");
+        let message = descriptor.resolve_model_file("gguf").unwrap_err().to_string();
+
+        assert!(message.contains("Searched:"));
+        for root in ModelDescriptor::search_roots() {
+            assert!(message.contains(&root.join("models/does-not-exist/model.gguf").display().to_string()));
+        }
+    }
+
+    #[test]
+    fn test_deserialize_with_defaults() {
+        let descriptor: ModelDescriptor = serde_json::from_str(
+            r#"{ "path": "models/phi3.gguf", "architecture": "phi3", "template": "phi3" }"#,
+        )
+        .unwrap();
+
+        assert_eq!(descriptor.architecture, ModelArchitecture::Phi3);
+        assert_eq!(descriptor.template, Some(ChatTemplate::Phi3));
+        assert_eq!(descriptor.pooling, Pooling::Mean);
+        assert!(descriptor.normalize);
+        assert!(descriptor.tokenizer.is_none());
+    }
+
+    #[test]
+    fn test_auto_backend_follows_model_files() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("model.safetensors"), b"").unwrap();
+        let descriptor = ModelDescriptor::new(dir.path());
+        assert_eq!(descriptor.embedding_backend().unwrap(), EmbeddingBackend::Candle);
+
+        std::fs::write(dir.path().join("model.onnx"), b"").unwrap();
+        assert_eq!(descriptor.embedding_backend().unwrap(), EmbeddingBackend::Ort);
+    }
+
+    #[test]
+    fn test_gguf_architecture_names() {
+        assert_eq!(ModelArchitecture::from_gguf_name("llama"), Some(ModelArchitecture::Llama));
+        assert_eq!(ModelArchitecture::from_gguf_name("qwen2"), Some(ModelArchitecture::Qwen2));
+        assert_eq!(ModelArchitecture::from_gguf_name("gemma"), None);
+    }
+}
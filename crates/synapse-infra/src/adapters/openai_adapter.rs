@@ -0,0 +1,639 @@
+//! OpenAI-compatible HTTP adapters.
+//!
+//! Talks to any server implementing the OpenAI `/chat/completions` and
+//! `/embeddings` wire format: llama.cpp server, vLLM, Ollama, LM Studio or
+//! OpenAI itself. Requests are retried with exponential backoff on connection
+//! errors, timeouts, 429 and 5xx responses. Streaming uses server-sent events.
+
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::time::Duration;
+use synapse_core::{
+    ChatMessage, EmbeddingPort, Error, FinishReason, GenerationParams, GenerationUsage, LlmPort, TokenEvent,
+    TokenStream,
+};
+
+/// Connection settings for an OpenAI-compatible endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenAiConfig {
+    /// API base URL including the version prefix, e.g. `http://localhost:8080/v1`
+    pub base_url: String,
+
+    /// Model name sent with every request
+    pub model: String,
+
+    /// Bearer token (`Authorization: Bearer ...`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+
+    /// Extra headers sent with every request
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub headers: HashMap<String, String>,
+
+    /// Seconds to wait for a response (for streams: for the headers and
+    /// between events)
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+
+    /// Retries after the first attempt
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+
+    /// Also send `top_k`, `min_p` and `repetition_penalty`, which llama.cpp
+    /// and vLLM accept but OpenAI rejects (default: off)
+    #[serde(default)]
+    pub extended_sampling: bool,
+
+    /// Embedding dimension (default: detected with a probe request)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimension: Option<usize>,
+
+    /// Model context window in tokens, used to budget prompts
+    #[serde(default = "default_context_window")]
+    pub context_window: usize,
+}
+
+fn default_timeout_secs() -> u64 {
+    60
+}
+
+fn default_context_window() -> usize {
+    4096
+}
+
+fn default_max_retries() -> u32 {
+    2
+}
+
+impl OpenAiConfig {
+    /// Settings for `model` at `base_url`, with defaults for everything else.
+    pub fn new(base_url: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            model: model.into(),
+            api_key: None,
+            headers: HashMap::new(),
+            timeout_secs: default_timeout_secs(),
+            max_retries: default_max_retries(),
+            extended_sampling: false,
+            dimension: None,
+            context_window: default_context_window(),
+        }
+    }
+}
+
+/// Shared HTTP client with retry handling.
+struct OpenAiClient {
+    http: reqwest::Client,
+    config: OpenAiConfig,
+}
+
+impl OpenAiClient {
+    fn new(config: OpenAiConfig) -> Result<Self, Error> {
+        let mut headers = reqwest::header::HeaderMap::new();
+        if let Some(key) = &config.api_key {
+            let value = format!("Bearer {}", key)
+                .parse()
+                .map_err(|e| Error::System(format!("Invalid API key header: {}", e)))?;
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
+        for (name, value) in &config.headers {
+            let name = reqwest::header::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| Error::System(format!("Invalid header name '{}': {}", name, e)))?;
+            let value = value
+                .parse()
+                .map_err(|e| Error::System(format!("Invalid value for header '{}': {}", name, e)))?;
+            headers.insert(name, value);
+        }
+
+        let http = reqwest::Client::builder()
+            .default_headers(headers)
+            .connect_timeout(Duration::from_secs(config.timeout_secs))
+            .build()
+            .map_err(|e| Error::System(format!("Failed to create HTTP client: {}", e)))?;
+
+        Ok(Self { http, config })
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.config.timeout_secs)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    /// POST `body` to `path`, retrying transient failures, and return the
+    /// successful response once its headers have arrived.
+    async fn post(&self, path: &str, body: &Value) -> Result<reqwest::Response, Error> {
+        let url = self.url(path);
+        let mut attempt = 0;
+        loop {
+            let request = self.http.post(&url).json(body).send();
+            let error = match tokio::time::timeout(self.timeout(), request).await {
+                Ok(Ok(response)) if response.status().is_success() => return Ok(response),
+                Ok(Ok(response)) => {
+                    let status = response.status();
+                    let text = response.text().await.unwrap_or_default();
+                    let error = Error::System(format!("{} returned {}: {}", url, status, text.trim()));
+                    if !(status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS) {
+                        return Err(error);
+                    }
+                    error
+                }
+                Ok(Err(e)) => Error::System(format!("Request to {} failed: {}", url, e)),
+                Err(_) => Error::System(format!("Request to {} timed out after {:?}", url, self.timeout())),
+            };
+
+            if attempt >= self.config.max_retries {
+                return Err(error);
+            }
+            let backoff = Duration::from_millis(200 * 2u64.pow(attempt));
+            tracing::warn!("{} (retrying in {:?})", error, backoff);
+            tokio::time::sleep(backoff).await;
+            attempt += 1;
+        }
+    }
+
+    /// POST and decode a JSON response body.
+    async fn post_json(&self, path: &str, body: &Value) -> Result<Value, Error> {
+        let response = self.post(path, body).await?;
+        tokio::time::timeout(self.timeout(), response.json::<Value>())
+            .await
+            .map_err(|_| Error::System(format!("Reading response from {} timed out", path)))?
+            .map_err(|e| Error::System(format!("Invalid response from {}: {}", path, e)))
+    }
+}
+
+/// OpenAI-compatible chat completion adapter.
+pub struct OpenAiLlmAdapter {
+    client: OpenAiClient,
+}
+
+impl OpenAiLlmAdapter {
+    /// Create an adapter for the endpoint in `config`.
+    pub fn new(config: OpenAiConfig) -> Result<Self, Error> {
+        Ok(Self { client: OpenAiClient::new(config)? })
+    }
+
+    fn request_body(&self, messages: &[ChatMessage], params: &GenerationParams, stream: bool) -> Value {
+        let mut body = json!({
+            "model": self.client.config.model,
+            "messages": messages,
+            "max_tokens": params.max_tokens,
+            "temperature": params.temperature,
+            "top_p": params.top_p,
+            "stream": stream,
+        });
+        if !params.stop.is_empty() {
+            body["stop"] = json!(params.stop);
+        }
+        if let Some(seed) = params.seed {
+            body["seed"] = json!(seed);
+        }
+        if params.frequency_penalty != 0.0 {
+            body["frequency_penalty"] = json!(params.frequency_penalty);
+        }
+        if self.client.config.extended_sampling {
+            body["top_k"] = json!(params.top_k);
+            body["min_p"] = json!(params.min_p);
+            body["repetition_penalty"] = json!(params.repetition_penalty);
+        }
+        if stream {
+            body["stream_options"] = json!({ "include_usage": true });
+        }
+        body
+    }
+}
+
+fn finish_reason(value: &Value) -> Option<FinishReason> {
+    match value.as_str()? {
+        "length" => Some(FinishReason::Length),
+        _ => Some(FinishReason::Stop),
+    }
+}
+
+fn usage(value: &Value) -> Option<(usize, usize)> {
+    let usage = value.get("usage").filter(|u| !u.is_null())?;
+    Some((
+        usage["prompt_tokens"].as_u64().unwrap_or(0) as usize,
+        usage["completion_tokens"].as_u64().unwrap_or(0) as usize,
+    ))
+}
+
+/// Incremental parser for a `text/event-stream` body of chat completion chunks.
+#[derive(Default)]
+struct SseParser {
+    /// Bytes of an unfinished line; decoded once the line is complete so a
+    /// character split across network chunks survives
+    buffer: Vec<u8>,
+    usage: GenerationUsage,
+    done: bool,
+}
+
+impl SseParser {
+    /// Feed raw bytes and return the text deltas they complete.
+    fn feed(&mut self, bytes: &[u8]) -> Result<Vec<String>, Error> {
+        self.buffer.extend_from_slice(bytes);
+        let mut deltas = Vec::new();
+        while let Some(newline) = self.buffer.iter().position(|&b| b == b'\n') {
+            let raw: Vec<u8> = self.buffer.drain(..=newline).collect();
+            let line = String::from_utf8_lossy(&raw);
+            let Some(data) = line.trim_end().strip_prefix("data:") else {
+                continue;
+            };
+            let data = data.trim_start();
+            if data == "[DONE]" {
+                self.done = true;
+                continue;
+            }
+
+            let chunk: Value = serde_json::from_str(data)
+                .map_err(|e| Error::System(format!("Invalid stream chunk '{}': {}", data, e)))?;
+            if let Some(message) = chunk.get("error") {
+                return Err(Error::System(format!("Server error: {}", message)));
+            }
+            if let Some((prompt_tokens, completion_tokens)) = usage(&chunk) {
+                self.usage.prompt_tokens = prompt_tokens;
+                self.usage.completion_tokens = completion_tokens;
+            }
+            if let Some(choice) = chunk["choices"].get(0) {
+                if let Some(content) = choice["delta"]["content"].as_str().filter(|c| !c.is_empty()) {
+                    deltas.push(content.to_string());
+                }
+                if let Some(reason) = finish_reason(&choice["finish_reason"]) {
+                    self.usage.finish_reason = reason;
+                }
+            }
+        }
+        Ok(deltas)
+    }
+}
+
+#[async_trait]
+impl LlmPort for OpenAiLlmAdapter {
+    async fn generate(&self, prompt: &str, max_tokens: usize) -> Result<String, Error> {
+        let params = GenerationParams::default().with_max_tokens(max_tokens);
+        self.chat(&[ChatMessage::user(prompt)], &params).await
+    }
+
+    fn context_window(&self) -> usize {
+        self.client.config.context_window
+    }
+
+    async fn generate_with_params(
+        &self,
+        prompt: &str,
+        max_tokens: usize,
+        temperature: f32,
+        top_p: f32,
+    ) -> Result<String, Error> {
+        let params = GenerationParams {
+            max_tokens,
+            temperature,
+            top_p,
+            ..GenerationParams::default()
+        };
+        self.chat(&[ChatMessage::user(prompt)], &params).await
+    }
+
+    async fn generate_stream(&self, prompt: &str, params: &GenerationParams) -> Result<TokenStream, Error> {
+        self.chat_stream(&[ChatMessage::user(prompt)], params).await
+    }
+
+    async fn chat(&self, messages: &[ChatMessage], params: &GenerationParams) -> Result<String, Error> {
+        let body = self.request_body(messages, params, false);
+        let response = self.client.post_json("chat/completions", &body).await?;
+        response["choices"][0]["message"]["content"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| Error::System(format!("Completion response has no content: {}", response)))
+    }
+
+    async fn chat_stream(&self, messages: &[ChatMessage], params: &GenerationParams) -> Result<TokenStream, Error> {
+        let body = self.request_body(messages, params, true);
+        let response = self.client.post("chat/completions", &body).await?;
+        let idle_timeout = self.client.timeout();
+
+        // State: (body stream, parser, pending deltas, finished)
+        let state = (response.bytes_stream().boxed(), SseParser::default(), Vec::<String>::new(), false);
+        let events = stream::unfold(state, move |(mut body, mut parser, mut pending, finished)| async move {
+            loop {
+                if !pending.is_empty() {
+                    let delta = pending.remove(0);
+                    return Some((Ok(TokenEvent::Delta(delta)), (body, parser, pending, finished)));
+                }
+                if finished {
+                    return None;
+                }
+                if parser.done {
+                    let usage = parser.usage.clone();
+                    return Some((Ok(TokenEvent::Done(usage)), (body, parser, pending, true)));
+                }
+                match tokio::time::timeout(idle_timeout, body.next()).await {
+                    Ok(Some(Ok(bytes))) => match parser.feed(&bytes) {
+                        Ok(deltas) => pending = deltas,
+                        Err(e) => return Some((Err(e), (body, parser, pending, true))),
+                    },
+                    Ok(Some(Err(e))) => {
+                        let error = Error::System(format!("Stream failed: {}", e));
+                        return Some((Err(error), (body, parser, pending, true)));
+                    }
+                    // Some servers close the connection without `[DONE]`.
+                    Ok(None) => parser.done = true,
+                    Err(_) => {
+                        let error = Error::System(format!("Stream stalled for {:?}", idle_timeout));
+                        return Some((Err(error), (body, parser, pending, true)));
+                    }
+                }
+            }
+        });
+        Ok(Box::pin(events))
+    }
+}
+
+/// OpenAI-compatible embedding adapter.
+pub struct OpenAiEmbeddingAdapter {
+    client: OpenAiClient,
+    dimension: usize,
+    provider: String,
+    /// Model name and endpoint
+    model_id: String,
+}
+
+impl OpenAiEmbeddingAdapter {
+    /// Create an adapter for the endpoint in `config`.
+    ///
+    /// If `config.dimension` is unset, call [`OpenAiEmbeddingAdapter::detect_dimension`]
+    /// before use.
+    pub fn new(config: OpenAiConfig) -> Result<Self, Error> {
+        let dimension = config.dimension.unwrap_or(0);
+        let provider = format!("openai-{}", config.model);
+        let model_id = format!("{}@{}", config.model, config.base_url);
+        Ok(Self {
+            client: OpenAiClient::new(config)?,
+            dimension,
+            provider,
+            model_id,
+        })
+    }
+
+    /// Embed a probe string to learn the dimension, if it is not configured.
+    pub async fn detect_dimension(mut self) -> Result<Self, Error> {
+        if self.dimension == 0 {
+            self.dimension = self.embed("dimension probe").await?.len();
+        }
+        Ok(self)
+    }
+}
+
+#[async_trait]
+impl EmbeddingPort for OpenAiEmbeddingAdapter {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, Error> {
+        let mut embeddings = self.embed_batch(&[text.to_string()]).await?;
+        embeddings
+            .pop()
+            .ok_or_else(|| Error::System("Embedding response was empty".into()))
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let body = json!({ "model": self.client.config.model, "input": texts });
+        let response = self.client.post_json("embeddings", &body).await?;
+
+        #[derive(Deserialize)]
+        struct Item {
+            index: usize,
+            embedding: Vec<f32>,
+        }
+        let mut items: Vec<Item> = serde_json::from_value(response["data"].clone())
+            .map_err(|e| Error::System(format!("Invalid embedding response: {}", e)))?;
+        if items.len() != texts.len() {
+            return Err(Error::System(format!(
+                "Requested {} embeddings but received {}",
+                texts.len(),
+                items.len()
+            )));
+        }
+        items.sort_by_key(|item| item.index);
+        Ok(items.into_iter().map(|item| item.embedding).collect())
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn provider_name(&self) -> &str {
+        &self.provider
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use synapse_core::collect_stream;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::Mutex;
+
+    /// A canned HTTP response.
+    struct Reply {
+        status: u16,
+        content_type: &'static str,
+        body: String,
+    }
+
+    impl Reply {
+        fn json(body: Value) -> Self {
+            Self { status: 200, content_type: "application/json", body: body.to_string() }
+        }
+
+        fn sse(events: &[&str]) -> Self {
+            let body = events.iter().map(|e| format!("data: {}\n\n", e)).collect();
+            Self { status: 200, content_type: "text/event-stream", body }
+        }
+
+        fn status(status: u16) -> Self {
+            Self { status, content_type: "application/json", body: r#"{"error":"busy"}"#.into() }
+        }
+    }
+
+    /// A recorded request: (request line and headers, JSON body).
+    type Recorded = Arc<Mutex<Vec<(String, Value)>>>;
+
+    /// Serve `replies` in order, one per connection, and record each request.
+    async fn mock_server(replies: Vec<Reply>) -> (String, Recorded) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+        let recorded: Recorded = Arc::default();
+        let log = recorded.clone();
+
+        tokio::spawn(async move {
+            for reply in replies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut raw = Vec::new();
+                let mut buf = [0u8; 4096];
+                let (head, body) = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    raw.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&raw).to_string();
+                    if let Some(split) = text.find("\r\n\r\n") {
+                        let head = text[..split].to_string();
+                        let length: usize = head
+                            .lines()
+                            .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                            .unwrap_or(0);
+                        if raw.len() >= split + 4 + length {
+                            break (head, text[split + 4..split + 4 + length].to_string());
+                        }
+                    }
+                };
+                log.lock().await.push((head, serde_json::from_str(&body).unwrap_or(Value::Null)));
+
+                let response = format!(
+                    "HTTP/1.1 {} X\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    reply.status,
+                    reply.content_type,
+                    reply.body.len(),
+                    reply.body
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+        });
+
+        (base_url, recorded)
+    }
+
+    fn config(base_url: &str) -> OpenAiConfig {
+        OpenAiConfig {
+            api_key: Some("secret".into()),
+            headers: HashMap::from([("x-team".to_string(), "synapse".to_string())]),
+            timeout_secs: 5,
+            ..OpenAiConfig::new(base_url, "test-model")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chat_sends_messages_and_params() {
+        let (base_url, recorded) = mock_server(vec![Reply::json(json!({
+            "choices": [{ "message": { "role": "assistant", "content": "Hi there" }, "finish_reason": "stop" }]
+        }))])
+        .await;
+        let llm = OpenAiLlmAdapter::new(config(&base_url)).unwrap();
+
+        let params = GenerationParams::default().with_max_tokens(12).with_stop("\n").with_seed(7);
+        let reply = llm.chat(&[ChatMessage::system("Be brief."), ChatMessage::user("Hello")], &params).await.unwrap();
+        assert_eq!(reply, "Hi there");
+
+        let recorded = recorded.lock().await;
+        let (head, body) = &recorded[0];
+        assert!(head.starts_with("POST /v1/chat/completions"));
+        assert!(head.to_ascii_lowercase().contains("authorization: bearer secret"));
+        assert!(head.to_ascii_lowercase().contains("x-team: synapse"));
+        assert_eq!(body["model"], "test-model");
+        assert_eq!(body["messages"][0], json!({ "role": "system", "content": "Be brief." }));
+        assert_eq!(body["max_tokens"], 12);
+        assert_eq!(body["stop"], json!(["\n"]));
+        assert_eq!(body["seed"], 7);
+        assert_eq!(body["stream"], false);
+        assert!(body.get("top_k").is_none(), "OpenAI rejects extended sampling fields");
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream_parses_sse() {
+        let (base_url, _) = mock_server(vec![Reply::sse(&[
+            r#"{"choices":[{"delta":{"role":"assistant"},"finish_reason":null}]}"#,
+            r#"{"choices":[{"delta":{"content":"Hel"},"finish_reason":null}]}"#,
+            r#"{"choices":[{"delta":{"content":"lo"},"finish_reason":"length"}]}"#,
+            r#"{"choices":[],"usage":{"prompt_tokens":5,"completion_tokens":2}}"#,
+            "[DONE]",
+        ])])
+        .await;
+        let llm = OpenAiLlmAdapter::new(config(&base_url)).unwrap();
+
+        let stream = llm.generate_stream("Hi", &GenerationParams::default()).await.unwrap();
+        let (text, usage) = collect_stream(stream).await.unwrap();
+
+        assert_eq!(text, "Hello");
+        assert_eq!(usage, GenerationUsage { prompt_tokens: 5, completion_tokens: 2, finish_reason: FinishReason::Length });
+    }
+
+    #[tokio::test]
+    async fn test_retries_server_errors() {
+        let (base_url, recorded) = mock_server(vec![
+            Reply::status(503),
+            Reply::status(429),
+            Reply::json(json!({ "choices": [{ "message": { "content": "ok" } }] })),
+        ])
+        .await;
+        let llm = OpenAiLlmAdapter::new(config(&base_url)).unwrap();
+
+        assert_eq!(llm.generate("Hi", 8).await.unwrap(), "ok");
+        assert_eq!(recorded.lock().await.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        let (base_url, recorded) = mock_server(vec![Reply::status(400), Reply::status(400)]).await;
+        let llm = OpenAiLlmAdapter::new(config(&base_url)).unwrap();
+
+        let error = llm.generate("Hi", 8).await.unwrap_err().to_string();
+        assert!(error.contains("400"));
+        assert_eq!(recorded.lock().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_embeddings_are_reordered_by_index() {
+        let (base_url, recorded) = mock_server(vec![
+            Reply::json(json!({ "data": [{ "index": 0, "embedding": [0.0, 1.0] }] })),
+            Reply::json(json!({ "data": [
+                { "index": 1, "embedding": [0.5, 0.5] },
+                { "index": 0, "embedding": [1.0, 0.0] },
+            ] })),
+        ])
+        .await;
+        let embedder = OpenAiEmbeddingAdapter::new(config(&base_url)).unwrap().detect_dimension().await.unwrap();
+        assert_eq!(embedder.dimension(), 2);
+
+        let texts = vec!["a".to_string(), "b".to_string()];
+        let embeddings = embedder.embed_batch(&texts).await.unwrap();
+        assert_eq!(embeddings, vec![vec![1.0, 0.0], vec![0.5, 0.5]]);
+
+        let recorded = recorded.lock().await;
+        assert!(recorded[1].0.starts_with("POST /v1/embeddings"));
+        assert_eq!(recorded[1].1["input"], json!(["a", "b"]));
+    }
+
+    #[test]
+    fn test_sse_parser_handles_split_chunks() {
+        let mut parser = SseParser::default();
+        let mut deltas = parser.feed(b"data: {\"choices\":[{\"delta\":{\"content\":\"a\"}}]}\n\ndata: {\"choi").unwrap();
+        deltas.extend(parser.feed(b"ces\":[{\"delta\":{\"content\":\"b\"}}]}\n\ndata: [DONE]\n\n").unwrap());
+
+        assert_eq!(deltas, vec!["a", "b"]);
+        assert!(parser.done);
+    }
+
+    #[test]
+    fn test_sse_parser_keeps_multibyte_characters_split_across_chunks() {
+        let event = "data: {\"choices\":[{\"delta\":{\"content\":\"héllo 🦀\"}}]}\n\n".as_bytes();
+        let split = event.iter().position(|&b| b == 0xC3).unwrap() + 1;
+        let mut parser = SseParser::default();
+        let mut deltas = parser.feed(&event[..split]).unwrap();
+        deltas.extend(parser.feed(&event[split..]).unwrap());
+
+        assert_eq!(deltas, vec!["héllo 🦀"]);
+    }
+}
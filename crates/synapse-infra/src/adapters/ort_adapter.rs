@@ -1,42 +1,70 @@
 //! ORT (ONNX Runtime) adapter for embeddings.
+//!
+//! Texts are embedded in length-bucketed, padded batches (see
+//! [`embedding_batch`](super::embedding_batch)).
 
 use async_trait::async_trait;
 use synapse_core::{Error, EmbeddingPort};
 use ort::session::{Session, builder::GraphOptimizationLevel};
 use std::sync::Arc;
-use tokenizers::Tokenizer;
+use tokenizers::{Encoding, Tokenizer, TruncationParams};
 use ndarray::Array2;
 
 use tokio::sync::Mutex;
 
+use super::embedding_batch::{plan_batches, pool, PaddedBatch, DEFAULT_MAX_BATCH_SIZE};
+use super::model_descriptor::{ModelDescriptor, Pooling};
+
+/// Input limit (including special tokens) when the descriptor does not set one.
+pub const DEFAULT_MAX_SEQUENCE_LENGTH: usize = 512;
+
+/// Provider name of the default model, kept stable for existing stores.
+const DEFAULT_PROVIDER: &str = "ort-minilm-l6-v2";
+
+/// `[CLS]` and `[SEP]` added around every input.
+const SPECIAL_TOKENS: usize = 2;
+
 /// ONNX Runtime adapter for embeddings.
 pub struct OrtAdapter {
     session: Arc<Mutex<Session>>,
     tokenizer: Tokenizer,
+    /// Same tokenizer without truncation, for counting
+    counting_tokenizer: Tokenizer,
+    provider: String,
+    model_id: String,
     dimension: usize,
+    pooling: Pooling,
+    normalize: bool,
+    max_batch_size: usize,
+    max_sequence_length: usize,
 }
 
 
 impl OrtAdapter {
-    /// Create a new ORT adapter.
-    ///
-    /// Requires the model file and tokenizer file to be present.
-    /// For MVP, we expect them at `./models/all-MiniLM-L6-v2/`
+    /// Create a new ORT adapter for the default model (all-MiniLM-L6-v2).
     pub fn new() -> Result<Self, Error> {
-        let model_dir = std::path::Path::new("models/all-MiniLM-L6-v2");
-        let model_path = model_dir.join("model.onnx");
-        let tokenizer_path = model_dir.join("tokenizer.json");
-
-        if !model_path.exists() || !tokenizer_path.exists() {
-            return Err(Error::System(format!(
-                "Embedding model not found at {:?}. Please run 'synapse init' to download models.",
-                model_dir
-            )));
-        }
+        Self::from_descriptor(&ModelDescriptor::minilm())
+    }
 
-        // Load Tokenizer
-        let tokenizer = Tokenizer::from_file(&tokenizer_path)
-            .map_err(|e| Error::System(format!("Failed to load tokenizer: {}", e)))?;
+    /// Create an ORT adapter for the model described by `descriptor`.
+    ///
+    /// Requires the ONNX model file and tokenizer file to be present.
+    pub fn from_descriptor(descriptor: &ModelDescriptor) -> Result<Self, Error> {
+        let model_path = descriptor.resolve_model_file("onnx")?;
+        let tokenizer_path = descriptor.resolve_tokenizer(&model_path)?;
+
+        // Load Tokenizer, truncating to the model's input limit so long text
+        // cannot overflow the position embeddings.
+        let max_sequence_length = descriptor.context_length.unwrap_or(DEFAULT_MAX_SEQUENCE_LENGTH);
+        let counting_tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| Error::System(format!("Failed to load tokenizer {:?}: {}", tokenizer_path, e)))?;
+        let mut tokenizer = counting_tokenizer.clone();
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: max_sequence_length,
+                ..TruncationParams::default()
+            }))
+            .map_err(|e| Error::System(format!("Failed to set truncation: {}", e)))?;
 
         // Load ONNX Session
         let session = Session::builder()
@@ -47,42 +75,56 @@ impl OrtAdapter {
             .map_err(|e| Error::System(format!("Failed to set threads: {}", e)))?
             .commit_from_file(&model_path)
             .map_err(|e| Error::System(format!("Failed to load ONNX model: {}", e)))?;
+        let dimension = output_dimension(&session, descriptor.dimension)?;
+
+        let name = model_path
+            .parent()
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| "onnx".to_string());
+        let provider = match name.as_str() {
+            "all-minilm-l6-v2" => DEFAULT_PROVIDER.to_string(),
+            name => format!("ort-{}", name),
+        };
 
         Ok(Self {
             session: Arc::new(Mutex::new(session)),
             tokenizer,
-            dimension: 384,
+            counting_tokenizer,
+            provider,
+            model_id: ModelDescriptor::model_id(&model_path),
+            dimension,
+            pooling: descriptor.pooling,
+            normalize: descriptor.normalize,
+            max_batch_size: descriptor.max_batch_size.unwrap_or(DEFAULT_MAX_BATCH_SIZE).max(1),
+            max_sequence_length,
         })
 
     }
-}
 
-#[async_trait]
-impl EmbeddingPort for OrtAdapter {
-    async fn embed(&self, text: &str) -> Result<Vec<f32>, Error> {
-        // 1. Tokenize
-        let encoding = self.tokenizer.encode(text, true)
-            .map_err(|e| Error::System(format!("Tokenization failed: {}", e)))?;
+    /// Override the maximum number of texts per session call.
+    pub fn with_max_batch_size(mut self, max_batch_size: usize) -> Self {
+        self.max_batch_size = max_batch_size.max(1);
+        self
+    }
 
-        let input_ids = encoding.get_ids();
-        let attention_mask = encoding.get_attention_mask();
-        let token_type_ids = encoding.get_type_ids();
-        let batch_size = 1;
-        let seq_len = input_ids.len();
+    /// Maximum number of texts per session call.
+    pub fn max_batch_size(&self) -> usize {
+        self.max_batch_size
+    }
 
-        // 2. Prepare Tensors (Convert to i64)
-        let input_ids_i64: Vec<i64> = input_ids.iter().map(|&x| x as i64).collect();
-        let attention_mask_i64: Vec<i64> = attention_mask.iter().map(|&x| x as i64).collect();
-        let token_type_ids_i64: Vec<i64> = token_type_ids.iter().map(|&x| x as i64).collect();
+    /// Run one padded batch through the session and pool the outputs.
+    async fn run_batch(&self, encodings: &[&Encoding]) -> Result<Vec<Vec<f32>>, Error> {
+        let batch = PaddedBatch::new(encodings);
+        let shape = (batch.batch_size, batch.seq_len);
 
-        let input_ids_array = Array2::from_shape_vec((batch_size, seq_len), input_ids_i64)
+        let input_ids_array = Array2::from_shape_vec(shape, batch.input_ids)
             .map_err(|e| Error::System(format!("Shape error: {}", e)))?;
-        let attention_mask_array = Array2::from_shape_vec((batch_size, seq_len), attention_mask_i64)
+        let attention_mask_array = Array2::from_shape_vec(shape, batch.attention_mask.clone())
             .map_err(|e| Error::System(format!("Shape error: {}", e)))?;
-        let token_type_ids_array = Array2::from_shape_vec((batch_size, seq_len), token_type_ids_i64)
+        let token_type_ids_array = Array2::from_shape_vec(shape, batch.token_type_ids)
             .map_err(|e| Error::System(format!("Shape error: {}", e)))?;
 
-        // 3. Run Inference
         let input_ids_val = ort::value::Value::from_array(input_ids_array)
              .map_err(|e| Error::System(format!("ORT value error: {}", e)))?;
         let attention_mask_val = ort::value::Value::from_array(attention_mask_array)
@@ -98,50 +140,64 @@ impl EmbeddingPort for OrtAdapter {
         ])
         .map_err(|e| Error::System(format!("ORT inference failed: {}", e)))?;
 
+        // `last_hidden_state` is [batch, seq_len, dim]
+        let (out_shape, data) = outputs["last_hidden_state"].try_extract_tensor::<f32>()
+            .map_err(|e| Error::System(format!("Failed to extract tensor: {}", e)))?;
+        if out_shape.len() != 3 || out_shape[0] as usize != shape.0 || out_shape[1] as usize != shape.1 {
+            return Err(Error::System(format!(
+                "Unexpected output shape {:?} for a {}x{} batch",
+                out_shape, shape.0, shape.1
+            )));
+        }
+
+        Ok(pool(data, &batch.attention_mask, shape.1, out_shape[2] as usize, self.pooling, self.normalize))
+    }
+}
 
+#[async_trait]
+impl EmbeddingPort for OrtAdapter {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, Error> {
+        let mut embeddings = self.embed_batch(&[text.to_string()]).await?;
+        embeddings
+            .pop()
+            .ok_or_else(|| Error::System("ORT returned no embedding".into()))
+    }
 
-        // 4. Extract Embeddings (Mean Pooling)
-        // For MiniLM, output[0] is 'last_hidden_state'
-        let (shape, data) = outputs["last_hidden_state"].try_extract_tensor::<f32>()
-            .map_err(|e| Error::System(format!("Failed to extract tensor: {}", e)))?;
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, Error> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
 
-        // shape is [1, seq_len, 384]
-        let dim = shape[2] as usize;
-        let seq_len = shape[1] as usize;
-
-        // Perform Mean Pooling manually (simplified)
-        // Sum vectors where attention_mask is 1, then divide by count
-        let mut sum_vec = vec![0.0; dim];
-        let mut count = 0.0;
-
-        for i in 0..seq_len {
-            if attention_mask[i] == 1 {
-                count += 1.0;
-                for j in 0..dim {
-                    // data is [batch, seq, dim], flattened
-                    // index = i * dim + j (since batch=1)
-                    let idx = i * dim + j;
-                    if idx < data.len() {
-                         sum_vec[j] += data[idx];
-                    }
-                }
+        let encodings = self.tokenizer.encode_batch(texts.to_vec(), true)
+            .map_err(|e| Error::System(format!("Tokenization failed: {}", e)))?;
+        let lengths: Vec<usize> = encodings.iter().map(|e| e.get_ids().len()).collect();
+
+        // Batches run in length order; results are written back in input order.
+        let mut results: Vec<Vec<f32>> = vec![Vec::new(); texts.len()];
+        for indices in plan_batches(&lengths, self.max_batch_size) {
+            let batch: Vec<&Encoding> = indices.iter().map(|&i| &encodings[i]).collect();
+            let embeddings = self.run_batch(&batch).await?;
+            for (i, embedding) in indices.into_iter().zip(embeddings) {
+                results[i] = embedding;
             }
         }
+        Ok(results)
+    }
 
-        // Normalize
-        for j in 0..dim {
-            sum_vec[j] /= count;
+    fn count_tokens(&self, text: &str) -> usize {
+        // Counted without truncation so callers can tell when to chunk.
+        match self.counting_tokenizer.encode(text, false) {
+            Ok(encoding) => encoding.get_ids().len(),
+            Err(_) => text.chars().count().div_ceil(4),
         }
+    }
 
-        // L2 Normalization (optional but recommended for cosine similarity)
-        let norm: f32 = sum_vec.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if norm > 0.0 {
-            for j in 0..dim {
-                sum_vec[j] /= norm;
-            }
-        }
+    fn max_input_tokens(&self) -> usize {
+        self.max_sequence_length.saturating_sub(SPECIAL_TOKENS)
+    }
 
-        Ok(sum_vec)
+    fn is_normalized(&self) -> bool {
+        self.normalize
     }
 
     fn dimension(&self) -> usize {
@@ -149,6 +205,34 @@ impl EmbeddingPort for OrtAdapter {
     }
 
     fn provider_name(&self) -> &str {
-        "ort-minilm-l6-v2"
+        &self.provider
+    }
+
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+}
+
+/// Hidden size from the `last_hidden_state` output, checked against the
+/// descriptor's `dimension` when both are known.
+fn output_dimension(session: &Session, configured: Option<usize>) -> Result<usize, Error> {
+    let declared = session
+        .outputs
+        .iter()
+        .find(|output| output.name == "last_hidden_state")
+        .and_then(|output| output.output_type.tensor_shape())
+        .and_then(|shape| shape.last().copied())
+        .filter(|&size| size > 0)
+        .map(|size| size as usize);
+
+    match (declared, configured) {
+        (Some(declared), Some(configured)) if declared != configured => Err(Error::System(format!(
+            "Model outputs dimension {} but the descriptor sets {}",
+            declared, configured
+        ))),
+        (Some(dimension), _) | (None, Some(dimension)) => Ok(dimension),
+        (None, None) => Err(Error::System(
+            "Model has a dynamic hidden size; set `dimension` in the descriptor".into(),
+        )),
     }
 }
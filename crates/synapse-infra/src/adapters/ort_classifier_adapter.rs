@@ -0,0 +1,129 @@
+//! ORT (ONNX Runtime) adapter for prompt-injection classification.
+//!
+//! Expects a sequence-classification export (e.g. a DeBERTa or BERT
+//! prompt-injection model) whose first output holds the logits.
+
+use async_trait::async_trait;
+use ndarray::Array2;
+use ort::session::{builder::GraphOptimizationLevel, Session};
+use synapse_core::{Error, InjectionClassifierPort};
+use tokenizers::{Tokenizer, TruncationParams};
+use tokio::sync::Mutex;
+
+use super::model_descriptor::ModelDescriptor;
+use super::ort_adapter::DEFAULT_MAX_SEQUENCE_LENGTH;
+
+/// Logit index of the injection label in the usual `SAFE`/`INJECTION` export.
+pub const DEFAULT_INJECTION_LABEL: usize = 1;
+
+/// ONNX Runtime prompt-injection classifier.
+pub struct OrtInjectionClassifier {
+    session: Mutex<Session>,
+    tokenizer: Tokenizer,
+    /// Whether the graph takes `token_type_ids` (BERT does, DeBERTa does not)
+    token_type_ids: bool,
+    injection_label: usize,
+}
+
+impl OrtInjectionClassifier {
+    /// Load the classifier described by `descriptor`.
+    ///
+    /// Requires the ONNX model file and tokenizer file to be present.
+    pub fn from_descriptor(descriptor: &ModelDescriptor) -> Result<Self, Error> {
+        let model_path = descriptor.resolve_model_file("onnx")?;
+        let tokenizer_path = descriptor.resolve_tokenizer(&model_path)?;
+
+        let mut tokenizer = Tokenizer::from_file(&tokenizer_path)
+            .map_err(|e| Error::System(format!("Failed to load tokenizer {:?}: {}", tokenizer_path, e)))?;
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: descriptor.context_length.unwrap_or(DEFAULT_MAX_SEQUENCE_LENGTH),
+                ..TruncationParams::default()
+            }))
+            .map_err(|e| Error::System(format!("Failed to set truncation: {}", e)))?;
+
+        let session = Session::builder()
+            .map_err(|e| Error::System(format!("Failed to create ORT builder: {}", e)))?
+            .with_optimization_level(GraphOptimizationLevel::Level3)
+            .map_err(|e| Error::System(format!("Failed to set optimization: {}", e)))?
+            .commit_from_file(&model_path)
+            .map_err(|e| Error::System(format!("Failed to load ONNX model: {}", e)))?;
+        let token_type_ids = session.inputs.iter().any(|input| input.name == "token_type_ids");
+
+        Ok(Self {
+            session: Mutex::new(session),
+            tokenizer,
+            token_type_ids,
+            injection_label: DEFAULT_INJECTION_LABEL,
+        })
+    }
+
+    /// Override which logit is the injection label.
+    pub fn with_injection_label(mut self, label: usize) -> Self {
+        self.injection_label = label;
+        self
+    }
+}
+
+#[async_trait]
+impl InjectionClassifierPort for OrtInjectionClassifier {
+    async fn injection_score(&self, text: &str) -> Result<f32, Error> {
+        let encoding = self.tokenizer.encode(text, true)
+            .map_err(|e| Error::System(format!("Tokenization failed: {}", e)))?;
+        let shape = (1, encoding.get_ids().len());
+        let tensor = |values: &[u32]| {
+            let values = values.iter().map(|&v| v as i64).collect();
+            let array = Array2::from_shape_vec(shape, values)
+                .map_err(|e| Error::System(format!("Shape error: {}", e)))?;
+            ort::value::Value::from_array(array)
+                .map_err(|e| Error::System(format!("ORT value error: {}", e)))
+        };
+
+        let mut inputs = vec![
+            ("input_ids", tensor(encoding.get_ids())?),
+            ("attention_mask", tensor(encoding.get_attention_mask())?),
+        ];
+        if self.token_type_ids {
+            inputs.push(("token_type_ids", tensor(encoding.get_type_ids())?));
+        }
+
+        let mut session = self.session.lock().await;
+        let outputs = session.run(inputs)
+            .map_err(|e| Error::System(format!("ORT inference failed: {}", e)))?;
+        let (_, logits) = outputs[0].try_extract_tensor::<f32>()
+            .map_err(|e| Error::System(format!("Failed to extract tensor: {}", e)))?;
+
+        injection_probability(logits, self.injection_label).ok_or_else(|| {
+            Error::System(format!(
+                "Classifier returned {} logits, expected label {}",
+                logits.len(),
+                self.injection_label
+            ))
+        })
+    }
+}
+
+/// Softmax probability of `label`, or the sigmoid of a single logit.
+fn injection_probability(logits: &[f32], label: usize) -> Option<f32> {
+    if let [logit] = logits {
+        return Some(1.0 / (1.0 + (-logit).exp()));
+    }
+    let target = *logits.get(label)?;
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let sum: f32 = logits.iter().map(|&l| (l - max).exp()).sum();
+    Some((target - max).exp() / sum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_injection_probability() {
+        assert!((injection_probability(&[0.0, 0.0], 1).unwrap() - 0.5).abs() < 1e-6);
+        assert!(injection_probability(&[-4.0, 4.0], 1).unwrap() > 0.99);
+        assert!(injection_probability(&[4.0, -4.0], 1).unwrap() < 0.01);
+        assert!(injection_probability(&[3.0], 0).unwrap() > 0.95);
+        assert!(injection_probability(&[0.0, 1.0], 2).is_none());
+    }
+}
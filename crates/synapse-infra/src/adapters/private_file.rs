@@ -0,0 +1,79 @@
+//! Files only the current user may read: signing keys, node identities
+//! and other local secrets.
+
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+/// Create `path` readable only by the current user and write `contents`.
+///
+/// Fails if the file already exists, so an existing secret is never
+/// overwritten.
+pub fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents)
+}
+
+/// Read the key at `path`, creating a random 32-byte key there (and its
+/// directory) on first use.
+pub fn load_or_create_key(path: &Path) -> std::io::Result<Vec<u8>> {
+    let key = match std::fs::read(path) {
+        Ok(key) => key,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let key: [u8; 32] = rand::random();
+            match write_private(path, &key) {
+                Ok(()) => key.to_vec(),
+                // Another process created it first
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => std::fs::read(path)?,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(e) => return Err(e),
+    };
+    if key.is_empty() {
+        return Err(std::io::Error::new(ErrorKind::InvalidData, format!("{} is empty", path.display())));
+    }
+    Ok(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_private() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("secret");
+        write_private(&path, b"key").unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"key");
+        assert!(write_private(&path, b"other").is_err());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_load_or_create_key() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("keys/review.key");
+        let key = load_or_create_key(&path).unwrap();
+        assert_eq!(key.len(), 32);
+        assert_eq!(load_or_create_key(&path).unwrap(), key);
+
+        std::fs::write(&path, b"").unwrap();
+        assert!(load_or_create_key(&path).is_err());
+    }
+}
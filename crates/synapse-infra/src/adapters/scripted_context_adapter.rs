@@ -0,0 +1,657 @@
+//! Replayable `ContextPort` for simulations and tests.
+//!
+//! A [`Timeline`] is a list of timestamped context changes (active window,
+//! process list, screenshot, input) stored as JSON or YAML.
+//! [`ScriptedContextAdapter`] answers every `ContextPort` call with the state
+//! the timeline describes at the current [`VirtualClock`] time, and
+//! [`ContextRecorder`] samples a real adapter into the same format.
+//!
+//! ```json
+//! {
+//!   "events": [
+//!     { "at_ms": 0, "type": "active_window",
+//!       "window": { "title": "Inbox", "process_name": "thunderbird",
+//!                   "is_visible": true, "bounds": [0, 0, 1280, 800] } },
+//!     { "at_ms": 0, "type": "processes",
+//!       "processes": [{ "pid": 1, "name": "systemd", "cmdline": ["/sbin/init"] }] },
+//!     { "at_ms": 1500, "type": "input", "event": { "KeyPress": "a" } },
+//!     { "at_ms": 5000, "type": "active_window", "window": null }
+//!   ]
+//! }
+//! ```
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use synapse_core::error::{Error, Result};
+use synapse_core::ports::{ContextPort, InputEvent, ProcessInfo, WindowInfo};
+use tracing::debug;
+
+/// Span of input that [`ContextRecorder`] asks the source to score.
+const INPUT_SCORE_WINDOW_MS: u64 = 1000;
+
+/// A change in the observed context.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContextEvent {
+    /// Focus moved (`None` when nothing has focus)
+    ActiveWindow { window: Option<WindowInfo> },
+    /// The full process list
+    Processes { processes: Vec<ProcessInfo> },
+    /// A screenshot, base64-encoded in the file
+    Screen {
+        #[serde(serialize_with = "to_base64", deserialize_with = "from_base64")]
+        image: Vec<u8>,
+    },
+    /// A single input event
+    Input {
+        #[serde(serialize_with = "to_map", deserialize_with = "from_map")]
+        event: InputEvent,
+    },
+    /// Result for `analyze_input_pattern` from here on (default 1.0)
+    InputScore { score: f32 },
+}
+
+/// A [`ContextEvent`] and when it happens, relative to the start of replay.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimedEvent {
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub event: ContextEvent,
+}
+
+/// A recorded or hand-written context session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Timeline {
+    pub events: Vec<TimedEvent>,
+}
+
+impl Timeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, at_ms: u64, event: ContextEvent) {
+        self.events.push(TimedEvent { at_ms, event });
+    }
+
+    /// Time of the last event.
+    pub fn duration_ms(&self) -> u64 {
+        self.events.iter().map(|e| e.at_ms).max().unwrap_or(0)
+    }
+
+    /// Load a timeline; `.yaml`/`.yml` files are read as YAML, anything else as JSON.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            Error::System(format!("Failed to read timeline {}: {}", path.display(), e))
+        })?;
+        let timeline = if is_yaml(path) {
+            serde_yaml::from_str(&text).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(&text).map_err(|e| e.to_string())
+        };
+        timeline.map_err(|e| Error::Validation {
+            message: format!("Invalid timeline {}: {}", path.display(), e),
+        })
+    }
+
+    /// Save a timeline, in YAML if the extension asks for it.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let text = if is_yaml(path) {
+            serde_yaml::to_string(self).map_err(|e| Error::System(e.to_string()))?
+        } else {
+            serde_json::to_string_pretty(self)?
+        };
+        std::fs::write(path, text).map_err(|e| {
+            Error::System(format!(
+                "Failed to write timeline {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+/// Replay time in milliseconds.
+///
+/// Clones share the same time, so a test can keep a handle and advance the
+/// clock of an adapter it has handed out.
+#[derive(Debug, Clone)]
+pub struct VirtualClock(ClockSource);
+
+#[derive(Debug, Clone)]
+enum ClockSource {
+    Manual(Arc<AtomicU64>),
+    Tokio(tokio::time::Instant),
+}
+
+impl VirtualClock {
+    /// A clock that stays at 0 until advanced.
+    pub fn manual() -> Self {
+        Self(ClockSource::Manual(Arc::new(AtomicU64::new(0))))
+    }
+
+    /// A clock that follows tokio time from now on. Under
+    /// `tokio::time::pause` it moves only when the runtime's time does,
+    /// which keeps sleeping loops deterministic.
+    pub fn realtime() -> Self {
+        Self(ClockSource::Tokio(tokio::time::Instant::now()))
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        match &self.0 {
+            ClockSource::Manual(ms) => ms.load(Ordering::SeqCst),
+            ClockSource::Tokio(start) => start.elapsed().as_millis() as u64,
+        }
+    }
+
+    /// Move a manual clock forward. Has no effect on a realtime clock.
+    pub fn advance(&self, by: Duration) {
+        if let ClockSource::Manual(ms) = &self.0 {
+            ms.fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+        }
+    }
+
+    /// Jump a manual clock to `ms`. Has no effect on a realtime clock.
+    pub fn set_ms(&self, at: u64) {
+        if let ClockSource::Manual(ms) = &self.0 {
+            ms.store(at, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        Self::manual()
+    }
+}
+
+/// `ContextPort` that replays a [`Timeline`].
+pub struct ScriptedContextAdapter {
+    events: Vec<TimedEvent>,
+    clock: VirtualClock,
+}
+
+impl ScriptedContextAdapter {
+    /// Replay `timeline` on a manual clock starting at 0.
+    pub fn new(timeline: Timeline) -> Self {
+        let mut events = timeline.events;
+        events.sort_by_key(|e| e.at_ms);
+        Self {
+            events,
+            clock: VirtualClock::manual(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(Timeline::load(path)?))
+    }
+
+    pub fn with_clock(mut self, clock: VirtualClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn clock(&self) -> &VirtualClock {
+        &self.clock
+    }
+
+    /// Whether every event in the timeline has been reached.
+    pub fn is_finished(&self) -> bool {
+        self.events
+            .last()
+            .is_none_or(|last| last.at_ms <= self.clock.now_ms())
+    }
+
+    /// Input events from the last `duration_ms`, oldest first.
+    pub fn input_events(&self, duration_ms: u64) -> Vec<InputEvent> {
+        let now = self.clock.now_ms();
+        let since = now.saturating_sub(duration_ms);
+        self.elapsed()
+            .iter()
+            .filter(|e| e.at_ms >= since)
+            .filter_map(|e| match &e.event {
+                ContextEvent::Input { event } => Some(event.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Events at or before the current time.
+    fn elapsed(&self) -> &[TimedEvent] {
+        let now = self.clock.now_ms();
+        &self.events[..self.events.partition_point(|e| e.at_ms <= now)]
+    }
+
+    /// The most recent event `pick` accepts.
+    fn latest<T>(&self, pick: impl Fn(&ContextEvent) -> Option<T>) -> Option<T> {
+        self.elapsed().iter().rev().find_map(|e| pick(&e.event))
+    }
+}
+
+#[async_trait]
+impl ContextPort for ScriptedContextAdapter {
+    async fn capture_screen(&self) -> Result<Vec<u8>> {
+        Ok(self
+            .latest(|e| match e {
+                ContextEvent::Screen { image } => Some(image.clone()),
+                _ => None,
+            })
+            .unwrap_or_default())
+    }
+
+    async fn get_active_window(&self) -> Result<WindowInfo> {
+        self.latest(|e| match e {
+            ContextEvent::ActiveWindow { window } => Some(window.clone()),
+            _ => None,
+        })
+        .flatten()
+        .ok_or_else(|| Error::NotFound {
+            id: "active window".into(),
+        })
+    }
+
+    async fn get_running_processes(&self) -> Result<Vec<String>> {
+        Ok(self
+            .list_processes()
+            .await?
+            .into_iter()
+            .map(|p| p.name)
+            .collect())
+    }
+
+    async fn list_processes(&self) -> Result<Vec<ProcessInfo>> {
+        Ok(self
+            .latest(|e| match e {
+                ContextEvent::Processes { processes } => Some(processes.clone()),
+                _ => None,
+            })
+            .unwrap_or_default())
+    }
+
+    async fn analyze_input_pattern(&self, _duration_ms: u64) -> Result<f32> {
+        Ok(self
+            .latest(|e| match e {
+                ContextEvent::InputScore { score } => Some(*score),
+                _ => None,
+            })
+            .unwrap_or(1.0))
+    }
+}
+
+/// Samples a live `ContextPort` into a [`Timeline`].
+///
+/// Only changes are recorded: the active window, input score and screenshot
+/// when they differ from the previous sample, and the process list when a
+/// process starts or exits. Individual input events are not visible through
+/// `ContextPort`; the recorder keeps `analyze_input_pattern` as
+/// [`ContextEvent::InputScore`] instead.
+///
+/// A source reporting no focused window (`Error::NotFound`) is recorded as
+/// `window: None`. Any other window, screen or input error (a headless
+/// display, a capture the platform does not support) leaves that part out of
+/// the sample rather than failing the recording.
+pub struct ContextRecorder {
+    source: Arc<dyn ContextPort>,
+    clock: VirtualClock,
+    screens: bool,
+    state: Mutex<RecorderState>,
+}
+
+#[derive(Default)]
+struct RecorderState {
+    timeline: Timeline,
+    window: Option<Option<WindowInfo>>,
+    pids: Option<Vec<u32>>,
+    input_score: Option<f32>,
+    screen: Option<Vec<u8>>,
+}
+
+impl ContextRecorder {
+    pub fn new(source: Arc<dyn ContextPort>) -> Self {
+        Self {
+            source,
+            clock: VirtualClock::realtime(),
+            screens: false,
+            state: Mutex::new(RecorderState::default()),
+        }
+    }
+
+    /// Also record screenshots (large).
+    pub fn with_screens(mut self, screens: bool) -> Self {
+        self.screens = screens;
+        self
+    }
+
+    /// Timestamp samples with `clock` instead of elapsed real time.
+    pub fn with_clock(mut self, clock: VirtualClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Take one sample of the source.
+    pub async fn sample(&self) -> Result<()> {
+        let at_ms = self.clock.now_ms();
+        let window = match self.source.get_active_window().await {
+            Ok(window) => Some(Some(window)),
+            Err(Error::NotFound { .. }) => Some(None),
+            Err(e) => {
+                debug!("Active window not recorded: {}", e);
+                None
+            }
+        };
+        let processes = self.source.list_processes().await?;
+        let input_score = match self.source.analyze_input_pattern(INPUT_SCORE_WINDOW_MS).await {
+            Ok(score) => Some(score),
+            Err(e) => {
+                debug!("Input score not recorded: {}", e);
+                None
+            }
+        };
+        let screen = if self.screens {
+            match self.source.capture_screen().await {
+                Ok(image) => Some(image).filter(|image| !image.is_empty()),
+                Err(e) => {
+                    debug!("Screen not recorded: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(window) = window {
+            if state.window.as_ref() != Some(&window) {
+                state.window = Some(window.clone());
+                state
+                    .timeline
+                    .push(at_ms, ContextEvent::ActiveWindow { window });
+            }
+        }
+        let pids: Vec<u32> = processes.iter().map(|p| p.pid).collect();
+        if state.pids.as_ref() != Some(&pids) {
+            state.pids = Some(pids);
+            state
+                .timeline
+                .push(at_ms, ContextEvent::Processes { processes });
+        }
+        if let Some(score) = input_score {
+            if state.input_score != Some(score) {
+                state.input_score = Some(score);
+                state.timeline.push(at_ms, ContextEvent::InputScore { score });
+            }
+        }
+        if let Some(image) = screen {
+            if state.screen.as_ref() != Some(&image) {
+                state.screen = Some(image.clone());
+                state.timeline.push(at_ms, ContextEvent::Screen { image });
+            }
+        }
+        Ok(())
+    }
+
+    /// Sample every `interval` until `duration` has passed.
+    pub async fn record(&self, interval: Duration, duration: Duration) -> Result<()> {
+        let end = self.clock.now_ms() + duration.as_millis() as u64;
+        loop {
+            self.sample().await?;
+            if self.clock.now_ms() >= end {
+                return Ok(());
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Everything recorded so far.
+    pub fn timeline(&self) -> Timeline {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .timeline
+            .clone()
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|e| e.to_str()),
+        Some("yaml" | "yml")
+    )
+}
+
+fn to_base64<S: Serializer>(bytes: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&BASE64.encode(bytes))
+}
+
+fn from_base64<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<u8>, D::Error> {
+    let encoded = String::deserialize(deserializer)?;
+    BASE64.decode(encoded).map_err(serde::de::Error::custom)
+}
+
+/// Write an enum as a plain map. YAML would otherwise use a `!Tag`, which
+/// cannot be read back inside the internally tagged [`ContextEvent`].
+fn to_map<S: Serializer>(
+    event: &InputEvent,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serde_json::to_value(event)
+        .map_err(serde::ser::Error::custom)?
+        .serialize(serializer)
+}
+
+fn from_map<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<InputEvent, D::Error> {
+    serde_json::from_value(serde_json::Value::deserialize(deserializer)?)
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(title: &str) -> WindowInfo {
+        WindowInfo {
+            title: title.to_string(),
+            process_name: "app".to_string(),
+            is_visible: true,
+            bounds: (0, 0, 800, 600),
+        }
+    }
+
+    fn process(pid: u32, name: &str) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn session() -> Timeline {
+        let mut timeline = Timeline::new();
+        timeline.push(
+            0,
+            ContextEvent::ActiveWindow {
+                window: Some(window("Editor")),
+            },
+        );
+        timeline.push(
+            0,
+            ContextEvent::Processes {
+                processes: vec![process(1, "init")],
+            },
+        );
+        timeline.push(
+            100,
+            ContextEvent::Input {
+                event: InputEvent::KeyPress("a".into()),
+            },
+        );
+        timeline.push(
+            200,
+            ContextEvent::Screen {
+                image: vec![0x89, b'P', b'N', b'G'],
+            },
+        );
+        timeline.push(
+            1000,
+            ContextEvent::Processes {
+                processes: vec![process(1, "init"), process(7, "miner")],
+            },
+        );
+        timeline.push(1000, ContextEvent::InputScore { score: 0.1 });
+        timeline.push(2000, ContextEvent::ActiveWindow { window: None });
+        timeline
+    }
+
+    #[tokio::test]
+    async fn test_replays_timeline_on_virtual_clock() {
+        let adapter = ScriptedContextAdapter::new(session());
+        let clock = adapter.clock().clone();
+
+        assert_eq!(adapter.get_active_window().await.unwrap().title, "Editor");
+        assert_eq!(adapter.get_running_processes().await.unwrap(), vec!["init"]);
+        assert!(adapter.capture_screen().await.unwrap().is_empty());
+        assert_eq!(adapter.analyze_input_pattern(1000).await.unwrap(), 1.0);
+
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(
+            adapter.capture_screen().await.unwrap(),
+            vec![0x89, b'P', b'N', b'G']
+        );
+        assert_eq!(
+            adapter.input_events(1000),
+            vec![InputEvent::KeyPress("a".into())]
+        );
+        assert!(adapter.input_events(100).is_empty());
+
+        clock.set_ms(1000);
+        assert_eq!(
+            adapter.get_running_processes().await.unwrap(),
+            vec!["init", "miner"]
+        );
+        assert_eq!(adapter.analyze_input_pattern(1000).await.unwrap(), 0.1);
+        assert!(!adapter.is_finished());
+
+        clock.advance(Duration::from_secs(5));
+        assert!(adapter.get_active_window().await.is_err());
+        assert!(adapter.is_finished());
+    }
+
+    #[test]
+    fn test_timeline_file_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["session.json", "session.yaml"] {
+            let path = dir.path().join(name);
+            session().save(&path).unwrap();
+            assert_eq!(Timeline::load(&path).unwrap(), session());
+        }
+
+        let path = dir.path().join("hand.json");
+        std::fs::write(
+            &path,
+            r#"{"events": [{"at_ms": 5, "type": "processes", "processes": [{"pid": 3, "name": "sh"}]}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            Timeline::load(&path).unwrap().events[0].event,
+            ContextEvent::Processes {
+                processes: vec![process(3, "sh")]
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recording_replays_identically() {
+        let clock = VirtualClock::manual();
+        let source = Arc::new(ScriptedContextAdapter::new(session()).with_clock(clock.clone()));
+        let recorder = ContextRecorder::new(source.clone())
+            .with_screens(true)
+            .with_clock(clock.clone());
+
+        for _ in 0..30 {
+            recorder.sample().await.unwrap();
+            clock.advance(Duration::from_millis(100));
+        }
+
+        let replay = ScriptedContextAdapter::new(recorder.timeline());
+        for at in (0..3000).step_by(100) {
+            clock.set_ms(at);
+            replay.clock().set_ms(at);
+            assert_eq!(
+                replay.get_active_window().await.ok(),
+                source.get_active_window().await.ok()
+            );
+            assert_eq!(
+                replay.list_processes().await.unwrap(),
+                source.list_processes().await.unwrap()
+            );
+            assert_eq!(
+                replay.capture_screen().await.unwrap(),
+                source.capture_screen().await.unwrap()
+            );
+            assert_eq!(
+                replay.analyze_input_pattern(1000).await.unwrap(),
+                source.analyze_input_pattern(1000).await.unwrap()
+            );
+        }
+        // Unchanged samples are not repeated
+        assert_eq!(recorder.timeline().events.len(), 7);
+    }
+
+    /// A source like a headless Linux host: processes only.
+    struct Headless;
+
+    #[async_trait]
+    impl ContextPort for Headless {
+        async fn capture_screen(&self) -> Result<Vec<u8>> {
+            Err(Error::System("unsupported".into()))
+        }
+
+        async fn get_active_window(&self) -> Result<WindowInfo> {
+            Err(Error::System("No X display available".into()))
+        }
+
+        async fn get_running_processes(&self) -> Result<Vec<String>> {
+            Ok(vec!["init".into()])
+        }
+
+        async fn list_processes(&self) -> Result<Vec<ProcessInfo>> {
+            Ok(vec![process(1, "init")])
+        }
+
+        async fn analyze_input_pattern(&self, _duration_ms: u64) -> Result<f32> {
+            Err(Error::System("unsupported".into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recorder_skips_unavailable_context() {
+        let recorder = ContextRecorder::new(Arc::new(Headless))
+            .with_screens(true)
+            .with_clock(VirtualClock::manual());
+        recorder.sample().await.unwrap();
+
+        // Not a spurious "no window" event, just the processes
+        assert_eq!(
+            recorder.timeline().events,
+            vec![TimedEvent {
+                at_ms: 0,
+                event: ContextEvent::Processes {
+                    processes: vec![process(1, "init")]
+                },
+            }]
+        );
+    }
+}
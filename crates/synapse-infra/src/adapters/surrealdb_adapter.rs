@@ -2,16 +2,26 @@
 //!
 //! Implements the MemoryPort trait using SurrealDB's embedded mode
 //! with vector search, graph relations, and namespace support.
+//! Every write is checked with an [`EmbeddingCheck`], so writers that skip
+//! `GuardedMemory` (e.g. replication) cannot store corrupted embeddings.
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use surrealdb::engine::local::{Db, Mem, SurrealKv};
 use surrealdb::sql::Thing;
 use surrealdb::Surreal;
-use synapse_core::{error::Error, MemoryNode, MemoryPort, NodeType, SearchResult};
+use synapse_core::logic::chunked_memory::{is_chunk, PARENT_ID_KEY};
+use synapse_core::logic::embedding_check::EmbeddingCheck;
+use synapse_core::logic::memory_api::validate_relation;
+use synapse_core::{error::Error, MemoryNode, MemoryPort, NodeType, Relationship, SearchResult};
 use std::sync::Arc;
 use tokio::sync::OnceCell;
 
+/// Tables holding non-edge records (`relationship` is the flat edge index of
+/// older stores). Relations name edge tables, so these are rejected as
+/// relation names.
+const RESERVED_TABLES: &[&str] = &["memory_node", "relationship"];
+
 /// Record type for SurrealDB serialization.
 #[derive(Debug, Serialize, Deserialize)]
 struct MemoryRecord {
@@ -24,6 +34,9 @@ struct MemoryRecord {
     metadata: String, // JSON serialized
     namespace: String,
     source: String,
+    /// Chunk of a larger memory; left out of counts
+    #[serde(default)]
+    chunk: bool,
 }
 
 /// Search result from SurrealDB vector query.
@@ -46,6 +59,7 @@ struct VectorSearchResult {
 pub struct SurrealDbAdapter {
     db: Arc<Surreal<Db>>,
     initialized: OnceCell<()>,
+    embedding_check: EmbeddingCheck,
 }
 
 impl SurrealDbAdapter {
@@ -67,6 +81,7 @@ impl SurrealDbAdapter {
         let adapter = Self {
             db: Arc::new(db),
             initialized: OnceCell::new(),
+            embedding_check: EmbeddingCheck::new(),
         };
 
         adapter.initialize().await?;
@@ -82,12 +97,27 @@ impl SurrealDbAdapter {
         let adapter = Self {
             db: Arc::new(db),
             initialized: OnceCell::new(),
+            embedding_check: EmbeddingCheck::new(),
         };
 
         adapter.initialize().await?;
         Ok(adapter)
     }
 
+    /// Reject writes whose embedding fails `check` (by default: any NaN,
+    /// infinity or all-zero vector). Nodes without an embedding are stored.
+    pub fn with_embedding_check(mut self, check: EmbeddingCheck) -> Self {
+        self.embedding_check = check;
+        self
+    }
+
+    fn validate(&self, node: &MemoryNode) -> Result<(), Error> {
+        if !node.embedding.is_empty() {
+            self.embedding_check.validate(&node.embedding)?;
+        }
+        Ok(())
+    }
+
     /// Initialize namespace, database, and schema.
     async fn initialize(&self) -> Result<(), Error> {
         self.initialized
@@ -114,6 +144,7 @@ impl SurrealDbAdapter {
                         DEFINE FIELD IF NOT EXISTS metadata ON memory_node TYPE string;
                         DEFINE FIELD IF NOT EXISTS namespace ON memory_node TYPE string;
                         DEFINE FIELD IF NOT EXISTS source ON memory_node TYPE string;
+                        DEFINE FIELD IF NOT EXISTS chunk ON memory_node TYPE bool DEFAULT false;
 
                         DEFINE INDEX IF NOT EXISTS idx_layer ON memory_node FIELDS layer;
                         DEFINE INDEX IF NOT EXISTS idx_namespace ON memory_node FIELDS namespace;
@@ -124,6 +155,14 @@ impl SurrealDbAdapter {
                     .await
                     .map_err(|e| Error::System(format!("Failed to create schema: {}", e)))?;
 
+                // Flag chunks stored before the `chunk` field existed
+                self.db
+                    .query("UPDATE memory_node SET chunk = string::contains(metadata, $key) WHERE chunk IS NONE")
+                    .bind(("key", format!("\"{}\":", PARENT_ID_KEY)))
+                    .await
+                    .and_then(|response| response.check())
+                    .map_err(|e| Error::System(format!("Failed to flag chunk nodes: {}", e)))?;
+
                 Ok::<(), Error>(())
             })
             .await?;
@@ -143,6 +182,7 @@ impl SurrealDbAdapter {
             metadata: serde_json::to_string(&node.metadata).unwrap_or_default(),
             namespace: node.namespace.clone(),
             source: node.source.clone(),
+            chunk: is_chunk(node),
         }
     }
 
@@ -174,6 +214,7 @@ impl SurrealDbAdapter {
 #[async_trait]
 impl MemoryPort for SurrealDbAdapter {
     async fn store(&self, node: MemoryNode) -> Result<String, Error> {
+        self.validate(&node)?;
         let record = Self::node_to_record(&node);
 
         let result: Option<MemoryRecord> = self
@@ -417,7 +458,54 @@ impl MemoryPort for SurrealDbAdapter {
             .collect())
     }
 
+    async fn get_by_namespace(&self, namespace: &str) -> Result<Vec<MemoryNode>, Error> {
+        let mut response = self
+            .db
+            .query("SELECT * FROM memory_node WHERE namespace = $namespace")
+            .bind(("namespace", namespace.to_string()))
+            .await
+            .map_err(|e| Error::System(format!("Get by namespace failed: {}", e)))?;
+
+        #[derive(Deserialize)]
+        struct NamespaceResult {
+            id: Thing,
+            content: String,
+            layer: u8,
+            node_type: String,
+            created_at: i64,
+            updated_at: i64,
+            embedding: Vec<f32>,
+            metadata: String,
+            namespace: String,
+            source: String,
+        }
+
+        let results: Vec<NamespaceResult> = response
+            .take(0)
+            .map_err(|e| Error::System(format!("Failed to parse namespace results: {}", e)))?;
+
+        Ok(results
+            .into_iter()
+            .map(|r| {
+                let record = MemoryRecord {
+                    content: r.content,
+                    layer: r.layer,
+                    node_type: r.node_type,
+                    created_at: r.created_at,
+                    updated_at: r.updated_at,
+                    embedding: r.embedding,
+                    metadata: r.metadata,
+                    namespace: r.namespace,
+                    source: r.source,
+                    chunk: false,
+                };
+                Self::record_to_node(r.id.id.to_string(), &record)
+            })
+            .collect())
+    }
+
     async fn update(&self, node: MemoryNode) -> Result<(), Error> {
+        self.validate(&node)?;
         let record = Self::node_to_record(&node);
 
         let _: Option<MemoryRecord> = self
@@ -431,6 +519,13 @@ impl MemoryPort for SurrealDbAdapter {
     }
 
     async fn delete(&self, id: &str) -> Result<(), Error> {
+        self.db
+            .query("LET $node = type::thing('memory_node', $id); LET $edges = array::union($node->?, $node<-?); DELETE $edges")
+            .bind(("id", id.to_string()))
+            .await
+            .and_then(|response| response.check())
+            .map_err(|e| Error::System(format!("Failed to delete relationships: {}", e)))?;
+
         let _: Option<MemoryRecord> = self
             .db
             .delete(("memory_node", id))
@@ -443,7 +538,7 @@ impl MemoryPort for SurrealDbAdapter {
     async fn count(&self) -> Result<usize, Error> {
         let mut response = self
             .db
-            .query("SELECT count() FROM memory_node GROUP ALL")
+            .query("SELECT count() FROM memory_node WHERE chunk != true GROUP ALL")
             .await
             .map_err(|e| Error::System(format!("Count failed: {}", e)))?;
 
@@ -460,25 +555,57 @@ impl MemoryPort for SurrealDbAdapter {
     }
 
     async fn add_relationship(&self, from_id: &str, relation: &str, to_id: &str) -> Result<(), Error> {
-        // Use backtick-escaped record IDs for UUIDs with hyphens
-        // SurrealDB syntax: RELATE memory_node:`uuid`->relation->memory_node:`uuid`
+        // The edge table is named after the relation, so it is spliced into
+        // the query: it must be a plain identifier and not one of the
+        // adapter's own tables. The record ids are bound.
+        validate_relation(relation)?;
+        if RESERVED_TABLES.contains(&relation) {
+            return Err(Error::Validation {
+                message: format!("'{}' is reserved and cannot be used as a relation", relation),
+            });
+        }
+
         let query = format!(
-            "RELATE memory_node:`{}`->{}->memory_node:`{}`",
-            from_id, relation, to_id
+            "LET $from = type::thing('memory_node', $from_id); \
+             LET $to = type::thing('memory_node', $to_id); \
+             RELATE $from->{}->$to",
+            relation
         );
 
         self.db
-            .query(&query)
+            .query(query)
+            .bind(("from_id", from_id.to_string()))
+            .bind(("to_id", to_id.to_string()))
             .await
+            .and_then(|response| response.check())
             .map_err(|e| Error::System(format!("Failed to create relationship: {}", e)))?;
 
         Ok(())
     }
 
+    async fn get_related(&self, id: &str) -> Result<Vec<Relationship>, Error> {
+        // Every edge table, in either direction: the relation is the table name.
+        let mut response = self
+            .db
+            .query(
+                "LET $node = type::thing('memory_node', $id); \
+                 SELECT record::id(in) AS from_id, record::tb(id) AS relation, record::id(out) AS to_id \
+                 FROM array::union($node->?, $node<-?)",
+            )
+            .bind(("id", id.to_string()))
+            .await
+            .and_then(|response| response.check())
+            .map_err(|e| Error::System(format!("Get related failed: {}", e)))?;
+
+        response
+            .take(1)
+            .map_err(|e| Error::System(format!("Failed to parse relationships: {}", e)))
+    }
+
     async fn count_by_layer(&self, layer: u8) -> Result<usize, Error> {
         let mut response = self
             .db
-            .query("SELECT count() FROM memory_node WHERE layer = $layer GROUP ALL")
+            .query("SELECT count() FROM memory_node WHERE layer = $layer AND chunk != true GROUP ALL")
             .bind(("layer", layer as i64))
             .await
             .map_err(|e| Error::System(format!("Count by layer failed: {}", e)))?;
@@ -494,6 +621,27 @@ impl MemoryPort for SurrealDbAdapter {
 
         Ok(result.map(|r| r.count).unwrap_or(0))
     }
+
+    async fn list_namespaces(&self) -> Result<Vec<String>, Error> {
+        let mut response = self
+            .db
+            .query("SELECT namespace FROM memory_node GROUP BY namespace")
+            .await
+            .map_err(|e| Error::System(format!("List namespaces failed: {}", e)))?;
+
+        #[derive(Deserialize)]
+        struct NamespaceResult {
+            namespace: String,
+        }
+
+        let results: Vec<NamespaceResult> = response
+            .take(0)
+            .map_err(|e| Error::System(format!("Failed to parse namespaces: {}", e)))?;
+
+        let mut namespaces: Vec<String> = results.into_iter().map(|r| r.namespace).collect();
+        namespaces.sort();
+        Ok(namespaces)
+    }
 }
 
 #[cfg(test)]
@@ -521,6 +669,29 @@ mod tests {
         assert_eq!(retrieved.unwrap().content, "Test content");
     }
 
+    #[tokio::test]
+    async fn test_rejects_invalid_embedding() {
+        let adapter = SurrealDbAdapter::new_memory()
+            .await
+            .unwrap()
+            .with_embedding_check(EmbeddingCheck {
+                dimension: Some(3),
+                ..EmbeddingCheck::new()
+            });
+
+        for embedding in [vec![f32::NAN, 0.2, 0.3], vec![0.0; 3], vec![0.1, 0.2]] {
+            let node = MemoryNode::new("Test".to_string()).with_embedding(embedding);
+            assert!(adapter.store(node).await.is_err());
+        }
+        let node = MemoryNode::new("Test".to_string()).with_embedding(vec![0.1, 0.2, 0.3]);
+        let id = adapter.store(node.clone()).await.unwrap();
+        let corrupted = MemoryNode { embedding: vec![f32::INFINITY, 0.2, 0.3], ..node };
+        assert!(adapter.update(corrupted).await.is_err());
+        let stored = adapter.get_by_id(&id).await.unwrap().unwrap();
+        assert!(stored.embedding.iter().all(|x| x.is_finite()));
+        assert_eq!(adapter.count().await.unwrap(), 1);
+    }
+
     #[tokio::test]
     async fn test_count() {
         let adapter = SurrealDbAdapter::new_memory().await.unwrap();
@@ -612,6 +783,12 @@ mod tests {
             .unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].node.namespace, "personal");
+
+        assert_eq!(adapter.list_namespaces().await.unwrap(), vec!["orionhealth", "personal"]);
+
+        let nodes = adapter.get_by_namespace("orionhealth").await.unwrap();
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].content, "Medical data");
     }
 
     #[tokio::test]
@@ -629,6 +806,30 @@ mod tests {
             .add_relationship(&summary_id, "summarizes", &fact_id)
             .await
             .unwrap();
+
+        let expected = vec![Relationship {
+            from_id: summary_id.clone(),
+            relation: "summarizes".to_string(),
+            to_id: fact_id.clone(),
+        }];
+        assert_eq!(adapter.get_related(&fact_id).await.unwrap(), expected);
+        assert_eq!(adapter.get_related(&summary_id).await.unwrap(), expected);
+
+        adapter.delete(&fact_id).await.unwrap();
+        assert!(adapter.get_related(&summary_id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reserved_and_malformed_relations_are_rejected() {
+        let adapter = SurrealDbAdapter::new_memory().await.unwrap();
+        let a = adapter.store(MemoryNode::new("A".to_string())).await.unwrap();
+        let b = adapter.store(MemoryNode::new("B".to_string())).await.unwrap();
+
+        for relation in ["memory_node", "relationship", "rel->memory_node:x; DELETE memory_node"] {
+            let result = adapter.add_relationship(&a, relation, &b).await;
+            assert!(matches!(result, Err(Error::Validation { .. })), "{} accepted", relation);
+        }
+        assert_eq!(adapter.count().await.unwrap(), 2);
     }
 
     #[tokio::test]
@@ -657,4 +858,19 @@ mod tests {
         assert_eq!(adapter.count_by_layer(1).await.unwrap(), 1);
         assert_eq!(adapter.count_by_layer(2).await.unwrap(), 0);
     }
+
+    #[tokio::test]
+    async fn test_counts_skip_chunks() {
+        let adapter = SurrealDbAdapter::new_memory().await.unwrap();
+
+        let parent = MemoryNode::new("Long text".to_string()).with_embedding(vec![0.1, 0.2, 0.3]);
+        let chunk = MemoryNode::new("Long".to_string())
+            .with_embedding(vec![0.1, 0.2, 0.3])
+            .with_metadata(PARENT_ID_KEY, serde_json::json!(parent.id));
+        adapter.store(parent).await.unwrap();
+        adapter.store(chunk).await.unwrap();
+
+        assert_eq!(adapter.count().await.unwrap(), 1);
+        assert_eq!(adapter.count_by_layer(0).await.unwrap(), 1);
+    }
 }
@@ -0,0 +1,259 @@
+//! P2P node configuration.
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use libp2p::{identity::Keypair, Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+use synapse_core::logic::threat_db::ThreatDatabase;
+use synapse_core::{ImmunePort, MemoryPort};
+
+use crate::device_key::DeviceKey;
+use crate::error::P2pError;
+use crate::node::{P2pHandle, P2pNode};
+use crate::replicated::ReplicatedMemory;
+use crate::threat::CollectiveImmune;
+
+/// Configuration for a [`P2pNode`](crate::P2pNode).
+///
+/// Only namespaces added with [`with_namespace`](Self::with_namespace) are
+/// replicated; everything else stays on this device.
+#[derive(Clone)]
+pub struct P2pConfig {
+    /// Key shared by the user's paired devices
+    pub device_key: DeviceKey,
+    /// This device's libp2p identity (loaded from `state_dir`, or generated
+    /// if `None`)
+    pub keypair: Option<Keypair>,
+    /// Where replica state and the identity are kept across restarts
+    pub state_dir: Option<PathBuf>,
+    /// Addresses to listen on
+    pub listen: Vec<Multiaddr>,
+    /// Devices to dial at startup (e.g. across subnets where mDNS can't reach)
+    pub peers: Vec<Multiaddr>,
+    /// Namespaces opted into replication
+    pub namespaces: HashSet<String>,
+    /// Discover devices on the local network
+    pub mdns: bool,
+    /// Gossipsub heartbeat interval
+    pub heartbeat: Duration,
+    /// Share threat reports and merge peers' reports into this database
+    pub threat_db: Option<Arc<ThreatDatabase>>,
+    /// Peers whose threat reports corroborate each other, besides the
+    /// paired devices seen on a replicated namespace
+    pub trusted_reporters: HashSet<PeerId>,
+}
+
+impl P2pConfig {
+    /// Listen on all interfaces with an OS-assigned port and mDNS enabled.
+    pub fn new(device_key: DeviceKey) -> Self {
+        Self {
+            device_key,
+            keypair: None,
+            state_dir: None,
+            listen: vec!["/ip4/0.0.0.0/tcp/0".parse().expect("valid multiaddr")],
+            peers: Vec::new(),
+            namespaces: HashSet::new(),
+            mdns: true,
+            heartbeat: Duration::from_secs(1),
+            threat_db: None,
+            trusted_reporters: HashSet::new(),
+        }
+    }
+
+    pub fn with_keypair(mut self, keypair: Keypair) -> Self {
+        self.keypair = Some(keypair);
+        self
+    }
+
+    /// Keep replica state and the node identity in `dir`, e.g. next to the
+    /// memory store. Without it both are lost on restart, so deletes made
+    /// just before a restart can come back from a peer.
+    pub fn with_state_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.state_dir = Some(dir.into());
+        self
+    }
+
+    /// Replace the listen addresses.
+    pub fn with_listen(mut self, addrs: Vec<Multiaddr>) -> Self {
+        self.listen = addrs;
+        self
+    }
+
+    /// Add a device to dial at startup.
+    pub fn with_peer(mut self, addr: Multiaddr) -> Self {
+        self.peers.push(addr);
+        self
+    }
+
+    /// Opt a namespace into replication.
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespaces.insert(namespace.into());
+        self
+    }
+
+    pub fn with_mdns(mut self, enabled: bool) -> Self {
+        self.mdns = enabled;
+        self
+    }
+
+    pub fn with_heartbeat(mut self, heartbeat: Duration) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// Join the shared threat-intelligence topic. Reports from peers are
+    /// recorded in `db`, which should be the one the immune adapter scans with.
+    pub fn with_threat_sharing(mut self, db: Arc<ThreatDatabase>) -> Self {
+        self.threat_db = Some(db);
+        self
+    }
+
+    /// Let `peer`'s threat reports count towards corroboration.
+    pub fn with_trusted_reporter(mut self, peer: PeerId) -> Self {
+        self.trusted_reporters.insert(peer);
+        self
+    }
+}
+
+/// The `p2p` section of `synapse.json`, shared by the server and the CLI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct P2pSettings {
+    /// Hex key shared by the user's paired devices (see [`DeviceKey`])
+    pub device_key: String,
+
+    /// Namespaces replicated to the other devices
+    #[serde(default)]
+    pub namespaces: Vec<String>,
+
+    /// Multiaddrs to listen on; all interfaces on any port if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub listen: Vec<String>,
+
+    /// Multiaddrs of devices to dial at startup
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub peers: Vec<String>,
+
+    /// Discover devices on the local network
+    #[serde(default = "default_mdns")]
+    pub mdns: bool,
+
+    /// Share threat reports on the network-wide topic
+    #[serde(default)]
+    pub share_threats: bool,
+
+    /// Peer ids whose threat reports corroborate, besides paired devices
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_reporters: Vec<String>,
+}
+
+fn default_mdns() -> bool {
+    true
+}
+
+/// Memory and immune ports routed through a running node.
+pub struct Replication {
+    /// Publishes local writes in replicated namespaces
+    pub memory: Arc<dyn MemoryPort>,
+    /// Shares reported threats when threat sharing is on
+    pub immune: Arc<dyn ImmunePort>,
+    pub handle: P2pHandle,
+}
+
+impl P2pSettings {
+    /// Node configuration keeping its state in `state_dir`; peer threat
+    /// reports go into `threats` when sharing is on.
+    pub fn to_config(
+        &self,
+        state_dir: impl Into<PathBuf>,
+        threats: Arc<ThreatDatabase>,
+    ) -> Result<P2pConfig, P2pError> {
+        let mut config = P2pConfig::new(DeviceKey::from_hex(&self.device_key)?)
+            .with_state_dir(state_dir)
+            .with_mdns(self.mdns);
+        if !self.listen.is_empty() {
+            config = config.with_listen(parse_all(&self.listen, "listen address")?);
+        }
+        for peer in parse_all(&self.peers, "peer address")? {
+            config = config.with_peer(peer);
+        }
+        for namespace in &self.namespaces {
+            config = config.with_namespace(namespace.clone());
+        }
+        if self.share_threats {
+            config = config.with_threat_sharing(threats);
+            for reporter in parse_all(&self.trusted_reporters, "trusted reporter")? {
+                config = config.with_trusted_reporter(reporter);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Start the node and route `memory` and `immune` through it.
+    ///
+    /// `memory` receives the peers' changes, so it should screen writes
+    /// (see [`P2pNode::spawn`]). `threats` should be the database `immune`
+    /// scans with.
+    pub fn start(
+        &self,
+        state_dir: impl Into<PathBuf>,
+        memory: Arc<dyn MemoryPort>,
+        immune: Arc<dyn ImmunePort>,
+        threats: Arc<ThreatDatabase>,
+    ) -> Result<Replication, P2pError> {
+        let handle = P2pNode::spawn(self.to_config(state_dir, threats)?, memory.clone())?;
+        let immune: Arc<dyn ImmunePort> = if self.share_threats {
+            Arc::new(CollectiveImmune::new(immune, handle.clone()))
+        } else {
+            immune
+        };
+        Ok(Replication {
+            memory: Arc::new(ReplicatedMemory::new(memory, handle.clone())),
+            immune,
+            handle,
+        })
+    }
+}
+
+fn parse_all<T: std::str::FromStr>(values: &[String], what: &str) -> Result<Vec<T>, P2pError> {
+    values
+        .iter()
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| P2pError::Network(format!("invalid {}: {}", what, value)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_from_json() {
+        let key = DeviceKey::generate();
+        let json = format!(
+            r#"{{"device_key": "{}", "namespaces": ["notes"], "peers": ["/ip4/10.0.0.2/tcp/4001"], "share_threats": true}}"#,
+            key.to_hex()
+        );
+        let settings: P2pSettings = serde_json::from_str(&json).unwrap();
+        assert!(settings.mdns);
+
+        let config = settings
+            .to_config("/tmp/p2p", Arc::new(ThreatDatabase::new()))
+            .unwrap();
+        assert_eq!(config.device_key, key);
+        assert!(config.namespaces.contains("notes"));
+        assert_eq!(config.peers.len(), 1);
+        assert!(config.threat_db.is_some());
+
+        let bad = P2pSettings {
+            peers: vec!["not an address".to_string()],
+            ..settings
+        };
+        assert!(bad.to_config("/tmp/p2p", Arc::new(ThreatDatabase::new())).is_err());
+    }
+}
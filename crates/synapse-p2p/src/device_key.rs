@@ -0,0 +1,142 @@
+//! Shared device key.
+//!
+//! Every device the user pairs holds the same 32-byte key (exchanged out of
+//! band as hex). It derives the gossip topics and encrypts every message with
+//! AES-256-GCM, so peers without the key can neither read nor forge updates.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use sha2::{Digest, Sha256};
+
+use crate::error::P2pError;
+
+const NONCE_LEN: usize = 12;
+
+/// Key shared by a user's paired devices.
+#[derive(Clone, PartialEq, Eq)]
+pub struct DeviceKey([u8; 32]);
+
+impl std::fmt::Debug for DeviceKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DeviceKey({})", self.network_id())
+    }
+}
+
+impl DeviceKey {
+    /// Generate a key for a new set of devices.
+    pub fn generate() -> Self {
+        Self(Aes256Gcm::generate_key(&mut OsRng).into())
+    }
+
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    /// Parse the 64-character hex form used for pairing.
+    pub fn from_hex(hex: &str) -> Result<Self, P2pError> {
+        let hex = hex.trim();
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(P2pError::DeviceKey(
+                "expected 64 hex characters".to_string(),
+            ));
+        }
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                .map_err(|_| P2pError::DeviceKey("expected 64 hex characters".to_string()))?;
+        }
+        Ok(Self(bytes))
+    }
+
+    /// Hex form to copy onto another device.
+    pub fn to_hex(&self) -> String {
+        self.0.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Public identifier of this device group (safe to log).
+    pub fn network_id(&self) -> String {
+        self.derive(b"synapse-p2p/network")[..8]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Gossip topic for a namespace. The namespace name is hashed so it is
+    /// not visible to other peers on the network.
+    pub fn topic(&self, namespace: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.derive(b"synapse-p2p/topic"));
+        hasher.update(namespace.as_bytes());
+        let digest: String = hasher.finalize()[..16]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        format!("synapse/memory/{}", digest)
+    }
+
+    /// Encrypt `plaintext` as `nonce || ciphertext`.
+    pub fn seal(&self, plaintext: &[u8]) -> Vec<u8> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, plaintext)
+            .expect("AES-GCM encryption of an in-memory buffer cannot fail");
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        sealed
+    }
+
+    /// Decrypt and authenticate a sealed message.
+    pub fn open(&self, sealed: &[u8]) -> Result<Vec<u8>, P2pError> {
+        if sealed.len() < NONCE_LEN {
+            return Err(P2pError::InvalidMessage("message too short".to_string()));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        self.cipher()
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| P2pError::InvalidMessage("not sealed with this device key".to_string()))
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        let key = self.derive(b"synapse-p2p/encryption");
+        Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+    }
+
+    fn derive(&self, label: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(label);
+        hasher.update(self.0);
+        hasher.finalize().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_open_round_trip() {
+        let key = DeviceKey::generate();
+        let sealed = key.seal(b"hello");
+        assert_ne!(&sealed[NONCE_LEN..], b"hello");
+        assert_eq!(key.open(&sealed).unwrap(), b"hello");
+
+        // Other keys and tampered messages are rejected
+        assert!(DeviceKey::generate().open(&sealed).is_err());
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(key.open(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_hex_and_topics() {
+        let key = DeviceKey::generate();
+        assert_eq!(DeviceKey::from_hex(&key.to_hex()).unwrap(), key);
+        assert!(DeviceKey::from_hex("abc").is_err());
+
+        assert_eq!(key.topic("work"), key.topic("work"));
+        assert_ne!(key.topic("work"), key.topic("personal"));
+        assert_ne!(key.topic("work"), DeviceKey::generate().topic("work"));
+        assert!(!key.topic("work").contains("work"));
+    }
+}
@@ -0,0 +1,33 @@
+//! P2P errors.
+
+use thiserror::Error;
+
+/// Errors from the p2p layer.
+#[derive(Error, Debug)]
+pub enum P2pError {
+    /// Malformed or wrong device key
+    #[error("Device key error: {0}")]
+    DeviceKey(String),
+
+    /// Message could not be decrypted or decoded
+    #[error("Invalid message: {0}")]
+    InvalidMessage(String),
+
+    /// libp2p transport or behaviour setup failed
+    #[error("Network error: {0}")]
+    Network(String),
+
+    /// Replica state or identity could not be read or written
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    /// The node's event loop has stopped
+    #[error("P2P node is not running")]
+    Stopped,
+}
+
+impl From<P2pError> for synapse_core::error::Error {
+    fn from(error: P2pError) -> Self {
+        synapse_core::error::Error::System(error.to_string())
+    }
+}
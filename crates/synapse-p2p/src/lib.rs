@@ -0,0 +1,350 @@
+//! # Synapse P2P
+//!
+//! Replicates memory between a user's own devices over libp2p.
+//!
+//! - Devices find each other with mDNS on the LAN or explicit multiaddrs.
+//! - All devices share a [`DeviceKey`]; every message is sealed with it, so
+//!   only paired devices can read or inject changes.
+//! - Only namespaces opted in through [`P2pConfig::with_namespace`] leave
+//!   the device. Nodes, deletes and graph edges are replicated.
+//! - Concurrent edits merge field by field through the CRDTs in
+//!   `synapse_core::logic::crdt`; a device that was offline catches up by
+//!   exchanging deltas since its version vector.
+//! - With [`P2pConfig::with_state_dir`], replica state (including delete
+//!   tombstones) and the device identity survive restarts.
+//! - Optionally, signed threat reports are shared on a network-wide topic
+//!   (see [`threat`]).
+//!
+//! The server and CLI read a [`P2pSettings`] from the `p2p` section of
+//! `synapse.json` and start the node with [`P2pSettings::start`].
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use synapse_core::MemoryPort;
+//! # use synapse_p2p::{DeviceKey, P2pConfig, P2pNode, ReplicatedMemory};
+//! # fn example(store: Arc<dyn MemoryPort>, hex: &str) -> Result<(), synapse_p2p::P2pError> {
+//! let config = P2pConfig::new(DeviceKey::from_hex(hex)?).with_namespace("notes");
+//! let handle = P2pNode::spawn(config, store.clone())?;
+//! let memory = ReplicatedMemory::new(store, handle);
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod config;
+pub mod device_key;
+pub mod error;
+pub mod message;
+pub mod node;
+pub mod replica_store;
+pub mod replicated;
+pub mod threat;
+
+pub use config::{P2pConfig, P2pSettings, Replication};
+pub use device_key::DeviceKey;
+pub use error::P2pError;
+pub use message::{SyncMessage, SyncOp, SyncPayload};
+pub use node::{P2pHandle, P2pNode};
+pub use replica_store::ReplicaStore;
+pub use replicated::ReplicatedMemory;
+pub use threat::CollectiveImmune;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use synapse_core::{MemoryNode, MemoryPort};
+    use synapse_infra::adapters::surrealdb_adapter::SurrealDbAdapter;
+
+    async fn wait_for<F, Fut>(mut check: F) -> bool
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = bool>,
+    {
+        for _ in 0..100 {
+            if check().await {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        false
+    }
+
+    async fn start(
+        key: &DeviceKey,
+        peer: Option<libp2p::Multiaddr>,
+    ) -> (ReplicatedMemory, Arc<dyn MemoryPort>) {
+        let store: Arc<dyn MemoryPort> = Arc::new(SurrealDbAdapter::new_memory().await.unwrap());
+        start_on(store, key, peer)
+    }
+
+    fn start_on(
+        store: Arc<dyn MemoryPort>,
+        key: &DeviceKey,
+        peer: Option<libp2p::Multiaddr>,
+    ) -> (ReplicatedMemory, Arc<dyn MemoryPort>) {
+        let mut config = P2pConfig::new(key.clone())
+            .with_listen(vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()])
+            .with_mdns(false)
+            .with_heartbeat(Duration::from_millis(100))
+            .with_namespace("shared");
+        if let Some(peer) = peer {
+            config = config.with_peer(peer);
+        }
+        let handle = P2pNode::spawn(config, store.clone()).unwrap();
+        (ReplicatedMemory::new(store.clone(), handle), store)
+    }
+
+    /// Start two nodes, the second dialing the first over localhost TCP.
+    async fn pair(
+        key_a: &DeviceKey,
+        key_b: &DeviceKey,
+    ) -> (ReplicatedMemory, ReplicatedMemory, Arc<dyn MemoryPort>) {
+        let (a, _) = start(key_a, None).await;
+        let handle = a.handle().clone();
+        assert!(wait_for(|| async { !handle.listen_addrs().await.unwrap().is_empty() }).await);
+        let addr = a.handle().listen_addrs().await.unwrap()[0].clone();
+
+        let (b, b_store) = start(key_b, Some(addr)).await;
+        (a, b, b_store)
+    }
+
+    #[tokio::test]
+    async fn test_two_nodes_replicate_over_tcp() {
+        let key = DeviceKey::generate();
+        let (a, b, b_store) = pair(&key, &key).await;
+        let (ha, hb) = (a.handle().clone(), b.handle().clone());
+        assert!(wait_for(|| async { ha.topic_peers("shared").await.unwrap() == 1 }).await);
+        assert!(wait_for(|| async { hb.topic_peers("shared").await.unwrap() == 1 }).await);
+
+        let fact = MemoryNode::new("Replicated fact".to_string())
+            .with_namespace("shared".to_string())
+            .with_embedding(vec![0.1; 4]);
+        let fact_id = a.store(fact).await.unwrap();
+        let summary = MemoryNode::with_layer("Summary".to_string(), 1)
+            .with_namespace("shared".to_string())
+            .with_embedding(vec![0.2; 4]);
+        let summary_id = a.store(summary).await.unwrap();
+        a.add_relationship(&summary_id, "summarizes", &fact_id)
+            .await
+            .unwrap();
+
+        let replicated =
+            wait_for(|| async { !b_store.get_related(&summary_id).await.unwrap().is_empty() })
+                .await;
+        assert!(replicated, "edge did not reach the second node");
+        let copy = b_store.get_by_id(&fact_id).await.unwrap().unwrap();
+        assert_eq!(copy.content, "Replicated fact");
+
+        // Private namespaces stay local
+        let private = MemoryNode::new("Private".to_string()).with_namespace("private".to_string());
+        let private_id = a.store(private).await.unwrap();
+
+        // Deletes flow the other way too
+        b.delete(&fact_id).await.unwrap();
+        assert!(wait_for(|| async { a.get_by_id(&fact_id).await.unwrap().is_none() }).await);
+        assert!(b_store.get_by_id(&private_id).await.unwrap().is_none());
+
+        ha.shutdown().await;
+        hb.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_late_device_catches_up() {
+        let key = DeviceKey::generate();
+        let (a, _) = start(&key, None).await;
+        let handle = a.handle().clone();
+        assert!(wait_for(|| async { !handle.listen_addrs().await.unwrap().is_empty() }).await);
+
+        // Written while no other device is online
+        let node =
+            MemoryNode::new("Written offline".to_string()).with_namespace("shared".to_string());
+        let id = a.store(node).await.unwrap();
+        let mut edited = a.get_by_id(&id).await.unwrap().unwrap();
+        edited.content = "Edited offline".to_string();
+        a.update(edited).await.unwrap();
+
+        let addr = handle.listen_addrs().await.unwrap()[0].clone();
+        let (_b, b_store) = start(&key, Some(addr)).await;
+        let synced = wait_for(|| async {
+            b_store
+                .get_by_id(&id)
+                .await
+                .unwrap()
+                .is_some_and(|n| n.content == "Edited offline")
+        })
+        .await;
+        assert!(synced, "second node did not catch up");
+    }
+
+    #[tokio::test]
+    async fn test_catch_up_larger_than_one_message() {
+        let key = DeviceKey::generate();
+        let (a, _) = start(&key, None).await;
+        let handle = a.handle().clone();
+        assert!(wait_for(|| async { !handle.listen_addrs().await.unwrap().is_empty() }).await);
+
+        // Together well over the 1 MiB message limit
+        let mut ids = Vec::new();
+        for i in 0..8 {
+            let content = format!("{}{}", i, "x".repeat(200 * 1024));
+            let node = MemoryNode::new(content).with_namespace("shared".to_string());
+            ids.push(a.store(node).await.unwrap());
+        }
+
+        let addr = handle.listen_addrs().await.unwrap()[0].clone();
+        let (_b, b_store) = start(&key, Some(addr)).await;
+        let synced = wait_for(|| async { b_store.count().await.unwrap() == ids.len() }).await;
+        assert!(synced, "large catch-up did not arrive in parts");
+    }
+
+    #[tokio::test]
+    async fn test_existing_memories_reach_new_device() {
+        let key = DeviceKey::generate();
+        let store: Arc<dyn MemoryPort> = Arc::new(SurrealDbAdapter::new_memory().await.unwrap());
+        let shared = |content: &str| {
+            MemoryNode::new(content.to_string())
+                .with_namespace("shared".to_string())
+                .with_embedding(vec![0.1; 4])
+        };
+        let fact_id = store.store(shared("Stored before pairing")).await.unwrap();
+        let summary_id = store.store(shared("Summary before pairing")).await.unwrap();
+        store
+            .add_relationship(&summary_id, "summarizes", &fact_id)
+            .await
+            .unwrap();
+        let private = MemoryNode::new("Private".to_string()).with_namespace("private".to_string());
+        let private_id = store.store(private).await.unwrap();
+
+        let (a, _) = start_on(store, &key, None);
+        let handle = a.handle().clone();
+        assert!(wait_for(|| async { !handle.listen_addrs().await.unwrap().is_empty() }).await);
+        let addr = handle.listen_addrs().await.unwrap()[0].clone();
+
+        let (_b, b_store) = start(&key, Some(addr)).await;
+        let synced =
+            wait_for(|| async { !b_store.get_related(&summary_id).await.unwrap().is_empty() })
+                .await;
+        assert!(synced, "existing memories did not reach the new device");
+        let copy = b_store.get_by_id(&fact_id).await.unwrap().unwrap();
+        assert_eq!(copy.content, "Stored before pairing");
+        assert!(b_store.get_by_id(&private_id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delete_survives_restart() {
+        let key = DeviceKey::generate();
+        let state = tempfile::TempDir::new().unwrap();
+        let a_store: Arc<dyn MemoryPort> = Arc::new(SurrealDbAdapter::new_memory().await.unwrap());
+        let spawn_a = || {
+            let config = P2pConfig::new(key.clone())
+                .with_listen(vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()])
+                .with_mdns(false)
+                .with_heartbeat(Duration::from_millis(100))
+                .with_namespace("shared")
+                .with_state_dir(state.path());
+            let handle = P2pNode::spawn(config, a_store.clone()).unwrap();
+            ReplicatedMemory::new(a_store.clone(), handle)
+        };
+        async fn address(memory: &ReplicatedMemory) -> libp2p::Multiaddr {
+            let handle = memory.handle().clone();
+            assert!(wait_for(|| async { !handle.listen_addrs().await.unwrap().is_empty() }).await);
+            handle.listen_addrs().await.unwrap()[0].clone()
+        }
+
+        let a = spawn_a();
+        let (b, b_store) = start(&key, Some(address(&a).await)).await;
+        let node = MemoryNode::new("Deleted later".to_string()).with_namespace("shared".to_string());
+        let id = a.store(node).await.unwrap();
+        assert!(wait_for(|| async { b_store.get_by_id(&id).await.unwrap().is_some() }).await);
+
+        // B goes offline; A deletes and restarts before they sync again
+        b.handle().shutdown().await;
+        a.delete(&id).await.unwrap();
+        let peer_id = a.handle().local_peer_id();
+        a.handle().shutdown().await;
+        let a = spawn_a();
+        assert_eq!(a.handle().local_peer_id(), peer_id);
+
+        // B comes back with its copy: the delete reaches B instead of the
+        // copy resurrecting on A
+        let (_b, _) = start_on(b_store.clone(), &key, Some(address(&a).await));
+        assert!(wait_for(|| async { b_store.get_by_id(&id).await.unwrap().is_none() }).await);
+        assert!(a_store.get_by_id(&id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unpaired_device_is_ignored() {
+        let (a, _b, b_store) = pair(&DeviceKey::generate(), &DeviceKey::generate()).await;
+
+        // Different keys mean different topics: nobody subscribes to A's
+        let ha = a.handle().clone();
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(ha.topic_peers("shared").await.unwrap(), 0);
+
+        let node = MemoryNode::new("Secret".to_string()).with_namespace("shared".to_string());
+        let id = a.store(node).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(b_store.get_by_id(&id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_threat_reports_reach_peers() {
+        use synapse_core::logic::threat_db::ThreatDatabase;
+        use synapse_core::{ImmunePort, ThreatLevel, ThreatReport};
+        use synapse_infra::adapters::immune_adapter::BasicImmuneAdapter;
+
+        let node = |db: Arc<ThreatDatabase>, peer: Option<libp2p::Multiaddr>| {
+            let mut config = P2pConfig::new(DeviceKey::generate())
+                .with_listen(vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()])
+                .with_mdns(false)
+                .with_threat_sharing(db);
+            if let Some(peer) = peer {
+                config = config.with_peer(peer);
+            }
+            config
+        };
+        let memory: Arc<dyn MemoryPort> = Arc::new(SurrealDbAdapter::new_memory().await.unwrap());
+
+        let db_a = Arc::new(ThreatDatabase::new());
+        let a = P2pNode::spawn(node(db_a.clone(), None), memory.clone()).unwrap();
+        assert!(wait_for(|| async { !a.listen_addrs().await.unwrap().is_empty() }).await);
+        let addr = a.listen_addrs().await.unwrap()[0].clone();
+
+        let db_b = Arc::new(ThreatDatabase::new());
+        let _b = P2pNode::spawn(node(db_b.clone(), Some(addr)), memory).unwrap();
+        let scanner_b = BasicImmuneAdapter::with_threat_db(db_b.clone());
+
+        let immune_a = CollectiveImmune::new(
+            Arc::new(BasicImmuneAdapter::with_threat_db(db_a.clone())),
+            a.clone(),
+        );
+        let report = ThreatReport {
+            source_id: "cryptominer".to_string(),
+            threat_type: "process".to_string(),
+            level: ThreatLevel::Malicious,
+            description: "Sustained 100% CPU from a temp directory".to_string(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_secs() as i64,
+            rule: None,
+        };
+
+        // Re-sent until B has joined the topic; duplicates are ignored
+        let received = wait_for(|| async {
+            immune_a.report_threat(report.clone()).await.unwrap();
+            db_b.get("cryptominer").is_some()
+        })
+        .await;
+        assert!(received, "threat report did not reach the peer");
+
+        // One untrusted peer changes nothing on B; A trusts itself
+        assert_eq!(
+            scanner_b.scan_process("cryptominer").await.unwrap(),
+            ThreatLevel::Safe
+        );
+        assert_eq!(db_a.level("cryptominer"), ThreatLevel::Malicious);
+    }
+}
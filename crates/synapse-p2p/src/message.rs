@@ -0,0 +1,72 @@
+//! Replication messages exchanged between paired devices.
+
+use serde::{Deserialize, Serialize};
+use synapse_core::logic::crdt::{Delta, VersionVector};
+use synapse_core::{MemoryNode, Relationship};
+
+use crate::device_key::DeviceKey;
+use crate::error::P2pError;
+
+/// A local change to replicate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SyncOp {
+    /// Node created or updated
+    Upsert(MemoryNode),
+    /// Node deleted
+    Delete { id: String },
+    /// Graph edge added
+    Relate(Relationship),
+}
+
+/// What travels on a namespace topic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SyncPayload {
+    /// CRDT changes, either a fresh local write or a catch-up reply
+    Delta(Delta),
+    /// Ask peers for everything after this version
+    Request(VersionVector),
+}
+
+/// A payload tagged with the namespace it belongs to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncMessage {
+    pub namespace: String,
+    pub payload: SyncPayload,
+}
+
+impl SyncMessage {
+    pub fn new(namespace: impl Into<String>, payload: SyncPayload) -> Self {
+        Self {
+            namespace: namespace.into(),
+            payload,
+        }
+    }
+
+    /// Serialize and seal with the device key.
+    pub fn encode(&self, key: &DeviceKey) -> Result<Vec<u8>, P2pError> {
+        let json = serde_json::to_vec(self).map_err(|e| P2pError::InvalidMessage(e.to_string()))?;
+        Ok(key.seal(&json))
+    }
+
+    /// Open and deserialize a sealed message.
+    pub fn decode(key: &DeviceKey, data: &[u8]) -> Result<Self, P2pError> {
+        let json = key.open(data)?;
+        let message: Self =
+            serde_json::from_slice(&json).map_err(|e| P2pError::InvalidMessage(e.to_string()))?;
+
+        if let SyncPayload::Delta(delta) = &message.payload {
+            if let Some(node) = delta
+                .state
+                .nodes
+                .values()
+                .find(|node| node.namespace != message.namespace)
+            {
+                return Err(P2pError::InvalidMessage(format!(
+                    "node namespace '{}' does not match '{}'",
+                    node.namespace, message.namespace
+                )));
+            }
+        }
+        Ok(message)
+    }
+}
@@ -0,0 +1,707 @@
+//! libp2p node: discovery, gossip and applying remote changes.
+//!
+//! Each opted-in namespace maps to one gossipsub topic derived from the
+//! device key. Messages are sealed with the key, so a peer that connects
+//! without it is rejected at validation and never reaches the memory store.
+//!
+//! Every namespace also has a CRDT [`Replica`], seeded at startup with what
+//! the store already holds. Local writes become deltas that are broadcast
+//! as they happen; when a device joins a topic, or a received delta shows a
+//! gap, the node broadcasts its version vector and peers answer with the
+//! delta since that version. With a state directory, every merged delta is
+//! also appended to a [`ReplicaStore`] and the replicas are reloaded on start.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use libp2p::gossipsub::{self, IdentTopic, MessageAcceptance, TopicHash};
+use libp2p::swarm::{behaviour::toggle::Toggle, NetworkBehaviour, SwarmEvent};
+use libp2p::{identity::Keypair, mdns, noise, tcp, yamux, Multiaddr, PeerId, Swarm, SwarmBuilder};
+use sha2::{Digest, Sha256};
+use synapse_core::logic::crdt::{Change, Delta, Replica, ReplicaId};
+use synapse_core::logic::memory_api::validate_relation;
+use synapse_core::{MemoryNode, MemoryPort, Relationship, ThreatReport};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, info, warn};
+
+use crate::config::P2pConfig;
+use crate::device_key::DeviceKey;
+use crate::error::P2pError;
+use crate::message::{SyncMessage, SyncOp, SyncPayload};
+use crate::replica_store::ReplicaStore;
+use crate::threat::{ThreatExchange, THREAT_TOPIC};
+
+/// Largest message accepted from the network.
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// Largest sealed delta published, leaving room in a `MAX_MESSAGE_SIZE`
+/// message for the gossipsub signature, key and topic.
+const MAX_DELTA_SIZE: usize = MAX_MESSAGE_SIZE - 4 * 1024;
+
+#[derive(NetworkBehaviour)]
+struct Behaviour {
+    gossipsub: gossipsub::Behaviour,
+    mdns: Toggle<mdns::tokio::Behaviour>,
+}
+
+enum Command {
+    Publish {
+        namespace: String,
+        op: SyncOp,
+        reply: oneshot::Sender<Result<(), P2pError>>,
+    },
+    ListenAddrs(oneshot::Sender<Vec<Multiaddr>>),
+    TopicPeers {
+        namespace: String,
+        reply: oneshot::Sender<usize>,
+    },
+    ReportThreat {
+        report: ThreatReport,
+        reply: oneshot::Sender<Result<(), P2pError>>,
+    },
+    /// Nodes and edges the store held at startup
+    Seed {
+        namespace: String,
+        nodes: Vec<MemoryNode>,
+        edges: Vec<Relationship>,
+    },
+    Shutdown,
+}
+
+/// Starts the replication node.
+pub struct P2pNode;
+
+impl P2pNode {
+    /// Start listening, dial configured peers and apply changes received for
+    /// opted-in namespaces to `memory`.
+    ///
+    /// What `memory` already holds in those namespaces is offered to peers.
+    /// Changes from peers are written to `memory` as they are, so pass the
+    /// store that screens writes (e.g. `synapse_immune::GuardedMemory`),
+    /// not the raw database.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn spawn(config: P2pConfig, memory: Arc<dyn MemoryPort>) -> Result<P2pHandle, P2pError> {
+        let store = config
+            .state_dir
+            .as_ref()
+            .map(ReplicaStore::open)
+            .transpose()?;
+        let keypair = match (&config.keypair, &store) {
+            (Some(keypair), _) => keypair.clone(),
+            (None, Some(store)) => store.identity()?,
+            (None, None) => Keypair::generate_ed25519(),
+        };
+        let local_peer_id = keypair.public().to_peer_id();
+        let mut swarm = build_swarm(keypair.clone(), &config)?;
+
+        let mut topics = HashMap::new();
+        let mut replicas = HashMap::new();
+        for namespace in &config.namespaces {
+            let topic = IdentTopic::new(config.device_key.topic(namespace));
+            swarm
+                .behaviour_mut()
+                .gossipsub
+                .subscribe(&topic)
+                .map_err(|e| P2pError::Network(e.to_string()))?;
+            topics.insert(topic.hash(), namespace.clone());
+            let replica = match &store {
+                Some(store) => store.load(namespace, replica_id(&local_peer_id))?,
+                None => Replica::new(replica_id(&local_peer_id)),
+            };
+            replicas.insert(namespace.clone(), replica);
+        }
+
+        let threat_topic = IdentTopic::new(THREAT_TOPIC);
+        let threats = match &config.threat_db {
+            Some(db) => {
+                swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .subscribe(&threat_topic)
+                    .map_err(|e| P2pError::Network(e.to_string()))?;
+                Some(ThreatExchange::new(
+                    db.clone(),
+                    keypair,
+                    config.trusted_reporters.clone(),
+                ))
+            }
+            None => None,
+        };
+
+        for addr in &config.listen {
+            swarm
+                .listen_on(addr.clone())
+                .map_err(|e| P2pError::Network(format!("listen on {}: {}", addr, e)))?;
+        }
+        for addr in &config.peers {
+            if let Err(e) = swarm.dial(addr.clone()) {
+                warn!("Failed to dial {}: {}", addr, e);
+            }
+        }
+
+        info!(
+            "P2P node {} started for network {} ({} namespaces)",
+            local_peer_id,
+            config.device_key.network_id(),
+            topics.len()
+        );
+
+        let (commands, command_rx) = mpsc::channel(64);
+        let (apply_tx, apply_rx) = mpsc::unbounded_channel();
+        let namespaces: Vec<String> = config.namespaces.iter().cloned().collect();
+        tokio::spawn(load_existing(memory.clone(), namespaces, commands.clone()));
+        tokio::spawn(apply_loop(memory, apply_rx));
+        tokio::spawn(
+            EventLoop {
+                swarm,
+                device_key: config.device_key.clone(),
+                topics,
+                replicas,
+                store,
+                threats,
+                threat_topic: threat_topic.hash(),
+                listen_addrs: Vec::new(),
+                apply: apply_tx,
+            }
+            .run(command_rx),
+        );
+
+        Ok(P2pHandle {
+            commands,
+            local_peer_id,
+            namespaces: Arc::new(config.namespaces),
+        })
+    }
+}
+
+/// Handle to a running [`P2pNode`]. Cheap to clone.
+#[derive(Clone)]
+pub struct P2pHandle {
+    commands: mpsc::Sender<Command>,
+    local_peer_id: PeerId,
+    namespaces: Arc<HashSet<String>>,
+}
+
+impl P2pHandle {
+    pub fn local_peer_id(&self) -> PeerId {
+        self.local_peer_id
+    }
+
+    /// Whether changes in `namespace` are replicated.
+    pub fn is_replicated(&self, namespace: &str) -> bool {
+        self.namespaces.contains(namespace)
+    }
+
+    /// Record a local change and broadcast it to the other devices.
+    ///
+    /// Having no device online is not an error: the change is sent when a
+    /// device next asks to catch up.
+    pub async fn publish(&self, namespace: &str, op: SyncOp) -> Result<(), P2pError> {
+        let (reply, rx) = oneshot::channel();
+        let namespace = namespace.to_string();
+        self.send(Command::Publish {
+            namespace,
+            op,
+            reply,
+        })
+        .await?;
+        rx.await.map_err(|_| P2pError::Stopped)?
+    }
+
+    /// Addresses this node is currently listening on.
+    pub async fn listen_addrs(&self) -> Result<Vec<Multiaddr>, P2pError> {
+        let (reply, rx) = oneshot::channel();
+        self.send(Command::ListenAddrs(reply)).await?;
+        rx.await.map_err(|_| P2pError::Stopped)
+    }
+
+    /// Number of connected devices subscribed to `namespace`.
+    pub async fn topic_peers(&self, namespace: &str) -> Result<usize, P2pError> {
+        let (reply, rx) = oneshot::channel();
+        let namespace = namespace.to_string();
+        self.send(Command::TopicPeers { namespace, reply }).await?;
+        rx.await.map_err(|_| P2pError::Stopped)
+    }
+
+    /// Sign and broadcast a threat report on the shared threat topic.
+    ///
+    /// Fails unless the node was started with threat sharing enabled.
+    pub async fn report_threat(&self, report: ThreatReport) -> Result<(), P2pError> {
+        let (reply, rx) = oneshot::channel();
+        self.send(Command::ReportThreat { report, reply }).await?;
+        rx.await.map_err(|_| P2pError::Stopped)?
+    }
+
+    /// Stop the node.
+    pub async fn shutdown(&self) {
+        let _ = self.commands.send(Command::Shutdown).await;
+    }
+
+    async fn send(&self, command: Command) -> Result<(), P2pError> {
+        self.commands
+            .send(command)
+            .await
+            .map_err(|_| P2pError::Stopped)
+    }
+}
+
+fn build_swarm(keypair: Keypair, config: &P2pConfig) -> Result<Swarm<Behaviour>, P2pError> {
+    let heartbeat = config.heartbeat;
+    let enable_mdns = config.mdns;
+
+    let swarm = SwarmBuilder::with_existing_identity(keypair)
+        .with_tokio()
+        .with_tcp(
+            tcp::Config::default(),
+            noise::Config::new,
+            yamux::Config::default,
+        )
+        .map_err(|e| P2pError::Network(e.to_string()))?
+        .with_behaviour(|key| {
+            let gossipsub_config = gossipsub::ConfigBuilder::default()
+                .heartbeat_interval(heartbeat)
+                .validation_mode(gossipsub::ValidationMode::Strict)
+                .validate_messages()
+                .max_transmit_size(MAX_MESSAGE_SIZE)
+                .build()?;
+            let gossipsub = gossipsub::Behaviour::new(
+                gossipsub::MessageAuthenticity::Signed(key.clone()),
+                gossipsub_config,
+            )?;
+
+            let mdns = if enable_mdns {
+                Some(mdns::tokio::Behaviour::new(
+                    mdns::Config::default(),
+                    key.public().to_peer_id(),
+                )?)
+            } else {
+                None
+            };
+
+            Ok(Behaviour {
+                gossipsub,
+                mdns: Toggle::from(mdns),
+            })
+        })
+        .map_err(|e| P2pError::Network(e.to_string()))?
+        .with_swarm_config(|c| c.with_idle_connection_timeout(Duration::from_secs(60)))
+        .build();
+
+    Ok(swarm)
+}
+
+struct EventLoop {
+    swarm: Swarm<Behaviour>,
+    device_key: DeviceKey,
+    topics: HashMap<TopicHash, String>,
+    replicas: HashMap<String, Replica>,
+    store: Option<ReplicaStore>,
+    threats: Option<ThreatExchange>,
+    threat_topic: TopicHash,
+    listen_addrs: Vec<Multiaddr>,
+    apply: mpsc::UnboundedSender<Vec<Change>>,
+}
+
+impl EventLoop {
+    async fn run(mut self, mut commands: mpsc::Receiver<Command>) {
+        loop {
+            tokio::select! {
+                event = self.swarm.select_next_some() => self.handle_event(event),
+                command = commands.recv() => match command {
+                    Some(Command::Shutdown) | None => break,
+                    Some(command) => self.handle_command(command),
+                },
+            }
+        }
+        debug!("P2P node {} stopped", self.swarm.local_peer_id());
+    }
+
+    fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::Publish {
+                namespace,
+                op,
+                reply,
+            } => {
+                let _ = reply.send(self.record(&namespace, op));
+            }
+            Command::ListenAddrs(reply) => {
+                let _ = reply.send(self.listen_addrs.clone());
+            }
+            Command::TopicPeers { namespace, reply } => {
+                let topic = IdentTopic::new(self.device_key.topic(&namespace)).hash();
+                let count = self
+                    .swarm
+                    .behaviour()
+                    .gossipsub
+                    .all_peers()
+                    .filter(|(_, topics)| topics.contains(&&topic))
+                    .count();
+                let _ = reply.send(count);
+            }
+            Command::ReportThreat { report, reply } => {
+                let _ = reply.send(self.share_threat(&report));
+            }
+            Command::Seed {
+                namespace,
+                nodes,
+                edges,
+            } => self.seed(&namespace, &nodes, &edges),
+            Command::Shutdown => {}
+        }
+    }
+
+    /// Record a local write in the namespace's replica and broadcast the delta.
+    fn record(&mut self, namespace: &str, op: SyncOp) -> Result<(), P2pError> {
+        let replica = self.replicas.get_mut(namespace).ok_or_else(|| {
+            P2pError::InvalidMessage(format!("namespace '{}' is not replicated", namespace))
+        })?;
+        let delta = match op {
+            SyncOp::Upsert(node) => replica.upsert(&node),
+            SyncOp::Delete { id } => replica.delete(&id, namespace),
+            SyncOp::Relate(edge) => replica.relate(edge),
+        };
+        self.persist(namespace, &delta);
+        self.send_delta(namespace, delta)
+    }
+
+    /// Record what the store held at startup and broadcast whatever the
+    /// replica did not already have.
+    fn seed(&mut self, namespace: &str, nodes: &[MemoryNode], edges: &[Relationship]) {
+        let Some(replica) = self.replicas.get_mut(namespace) else {
+            return;
+        };
+        let since = replica.version().clone();
+        let seeded_nodes = nodes.iter().filter(|node| replica.seed(node)).count();
+        let seeded_edges = edges.iter().filter(|edge| replica.seed_edge(edge)).count();
+        if seeded_nodes + seeded_edges == 0 {
+            return;
+        }
+        info!(
+            "Replicating {} existing memories and {} edges in '{}'",
+            seeded_nodes, seeded_edges, namespace
+        );
+        let delta = replica.delta_since(&since);
+        self.persist(namespace, &delta);
+        if let Err(e) = self.send_delta(namespace, delta) {
+            warn!("Failed to broadcast existing memories in '{}': {}", namespace, e);
+        }
+    }
+
+    /// Append a merged delta to the state directory, if there is one.
+    fn persist(&self, namespace: &str, delta: &Delta) {
+        if let (Some(store), Some(replica)) = (&self.store, self.replicas.get(namespace)) {
+            if let Err(e) = store.append(namespace, delta, replica) {
+                warn!("Failed to persist replica of '{}': {}", namespace, e);
+            }
+        }
+    }
+
+    /// Broadcast `delta`, split into parts that each fit in one message.
+    ///
+    /// A part that fails does not stop the others; the last part then
+    /// leaves the receiver's version alone so it asks to catch up.
+    fn send_delta(&mut self, namespace: &str, delta: Delta) -> Result<(), P2pError> {
+        if delta.is_empty() {
+            return Ok(());
+        }
+        let mut parts = self.fit(namespace, delta);
+        let last = parts.pop().expect("fit returns at least one part");
+        let mut result = Ok(());
+        for part in parts {
+            if let Err(e) = self.publish(SyncMessage::new(namespace, SyncPayload::Delta(part))) {
+                warn!("Failed to send part of a delta for '{}': {}", namespace, e);
+                result = Err(e);
+            }
+        }
+        let last = match result {
+            Ok(()) => last,
+            Err(_) => Delta {
+                until: last.since.clone(),
+                ..last
+            },
+        };
+        self.publish(SyncMessage::new(namespace, SyncPayload::Delta(last)))
+            .and(result)
+    }
+
+    /// Halve `delta` until each part seals to at most `MAX_DELTA_SIZE`. A
+    /// single node that is still too large is left for `publish` to reject.
+    fn fit(&self, namespace: &str, delta: Delta) -> Vec<Delta> {
+        let nodes = delta.state.nodes.len();
+        let message = SyncMessage::new(namespace, SyncPayload::Delta(delta));
+        let fits = message
+            .encode(&self.device_key)
+            .map_or(true, |data| data.len() <= MAX_DELTA_SIZE);
+        let SyncPayload::Delta(delta) = message.payload else {
+            unreachable!("built from a delta")
+        };
+        if fits || nodes <= 1 {
+            return vec![delta];
+        }
+        delta
+            .split(nodes.div_ceil(2))
+            .into_iter()
+            .flat_map(|part| self.fit(namespace, part))
+            .collect()
+    }
+
+    fn share_threat(&mut self, report: &ThreatReport) -> Result<(), P2pError> {
+        let threats = self
+            .threats
+            .as_ref()
+            .ok_or_else(|| P2pError::Network("threat sharing is not enabled".to_string()))?;
+        let data = threats.encode(report)?;
+        match self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(IdentTopic::new(THREAT_TOPIC), data)
+        {
+            Ok(_) | Err(gossipsub::PublishError::InsufficientPeers) => Ok(()),
+            Err(e) => Err(P2pError::Network(e.to_string())),
+        }
+    }
+
+    fn request_sync(&mut self, namespace: &str) {
+        let version = self.replicas[namespace].version().clone();
+        let message = SyncMessage::new(namespace, SyncPayload::Request(version));
+        if let Err(e) = self.publish(message) {
+            warn!("Failed to request sync for '{}': {}", namespace, e);
+        }
+    }
+
+    fn publish(&mut self, message: SyncMessage) -> Result<(), P2pError> {
+        let topic = IdentTopic::new(self.device_key.topic(&message.namespace));
+        let data = message.encode(&self.device_key)?;
+        if data.len() > MAX_DELTA_SIZE {
+            return Err(P2pError::InvalidMessage(format!(
+                "{} byte message for '{}' exceeds the size limit",
+                data.len(),
+                message.namespace
+            )));
+        }
+        match self.swarm.behaviour_mut().gossipsub.publish(topic, data) {
+            Ok(_) => Ok(()),
+            Err(gossipsub::PublishError::InsufficientPeers) => {
+                debug!(
+                    "No devices online for '{}'; change kept local",
+                    message.namespace
+                );
+                Ok(())
+            }
+            Err(e) => Err(P2pError::Network(e.to_string())),
+        }
+    }
+
+    fn handle_event(&mut self, event: SwarmEvent<BehaviourEvent>) {
+        match event {
+            SwarmEvent::NewListenAddr { address, .. } => {
+                debug!("Listening on {}", address);
+                self.listen_addrs.push(address);
+            }
+            SwarmEvent::ExpiredListenAddr { address, .. } => {
+                self.listen_addrs.retain(|a| a != &address);
+            }
+            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                debug!("Connected to {}", peer_id);
+            }
+            SwarmEvent::Behaviour(BehaviourEvent::Mdns(mdns::Event::Discovered(peers))) => {
+                for (peer_id, addr) in peers {
+                    if self.swarm.is_connected(&peer_id) {
+                        continue;
+                    }
+                    debug!("Discovered {} at {}", peer_id, addr);
+                    if let Err(e) = self.swarm.dial(addr) {
+                        debug!("Failed to dial {}: {}", peer_id, e);
+                    }
+                }
+            }
+            SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(gossipsub::Event::Subscribed {
+                peer_id,
+                topic,
+            })) => {
+                // A device came online: catch up on what it wrote meanwhile
+                if let Some(namespace) = self.topics.get(&topic).cloned() {
+                    debug!("{} joined '{}'", peer_id, namespace);
+                    self.request_sync(&namespace);
+                }
+            }
+            SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(gossipsub::Event::Message {
+                propagation_source,
+                message_id,
+                message,
+            })) if message.topic == self.threat_topic => {
+                let acceptance = match self.threats.as_mut().map(|t| t.receive(&message.data)) {
+                    Some(Ok(true)) => MessageAcceptance::Accept,
+                    Some(Ok(false)) | None => MessageAcceptance::Ignore,
+                    Some(Err(e)) => {
+                        warn!("Rejected threat report from {}: {}", propagation_source, e);
+                        MessageAcceptance::Reject
+                    }
+                };
+                self.report_validation(&message_id, &propagation_source, acceptance);
+            }
+            SwarmEvent::Behaviour(BehaviourEvent::Gossipsub(gossipsub::Event::Message {
+                propagation_source,
+                message_id,
+                message,
+            })) => {
+                let acceptance = match self.validate(&message) {
+                    Ok(sync) => {
+                        // Only a paired device can seal a sync message
+                        if let (Some(threats), Some(author)) =
+                            (self.threats.as_mut(), message.source)
+                        {
+                            threats.trust(author);
+                        }
+                        self.receive(sync);
+                        MessageAcceptance::Accept
+                    }
+                    Err(e) => {
+                        warn!("Rejected message from {}: {}", propagation_source, e);
+                        MessageAcceptance::Reject
+                    }
+                };
+                self.report_validation(&message_id, &propagation_source, acceptance);
+            }
+            _ => {}
+        }
+    }
+
+    fn report_validation(
+        &mut self,
+        message_id: &gossipsub::MessageId,
+        source: &PeerId,
+        acceptance: MessageAcceptance,
+    ) {
+        if let Err(e) = self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .report_message_validation_result(message_id, source, acceptance)
+        {
+            debug!("Failed to report validation of {}: {}", message_id, e);
+        }
+    }
+
+    fn validate(&self, message: &gossipsub::Message) -> Result<SyncMessage, P2pError> {
+        let namespace = self
+            .topics
+            .get(&message.topic)
+            .ok_or_else(|| P2pError::InvalidMessage("unknown topic".to_string()))?;
+
+        let sync = SyncMessage::decode(&self.device_key, &message.data)?;
+        if &sync.namespace != namespace {
+            return Err(P2pError::InvalidMessage(format!(
+                "namespace '{}' sent on the topic for '{}'",
+                sync.namespace, namespace
+            )));
+        }
+        Ok(sync)
+    }
+
+    fn receive(&mut self, message: SyncMessage) {
+        let namespace = message.namespace;
+        let replica = self
+            .replicas
+            .get_mut(&namespace)
+            .expect("validated messages belong to a replicated namespace");
+
+        match message.payload {
+            SyncPayload::Delta(delta) => {
+                let changes = replica.apply(&delta);
+                let missed_changes = !replica.version().covers(&delta.until);
+                if !delta.is_empty() {
+                    self.persist(&namespace, &delta);
+                }
+                if !changes.is_empty() {
+                    let _ = self.apply.send(changes);
+                }
+                if missed_changes {
+                    self.request_sync(&namespace);
+                }
+            }
+            SyncPayload::Request(version) => {
+                let delta = replica.delta_since(&version);
+                if let Err(e) = self.send_delta(&namespace, delta) {
+                    warn!("Failed to answer sync request for '{}': {}", namespace, e);
+                }
+            }
+        }
+    }
+}
+
+/// Replica id for this device, derived from its peer id.
+fn replica_id(peer_id: &PeerId) -> ReplicaId {
+    let digest = Sha256::digest(peer_id.to_bytes());
+    u64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"))
+}
+
+/// Read each replicated namespace from the store and hand it to the event
+/// loop, so memories written before the node started reach peers too.
+async fn load_existing(
+    memory: Arc<dyn MemoryPort>,
+    namespaces: Vec<String>,
+    commands: mpsc::Sender<Command>,
+) {
+    for namespace in namespaces {
+        let nodes = match memory.get_by_namespace(&namespace).await {
+            Ok(nodes) => nodes,
+            Err(e) => {
+                warn!("Failed to read '{}' for replication: {}", namespace, e);
+                continue;
+            }
+        };
+        let mut edges = Vec::new();
+        for node in &nodes {
+            match memory.get_related(&node.id).await {
+                // Edges follow the namespace of their source node
+                Ok(related) => edges.extend(related.into_iter().filter(|e| e.from_id == node.id)),
+                Err(e) => {
+                    debug!("Not replicating existing edges in '{}': {}", namespace, e);
+                    break;
+                }
+            }
+        }
+        let seed = Command::Seed {
+            namespace,
+            nodes,
+            edges,
+        };
+        if commands.send(seed).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Apply merged changes to the local store one batch at a time, in order.
+async fn apply_loop(memory: Arc<dyn MemoryPort>, mut rx: mpsc::UnboundedReceiver<Vec<Change>>) {
+    while let Some(changes) = rx.recv().await {
+        for change in changes {
+            if let Err(e) = apply(memory.as_ref(), change).await {
+                warn!("Failed to apply replicated change: {}", e);
+            }
+        }
+    }
+}
+
+async fn apply(memory: &dyn MemoryPort, change: Change) -> synapse_core::Result<()> {
+    match change {
+        Change::Upsert(node) => match memory.get_by_id(&node.id).await? {
+            Some(_) => memory.update(node).await,
+            None => memory.store(node).await.map(|_| ()),
+        },
+        Change::Delete(id) => memory.delete(&id).await,
+        Change::Relate(edge) => {
+            // The relation comes from a peer and names a table in the store
+            validate_relation(&edge.relation)?;
+            memory
+                .add_relationship(&edge.from_id, &edge.relation, &edge.to_id)
+                .await
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! On-disk replica state and node identity.
+//!
+//! Without it a restart forgets tombstones and version vectors, so a node
+//! deleted just before the restart comes back with the next edit from a
+//! peer, and the device rejoins under a new replica id. Each namespace has a
+//! snapshot plus an append-only log of the deltas merged since; loading
+//! replays the log and compacts both into a new snapshot, and so does an
+//! append that takes the log past its size limit.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use libp2p::identity::Keypair;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use synapse_core::logic::crdt::{Delta, Replica, ReplicaId, ReplicaState, VersionVector};
+use synapse_infra::adapters::private_file::write_private;
+use tracing::{debug, info, warn};
+
+use crate::error::P2pError;
+
+/// File holding the protobuf-encoded libp2p keypair.
+const IDENTITY_FILE: &str = "identity.key";
+
+/// Log size at which an append compacts the log into the snapshot. Upserts
+/// carry whole nodes with their embeddings, so the log grows quickly.
+pub const COMPACT_LOG_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Default, Serialize, Deserialize)]
+struct Snapshot {
+    state: ReplicaState,
+    version: VersionVector,
+}
+
+/// Directory holding replica snapshots, delta logs and the node identity.
+#[derive(Debug, Clone)]
+pub struct ReplicaStore {
+    dir: PathBuf,
+    compact_at: u64,
+}
+
+impl ReplicaStore {
+    /// Use `dir`, creating it if needed.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, P2pError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|e| storage(&dir, e))?;
+        Ok(Self {
+            dir,
+            compact_at: COMPACT_LOG_SIZE,
+        })
+    }
+
+    /// Compact a namespace's log once it reaches `bytes`.
+    pub fn with_compaction_threshold(mut self, bytes: u64) -> Self {
+        self.compact_at = bytes;
+        self
+    }
+
+    /// The stored identity, or a new one that is saved for next time.
+    pub fn identity(&self) -> Result<Keypair, P2pError> {
+        let path = self.dir.join(IDENTITY_FILE);
+        match fs::read(&path) {
+            Ok(bytes) => Keypair::from_protobuf_encoding(&bytes)
+                .map_err(|e| P2pError::Storage(format!("invalid identity {:?}: {}", path, e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let keypair = Keypair::generate_ed25519();
+                let bytes = keypair
+                    .to_protobuf_encoding()
+                    .map_err(|e| P2pError::Storage(e.to_string()))?;
+                write_private(&path, &bytes).map_err(|e| storage(&path, e))?;
+                info!("Generated node identity at {:?}", path);
+                Ok(keypair)
+            }
+            Err(e) => Err(storage(&path, e)),
+        }
+    }
+
+    /// Rebuild the replica of `namespace` (empty if nothing is stored) and
+    /// compact its log into the snapshot.
+    pub fn load(&self, namespace: &str, replica: ReplicaId) -> Result<Replica, P2pError> {
+        let (snapshot_path, log_path) = self.paths(namespace);
+        let snapshot: Snapshot = match fs::read(&snapshot_path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| P2pError::Storage(format!("invalid snapshot {:?}: {}", snapshot_path, e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Snapshot::default(),
+            Err(e) => return Err(storage(&snapshot_path, e)),
+        };
+        let mut loaded = Replica::restore(replica, snapshot.state, snapshot.version);
+
+        let log = match File::open(&log_path) {
+            Ok(file) => Some(file),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(storage(&log_path, e)),
+        };
+        let Some(log) = log else {
+            return Ok(loaded);
+        };
+        for line in BufReader::new(log).lines() {
+            let line = line.map_err(|e| storage(&log_path, e))?;
+            match serde_json::from_str::<Delta>(&line) {
+                Ok(delta) => {
+                    loaded.apply(&delta);
+                }
+                // A write cut short by a crash; everything before it is kept
+                Err(e) => warn!("Skipping unreadable entry in {:?}: {}", log_path, e),
+            }
+        }
+
+        self.compact(namespace, &loaded)?;
+        Ok(loaded)
+    }
+
+    /// Record a delta merged into `replica`, the replica of `namespace`.
+    ///
+    /// `replica` must already include `delta`: it becomes the new snapshot
+    /// when the log reaches the compaction threshold.
+    pub fn append(&self, namespace: &str, delta: &Delta, replica: &Replica) -> Result<(), P2pError> {
+        let (_, log_path) = self.paths(namespace);
+        let mut line = serde_json::to_vec(delta).map_err(|e| P2pError::Storage(e.to_string()))?;
+        line.push(b'\n');
+        let size = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_path)
+            .and_then(|mut file| {
+                file.write_all(&line)?;
+                file.metadata()
+            })
+            .map_err(|e| storage(&log_path, e))?
+            .len();
+        if size >= self.compact_at {
+            debug!("Compacting {:?} ({} bytes)", log_path, size);
+            self.compact(namespace, replica)?;
+        }
+        Ok(())
+    }
+
+    /// Replace the snapshot of `namespace` with `replica` and empty its log.
+    fn compact(&self, namespace: &str, replica: &Replica) -> Result<(), P2pError> {
+        let (snapshot_path, log_path) = self.paths(namespace);
+        let snapshot = Snapshot {
+            state: replica.state().clone(),
+            version: replica.version().clone(),
+        };
+        let bytes = serde_json::to_vec(&snapshot).map_err(|e| P2pError::Storage(e.to_string()))?;
+        let partial = snapshot_path.with_extension("json.tmp");
+        fs::write(&partial, bytes)
+            .and_then(|_| fs::rename(&partial, &snapshot_path))
+            .and_then(|_| File::create(&log_path).map(|_| ()))
+            .map_err(|e| storage(&snapshot_path, e))
+    }
+
+    /// Snapshot and log paths. The namespace is hashed into a file name.
+    fn paths(&self, namespace: &str) -> (PathBuf, PathBuf) {
+        let name: String = Sha256::digest(namespace.as_bytes())[..8]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        (
+            self.dir.join(format!("replica-{}.json", name)),
+            self.dir.join(format!("replica-{}.log", name)),
+        )
+    }
+}
+
+fn storage(path: &Path, error: std::io::Error) -> P2pError {
+    P2pError::Storage(format!("{:?}: {}", path, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use synapse_core::MemoryNode;
+    use tempfile::TempDir;
+
+    fn node(id: &str, content: &str) -> MemoryNode {
+        let mut node = MemoryNode::new(content.to_string()).with_namespace("shared".to_string());
+        node.id = id.to_string();
+        node
+    }
+
+    #[test]
+    fn test_replica_survives_restart() {
+        let dir = TempDir::new().unwrap();
+        let store = ReplicaStore::open(dir.path()).unwrap();
+
+        let mut replica = store.load("shared", 1).unwrap();
+        let kept = replica.upsert(&node("n1", "kept"));
+        store.append("shared", &kept, &replica).unwrap();
+        let deleted = replica.upsert(&node("n2", "deleted"));
+        store.append("shared", &deleted, &replica).unwrap();
+        let delete = replica.delete("n2", "shared");
+        store.append("shared", &delete, &replica).unwrap();
+        let mut peer = Replica::new(2);
+        let remote = peer.upsert(&node("n3", "from peer"));
+        replica.apply(&remote);
+        store.append("shared", &remote, &replica).unwrap();
+
+        // Loading twice checks both the replayed log and the compacted snapshot
+        for _ in 0..2 {
+            let mut restored = store.load("shared", 1).unwrap();
+            assert_eq!(restored.state(), replica.state());
+            assert_eq!(restored.version(), replica.version());
+
+            // The tombstone holds against the peer's stale copy
+            assert!(restored.apply(&peer.upsert(&node("n2", "stale"))).is_empty());
+            // and the clock continues after the stored writes
+            let next = restored.upsert(&node("n1", "edited"));
+            assert!(next.until.get(1) > replica.version().get(1));
+        }
+        assert!(store.load("other", 1).unwrap().state().is_empty());
+    }
+
+    #[test]
+    fn test_log_is_compacted_when_large() {
+        let dir = TempDir::new().unwrap();
+        let store = ReplicaStore::open(dir.path())
+            .unwrap()
+            .with_compaction_threshold(4096);
+        let (_, log_path) = store.paths("shared");
+
+        let mut replica = store.load("shared", 1).unwrap();
+        for i in 0..20 {
+            let delta = replica.upsert(&node("n1", &format!("{}{}", i, "x".repeat(512))));
+            store.append("shared", &delta, &replica).unwrap();
+            assert!(fs::metadata(&log_path).unwrap().len() < 4096);
+        }
+
+        let restored = store.load("shared", 1).unwrap();
+        assert_eq!(restored.state(), replica.state());
+        assert_eq!(restored.version(), replica.version());
+    }
+
+    #[test]
+    fn test_identity_is_stable() {
+        let dir = TempDir::new().unwrap();
+        let first = ReplicaStore::open(dir.path()).unwrap().identity().unwrap();
+        let second = ReplicaStore::open(dir.path()).unwrap().identity().unwrap();
+        assert_eq!(first.public(), second.public());
+    }
+}
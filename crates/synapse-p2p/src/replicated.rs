@@ -0,0 +1,140 @@
+//! `MemoryPort` decorator that replicates local writes to paired devices.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use synapse_core::error::Result;
+use synapse_core::{MemoryNode, MemoryPort, Relationship, SearchResult};
+use tracing::warn;
+
+use crate::message::SyncOp;
+use crate::node::P2pHandle;
+
+/// Wraps a memory store and publishes writes in opted-in namespaces.
+///
+/// Local writes always succeed independently of the network; a failed
+/// publish is logged. Changes received from other devices are merged by the
+/// node and applied to the inner store directly, so they are not re-broadcast.
+pub struct ReplicatedMemory {
+    inner: Arc<dyn MemoryPort>,
+    p2p: P2pHandle,
+}
+
+impl ReplicatedMemory {
+    pub fn new(inner: Arc<dyn MemoryPort>, p2p: P2pHandle) -> Self {
+        Self { inner, p2p }
+    }
+
+    pub fn handle(&self) -> &P2pHandle {
+        &self.p2p
+    }
+
+    async fn publish(&self, namespace: &str, op: SyncOp) {
+        if !self.p2p.is_replicated(namespace) {
+            return;
+        }
+        if let Err(e) = self.p2p.publish(namespace, op).await {
+            warn!("Failed to replicate change in '{}': {}", namespace, e);
+        }
+    }
+
+    async fn namespace_of(&self, id: &str) -> Result<Option<String>> {
+        Ok(self.inner.get_by_id(id).await?.map(|node| node.namespace))
+    }
+}
+
+#[async_trait]
+impl MemoryPort for ReplicatedMemory {
+    async fn store(&self, mut node: MemoryNode) -> Result<String> {
+        let id = self.inner.store(node.clone()).await?;
+        node.id = id.clone();
+        let namespace = node.namespace.clone();
+        self.publish(&namespace, SyncOp::Upsert(node)).await;
+        Ok(id)
+    }
+
+    async fn search(&self, embedding: &[f32], top_k: usize) -> Result<Vec<SearchResult>> {
+        self.inner.search(embedding, top_k).await
+    }
+
+    async fn search_layer(
+        &self,
+        embedding: &[f32],
+        layer: u8,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.inner.search_layer(embedding, layer, top_k).await
+    }
+
+    async fn search_namespace(
+        &self,
+        embedding: &[f32],
+        namespace: &str,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult>> {
+        self.inner
+            .search_namespace(embedding, namespace, top_k)
+            .await
+    }
+
+    async fn get_by_id(&self, id: &str) -> Result<Option<MemoryNode>> {
+        self.inner.get_by_id(id).await
+    }
+
+    async fn get_by_layer(&self, layer: u8) -> Result<Vec<MemoryNode>> {
+        self.inner.get_by_layer(layer).await
+    }
+
+    async fn update(&self, node: MemoryNode) -> Result<()> {
+        self.inner.update(node.clone()).await?;
+        let namespace = node.namespace.clone();
+        self.publish(&namespace, SyncOp::Upsert(node)).await;
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<()> {
+        let namespace = self.namespace_of(id).await?;
+        self.inner.delete(id).await?;
+        if let Some(namespace) = namespace {
+            self.publish(&namespace, SyncOp::Delete { id: id.to_string() })
+                .await;
+        }
+        Ok(())
+    }
+
+    async fn count(&self) -> Result<usize> {
+        self.inner.count().await
+    }
+
+    async fn add_relationship(&self, from_id: &str, relation: &str, to_id: &str) -> Result<()> {
+        self.inner
+            .add_relationship(from_id, relation, to_id)
+            .await?;
+        // Edges follow the namespace of their source node
+        if let Some(namespace) = self.namespace_of(from_id).await? {
+            let edge = Relationship {
+                from_id: from_id.to_string(),
+                relation: relation.to_string(),
+                to_id: to_id.to_string(),
+            };
+            self.publish(&namespace, SyncOp::Relate(edge)).await;
+        }
+        Ok(())
+    }
+
+    async fn get_related(&self, id: &str) -> Result<Vec<Relationship>> {
+        self.inner.get_related(id).await
+    }
+
+    async fn get_by_namespace(&self, namespace: &str) -> Result<Vec<MemoryNode>> {
+        self.inner.get_by_namespace(namespace).await
+    }
+
+    async fn count_by_layer(&self, layer: u8) -> Result<usize> {
+        self.inner.count_by_layer(layer).await
+    }
+
+    async fn list_namespaces(&self) -> Result<Vec<String>> {
+        self.inner.list_namespaces().await
+    }
+}
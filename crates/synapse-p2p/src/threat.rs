@@ -0,0 +1,392 @@
+//! Collective threat intelligence.
+//!
+//! Threat reports are signed with the node's libp2p identity and gossiped on
+//! a shared topic that is not tied to the device key, so any connected
+//! Synapse node can contribute. Received reports are verified, checked for
+//! sane contents, deduplicated and rate-limited per reporter before they
+//! reach the local [`ThreatDatabase`]. Since identities are free, only
+//! reports from paired devices and configured reporters count towards
+//! corroboration.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use libp2p::identity::{Keypair, PublicKey};
+use libp2p::PeerId;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use synapse_core::error::Result;
+use synapse_core::logic::threat_db::ThreatDatabase;
+use synapse_core::{ImmunePort, ThreatLevel, ThreatReport};
+use tracing::warn;
+
+use crate::error::P2pError;
+use crate::node::P2pHandle;
+
+/// Gossipsub topic for threat reports.
+pub const THREAT_TOPIC: &str = "synapse/threats/v1";
+
+/// Reports accepted from one reporter per [`RATE_WINDOW`].
+const RATE_LIMIT: u32 = 20;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Reporters tracked for rate limiting; new reporters beyond this are
+/// turned away until older windows expire.
+const TRACKED_REPORTERS: usize = 1024;
+
+/// Report hashes remembered for deduplication.
+const SEEN_CAPACITY: usize = 4096;
+
+/// How far a report's timestamp may be from local time.
+const MAX_CLOCK_SKEW_SECS: i64 = 24 * 60 * 60;
+
+/// A threat report with the reporter's signature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedThreatReport {
+    /// JSON-encoded [`ThreatReport`] (the signed bytes)
+    pub payload: Vec<u8>,
+    /// Reporter's public key, protobuf-encoded
+    pub public_key: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+impl SignedThreatReport {
+    pub fn sign(report: &ThreatReport, keypair: &Keypair) -> std::result::Result<Self, P2pError> {
+        let payload =
+            serde_json::to_vec(report).map_err(|e| P2pError::InvalidMessage(e.to_string()))?;
+        let signature = keypair
+            .sign(&payload)
+            .map_err(|e| P2pError::Network(format!("signing failed: {}", e)))?;
+        Ok(Self {
+            payload,
+            public_key: keypair.public().encode_protobuf(),
+            signature,
+        })
+    }
+
+    /// Check the signature and return the report with its reporter.
+    pub fn verify(&self) -> std::result::Result<(ThreatReport, PeerId), P2pError> {
+        let public_key = PublicKey::try_decode_protobuf(&self.public_key)
+            .map_err(|e| P2pError::InvalidMessage(format!("bad public key: {}", e)))?;
+        if !public_key.verify(&self.payload, &self.signature) {
+            return Err(P2pError::InvalidMessage("bad signature".to_string()));
+        }
+        let report = serde_json::from_slice(&self.payload)
+            .map_err(|e| P2pError::InvalidMessage(e.to_string()))?;
+        Ok((report, public_key.to_peer_id()))
+    }
+}
+
+/// Reject reports that are empty, oversized, harmless or wildly dated.
+pub fn validate_report(report: &ThreatReport, now: i64) -> std::result::Result<(), P2pError> {
+    let invalid = |reason: &str| Err(P2pError::InvalidMessage(reason.to_string()));
+    if report.source_id.trim().is_empty() || report.source_id.len() > 256 {
+        return invalid("indicator must be 1-256 bytes");
+    }
+    if report.threat_type.len() > 64
+        || report.description.len() > 1024
+        || report.rule.as_ref().is_some_and(|rule| rule.len() > 128)
+    {
+        return invalid("threat type, description or rule too long");
+    }
+    if report.level == ThreatLevel::Safe {
+        return invalid("report does not describe a threat");
+    }
+    if is_instance_scoped(&report.source_id) {
+        return invalid("indicator names a process instance on the reporter");
+    }
+    if (report.timestamp - now).abs() > MAX_CLOCK_SKEW_SECS {
+        return invalid("timestamp too far from local time");
+    }
+    Ok(())
+}
+
+/// Whether `indicator` names one process instance (`name[pid]`), which
+/// means nothing on another machine.
+pub fn is_instance_scoped(indicator: &str) -> bool {
+    indicator
+        .strip_suffix(']')
+        .and_then(|rest| rest.rsplit_once('['))
+        .is_some_and(|(_, pid)| !pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Outcome of [`ReportGuard::admit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    New,
+    Duplicate,
+    RateLimited,
+}
+
+/// Deduplication and per-reporter rate limiting for incoming reports.
+#[derive(Debug, Default)]
+pub struct ReportGuard {
+    seen: HashSet<[u8; 32]>,
+    order: VecDeque<[u8; 32]>,
+    windows: HashMap<PeerId, (Instant, u32)>,
+}
+
+impl ReportGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn admit(&mut self, reporter: PeerId, payload: &[u8], now: Instant) -> Admission {
+        let hash: [u8; 32] = Sha256::digest(payload).into();
+        if self.seen.contains(&hash) {
+            return Admission::Duplicate;
+        }
+
+        if !self.windows.contains_key(&reporter) && self.windows.len() >= TRACKED_REPORTERS {
+            self.windows
+                .retain(|_, (start, _)| now.duration_since(*start) < RATE_WINDOW);
+            if self.windows.len() >= TRACKED_REPORTERS {
+                return Admission::RateLimited;
+            }
+        }
+        let (start, count) = self.windows.entry(reporter).or_insert((now, 0));
+        if now.duration_since(*start) >= RATE_WINDOW {
+            *start = now;
+            *count = 0;
+        }
+        if *count >= RATE_LIMIT {
+            return Admission::RateLimited;
+        }
+        *count += 1;
+
+        self.seen.insert(hash);
+        self.order.push_back(hash);
+        if self.order.len() > SEEN_CAPACITY {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        Admission::New
+    }
+}
+
+/// `ImmunePort` decorator that shares reported threats with the network.
+///
+/// Reports about a single process instance (e.g. a behavioural rule match
+/// on `sh[2]`) are only recorded locally.
+///
+/// Wrap an adapter that shares its [`ThreatDatabase`] with the p2p node
+/// (`P2pConfig::with_threat_sharing`) so peer reports also inform
+/// `scan_process`.
+pub struct CollectiveImmune {
+    inner: Arc<dyn ImmunePort>,
+    p2p: P2pHandle,
+}
+
+impl CollectiveImmune {
+    pub fn new(inner: Arc<dyn ImmunePort>, p2p: P2pHandle) -> Self {
+        Self { inner, p2p }
+    }
+}
+
+#[async_trait]
+impl ImmunePort for CollectiveImmune {
+    async fn check_integrity(&self) -> Result<bool> {
+        self.inner.check_integrity().await
+    }
+
+    async fn scan_process(&self, process_name: &str) -> Result<ThreatLevel> {
+        self.inner.scan_process(process_name).await
+    }
+
+    async fn report_threat(&self, report: ThreatReport) -> Result<()> {
+        self.inner.report_threat(report.clone()).await?;
+        if is_instance_scoped(&report.source_id) {
+            return Ok(());
+        }
+        if let Err(e) = self.p2p.report_threat(report).await {
+            warn!("Failed to share threat report: {}", e);
+        }
+        Ok(())
+    }
+}
+
+/// Threat sharing state owned by the node's event loop.
+pub(crate) struct ThreatExchange {
+    db: Arc<ThreatDatabase>,
+    keypair: Keypair,
+    guard: ReportGuard,
+    trusted: HashSet<PeerId>,
+}
+
+impl ThreatExchange {
+    pub(crate) fn new(
+        db: Arc<ThreatDatabase>,
+        keypair: Keypair,
+        trusted: HashSet<PeerId>,
+    ) -> Self {
+        Self {
+            db,
+            keypair,
+            guard: ReportGuard::new(),
+            trusted,
+        }
+    }
+
+    /// Count `peer`'s reports towards corroboration.
+    pub(crate) fn trust(&mut self, peer: PeerId) {
+        self.trusted.insert(peer);
+    }
+
+    pub(crate) fn encode(&self, report: &ThreatReport) -> std::result::Result<Vec<u8>, P2pError> {
+        validate_report(report, unix_now())?;
+        let signed = SignedThreatReport::sign(report, &self.keypair)?;
+        serde_json::to_vec(&signed).map_err(|e| P2pError::InvalidMessage(e.to_string()))
+    }
+
+    /// Verify and record a received report. `Ok(false)` means it was valid
+    /// but ignored: a duplicate, over the reporter's rate limit, or not
+    /// admitted to a full database.
+    pub(crate) fn receive(&mut self, data: &[u8]) -> std::result::Result<bool, P2pError> {
+        let signed: SignedThreatReport =
+            serde_json::from_slice(data).map_err(|e| P2pError::InvalidMessage(e.to_string()))?;
+        let (report, reporter) = signed.verify()?;
+        validate_report(&report, unix_now())?;
+
+        match self.guard.admit(reporter, &signed.payload, Instant::now()) {
+            Admission::New => {
+                let trusted = self.trusted.contains(&reporter);
+                Ok(self.db.record_peer(&report, &reporter.to_string(), trusted))
+            }
+            Admission::Duplicate | Admission::RateLimited => Ok(false),
+        }
+    }
+}
+
+fn unix_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(indicator: &str) -> ThreatReport {
+        ThreatReport {
+            source_id: indicator.to_string(),
+            threat_type: "process".to_string(),
+            level: ThreatLevel::Malicious,
+            description: "test".to_string(),
+            timestamp: unix_now(),
+            rule: None,
+        }
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = Keypair::generate_ed25519();
+        let signed = SignedThreatReport::sign(&report("evil.exe"), &keypair).unwrap();
+        let (verified, reporter) = signed.verify().unwrap();
+        assert_eq!(verified.source_id, "evil.exe");
+        assert_eq!(reporter, keypair.public().to_peer_id());
+
+        let mut forged = signed.clone();
+        forged.payload = serde_json::to_vec(&report("notepad.exe")).unwrap();
+        assert!(forged.verify().is_err());
+
+        let mut impostor = signed;
+        impostor.public_key = Keypair::generate_ed25519().public().encode_protobuf();
+        assert!(impostor.verify().is_err());
+    }
+
+    #[test]
+    fn test_validation() {
+        let now = unix_now();
+        assert!(validate_report(&report("evil.exe"), now).is_ok());
+        assert!(validate_report(&report(" "), now).is_err());
+        assert!(validate_report(&report("sh[2]"), now).is_err());
+        assert!(validate_report(&report("[2]"), now).is_err());
+        assert!(validate_report(&report("notepad[x]"), now).is_ok());
+        assert!(validate_report(
+            &ThreatReport {
+                level: ThreatLevel::Safe,
+                ..report("x")
+            },
+            now
+        )
+        .is_err());
+        assert!(validate_report(
+            &ThreatReport {
+                timestamp: 0,
+                ..report("x")
+            },
+            now
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_guard_dedups_and_rate_limits() {
+        let mut guard = ReportGuard::new();
+        let peer = PeerId::random();
+        let start = Instant::now();
+
+        assert_eq!(guard.admit(peer, b"a", start), Admission::New);
+        assert_eq!(guard.admit(peer, b"a", start), Admission::Duplicate);
+
+        for i in 1..RATE_LIMIT {
+            assert_eq!(guard.admit(peer, &i.to_be_bytes(), start), Admission::New);
+        }
+        assert_eq!(guard.admit(peer, b"over", start), Admission::RateLimited);
+        assert_eq!(
+            guard.admit(PeerId::random(), b"other", start),
+            Admission::New
+        );
+
+        // The limit resets after the window
+        assert_eq!(
+            guard.admit(peer, b"over", start + RATE_WINDOW),
+            Admission::New
+        );
+
+        // Fresh identities cannot grow the window map without bound
+        for i in 0..TRACKED_REPORTERS {
+            guard.admit(PeerId::random(), &(i as u64).to_be_bytes(), start + RATE_WINDOW);
+        }
+        assert_eq!(
+            guard.admit(PeerId::random(), b"flood", start + RATE_WINDOW),
+            Admission::RateLimited
+        );
+        assert_eq!(
+            guard.admit(PeerId::random(), b"flood", start + RATE_WINDOW * 2),
+            Admission::New
+        );
+    }
+
+    #[test]
+    fn test_only_trusted_reporters_corroborate() {
+        let db = Arc::new(ThreatDatabase::new());
+        let paired = [Keypair::generate_ed25519(), Keypair::generate_ed25519()];
+        let trusted = paired.iter().map(|k| k.public().to_peer_id()).collect();
+        let mut exchange = ThreatExchange::new(db.clone(), Keypair::generate_ed25519(), trusted);
+
+        let send = |exchange: &mut ThreatExchange, indicator: &str, keypair: &Keypair| {
+            let report = ThreatReport {
+                description: format!("seen by {}", keypair.public().to_peer_id()),
+                ..report(indicator)
+            };
+            let signed = SignedThreatReport::sign(&report, keypair).unwrap();
+            exchange.receive(&serde_json::to_vec(&signed).unwrap()).unwrap()
+        };
+
+        for _ in 0..3 {
+            assert!(send(&mut exchange, "notepad.exe", &Keypair::generate_ed25519()));
+        }
+        assert_eq!(db.level("notepad.exe"), ThreatLevel::Safe);
+
+        for keypair in &paired {
+            assert!(send(&mut exchange, "evil.exe", keypair));
+        }
+        assert_eq!(db.level("evil.exe"), ThreatLevel::Malicious);
+    }
+}
@@ -0,0 +1,36 @@
+//! API-key authentication.
+//!
+//! When a key is configured, requests must send it as
+//! `Authorization: Bearer <key>` or `X-API-Key: <key>`.
+
+use axum::extract::{Request, State};
+use axum::http::header::AUTHORIZATION;
+use axum::http::HeaderMap;
+use axum::middleware::Next;
+use axum::response::Response;
+use synapse_core::logic::memory_api::constant_time_eq;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Header accepted as an alternative to a bearer token.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Middleware rejecting requests without the configured API key.
+pub async fn require_api_key(State(state): State<AppState>, request: Request, next: Next) -> Result<Response, ApiError> {
+    if let Some(expected) = &state.api_key {
+        match presented_key(request.headers()) {
+            Some(key) if constant_time_eq(key.as_bytes(), expected.as_bytes()) => {}
+            _ => return Err(ApiError::unauthorized()),
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    bearer.or_else(|| headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()))
+}
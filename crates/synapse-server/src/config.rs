@@ -0,0 +1,178 @@
+//! Server configuration and adapter loading.
+//!
+//! Reads the model settings from the same `synapse.json` as the CLI; other
+//! fields in that file are ignored.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use synapse_core::logic::threat_db::ThreatDatabase;
+use synapse_core::ports::{ContextPort, EmbeddingPort, ImmunePort, LlmPort};
+use synapse_infra::adapters::cached_embedder::{CachedEmbedder, DEFAULT_CACHE_CAPACITY};
+use synapse_infra::adapters::candle_adapter::CandleAdapter;
+use synapse_infra::adapters::candle_embedding_adapter::CandleEmbeddingAdapter;
+use synapse_infra::adapters::immune_adapter::BasicImmuneAdapter;
+use synapse_infra::adapters::integrity::IntegrityConfig;
+use synapse_infra::adapters::model_descriptor::{EmbeddingBackend, ModelDescriptor};
+use synapse_infra::adapters::openai_adapter::{OpenAiConfig, OpenAiEmbeddingAdapter, OpenAiLlmAdapter};
+use synapse_infra::adapters::ort_adapter::OrtAdapter;
+use synapse_infra::adapters::ort_classifier_adapter::OrtInjectionClassifier;
+use synapse_immune::engine::RELOAD_INTERVAL;
+use synapse_immune::injection::{InjectionDetector, DEFAULT_THRESHOLD};
+use synapse_immune::{RuleEngine, RuleScanner, SWEEP_INTERVAL};
+use synapse_p2p::P2pSettings;
+
+/// Model settings used by the server.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerConfig {
+    /// Chat/summarization model
+    #[serde(default = "ModelDescriptor::tinyllama")]
+    pub llm: ModelDescriptor,
+
+    /// Embedding model
+    #[serde(default = "ModelDescriptor::minilm")]
+    pub embedding: ModelDescriptor,
+
+    /// OpenAI-compatible server to use instead of the local LLM
+    #[serde(default)]
+    pub llm_endpoint: Option<OpenAiConfig>,
+
+    /// OpenAI-compatible server to use instead of the local embedding model
+    #[serde(default)]
+    pub embedding_endpoint: Option<OpenAiConfig>,
+
+    /// Prompt-injection screening of stored and retrieved memories
+    #[serde(default)]
+    pub injection: InjectionConfig,
+
+    /// Self-integrity verification
+    #[serde(default)]
+    pub integrity: IntegrityConfig,
+
+    /// Threat rule file or directory, reloaded when it changes
+    #[serde(default)]
+    pub threat_rules: Option<PathBuf>,
+
+    /// Replication to the user's other devices and threat sharing
+    #[serde(default)]
+    pub p2p: Option<P2pSettings>,
+}
+
+/// How memories are screened for prompt injection.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct InjectionConfig {
+    /// Score at or above which a memory is quarantined
+    pub threshold: f32,
+
+    /// ONNX sequence-classification model to use alongside the heuristics
+    pub classifier: Option<ModelDescriptor>,
+}
+
+impl Default for InjectionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            classifier: None,
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            llm: ModelDescriptor::tinyllama(),
+            embedding: ModelDescriptor::minilm(),
+            llm_endpoint: None,
+            embedding_endpoint: None,
+            injection: InjectionConfig::default(),
+            integrity: IntegrityConfig::default(),
+            threat_rules: None,
+            p2p: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Load `path`, or defaults if it does not exist.
+    pub async fn load_or_default(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !tokio::fs::try_exists(path).await.unwrap_or(false) {
+            return Ok(Self::default());
+        }
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .context("Failed to read config file")?;
+        serde_json::from_str(&content).context("Failed to parse config JSON")
+    }
+
+    /// Build the immune adapter: integrity checks against the manifest
+    /// (in `data_dir` unless configured), and known threats from `threats`.
+    pub fn load_immune(&self, data_dir: &Path, threats: Arc<ThreatDatabase>) -> Result<Arc<dyn ImmunePort>> {
+        let mut immune = BasicImmuneAdapter::with_threat_db(threats);
+        if let Some(checker) = self.integrity.load_checker(data_dir)? {
+            immune = immune.with_integrity(checker);
+        }
+        Ok(Arc::new(immune))
+    }
+
+    /// Scan processes with the threat rules, if any are set, reporting
+    /// matches to `immune`. All processes are also swept in the background.
+    pub fn with_threat_rules(
+        &self,
+        immune: Arc<dyn ImmunePort>,
+        context: Arc<dyn ContextPort>,
+    ) -> Result<Arc<dyn ImmunePort>> {
+        let Some(rules) = &self.threat_rules else {
+            return Ok(immune);
+        };
+        let engine = Arc::new(RuleEngine::load(rules).context("Failed to load threat rules")?);
+        engine.watch(RELOAD_INTERVAL);
+        let scanner = Arc::new(RuleScanner::new(immune, context, engine));
+        scanner.watch(SWEEP_INTERVAL);
+        Ok(scanner)
+    }
+
+    /// Load the configured LLM (local model or HTTP endpoint).
+    pub fn load_llm(&self) -> Result<Arc<dyn LlmPort>> {
+        let llm: Arc<dyn LlmPort> = match &self.llm_endpoint {
+            Some(endpoint) => Arc::new(OpenAiLlmAdapter::new(endpoint.clone())?),
+            None => Arc::new(CandleAdapter::from_descriptor(&self.llm).context("Failed to load LLM")?),
+        };
+        Ok(llm)
+    }
+
+    /// Load the configured embedder, cached under `data_dir`.
+    pub async fn load_embedder(&self, data_dir: &Path) -> Result<Arc<dyn EmbeddingPort>> {
+        let cache_path = data_dir.join("embedding_cache");
+        if let Some(endpoint) = &self.embedding_endpoint {
+            let embedder = OpenAiEmbeddingAdapter::new(endpoint.clone())?.detect_dimension().await?;
+            return cached(embedder, cache_path);
+        }
+        match self.embedding.embedding_backend()? {
+            EmbeddingBackend::Candle => cached(CandleEmbeddingAdapter::from_descriptor(&self.embedding)?, cache_path),
+            _ => cached(OrtAdapter::from_descriptor(&self.embedding)?, cache_path),
+        }
+    }
+}
+
+impl InjectionConfig {
+    /// Build the detector, loading the classifier if one is configured.
+    pub fn load_detector(&self) -> Result<InjectionDetector> {
+        let mut detector = InjectionDetector::new().with_threshold(self.threshold);
+        if let Some(descriptor) = &self.classifier {
+            let classifier = OrtInjectionClassifier::from_descriptor(descriptor)
+                .context("Failed to load injection classifier")?;
+            detector = detector.with_classifier(Arc::new(classifier));
+        }
+        Ok(detector)
+    }
+}
+
+fn cached<E: EmbeddingPort + 'static>(embedder: E, path: PathBuf) -> Result<Arc<dyn EmbeddingPort>> {
+    let cached = CachedEmbedder::new(embedder, DEFAULT_CACHE_CAPACITY)
+        .with_persistent_tier(&path.to_string_lossy())?;
+    Ok(Arc::new(cached))
+}
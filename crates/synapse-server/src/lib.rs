@@ -0,0 +1,317 @@
+//! # Synapse Server
+//!
+//! Long-running HTTP/JSON API over the Synapse memory system, so apps do
+//! not have to reopen the database and reload models on every call.
+//!
+//! ## Endpoints
+//!
+//! | Method | Path | |
+//! |---|---|---|
+//! | `GET` | `/health` | Liveness (public) |
+//! | `GET` | `/openapi.json` | OpenAPI 3 description (public) |
+//! | `POST` | `/v1/memories` | Store a memory |
+//! | `POST` | `/v1/memories/search` | Semantic search |
+//! | `GET`, `DELETE` | `/v1/memories/:id` | Fetch or delete a memory |
+//! | `GET` | `/v1/memories/:id/relationships` | Graph edges of a memory |
+//! | `POST` | `/v1/relationships` | Link two memories |
+//! | `POST` | `/v1/buffer` | Queue an exchange for digestion |
+//! | `POST` | `/v1/digest` | Digest the buffer |
+//! | `POST` | `/v1/consolidate` | Consolidate layers |
+//! | `POST` | `/v1/chat` | Chat (JSON or SSE) |
+//! | `GET` | `/v1/stats` | Counts |
+//! | `POST` | `/mcp` | Model Context Protocol (see [`mcp`]) |
+//!
+//! `/v1` and `/mcp` require the API key when one is configured (see [`auth`]).
+//! `/mcp` only accepts JSON bodies and rejects foreign browser origins.
+
+pub mod auth;
+pub mod config;
+pub mod error;
+pub mod mcp;
+pub mod openapi;
+pub mod routes;
+pub mod state;
+
+pub use error::{ApiError, ApiResult};
+pub use state::AppState;
+
+use axum::middleware;
+use axum::routing::{get, post};
+use axum::Router;
+
+use routes::{chat, memories, metabolism};
+
+/// Build the application router.
+pub fn router(state: AppState) -> Router {
+    let api = Router::new()
+        .route("/v1/memories", post(memories::store))
+        .route("/v1/memories/search", post(memories::search))
+        .route("/v1/memories/:id", get(memories::get).delete(memories::delete))
+        .route("/v1/memories/:id/relationships", get(memories::related))
+        .route("/v1/relationships", post(memories::relate))
+        .route("/v1/buffer", post(metabolism::push))
+        .route("/v1/digest", post(metabolism::digest))
+        .route("/v1/consolidate", post(metabolism::consolidate))
+        .route("/v1/chat", post(chat::chat))
+        .route("/v1/stats", get(metabolism::stats))
+        .route("/mcp", post(routes::mcp::handle))
+        .route_layer(middleware::from_fn_with_state(state.clone(), auth::require_api_key));
+
+    Router::new()
+        .route("/health", get(routes::health))
+        .route("/openapi.json", get(openapi::spec))
+        .merge(api)
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use http_body_util::BodyExt;
+    use serde_json::{json, Value};
+    use synapse_infra::adapters::mock_embedding_adapter::MockEmbeddingAdapter;
+    use synapse_infra::adapters::mock_llm_adapter::MockLlmAdapter;
+    use synapse_infra::adapters::sled_adapter::SledAdapter;
+    use synapse_infra::adapters::surrealdb_adapter::SurrealDbAdapter;
+    use tempfile::TempDir;
+    use tower::ServiceExt;
+
+    struct TestServer {
+        router: Router,
+        _dir: TempDir,
+    }
+
+    async fn server(api_key: Option<&str>) -> TestServer {
+        let dir = TempDir::new().unwrap();
+        let buffer = SledAdapter::new(&dir.path().join("buffer").to_string_lossy()).unwrap();
+        let mut state = AppState::new(
+            Arc::new(SurrealDbAdapter::new_memory().await.unwrap()),
+            Arc::new(MockEmbeddingAdapter::new()),
+            Arc::new(MockLlmAdapter::new()),
+            Arc::new(buffer),
+        );
+        if let Some(key) = api_key {
+            state = state.with_api_key(key);
+        }
+        TestServer { router: router(state), _dir: dir }
+    }
+
+    impl TestServer {
+        async fn send(&self, request: Request<Body>) -> (StatusCode, Vec<u8>) {
+            let response = self.router.clone().oneshot(request).await.unwrap();
+            let status = response.status();
+            let body = response.into_body().collect().await.unwrap().to_bytes().to_vec();
+            (status, body)
+        }
+
+        async fn call(&self, method: &str, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+            let request = Request::builder().method(method).uri(uri);
+            let request = match body {
+                Some(body) => request
+                    .header("content-type", "application/json")
+                    .body(Body::from(body.to_string())),
+                None => request.body(Body::empty()),
+            };
+            let (status, body) = self.send(request.unwrap()).await;
+            let json = serde_json::from_slice(&body).unwrap_or(Value::Null);
+            (status, json)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_store_get_search_delete() {
+        let server = server(None).await;
+
+        let (status, stored) = server
+            .call("POST", "/v1/memories", Some(json!({ "content": "The cat sleeps on the mat", "namespace": "pets" })))
+            .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let id = stored["id"].as_str().unwrap().to_string();
+
+        let (status, memory) = server.call("GET", &format!("/v1/memories/{}", id), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(memory["content"], "The cat sleeps on the mat");
+        assert_eq!(memory["namespace"], "pets");
+        assert_eq!(memory["source"], "api");
+        assert!(memory.get("embedding").is_none());
+
+        let (status, hits) = server
+            .call("POST", "/v1/memories/search", Some(json!({ "query": "The cat sleeps on the mat", "namespace": "pets" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(hits[0]["id"], id.as_str());
+        assert!(hits[0]["distance"].is_number());
+
+        let (status, _) = server.call("DELETE", &format!("/v1/memories/{}", id), None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (status, error) = server.call("GET", &format!("/v1/memories/{}", id), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert!(error["error"].as_str().unwrap().contains(&id));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_requests_are_rejected() {
+        let server = server(None).await;
+
+        let (status, _) = server.call("POST", "/v1/memories", Some(json!({ "content": "  " }))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = server
+            .call("POST", "/v1/memories/search", Some(json!({ "query": "x", "top_k": 0 })))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = server
+            .call("POST", "/v1/relationships", Some(json!({ "from_id": "a", "relation": "x; DELETE", "to_id": "b" })))
+            .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_relationships() {
+        let server = server(None).await;
+        let (_, a) = server.call("POST", "/v1/memories", Some(json!({ "content": "Summary" }))).await;
+        let (_, b) = server.call("POST", "/v1/memories", Some(json!({ "content": "Fact" }))).await;
+        let edge = json!({ "from_id": a["id"], "relation": "summarizes", "to_id": b["id"] });
+
+        let (status, created) = server.call("POST", "/v1/relationships", Some(edge.clone())).await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created, edge);
+
+        let uri = format!("/v1/memories/{}/relationships", b["id"].as_str().unwrap());
+        let (status, edges) = server.call("GET", &uri, None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(edges, json!([edge]));
+
+        let missing = json!({ "from_id": a["id"], "relation": "summarizes", "to_id": "missing" });
+        let (status, _) = server.call("POST", "/v1/relationships", Some(missing)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_buffer_digest_and_stats() {
+        let server = server(None).await;
+        let exchange = json!({ "user_input": "My dog is Rex", "ai_response": "Nice name!" });
+        let (status, pushed) = server.call("POST", "/v1/buffer", Some(exchange)).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(pushed["buffer_size"], 1);
+
+        // Below the default threshold unless forced
+        let (_, digest) = server.call("POST", "/v1/digest", None).await;
+        assert_eq!(digest["digested"], 0);
+        let (status, digest) = server.call("POST", "/v1/digest", Some(json!({ "force": true }))).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(digest["digested"], 1);
+
+        let (status, consolidated) = server.call("POST", "/v1/consolidate", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(consolidated["summaries"], 0);
+
+        let (status, stats) = server.call("GET", "/v1/stats", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(stats, json!({ "total_memories": 1, "buffer_size": 0, "layers": [{ "layer": 0, "count": 1 }] }));
+    }
+
+    #[tokio::test]
+    async fn test_chat_json_records_exchange_in_session() {
+        let server = server(None).await;
+        server.call("POST", "/v1/memories", Some(json!({ "content": "Rex is a dog" }))).await;
+
+        let (status, reply) = server
+            .call("POST", "/v1/chat", Some(json!({ "message": "Who is Rex?", "session_id": "s1" })))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(reply["session_id"], "s1");
+        assert!(reply["reply"].as_str().unwrap().starts_with("Mock response"));
+        assert_eq!(reply["memories"][0]["content"], "Rex is a dog");
+        assert_eq!(reply["usage"]["finish_reason"], "stop");
+
+        let (_, stats) = server.call("GET", "/v1/stats", None).await;
+        assert_eq!(stats["buffer_size"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_chat_streams_server_sent_events() {
+        let server = server(None).await;
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/chat")
+            .header("content-type", "application/json")
+            .body(Body::from(json!({ "message": "Hello", "stream": true }).to_string()))
+            .unwrap();
+
+        let (status, body) = server.send(request).await;
+        assert_eq!(status, StatusCode::OK);
+        let body = String::from_utf8(body).unwrap();
+        let events: Vec<&str> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("event: "))
+            .collect();
+        assert_eq!(events.first(), Some(&"memories"));
+        assert!(events.contains(&"delta"));
+        assert_eq!(events.last(), Some(&"done"));
+
+        let (_, stats) = server.call("GET", "/v1/stats", None).await;
+        assert_eq!(stats["buffer_size"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_api_key_is_required_when_configured() {
+        let server = server(Some("secret")).await;
+
+        let (status, _) = server.call("GET", "/v1/stats", None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        for (header, value) in [("authorization", "Bearer secret"), ("x-api-key", "secret")] {
+            let request = Request::get("/v1/stats").header(header, value).body(Body::empty()).unwrap();
+            assert_eq!(server.send(request).await.0, StatusCode::OK);
+        }
+        let request = Request::get("/v1/stats").header("x-api-key", "wrong").body(Body::empty()).unwrap();
+        assert_eq!(server.send(request).await.0, StatusCode::UNAUTHORIZED);
+
+        // Health and the API description stay public
+        assert_eq!(server.call("GET", "/health", None).await.0, StatusCode::OK);
+        let (status, spec) = server.call("GET", "/openapi.json", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(spec["paths"]["/v1/chat"]["post"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_mcp_over_http() {
+        let server = server(Some("secret")).await;
+        let ping = json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" }).to_string();
+
+        let mcp = |content_type: &str, body: &str| {
+            Request::post("/mcp")
+                .header("x-api-key", "secret")
+                .header("content-type", content_type)
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        let request = Request::post("/mcp").body(Body::from(ping.clone())).unwrap();
+        assert_eq!(server.send(request).await.0, StatusCode::UNAUTHORIZED);
+
+        let (status, body) = server.send(mcp("application/json", &ping)).await;
+        assert_eq!(status, StatusCode::OK);
+        let response: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(response, json!({ "jsonrpc": "2.0", "id": 1, "result": {} }));
+
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }).to_string();
+        assert_eq!(server.send(mcp("application/json", &notification)).await.0, StatusCode::ACCEPTED);
+
+        // A web page can send text/plain without a preflight; it is refused
+        assert_eq!(server.send(mcp("text/plain", &ping)).await.0, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let mut request = mcp("application/json", &ping);
+        request.headers_mut().insert("origin", "https://evil.example".parse().unwrap());
+        assert_eq!(server.send(request).await.0, StatusCode::FORBIDDEN);
+        let mut request = mcp("application/json", &ping);
+        request.headers_mut().insert("origin", "http://localhost:6274".parse().unwrap());
+        assert_eq!(server.send(request).await.0, StatusCode::OK);
+    }
+}
@@ -0,0 +1,188 @@
+//! Synapse Server - HTTP/JSON API for Synapse Protocol.
+//!
+//! With `--mcp` it instead speaks the Model Context Protocol over stdio, for
+//! assistants that launch it as a subprocess. Logs always go to stderr.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use synapse_core::logic::threat_db::ThreatDatabase;
+use synapse_core::ports::ContextPort;
+use synapse_core::MemoryPort;
+use synapse_grpc::SynapseGrpc;
+use synapse_core::logic::embedding_check::EmbeddingCheck;
+use synapse_immune::{EmbeddingScanner, GuardedMemory, ImmuneSystem, REVIEW_KEY_FILE};
+use synapse_infra::adapters::private_file::load_or_create_key;
+use synapse_infra::adapters::sled_adapter::SledAdapter;
+use synapse_infra::adapters::surrealdb_adapter::SurrealDbAdapter;
+use synapse_server::config::ServerConfig;
+use synapse_server::mcp::{self, McpServer};
+use synapse_server::AppState;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+#[derive(Parser)]
+#[command(name = "synapse-server")]
+#[command(author, version, about = "Synapse Protocol - HTTP/JSON API server")]
+struct Args {
+    /// Address to listen on
+    #[arg(short, long, default_value = "127.0.0.1:7878")]
+    bind: SocketAddr,
+
+    /// Also serve the gRPC API on this address
+    #[arg(long)]
+    grpc: Option<SocketAddr>,
+
+    /// Directory holding the memory database and buffer
+    #[arg(short, long, default_value = "./synapse_data")]
+    data_dir: PathBuf,
+
+    /// Model configuration file
+    #[arg(short, long, default_value = "./synapse.json")]
+    config: PathBuf,
+
+    /// Require this API key on every /v1 request
+    #[arg(long, env = "SYNAPSE_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// Also accept /mcp requests from this browser origin (repeatable;
+    /// loopback origins are always accepted)
+    #[arg(long = "allow-origin")]
+    allowed_origins: Vec<String>,
+
+    /// Serve the Model Context Protocol over stdin/stdout instead of HTTP
+    #[arg(long)]
+    mcp: bool,
+
+    /// Scan stored embeddings for anomalies and re-embed them this often
+    /// (seconds, 0 disables)
+    #[arg(long, default_value = "3600")]
+    scan_interval: u64,
+
+    /// Enable verbose logging
+    #[arg(short, long)]
+    verbose: bool,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let filter = if args.verbose { "debug" } else { "info" };
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| filter.into()))
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+
+    if !args.mcp && args.api_key.is_none() {
+        for address in std::iter::once(args.bind).chain(args.grpc) {
+            if !address.ip().is_loopback() {
+                bail!("Refusing to listen on {} without an API key (set SYNAPSE_API_KEY)", address);
+            }
+        }
+    }
+
+    let config = ServerConfig::load_or_default(&args.config).await?;
+    tracing::info!("Loading models...");
+    let embedder = config.load_embedder(&args.data_dir).await
+        .context("Failed to load embedding model")?;
+
+    let memory_path = args.data_dir.join("memory");
+    let buffer_path = args.data_dir.join("buffer");
+    let store = Arc::new(
+        SurrealDbAdapter::new(&memory_path.to_string_lossy()).await?
+            .with_embedding_check(EmbeddingCheck::for_embedder(embedder.as_ref())),
+    );
+    let threats = Arc::new(ThreatDatabase::new());
+    let immune = config.load_immune(&args.data_dir, threats.clone())?;
+    let detector = config.injection.load_detector()?;
+    let mut memory: Arc<dyn MemoryPort> = Arc::new(
+        GuardedMemory::new(store.clone(), immune.clone())
+            .with_detector(detector)
+            .with_review_key(
+                load_or_create_key(&args.data_dir.join(REVIEW_KEY_FILE)).context("Failed to load review key")?,
+            ),
+    );
+
+    // Peers' changes go through the guarded store. Only process threats
+    // are shared; quarantined memories are reported locally.
+    let mut reporter = immune.clone();
+    if let Some(p2p) = &config.p2p {
+        let replication = p2p.start(args.data_dir.join("p2p"), memory, immune.clone(), threats)
+            .context("Failed to start P2P node")?;
+        tracing::info!("P2P node {} started", replication.handle.local_peer_id());
+        memory = replication.memory;
+        reporter = replication.immune;
+    }
+    let context = context_adapter();
+    let scanner = config.with_threat_rules(reporter, context.clone())?;
+    ImmuneSystem::new(context, scanner).start().await;
+
+    if args.scan_interval > 0 {
+        let scanner = EmbeddingScanner::new(store, immune).with_embedder(embedder.clone());
+        Arc::new(scanner).watch(Duration::from_secs(args.scan_interval), true);
+    }
+    let buffer = Arc::new(SledAdapter::new(&buffer_path.to_string_lossy())?);
+
+    // MCP tools only need memory and the buffer, so the LLM is not loaded.
+    if args.mcp {
+        tracing::info!("Serving MCP on stdio");
+        let server = McpServer::new(memory, embedder, buffer);
+        let stdin = tokio::io::BufReader::new(tokio::io::stdin());
+        mcp::serve_stdio(&server, stdin, tokio::io::stdout()).await?;
+        return Ok(());
+    }
+
+    let llm = config.load_llm()?;
+    let mut state = AppState::new(memory.clone(), embedder.clone(), llm.clone(), buffer.clone())
+        .with_allowed_origins(args.allowed_origins.clone());
+    if let Some(api_key) = &args.api_key {
+        state = state.with_api_key(api_key.clone());
+    }
+
+    if let Some(address) = args.grpc {
+        let mut grpc = SynapseGrpc::new(memory, embedder, llm, buffer)
+            .with_maintenance_lock(state.maintenance_lock());
+        if let Some(api_key) = &args.api_key {
+            grpc = grpc.with_api_key(api_key.clone());
+        }
+        let listener = tokio::net::TcpListener::bind(address).await
+            .with_context(|| format!("Failed to bind {}", address))?;
+        tracing::info!("gRPC listening on {}", address);
+        tokio::spawn(async move {
+            let shutdown = async {
+                let _ = tokio::signal::ctrl_c().await;
+            };
+            if let Err(e) = grpc.serve(listener, shutdown).await {
+                tracing::error!("gRPC server failed: {}", e);
+            }
+        });
+    }
+
+    let listener = tokio::net::TcpListener::bind(args.bind).await
+        .with_context(|| format!("Failed to bind {}", args.bind))?;
+    tracing::info!("Listening on http://{}", args.bind);
+
+    axum::serve(listener, synapse_server::router(state))
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    Ok(())
+}
+
+/// The context adapter for the current OS.
+fn context_adapter() -> Arc<dyn ContextPort> {
+    #[cfg(target_os = "linux")]
+    let adapter = synapse_infra::adapters::linux_context_adapter::LinuxContextAdapter::new();
+
+    #[cfg(not(target_os = "linux"))]
+    let adapter = synapse_infra::adapters::context_adapter::WindowsContextAdapter::new();
+
+    Arc::new(adapter)
+}
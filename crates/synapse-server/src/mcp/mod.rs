@@ -0,0 +1,293 @@
+//! Model Context Protocol server.
+//!
+//! Exposes Synapse memory to coding assistants and agents as MCP tools
+//! (`remember`, `recall`, `forget`, `list_namespaces`, `get_related`,
+//! `log_exchange`) and resources (`synapse://memory/{id}`).
+//!
+//! Two transports share [`McpServer::handle`]:
+//! - stdio: newline-delimited JSON-RPC ([`serve_stdio`], `synapse-server --mcp`)
+//! - HTTP: `POST /mcp` on the API server, behind the API key
+
+pub mod protocol;
+pub mod resources;
+pub mod tools;
+
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use synapse_core::ports::{BufferPort, EmbeddingPort, MemoryPort};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+use protocol::{Request, RpcError};
+
+/// Newest protocol revision spoken; older clients get the revision they ask for.
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+const SUPPORTED_VERSIONS: &[&str] = &[PROTOCOL_VERSION, "2025-03-26", "2024-11-05"];
+
+/// MCP request handler over the memory ports.
+#[derive(Clone)]
+pub struct McpServer {
+    pub(crate) memory: Arc<dyn MemoryPort>,
+    pub(crate) embedder: Arc<dyn EmbeddingPort>,
+    pub(crate) buffer: Arc<dyn BufferPort>,
+}
+
+#[derive(Deserialize)]
+struct InitializeParams {
+    #[serde(rename = "protocolVersion")]
+    protocol_version: String,
+}
+
+impl McpServer {
+    pub fn new(memory: Arc<dyn MemoryPort>, embedder: Arc<dyn EmbeddingPort>, buffer: Arc<dyn BufferPort>) -> Self {
+        Self {
+            memory,
+            embedder,
+            buffer,
+        }
+    }
+
+    /// Handle one JSON-RPC message (or batch). Returns the response, or
+    /// `None` when there is nothing to send (notifications).
+    pub async fn handle(&self, message: Value) -> Option<Value> {
+        if let Value::Array(batch) = message {
+            if batch.is_empty() {
+                return Some(protocol::failure(Value::Null, RpcError::new(protocol::INVALID_REQUEST, "Empty batch")));
+            }
+            let mut responses = Vec::new();
+            for message in batch {
+                if let Some(response) = Box::pin(self.handle(message)).await {
+                    responses.push(response);
+                }
+            }
+            return (!responses.is_empty()).then_some(Value::Array(responses));
+        }
+
+        let request: Request = match serde_json::from_value(message) {
+            Ok(request) => request,
+            Err(e) => return Some(protocol::failure(Value::Null, RpcError::new(protocol::INVALID_REQUEST, e.to_string()))),
+        };
+        if request.jsonrpc != "2.0" {
+            let id = request.id.unwrap_or(Value::Null);
+            return Some(protocol::failure(id, RpcError::new(protocol::INVALID_REQUEST, "jsonrpc must be \"2.0\"")));
+        }
+
+        let result = self.dispatch(&request.method, request.params).await;
+        let id = request.id?;
+        Some(match result {
+            Ok(result) => protocol::success(id, result),
+            Err(error) => protocol::failure(id, error),
+        })
+    }
+
+    /// Handle one line of the stdio transport.
+    pub async fn handle_line(&self, line: &str) -> Option<Value> {
+        match serde_json::from_str(line) {
+            Ok(message) => self.handle(message).await,
+            Err(e) => Some(protocol::failure(Value::Null, RpcError::new(protocol::PARSE_ERROR, e.to_string()))),
+        }
+    }
+
+    async fn dispatch(&self, method: &str, params: Value) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => {
+                let params: InitializeParams = protocol::params(params)?;
+                let version = if SUPPORTED_VERSIONS.contains(&params.protocol_version.as_str()) {
+                    params.protocol_version.as_str()
+                } else {
+                    PROTOCOL_VERSION
+                };
+                Ok(json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": {}, "resources": {} },
+                    "serverInfo": { "name": "synapse", "version": env!("CARGO_PKG_VERSION") },
+                    "instructions": "Synapse is a long-term memory store. Use `recall` before answering \
+                                     questions about the user or project, and `remember` to save durable facts."
+                }))
+            }
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({ "tools": tools::definitions() })),
+            "tools/call" => tools::call(self, params).await,
+            // Memories are addressed through the template rather than enumerated.
+            "resources/list" => Ok(json!({ "resources": [] })),
+            "resources/templates/list" => Ok(json!({ "resourceTemplates": resources::templates() })),
+            "resources/read" => resources::read(self, params).await,
+            method if method.starts_with("notifications/") => Ok(Value::Null),
+            _ => Err(RpcError::new(protocol::METHOD_NOT_FOUND, format!("Method not found: {}", method))),
+        }
+    }
+}
+
+/// Serve newline-delimited JSON-RPC until `reader` reaches EOF.
+pub async fn serve_stdio<R, W>(server: &McpServer, reader: R, mut writer: W) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut lines = reader.lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_line(&line).await {
+            writer.write_all(response.to_string().as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use synapse_infra::adapters::mock_embedding_adapter::MockEmbeddingAdapter;
+    use synapse_infra::adapters::sled_adapter::SledAdapter;
+    use synapse_infra::adapters::surrealdb_adapter::SurrealDbAdapter;
+    use tempfile::TempDir;
+    use tokio::io::{BufReader, DuplexStream, Lines};
+
+    /// Client end of a stdio session.
+    struct Client {
+        input: DuplexStream,
+        output: Lines<BufReader<DuplexStream>>,
+        next_id: u64,
+    }
+
+    impl Client {
+        async fn send(&mut self, message: Value) {
+            self.input.write_all(format!("{}\n", message).as_bytes()).await.unwrap();
+        }
+
+        async fn receive(&mut self) -> Value {
+            let line = self.output.next_line().await.unwrap().expect("server closed stdout");
+            serde_json::from_str(&line).unwrap()
+        }
+
+        async fn request(&mut self, method: &str, params: Value) -> Value {
+            self.next_id += 1;
+            self.send(json!({ "jsonrpc": "2.0", "id": self.next_id, "method": method, "params": params })).await;
+            let response = self.receive().await;
+            assert_eq!(response["id"], self.next_id);
+            response
+        }
+
+        async fn tool(&mut self, name: &str, arguments: Value) -> Value {
+            let response = self.request("tools/call", json!({ "name": name, "arguments": arguments })).await;
+            response["result"].clone()
+        }
+    }
+
+    async fn connect() -> (Client, tokio::task::JoinHandle<std::io::Result<()>>, TempDir) {
+        let dir = TempDir::new().unwrap();
+        let buffer = SledAdapter::new(&dir.path().join("buffer").to_string_lossy()).unwrap();
+        let server = McpServer::new(
+            Arc::new(SurrealDbAdapter::new_memory().await.unwrap()),
+            Arc::new(MockEmbeddingAdapter::new()),
+            Arc::new(buffer),
+        );
+
+        let (client_in, server_in) = tokio::io::duplex(64 * 1024);
+        let (server_out, client_out) = tokio::io::duplex(64 * 1024);
+        let task = tokio::spawn(async move { serve_stdio(&server, BufReader::new(server_in), server_out).await });
+        let client = Client {
+            input: client_in,
+            output: BufReader::new(client_out).lines(),
+            next_id: 0,
+        };
+        (client, task, dir)
+    }
+
+    #[tokio::test]
+    async fn test_stdio_session() {
+        let (mut client, task, _dir) = connect().await;
+
+        // Handshake
+        let init = client
+            .request("initialize", json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": { "name": "test", "version": "0" }
+            }))
+            .await;
+        assert_eq!(init["result"]["protocolVersion"], "2024-11-05");
+        assert!(init["result"]["capabilities"]["tools"].is_object());
+        client.send(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await;
+
+        let tools = client.request("tools/list", json!({})).await;
+        let names: Vec<&str> = tools["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        for name in ["remember", "recall", "forget", "list_namespaces", "get_related"] {
+            assert!(names.contains(&name), "missing tool {}", name);
+        }
+
+        // remember -> recall -> read resource
+        let stored = client.tool("remember", json!({ "content": "Rex is a dog", "namespace": "pets" })).await;
+        assert_eq!(stored["isError"], false);
+        let id = stored["structuredContent"]["id"].as_str().unwrap().to_string();
+        let uri = stored["structuredContent"]["uri"].as_str().unwrap().to_string();
+        assert_eq!(uri, format!("synapse://memory/{}", id));
+
+        let recalled = client.tool("recall", json!({ "query": "Rex is a dog" })).await;
+        assert_eq!(recalled["structuredContent"]["memories"][0]["id"], id.as_str());
+        let text: Value = serde_json::from_str(recalled["content"][0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(text, recalled["structuredContent"]);
+
+        let namespaces = client.tool("list_namespaces", json!({})).await;
+        assert_eq!(namespaces["structuredContent"]["namespaces"], json!(["pets"]));
+
+        let read = client.request("resources/read", json!({ "uri": uri })).await;
+        let contents = &read["result"]["contents"][0];
+        assert_eq!(contents["mimeType"], "application/json");
+        let node: Value = serde_json::from_str(contents["text"].as_str().unwrap()).unwrap();
+        assert_eq!(node["content"], "Rex is a dog");
+
+        let related = client.tool("get_related", json!({ "id": id })).await;
+        assert_eq!(related["structuredContent"]["relationships"], json!([]));
+
+        let logged = client.tool("log_exchange", json!({ "user_input": "hi", "ai_response": "hello" })).await;
+        assert_eq!(logged["isError"], false);
+
+        // forget, after which the memory is gone
+        let forgotten = client.tool("forget", json!({ "id": id })).await;
+        assert_eq!(forgotten["isError"], false);
+        let again = client.tool("forget", json!({ "id": id })).await;
+        assert_eq!(again["isError"], true);
+        let read = client.request("resources/read", json!({ "uri": uri })).await;
+        assert_eq!(read["error"]["code"], protocol::RESOURCE_NOT_FOUND);
+
+        drop(client);
+        task.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_protocol_errors() {
+        let (mut client, _task, _dir) = connect().await;
+
+        client.input.write_all(b"{not json\n").await.unwrap();
+        let response = client.receive().await;
+        assert_eq!(response["error"]["code"], protocol::PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+
+        let response = client.request("no/such/method", json!({})).await;
+        assert_eq!(response["error"]["code"], protocol::METHOD_NOT_FOUND);
+
+        let response = client.request("tools/call", json!({ "name": "no_such_tool" })).await;
+        assert_eq!(response["error"]["code"], protocol::INVALID_PARAMS);
+
+        // Bad tool arguments are reported to the model, not as protocol errors
+        let result = client.tool("recall", json!({ "top_k": 3 })).await;
+        assert_eq!(result["isError"], true);
+        assert!(result["content"][0]["text"].as_str().unwrap().contains("query"));
+
+        // Notifications get no reply; the next response is for the ping
+        client.send(json!({ "jsonrpc": "2.0", "method": "notifications/cancelled", "params": {} })).await;
+        let response = client.request("ping", json!({})).await;
+        assert_eq!(response["result"], json!({}));
+    }
+}
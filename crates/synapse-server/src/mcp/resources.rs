@@ -0,0 +1,55 @@
+//! MCP resources: every memory node is readable as `synapse://memory/{id}`.
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::protocol::{self, RpcError, RESOURCE_NOT_FOUND};
+use super::McpServer;
+use crate::routes::memories::MemoryView;
+
+/// URI prefix of memory resources.
+pub const MEMORY_URI_PREFIX: &str = "synapse://memory/";
+
+/// The resource URI of a memory.
+pub fn memory_uri(id: &str) -> String {
+    format!("{}{}", MEMORY_URI_PREFIX, id)
+}
+
+/// `resources/templates/list` result entries.
+pub fn templates() -> Value {
+    json!([{
+        "uriTemplate": format!("{}{{id}}", MEMORY_URI_PREFIX),
+        "name": "memory",
+        "description": "A memory node (content, layer, namespace and metadata) by ID",
+        "mimeType": "application/json"
+    }])
+}
+
+#[derive(Deserialize)]
+struct ReadParams {
+    uri: String,
+}
+
+/// `resources/read`
+pub async fn read(server: &McpServer, params: Value) -> Result<Value, RpcError> {
+    let ReadParams { uri } = protocol::params(params)?;
+    let id = uri
+        .strip_prefix(MEMORY_URI_PREFIX)
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| RpcError::new(RESOURCE_NOT_FOUND, format!("Unknown resource: {}", uri)))?;
+
+    let node = server
+        .memory
+        .get_by_id(id)
+        .await?
+        .ok_or_else(|| RpcError::new(RESOURCE_NOT_FOUND, format!("Memory not found: {}", id)))?;
+
+    let view = MemoryView::from(node);
+    Ok(json!({
+        "contents": [{
+            "uri": uri,
+            "mimeType": "application/json",
+            "text": serde_json::to_string(&view).map_err(|e| RpcError::new(protocol::INTERNAL_ERROR, e.to_string()))?
+        }]
+    }))
+}
@@ -0,0 +1,226 @@
+//! MCP tools over long-term memory and the short-term buffer.
+//!
+//! Tool failures (bad arguments, unknown IDs, store errors) are returned as
+//! results with `isError: true` so the model can see and correct them; only
+//! unknown tool names are protocol errors.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use synapse_core::logic::chunked_memory::ChunkedMemory;
+use synapse_core::logic::memory_api::{self, DEFAULT_TOP_K, MAX_TOP_K};
+use synapse_core::{Interaction, MemoryNode};
+
+use super::protocol::{self, RpcError};
+use super::resources::memory_uri;
+use super::McpServer;
+use crate::routes::memories::SearchHit;
+
+/// `tools/list` result entries.
+pub fn definitions() -> Value {
+    json!([
+        {
+            "name": "remember",
+            "description": "Store a fact or note in long-term memory. Returns its ID and resource URI.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "content": { "type": "string", "description": "Text to remember" },
+                    "namespace": { "type": "string", "description": "Namespace (default: \"default\")" },
+                    "metadata": { "type": "object", "description": "Extra JSON metadata" }
+                },
+                "required": ["content"]
+            }
+        },
+        {
+            "name": "recall",
+            "description": "Search long-term memory by meaning. Returns the closest memories first.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "top_k": { "type": "integer", "minimum": 1, "maximum": MAX_TOP_K, "default": 5 },
+                    "namespace": { "type": "string", "description": "Only search this namespace" }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "forget",
+            "description": "Delete a memory (and its chunks) by ID.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "id": { "type": "string" } },
+                "required": ["id"]
+            }
+        },
+        {
+            "name": "list_namespaces",
+            "description": "List the namespaces that contain memories.",
+            "inputSchema": { "type": "object", "properties": {} }
+        },
+        {
+            "name": "get_related",
+            "description": "List graph relationships (e.g. summary -> summarizes -> fact) of a memory.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "id": { "type": "string" } },
+                "required": ["id"]
+            }
+        },
+        {
+            "name": "log_exchange",
+            "description": "Queue a user/assistant exchange in the short-term buffer for later digestion.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "user_input": { "type": "string" },
+                    "ai_response": { "type": "string" },
+                    "session_id": { "type": "string" }
+                },
+                "required": ["user_input", "ai_response"]
+            }
+        }
+    ])
+}
+
+#[derive(Deserialize)]
+struct CallParams {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Deserialize)]
+struct RememberArgs {
+    content: String,
+    #[serde(default)]
+    namespace: Option<String>,
+    #[serde(default)]
+    metadata: HashMap<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct RecallArgs {
+    query: String,
+    #[serde(default = "default_top_k")]
+    top_k: usize,
+    #[serde(default)]
+    namespace: Option<String>,
+}
+
+fn default_top_k() -> usize {
+    DEFAULT_TOP_K
+}
+
+#[derive(Deserialize)]
+struct IdArgs {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct LogArgs {
+    user_input: String,
+    ai_response: String,
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+/// `tools/call`
+pub async fn call(server: &McpServer, params: Value) -> Result<Value, RpcError> {
+    let CallParams { name, arguments } = protocol::params(params)?;
+    let outcome = match name.as_str() {
+        "remember" => remember(server, arguments).await,
+        "recall" => recall(server, arguments).await,
+        "forget" => forget(server, arguments).await,
+        "list_namespaces" => server.memory.list_namespaces().await.map(|n| json!({ "namespaces": n })).map_err(|e| e.to_string()),
+        "get_related" => get_related(server, arguments).await,
+        "log_exchange" => log_exchange(server, arguments).await,
+        _ => return Err(RpcError::invalid_params(format!("Unknown tool: {}", name))),
+    };
+
+    Ok(match outcome {
+        Ok(value) => json!({
+            "content": [{ "type": "text", "text": value.to_string() }],
+            "structuredContent": value,
+            "isError": false
+        }),
+        Err(message) => json!({
+            "content": [{ "type": "text", "text": message }],
+            "isError": true
+        }),
+    })
+}
+
+type ToolResult = std::result::Result<Value, String>;
+
+fn arguments<T: serde::de::DeserializeOwned>(arguments: Value) -> std::result::Result<T, String> {
+    protocol::params(arguments).map_err(|e| format!("Invalid arguments: {}", e.message))
+}
+
+async fn remember(server: &McpServer, args: Value) -> ToolResult {
+    let args: RememberArgs = arguments(args)?;
+    let mut node = MemoryNode::new(args.content);
+    if let Some(namespace) = args.namespace {
+        node = node.with_namespace(namespace);
+    }
+    node.source = "mcp".to_string();
+    node.metadata = args.metadata;
+
+    let id = memory_api::store(server.memory.clone(), server.embedder.clone(), node)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(json!({ "id": id, "uri": memory_uri(&id) }))
+}
+
+async fn recall(server: &McpServer, args: Value) -> ToolResult {
+    let args: RecallArgs = arguments(args)?;
+    let results = memory_api::search(
+        server.memory.clone(),
+        server.embedder.clone(),
+        &args.query,
+        args.namespace.as_deref(),
+        args.top_k,
+    )
+    .await
+    .map_err(|e| e.to_string())?;
+
+    let memories: Vec<SearchHit> = results.into_iter().map(SearchHit::from).collect();
+    Ok(json!({ "memories": memories }))
+}
+
+async fn forget(server: &McpServer, args: Value) -> ToolResult {
+    let IdArgs { id } = arguments(args)?;
+    existing(server, &id).await?;
+    ChunkedMemory::new(server.memory.clone(), server.embedder.clone())
+        .delete(&id)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(json!({ "deleted": id }))
+}
+
+async fn get_related(server: &McpServer, args: Value) -> ToolResult {
+    let IdArgs { id } = arguments(args)?;
+    existing(server, &id).await?;
+    let relationships = server.memory.get_related(&id).await.map_err(|e| e.to_string())?;
+    Ok(json!({ "relationships": relationships }))
+}
+
+async fn log_exchange(server: &McpServer, args: Value) -> ToolResult {
+    let args: LogArgs = arguments(args)?;
+    let mut interaction = Interaction::new(args.user_input, args.ai_response);
+    if let Some(session_id) = args.session_id {
+        interaction = interaction.with_session(session_id);
+    }
+    let id = interaction.id.clone();
+    memory_api::push(server.buffer.as_ref(), interaction).await.map_err(|e| e.to_string())?;
+    Ok(json!({ "id": id }))
+}
+
+async fn existing(server: &McpServer, id: &str) -> std::result::Result<(), String> {
+    match server.memory.get_by_id(id).await.map_err(|e| e.to_string())? {
+        Some(_) => Ok(()),
+        None => Err(format!("Memory not found: {}", id)),
+    }
+}
@@ -0,0 +1,240 @@
+//! OpenAPI 3 description of the API, served at `/openapi.json`.
+
+use axum::Json;
+use serde_json::{json, Value};
+
+/// `GET /openapi.json`
+pub async fn spec() -> Json<Value> {
+    Json(document())
+}
+
+/// The OpenAPI document.
+pub fn document() -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Synapse API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Long-term memory, short-term buffer and memory-grounded chat."
+        },
+        "security": [{ "bearer": [] }, { "apiKey": [] }],
+        "paths": {
+            "/health": {
+                "get": public(op("Liveness probe", None, "200", "Server is up", json!({ "type": "object" })))
+            },
+            "/openapi.json": {
+                "get": public(op("This document", None, "200", "OpenAPI document", json!({ "type": "object" })))
+            },
+            "/v1/memories": {
+                "post": op("Store a memory (long text is chunked)", Some(schema("StoreRequest")), "201", "Stored", schema("StoreResponse"))
+            },
+            "/v1/memories/search": {
+                "post": op("Semantic search, closest first", Some(schema("SearchRequest")), "200", "Matches", array("SearchHit"))
+            },
+            "/v1/memories/{id}": {
+                "parameters": [id_param()],
+                "get": op("Fetch a memory", None, "200", "The memory", schema("Memory")),
+                "delete": {
+                    "summary": "Delete a memory and its chunks",
+                    "responses": with_errors(json!({ "204": { "description": "Deleted" } }))
+                }
+            },
+            "/v1/memories/{id}/relationships": {
+                "parameters": [id_param()],
+                "get": op("Graph edges where the memory is either end", None, "200", "Edges", array("Relationship"))
+            },
+            "/v1/relationships": {
+                "post": op("Link two memories", Some(schema("Relationship")), "201", "Linked", schema("Relationship"))
+            },
+            "/v1/buffer": {
+                "post": op("Queue an exchange for digestion", Some(schema("PushRequest")), "202", "Queued", schema("PushResponse"))
+            },
+            "/v1/digest": {
+                "post": op("Summarize the buffer into Layer 0 (body optional)", Some(schema("DigestRequest")), "200", "Interactions digested", schema("DigestResponse"))
+            },
+            "/v1/consolidate": {
+                "post": op("Summarize full layers into the layer above", None, "200", "Summaries created", schema("ConsolidateResponse"))
+            },
+            "/v1/chat": {
+                "post": {
+                    "summary": "Chat grounded in memory",
+                    "description": "With `stream: true` the reply is `text/event-stream` with events `memories` ({session_id, memories}), `delta` ({text}), `done` ({usage}) and `error` ({error}).",
+                    "requestBody": body(schema("ChatRequest")),
+                    "responses": with_errors(json!({
+                        "200": {
+                            "description": "Reply",
+                            "content": {
+                                "application/json": { "schema": schema("ChatResponse") },
+                                "text/event-stream": { "schema": { "type": "string" } }
+                            }
+                        }
+                    }))
+                }
+            },
+            "/v1/stats": {
+                "get": op("Memory and buffer counts", None, "200", "Counts", schema("StatsResponse"))
+            },
+            "/mcp": {
+                "post": {
+                    "summary": "Model Context Protocol (JSON-RPC 2.0)",
+                    "description": "One JSON-RPC message or batch per request. Notifications are answered with 202 and no body.",
+                    "requestBody": body(json!({ "type": "object" })),
+                    "responses": with_errors(json!({
+                        "200": { "description": "JSON-RPC response", "content": { "application/json": { "schema": { "type": "object" } } } },
+                        "202": { "description": "Notification accepted" }
+                    }))
+                }
+            }
+        },
+        "components": {
+            "securitySchemes": {
+                "bearer": { "type": "http", "scheme": "bearer" },
+                "apiKey": { "type": "apiKey", "in": "header", "name": "X-API-Key" }
+            },
+            "schemas": {
+                "Error": object(&["error"], json!({ "error": string() })),
+                "StoreRequest": object(&["content"], json!({
+                    "content": string(),
+                    "namespace": string(),
+                    "source": string(),
+                    "metadata": { "type": "object", "additionalProperties": true }
+                })),
+                "StoreResponse": object(&["id"], json!({ "id": string() })),
+                "SearchRequest": object(&["query"], json!({
+                    "query": string(),
+                    "top_k": { "type": "integer", "minimum": 1, "maximum": synapse_core::logic::memory_api::MAX_TOP_K, "default": 5 },
+                    "namespace": string()
+                })),
+                "Memory": object(&["id", "content", "layer", "node_type", "namespace"], memory_properties()),
+                "SearchHit": {
+                    "allOf": [schema("Memory"), object(&["distance"], json!({ "distance": { "type": "number" } }))]
+                },
+                "Relationship": object(&["from_id", "relation", "to_id"], json!({
+                    "from_id": string(),
+                    "relation": { "type": "string", "pattern": "^[A-Za-z0-9_]+$" },
+                    "to_id": string()
+                })),
+                "PushRequest": object(&["user_input", "ai_response"], json!({
+                    "user_input": string(),
+                    "ai_response": string(),
+                    "session_id": string()
+                })),
+                "PushResponse": object(&["id", "buffer_size"], json!({ "id": string(), "buffer_size": integer() })),
+                "DigestRequest": object(&[], json!({ "force": { "type": "boolean", "default": false } })),
+                "DigestResponse": object(&["digested"], json!({ "digested": integer() })),
+                "ConsolidateResponse": object(&["summaries"], json!({ "summaries": integer() })),
+                "ChatRequest": object(&["message"], json!({
+                    "message": string(),
+                    "session_id": string(),
+                    "namespace": string(),
+                    "stream": { "type": "boolean", "default": false },
+                    "max_tokens": integer(),
+                    "temperature": { "type": "number" }
+                })),
+                "Usage": object(&["prompt_tokens", "completion_tokens", "finish_reason"], json!({
+                    "prompt_tokens": integer(),
+                    "completion_tokens": integer(),
+                    "finish_reason": { "type": "string", "enum": ["stop", "length", "cancelled"] }
+                })),
+                "ChatResponse": object(&["session_id", "reply", "memories", "usage"], json!({
+                    "session_id": string(),
+                    "reply": string(),
+                    "memories": array("SearchHit"),
+                    "usage": schema("Usage")
+                })),
+                "StatsResponse": object(&["total_memories", "buffer_size", "layers"], json!({
+                    "total_memories": integer(),
+                    "buffer_size": integer(),
+                    "layers": {
+                        "type": "array",
+                        "items": object(&["layer", "count"], json!({ "layer": integer(), "count": integer() }))
+                    }
+                }))
+            }
+        }
+    })
+}
+
+fn memory_properties() -> Value {
+    json!({
+        "id": string(),
+        "content": string(),
+        "layer": integer(),
+        "node_type": { "type": "string", "enum": ["Fact", "Summary", "Thought", "Profile", "System", "External"] },
+        "created_at": integer(),
+        "updated_at": integer(),
+        "namespace": string(),
+        "source": string(),
+        "metadata": { "type": "object", "additionalProperties": true }
+    })
+}
+
+/// An operation with an optional JSON body and one JSON success response.
+fn op(summary: &str, request: Option<Value>, status: &str, description: &str, response: Value) -> Value {
+    let mut operation = json!({
+        "summary": summary,
+        "responses": with_errors(json!({
+            status: { "description": description, "content": { "application/json": { "schema": response } } }
+        }))
+    });
+    if let Some(request) = request {
+        operation["requestBody"] = body(request);
+    }
+    operation
+}
+
+/// Mark an operation as not requiring the API key.
+fn public(mut operation: Value) -> Value {
+    operation["security"] = json!([]);
+    if let Some(responses) = operation["responses"].as_object_mut() {
+        responses.remove("401");
+    }
+    operation
+}
+
+fn with_errors(mut responses: Value) -> Value {
+    for (status, description) in [
+        ("400", "Invalid request"),
+        ("401", "Missing or invalid API key"),
+        ("404", "Memory not found"),
+        ("500", "Server error"),
+    ] {
+        responses[status] = json!({
+            "description": description,
+            "content": { "application/json": { "schema": schema("Error") } }
+        });
+    }
+    responses
+}
+
+fn body(schema: Value) -> Value {
+    json!({ "required": true, "content": { "application/json": { "schema": schema } } })
+}
+
+fn id_param() -> Value {
+    json!({ "name": "id", "in": "path", "required": true, "schema": string() })
+}
+
+fn schema(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn array(name: &str) -> Value {
+    json!({ "type": "array", "items": schema(name) })
+}
+
+fn object(required: &[&str], properties: Value) -> Value {
+    let mut object = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        object["required"] = json!(required);
+    }
+    object
+}
+
+fn string() -> Value {
+    json!({ "type": "string" })
+}
+
+fn integer() -> Value {
+    json!({ "type": "integer", "minimum": 0 })
+}
@@ -0,0 +1,140 @@
+//! Memory-grounded chat, as JSON or a server-sent event stream.
+//!
+//! Streams emit `memories` (session ID and the memories in the prompt), then
+//! one `delta` per text fragment, then `done` with token usage. A failure
+//! mid-stream is sent as an `error` event. The exchange is pushed to the
+//! buffer only once the reply is complete, so a client that disconnects
+//! early leaves nothing behind.
+
+use std::convert::Infallible;
+
+use axum::extract::State;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use synapse_core::ports::{collect_stream, FinishReason, GenerationParams, GenerationUsage, TokenEvent};
+
+use crate::error::{ApiError, ApiResult};
+use crate::routes::memories::SearchHit;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct ChatRequest {
+    pub message: String,
+    /// Continue an existing session (a new one is started if unknown)
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Only recall memories from this namespace (applies to new sessions)
+    #[serde(default)]
+    pub namespace: Option<String>,
+    /// Reply with server-sent events instead of a single JSON object
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub max_tokens: Option<usize>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: usize,
+    pub completion_tokens: usize,
+    /// "stop", "length" or "cancelled"
+    pub finish_reason: String,
+}
+
+impl From<GenerationUsage> for Usage {
+    fn from(usage: GenerationUsage) -> Self {
+        let finish_reason = match usage.finish_reason {
+            FinishReason::Stop => "stop",
+            FinishReason::Length => "length",
+            FinishReason::Cancelled => "cancelled",
+        };
+        Self {
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            finish_reason: finish_reason.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChatResponse {
+    pub session_id: String,
+    pub reply: String,
+    pub memories: Vec<SearchHit>,
+    pub usage: Usage,
+}
+
+/// `POST /v1/chat`
+pub async fn chat(State(state): State<AppState>, Json(request): Json<ChatRequest>) -> ApiResult<Response> {
+    if request.message.trim().is_empty() {
+        return Err(ApiError::bad_request("message must not be empty"));
+    }
+
+    let mut params = GenerationParams::default();
+    if let Some(max_tokens) = request.max_tokens {
+        params = params.with_max_tokens(max_tokens);
+    }
+    if let Some(temperature) = request.temperature {
+        params = params.with_temperature(temperature);
+    }
+
+    // Turns on one session run one at a time, in order.
+    let mut session = state
+        .chat_session(request.session_id.as_deref(), request.namespace.as_deref())
+        .lock_owned()
+        .await;
+    let session_id = session.session_id().to_string();
+    let turn = session.prepare(&request.message).await?;
+    let memories: Vec<SearchHit> = turn.memories.iter().cloned().map(SearchHit::from).collect();
+    let mut tokens = session.stream(&turn, &params).await?;
+
+    if !request.stream {
+        let (reply, usage) = collect_stream(tokens).await?;
+        let reply = reply.trim().to_string();
+        session.record(&request.message, &reply).await?;
+        let response = ChatResponse {
+            session_id,
+            reply,
+            memories,
+            usage: usage.into(),
+        };
+        return Ok(Json(response).into_response());
+    }
+
+    let events = async_stream::stream! {
+        yield Ok::<_, Infallible>(event("memories", json!({ "session_id": session_id, "memories": memories })));
+
+        let mut reply = String::new();
+        while let Some(token) = tokens.next().await {
+            match token {
+                Ok(TokenEvent::Delta(text)) => {
+                    reply.push_str(&text);
+                    yield Ok(event("delta", json!({ "text": text })));
+                }
+                Ok(TokenEvent::Done(usage)) => {
+                    match session.record(&request.message, reply.trim()).await {
+                        Ok(()) => yield Ok(event("done", json!({ "usage": Usage::from(usage) }))),
+                        Err(e) => yield Ok(event("error", json!({ "error": e.to_string() }))),
+                    }
+                    break;
+                }
+                Err(e) => {
+                    yield Ok(event("error", json!({ "error": e.to_string() })));
+                    break;
+                }
+            }
+        }
+    };
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()).into_response())
+}
+
+fn event(name: &str, data: serde_json::Value) -> Event {
+    Event::default().event(name).data(data.to_string())
+}
@@ -0,0 +1,61 @@
+//! MCP over HTTP: one JSON-RPC message (or batch) per POST.
+//!
+//! Bodies must be `application/json`, so a web page cannot send one
+//! without a CORS preflight, and requests from a browser must come from a
+//! loopback or explicitly allowed origin.
+
+use axum::extract::State;
+use axum::http::header::ORIGIN;
+use axum::http::{HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde_json::Value;
+
+use crate::error::ApiError;
+use crate::mcp::McpServer;
+use crate::state::AppState;
+
+/// `POST /mcp` - returns the JSON-RPC response, or 202 for notifications.
+pub async fn handle(State(state): State<AppState>, headers: HeaderMap, Json(message): Json<Value>) -> Response {
+    if let Some(origin) = headers.get(ORIGIN) {
+        let origin = origin.to_str().unwrap_or_default();
+        if !origin_allowed(origin, &state.allowed_origins) {
+            tracing::warn!("Rejected MCP request from origin {}", origin);
+            return ApiError::new(StatusCode::FORBIDDEN, format!("Origin not allowed: {}", origin)).into_response();
+        }
+    }
+
+    let server = McpServer::new(state.memory.clone(), state.embedder.clone(), state.buffer.clone());
+    match server.handle(message).await {
+        Some(response) => Json(response).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+/// Loopback origins (any port) and the configured ones are allowed.
+fn origin_allowed(origin: &str, allowed: &[String]) -> bool {
+    if allowed.iter().any(|a| a.trim_end_matches('/') == origin) {
+        return true;
+    }
+    let Ok(uri) = origin.parse::<Uri>() else {
+        return false;
+    };
+    matches!(uri.scheme_str(), Some("http" | "https"))
+        && matches!(uri.host(), Some("localhost" | "127.0.0.1" | "[::1]"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_allowed() {
+        let allowed = vec!["https://app.example.com".to_string()];
+        for origin in ["http://localhost:5173", "http://127.0.0.1:7878", "http://[::1]:8080", "https://app.example.com"] {
+            assert!(origin_allowed(origin, &allowed), "{}", origin);
+        }
+        for origin in ["https://evil.example", "http://localhost.evil.example", "null", "file://", ""] {
+            assert!(!origin_allowed(origin, &allowed), "{}", origin);
+        }
+    }
+}
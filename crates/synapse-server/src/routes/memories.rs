@@ -0,0 +1,157 @@
+//! Memory storage, search and graph endpoints.
+
+use std::collections::HashMap;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use synapse_core::logic::chunked_memory::ChunkedMemory;
+use synapse_core::logic::memory_api::{self, DEFAULT_TOP_K};
+use synapse_core::{MemoryNode, NodeType, Relationship, SearchResult};
+
+use crate::error::{ApiError, ApiResult};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct StoreRequest {
+    pub content: String,
+    #[serde(default)]
+    pub namespace: Option<String>,
+    #[serde(default)]
+    pub source: Option<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoreResponse {
+    pub id: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchRequest {
+    pub query: String,
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+
+fn default_top_k() -> usize {
+    DEFAULT_TOP_K
+}
+
+/// A memory node without its embedding.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryView {
+    pub id: String,
+    pub content: String,
+    pub layer: u8,
+    pub node_type: NodeType,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub namespace: String,
+    pub source: String,
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl From<MemoryNode> for MemoryView {
+    fn from(node: MemoryNode) -> Self {
+        Self {
+            id: node.id,
+            content: node.content,
+            layer: node.layer,
+            node_type: node.node_type,
+            created_at: node.created_at,
+            updated_at: node.updated_at,
+            namespace: node.namespace,
+            source: node.source,
+            metadata: node.metadata,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub memory: MemoryView,
+    pub distance: f32,
+}
+
+impl From<SearchResult> for SearchHit {
+    fn from(result: SearchResult) -> Self {
+        Self {
+            memory: result.node.into(),
+            distance: result.distance,
+        }
+    }
+}
+
+/// `POST /v1/memories` - embed (chunking long text) and store.
+pub async fn store(State(state): State<AppState>, Json(request): Json<StoreRequest>) -> ApiResult<(StatusCode, Json<StoreResponse>)> {
+    let mut node = MemoryNode::new(request.content);
+    if let Some(namespace) = request.namespace {
+        node = node.with_namespace(namespace);
+    }
+    node.source = request.source.unwrap_or_else(|| "api".to_string());
+    node.metadata = request.metadata;
+
+    let id = memory_api::store(state.memory.clone(), state.embedder.clone(), node).await?;
+    Ok((StatusCode::CREATED, Json(StoreResponse { id })))
+}
+
+/// `POST /v1/memories/search` - semantic search, closest first.
+pub async fn search(State(state): State<AppState>, Json(request): Json<SearchRequest>) -> ApiResult<Json<Vec<SearchHit>>> {
+    let results = memory_api::search(
+        state.memory.clone(),
+        state.embedder.clone(),
+        &request.query,
+        request.namespace.as_deref(),
+        request.top_k,
+    )
+    .await?;
+    Ok(Json(results.into_iter().map(SearchHit::from).collect()))
+}
+
+/// `GET /v1/memories/:id`
+pub async fn get(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Json<MemoryView>> {
+    let node = existing(&state, &id).await?;
+    Ok(Json(node.into()))
+}
+
+/// `DELETE /v1/memories/:id` - delete a memory and its chunks.
+pub async fn delete(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<StatusCode> {
+    existing(&state, &id).await?;
+    ChunkedMemory::new(state.memory.clone(), state.embedder.clone())
+        .delete(&id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `GET /v1/memories/:id/relationships` - edges where the memory is either end.
+pub async fn related(State(state): State<AppState>, Path(id): Path<String>) -> ApiResult<Json<Vec<Relationship>>> {
+    existing(&state, &id).await?;
+    Ok(Json(state.memory.get_related(&id).await?))
+}
+
+/// `POST /v1/relationships` - link two existing memories.
+pub async fn relate(State(state): State<AppState>, Json(request): Json<Relationship>) -> ApiResult<(StatusCode, Json<Relationship>)> {
+    memory_api::validate_relation(&request.relation)?;
+    existing(&state, &request.from_id).await?;
+    existing(&state, &request.to_id).await?;
+
+    state
+        .memory
+        .add_relationship(&request.from_id, &request.relation, &request.to_id)
+        .await?;
+    Ok((StatusCode::CREATED, Json(request)))
+}
+
+async fn existing(state: &AppState, id: &str) -> ApiResult<MemoryNode> {
+    state
+        .memory
+        .get_by_id(id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(id))
+}
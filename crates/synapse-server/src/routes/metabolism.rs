@@ -0,0 +1,104 @@
+//! Short-term buffer, digestion, consolidation and statistics endpoints.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+use synapse_core::logic::consolidation::LayerConsolidator;
+use synapse_core::logic::memory_api;
+use synapse_core::Interaction;
+
+use crate::error::ApiResult;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct PushRequest {
+    pub user_input: String,
+    pub ai_response: String,
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushResponse {
+    pub id: String,
+    pub buffer_size: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DigestRequest {
+    /// Digest even when the buffer is below the threshold
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DigestResponse {
+    pub digested: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ConsolidateResponse {
+    pub summaries: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LayerCount {
+    pub layer: u8,
+    pub count: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsResponse {
+    pub total_memories: usize,
+    pub buffer_size: usize,
+    /// Non-empty layers, lowest first
+    pub layers: Vec<LayerCount>,
+}
+
+/// `POST /v1/buffer` - queue an exchange for digestion.
+pub async fn push(State(state): State<AppState>, Json(request): Json<PushRequest>) -> ApiResult<(StatusCode, Json<PushResponse>)> {
+    let mut interaction = Interaction::new(request.user_input, request.ai_response);
+    if let Some(session_id) = request.session_id {
+        interaction = interaction.with_session(session_id);
+    }
+    let id = interaction.id.clone();
+    let buffer_size = memory_api::push(state.buffer.as_ref(), interaction).await?;
+    Ok((StatusCode::ACCEPTED, Json(PushResponse { id, buffer_size })))
+}
+
+/// `POST /v1/digest` - summarize the buffer into Layer 0.
+pub async fn digest(State(state): State<AppState>, request: Option<Json<DigestRequest>>) -> ApiResult<Json<DigestResponse>> {
+    let force = request.is_some_and(|Json(request)| request.force);
+    let _running = state.maintenance.lock().await;
+
+    let digested = memory_api::digest(
+        state.buffer.clone(),
+        state.memory.clone(),
+        state.llm.clone(),
+        state.embedder.clone(),
+        force,
+    )
+    .await?;
+    Ok(Json(DigestResponse { digested }))
+}
+
+/// `POST /v1/consolidate` - summarize full layers into the layer above.
+pub async fn consolidate(State(state): State<AppState>) -> ApiResult<Json<ConsolidateResponse>> {
+    let _running = state.maintenance.lock().await;
+
+    let summaries = LayerConsolidator::new(state.memory.clone(), state.llm.clone(), state.embedder.clone())
+        .consolidate_all()
+        .await?;
+    Ok(Json(ConsolidateResponse { summaries }))
+}
+
+/// `GET /v1/stats`
+pub async fn stats(State(state): State<AppState>) -> ApiResult<Json<StatsResponse>> {
+    let stats = memory_api::stats(state.memory.as_ref(), state.buffer.as_ref()).await?;
+    Ok(Json(StatsResponse {
+        total_memories: stats.total_memories,
+        buffer_size: stats.buffer_size,
+        layers: stats.layers.into_iter().map(|(layer, count)| LayerCount { layer, count }).collect(),
+    }))
+}
@@ -0,0 +1,14 @@
+//! Request handlers, grouped by resource.
+
+pub mod chat;
+pub mod mcp;
+pub mod memories;
+pub mod metabolism;
+
+use axum::Json;
+use serde_json::{json, Value};
+
+/// `GET /health` - liveness probe (no authentication).
+pub async fn health() -> Json<Value> {
+    Json(json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }))
+}
@@ -0,0 +1,96 @@
+//! Shared application state.
+
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex as StdMutex};
+
+use lru::LruCache;
+use synapse_core::logic::memory_chat::MemoryChat;
+use synapse_core::ports::{BufferPort, EmbeddingPort, LlmPort, MemoryPort};
+use tokio::sync::Mutex;
+
+/// Chat sessions kept in memory; the least recently used is forgotten first.
+pub const MAX_CHAT_SESSIONS: usize = 64;
+
+/// Adapters and per-process state shared by every request.
+#[derive(Clone)]
+pub struct AppState {
+    pub memory: Arc<dyn MemoryPort>,
+    pub embedder: Arc<dyn EmbeddingPort>,
+    pub llm: Arc<dyn LlmPort>,
+    pub buffer: Arc<dyn BufferPort>,
+    pub(crate) api_key: Option<Arc<str>>,
+    /// Browser origins besides loopback allowed to call `/mcp`
+    pub(crate) allowed_origins: Arc<[String]>,
+    sessions: Arc<StdMutex<LruCache<String, Arc<Mutex<MemoryChat>>>>>,
+    /// Serializes digest and consolidation runs.
+    pub(crate) maintenance: Arc<Mutex<()>>,
+}
+
+impl AppState {
+    /// Create state without authentication.
+    pub fn new(
+        memory: Arc<dyn MemoryPort>,
+        embedder: Arc<dyn EmbeddingPort>,
+        llm: Arc<dyn LlmPort>,
+        buffer: Arc<dyn BufferPort>,
+    ) -> Self {
+        Self {
+            memory,
+            embedder,
+            llm,
+            buffer,
+            api_key: None,
+            allowed_origins: Arc::from([]),
+            sessions: Arc::new(StdMutex::new(LruCache::new(
+                NonZeroUsize::new(MAX_CHAT_SESSIONS).expect("MAX_CHAT_SESSIONS is non-zero"),
+            ))),
+            maintenance: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Require this key on every `/v1` request.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(Arc::from(api_key.into()));
+        self
+    }
+
+    /// Also accept `/mcp` requests from these browser origins
+    /// (e.g. `https://app.example.com`).
+    pub fn with_allowed_origins(mut self, origins: impl IntoIterator<Item = String>) -> Self {
+        self.allowed_origins = origins.into_iter().collect();
+        self
+    }
+
+    /// The lock serializing digest and consolidation, to share with other
+    /// APIs served from the same process.
+    pub fn maintenance_lock(&self) -> Arc<Mutex<()>> {
+        self.maintenance.clone()
+    }
+
+    /// Look up a chat session, or start one (scoped to `namespace`) if
+    /// `session_id` is unknown or absent.
+    pub(crate) fn chat_session(&self, session_id: Option<&str>, namespace: Option<&str>) -> Arc<Mutex<MemoryChat>> {
+        let mut sessions = self.sessions.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(session) = session_id.and_then(|id| sessions.get(id)) {
+            return session.clone();
+        }
+
+        let mut chat = MemoryChat::new(
+            self.llm.clone(),
+            self.embedder.clone(),
+            self.memory.clone(),
+            self.buffer.clone(),
+        );
+        if let Some(session_id) = session_id {
+            chat = chat.with_session(session_id);
+        }
+        if let Some(namespace) = namespace {
+            chat = chat.with_namespace(namespace);
+        }
+
+        let id = chat.session_id().to_string();
+        let session = Arc::new(Mutex::new(chat));
+        sessions.put(id, session.clone());
+        session
+    }
+}